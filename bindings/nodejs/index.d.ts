@@ -40,8 +40,11 @@ export declare class Connection {
   queryRow(sql: string, params?: Params | undefined | null): Promise<Row | null>
   /** Execute a SQL query and fetch all data into the result */
   queryAll(sql: string, params?: Params | undefined | null): Promise<Array<Row>>
-  /** Execute a SQL query, and return all rows. */
-  queryIter(sql: string, params?: Params | undefined | null): Promise<RowIterator>
+  /**
+   * Execute a SQL query, and return all rows.
+   * The result can be iterated directly with `for await (const row of conn.queryIter(sql))`.
+   */
+  queryIter(sql: string, params?: Params | undefined | null): Promise<RowIterator> & AsyncIterable<Row>
   /** Execute a SQL query, and return all rows with schema and stats. */
   queryIterExt(sql: string, params?: Params | undefined | null): Promise<RowIteratorExt>
   /**
@@ -111,4 +114,5 @@ export declare class ServerStats {
   get writeBytes(): bigint
   get spillFileNums(): bigint
   get runningTimeMs(): number
+  get rejectedRows(): bigint
 }
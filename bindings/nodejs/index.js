@@ -18,8 +18,21 @@
 
 const { Readable } = require("node:stream");
 
-const { Client, RowIterator } = require("./generated.js");
+const { Client, Connection, RowIterator } = require("./generated.js");
 
+// `next()` resolves to the error instead of rejecting when reading rows fails.
+async function nextRow(reader) {
+  const item = await reader.next();
+  if (item instanceof Error) {
+    throw item;
+  }
+  return item;
+}
+
+/**
+ * Rows are only read from the server when the stream is read, one row per `_read()`,
+ * so a slow consumer pauses the query result stream in Rust.
+ */
 class RowsStream extends Readable {
   constructor(reader, options) {
     super({ objectMode: true, ...options });
@@ -27,20 +40,19 @@ class RowsStream extends Readable {
   }
 
   _read() {
-    this.reader
-      .next()
+    nextRow(this.reader)
       .then((item) => {
         this.push(item);
       })
       .catch((e) => {
-        this.emit("error", e);
+        this.destroy(e);
       });
   }
 }
 
 RowIterator.prototype[Symbol.asyncIterator] = async function* () {
   while (true) {
-    const item = await this.next();
+    const item = await nextRow(this);
     if (item === null) {
       break;
     }
@@ -52,4 +64,14 @@ RowIterator.prototype.stream = function () {
   return new RowsStream(this);
 };
 
+// Allow `for await (const row of conn.queryIter(sql))` without awaiting the iterator first.
+const queryIter = Connection.prototype.queryIter;
+Connection.prototype.queryIter = function (sql, params) {
+  const promise = queryIter.call(this, sql, params);
+  promise[Symbol.asyncIterator] = async function* () {
+    yield* await promise;
+  };
+  return promise;
+};
+
 module.exports.Client = Client;
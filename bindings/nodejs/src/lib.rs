@@ -105,7 +105,9 @@ impl Connection {
 
     #[napi]
     pub fn format_sql(&self, sql: String, params: Option<Params>) -> Result<String> {
-        Ok(self.inner.format_sql(&sql, params))
+        self.inner
+            .format_sql(&sql, params)
+            .map_err(format_napi_error)
     }
 
     /// Execute a SQL query, return the number of affected rows.
@@ -143,7 +145,8 @@ impl Connection {
     }
 
     /// Execute a SQL query, and return all rows.
-    #[napi]
+    /// The result can be iterated directly with `for await (const row of conn.queryIter(sql))`.
+    #[napi(ts_return_type = "Promise<RowIterator> & AsyncIterable<Row>")]
     pub async fn query_iter(&self, sql: String, params: Option<Params>) -> Result<RowIterator> {
         let iterator = self
             .inner
@@ -190,14 +193,12 @@ impl Connection {
         format_options: Option<BTreeMap<String, String>>,
         copy_options: Option<BTreeMap<String, String>>,
     ) -> Result<ServerStats> {
-        let format_options = match format_options {
-            None => None,
-            Some(ref opts) => Some(opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect()),
-        };
-        let copy_options = match copy_options {
-            None => None,
-            Some(ref opts) => Some(opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect()),
-        };
+        let format_options = format_options
+            .as_ref()
+            .map(|opts| opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect());
+        let copy_options = copy_options
+            .as_ref()
+            .map(|opts| opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect());
         let ss = self
             .inner
             .load_file(&sql, Path::new(&file), format_options, copy_options)
@@ -290,21 +291,21 @@ impl<'v> ToNapiValue for Value<'v> {
             }
             databend_driver::Value::Array(inner) => {
                 let mut arr = ctx.create_array(inner.len() as u32)?;
-                for (i, v) in inner.into_iter().enumerate() {
+                for (i, v) in inner.iter().enumerate() {
                     arr.set(i as u32, Value::new(v, val.opts))?;
                 }
                 Array::to_napi_value(env, arr)
             }
             databend_driver::Value::Map(inner) => {
                 let mut obj = ctx.create_object()?;
-                for (k, v) in inner.into_iter() {
+                for (k, v) in inner.iter() {
                     obj.set(k.to_string(), Value::new(v, val.opts))?;
                 }
                 Object::to_napi_value(env, obj)
             }
             databend_driver::Value::Tuple(inner) => {
                 let mut arr = ctx.create_array(inner.len() as u32)?;
-                for (i, v) in inner.into_iter().enumerate() {
+                for (i, v) in inner.iter().enumerate() {
                     arr.set(i as u32, Value::new(v, val.opts))?;
                 }
                 Array::to_napi_value(env, arr)
@@ -334,7 +335,7 @@ impl ToNapiValue for NumberValue {
             databend_driver::NumberValue::Int8(i) => i8::to_napi_value(env, i),
             databend_driver::NumberValue::Int16(i) => i16::to_napi_value(env, i),
             databend_driver::NumberValue::Int32(i) => i32::to_napi_value(env, i),
-            databend_driver::NumberValue::Int64(i) => i64::to_napi_value(env, i),
+            databend_driver::NumberValue::Int64(i) => i64n::to_napi_value(env, i64n(i)),
             databend_driver::NumberValue::UInt8(i) => u8::to_napi_value(env, i),
             databend_driver::NumberValue::UInt16(i) => u16::to_napi_value(env, i),
             databend_driver::NumberValue::UInt32(i) => u32::to_napi_value(env, i),
@@ -412,7 +413,7 @@ impl RowIterator {
     /// Return a Readable Stream for the query result.
     /// Should be used with `ObjectMode` set to `true`.
     #[napi(ts_return_type = "import('stream').Readable")]
-    pub fn stream(&self) -> () {
+    pub fn stream(&self) {
         unreachable!()
     }
 }
@@ -501,7 +502,7 @@ impl Row {
     }
 
     #[napi]
-    pub fn values(&self) -> Vec<Value> {
+    pub fn values(&self) -> Vec<Value<'_>> {
         self.inner
             .values()
             .iter()
@@ -510,7 +511,7 @@ impl Row {
     }
 
     #[napi]
-    pub fn data(&self) -> HashMap<String, Value> {
+    pub fn data(&self) -> HashMap<String, Value<'_>> {
         let mut map = HashMap::new();
         let schema = self.inner.schema();
         for (name, value) in schema
@@ -570,6 +571,11 @@ impl ServerStats {
     pub fn running_time_ms(&self) -> f64 {
         self.0.running_time_ms
     }
+
+    #[napi(getter)]
+    pub fn rejected_rows(&self) -> usize {
+        self.0.rejected_rows
+    }
 }
 
 fn format_napi_error(err: databend_driver::Error) -> Error {
@@ -224,6 +224,16 @@ Then("Select numbers should iterate all rows", async function () {
     assert.deepEqual(ret, expected);
   }
 
+  // async iter without awaiting the iterator
+  {
+    let ret = [];
+    for await (const row of this.conn.queryIter("SELECT number FROM numbers(5)")) {
+      ret.push(row.values()[0]);
+    }
+    const expected = [0n, 1n, 2n, 3n, 4n];
+    assert.deepStrictEqual(ret, expected);
+  }
+
   // async iter return with field names
   {
     let rows = await this.conn.queryIter("SELECT number as n FROM numbers(5)");
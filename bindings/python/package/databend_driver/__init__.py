@@ -15,3 +15,5 @@
 # flake8: noqa
 
 from ._databend_driver import *
+from . import types
+from .types import BINARY, DATETIME, NUMBER, ROWID, STRING
@@ -14,6 +14,8 @@
 
 # flake8: noqa
 
+from typing import Any, AsyncIterator, Awaitable, Callable
+
 class ServerStats:
     @property
     def total_rows(self) -> int: ...
@@ -29,6 +31,8 @@ class ServerStats:
     def write_bytes(self) -> int: ...
     @property
     def running_time_ms(self) -> float: ...
+    @property
+    def rejected_rows(self) -> int: ...
 
 class ConnectionInfo:
     @property
@@ -49,25 +53,54 @@ class Field:
     def name(self) -> str: ...
     @property
     def data_type(self) -> str: ...
+    @property
+    def nullable(self) -> bool: ...
 
 class Schema:
     def fields(self) -> list[Field]: ...
 
 class Row:
     def values(self) -> tuple: ...
+    def keys(self) -> list[str]: ...
+    def items(self) -> list[tuple[str, Any]]: ...
+    def get(self, key: str, default: Any = None) -> Any: ...
+    def __len__(self) -> int: ...
+    def __contains__(self, key: str) -> bool: ...
+    def __getitem__(self, key: int | str) -> Any: ...
 
 class RowIterator:
     def __aiter__(self) -> RowIterator: ...
     async def __anext__(self) -> Row: ...
     def schema(self) -> Schema: ...
+    def fetch_batch(self, n: int) -> list[tuple[Any, ...]]: ...
+    async def afetch_batch(self, n: int) -> list[tuple[Any, ...]]: ...
+    def close(self) -> None: ...
+    async def aclose(self) -> None: ...
 
 class AsyncDatabendConnection:
     async def info(self) -> ConnectionInfo: ...
     async def version(self) -> str: ...
     async def exec(self, sql: str) -> int: ...
+    async def exec_script(self, script: str) -> int: ...
     async def query_row(self, sql: str) -> Row: ...
     async def query_iter(self, sql: str) -> RowIterator: ...
+    async def schema(self, sql: str) -> Schema: ...
+    async def executemany(self, sql: str, seq_of_parameters: list[list[Any] | tuple[Any, ...]]) -> int: ...
     async def stream_load(self, sql: str, data: list[list[str]]) -> ServerStats: ...
+    async def put(self, local_pattern: str, stage: str) -> list[Row]: ...
+    async def get(self, stage: str, local_dir: str) -> list[Row]: ...
+    def release(self) -> None: ...
+    async def __aenter__(self) -> AsyncDatabendConnection: ...
+    async def __aexit__(self, *args) -> None: ...
+    async def load_data(
+        self,
+        sql: str,
+        data: AsyncIterator[bytes] | Any,
+        size: int | None = None,
+        format_options: dict[str, str] | None = None,
+        copy_options: dict[str, str] | None = None,
+        progress: Callable[[int, int | None], Awaitable[None] | None] | None = None,
+    ) -> ServerStats: ...
 
 class AsyncDatabendClient:
     def __init__(self, dsn: str): ...
@@ -77,10 +110,32 @@ class BlockingDatabendConnection:
     def info(self) -> ConnectionInfo: ...
     def version(self) -> str: ...
     def exec(self, sql: str) -> int: ...
+    def exec_script(self, script: str) -> int: ...
     def query_row(self, sql: str) -> Row: ...
     def query_iter(self, sql: str) -> RowIterator: ...
+    def schema(self, sql: str) -> Schema: ...
+    def executemany(self, sql: str, seq_of_parameters: list[list[Any] | tuple[Any, ...]]) -> int: ...
     def stream_load(self, sql: str, data: list[list[str]]) -> ServerStats: ...
+    def put(self, local_pattern: str, stage: str) -> list[Row]: ...
+    def get(self, stage: str, local_dir: str) -> list[Row]: ...
+    def release(self) -> None: ...
+    def __enter__(self) -> BlockingDatabendConnection: ...
+    def __exit__(self, *args) -> None: ...
+
+class DatabendConnectionPool:
+    def __init__(self, dsn: str, max_size: int = 10): ...
+    def acquire(self, timeout: float | None = None) -> BlockingDatabendConnection: ...
+    async def acquire_async(self, timeout: float | None = None) -> AsyncDatabendConnection: ...
+    def release(self, conn: BlockingDatabendConnection | AsyncDatabendConnection) -> None: ...
+    @property
+    def max_size(self) -> int: ...
+    @property
+    def idle(self) -> int: ...
+    @property
+    def in_use(self) -> int: ...
 
 class BlockingDatabendClient:
     def __init__(self, dsn: str): ...
     def get_conn(self) -> BlockingDatabendConnection: ...
+
+def split_statements(script: str) -> list[str]: ...
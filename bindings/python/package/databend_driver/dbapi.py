@@ -0,0 +1,228 @@
+# Copyright 2021 Datafuse Labs
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#     http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+"""PEP 249 module on top of `BlockingDatabendConnection`, used by the SQLAlchemy dialect.
+
+All cursors of a connection share the same session."""
+
+import datetime
+import re
+
+from ._databend_driver import BlockingDatabendClient
+from .types import BINARY, DATETIME, NUMBER, ROWID, STRING  # noqa: F401
+
+apilevel = "2.0"
+threadsafety = 1
+paramstyle = "qmark"
+
+Date = datetime.date
+Time = datetime.time
+Timestamp = datetime.datetime
+Binary = bytes
+
+
+def DateFromTicks(ticks):
+    return datetime.date.fromtimestamp(ticks)
+
+
+def TimeFromTicks(ticks):
+    return datetime.datetime.fromtimestamp(ticks).time()
+
+
+def TimestampFromTicks(ticks):
+    return datetime.datetime.fromtimestamp(ticks)
+
+
+class Warning(Exception):
+    pass
+
+
+class Error(Exception):
+    pass
+
+
+class InterfaceError(Error):
+    pass
+
+
+class DatabaseError(Error):
+    pass
+
+
+class DataError(DatabaseError):
+    pass
+
+
+class OperationalError(DatabaseError):
+    pass
+
+
+class IntegrityError(DatabaseError):
+    pass
+
+
+class InternalError(DatabaseError):
+    pass
+
+
+class ProgrammingError(DatabaseError):
+    pass
+
+
+class NotSupportedError(DatabaseError):
+    pass
+
+
+# `INSERT INTO t (a, b) VALUES (?, ?)`, the placeholders are dropped to load
+# all parameter sets with one stage attachment insert.
+_INSERT_VALUES = re.compile(
+    r"^\s*((?:INSERT|REPLACE)\b.*\bVALUES)\s*\(\s*\?(?:\s*,\s*\?)*\s*\)\s*;?\s*$",
+    re.IGNORECASE | re.DOTALL,
+)
+
+
+_FETCH_ALL_BATCH = 10000
+
+
+def _translate(e):
+    # errors from server and driver are raised as plain `Exception`
+    if type(e) is Exception:
+        return DatabaseError(str(e))
+    return e
+
+
+def connect(dsn):
+    return Connection(dsn)
+
+
+class Connection:
+    def __init__(self, dsn):
+        try:
+            self._conn = BlockingDatabendClient(dsn).get_conn()
+        except Exception as e:
+            raise _translate(e) from e
+
+    def close(self):
+        self._conn = None
+
+    def commit(self):
+        pass
+
+    def rollback(self):
+        pass
+
+    def cursor(self):
+        if self._conn is None:
+            raise InterfaceError("connection is closed")
+        return Cursor(self._conn)
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *args):
+        self.close()
+
+
+class Cursor:
+    arraysize = 1
+
+    def __init__(self, conn):
+        self._conn = conn
+        self._rows = None
+        self.description = None
+        self.rowcount = -1
+
+    def close(self):
+        self._reset()
+        self._conn = None
+
+    def _reset(self):
+        if self._rows is not None:
+            self._rows.close()
+        self._rows = None
+        self.description = None
+        self.rowcount = -1
+
+    def _check(self):
+        if self._conn is None:
+            raise InterfaceError("cursor is closed")
+
+    def execute(self, operation, parameters=None):
+        self._check()
+        self._reset()
+        try:
+            rows = self._conn.query_iter(operation, parameters)
+            fields = rows.schema().fields()
+        except Exception as e:
+            raise _translate(e) from e
+        if fields:
+            self._rows = rows
+            self.description = [
+                (f.name, f.data_type, None, None, None, None, f.nullable)
+                for f in fields
+            ]
+
+    def executemany(self, operation, seq_of_parameters):
+        self._check()
+        self._reset()
+        seq_of_parameters = list(seq_of_parameters)
+        matched = _INSERT_VALUES.match(operation)
+        if matched:
+            try:
+                self.rowcount = self._conn.executemany(
+                    matched.group(1), seq_of_parameters
+                )
+            except Exception as e:
+                raise _translate(e) from e
+            return
+        for parameters in seq_of_parameters:
+            self.execute(operation, parameters)
+        self._reset()
+
+    def fetchone(self):
+        self._check()
+        if self._rows is None:
+            raise ProgrammingError("no results to fetch")
+        try:
+            return next(self._rows).values()
+        except StopIteration:
+            return None
+        except Exception as e:
+            raise _translate(e) from e
+
+    def fetchmany(self, size=None):
+        self._check()
+        if self._rows is None:
+            raise ProgrammingError("no results to fetch")
+        try:
+            return self._rows.fetch_batch(size or self.arraysize)
+        except Exception as e:
+            raise _translate(e) from e
+
+    def fetchall(self):
+        rows = []
+        while True:
+            batch = self.fetchmany(_FETCH_ALL_BATCH)
+            if not batch:
+                return rows
+            rows.extend(batch)
+
+    def setinputsizes(self, sizes):
+        pass
+
+    def setoutputsize(self, size, column=None):
+        pass
+
+    def __iter__(self):
+        return iter(self.fetchone, None)
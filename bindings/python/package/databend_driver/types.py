@@ -0,0 +1,90 @@
+# Copyright 2021 Datafuse Labs
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+# You may obtain a copy of the License at
+#
+#     http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+"""Type objects comparing equal to `Field.data_type` and the `type_code` of
+cursor `description`, e.g. `field.data_type == types.NUMBER`."""
+
+
+def base_type(data_type: str) -> str:
+    """`Nullable(Decimal(10, 2))` -> `Decimal`"""
+    while data_type.startswith("Nullable(") and data_type.endswith(")"):
+        data_type = data_type[len("Nullable(") : -1]
+    return data_type.split("(", 1)[0]
+
+
+class DBAPITypeObject:
+    def __init__(self, name: str, *types: str):
+        self.name = name
+        self.types = frozenset(types)
+
+    def __eq__(self, other):
+        if isinstance(other, str):
+            return base_type(other) in self.types
+        if isinstance(other, DBAPITypeObject):
+            return self.types == other.types
+        return NotImplemented
+
+    def __ne__(self, other):
+        eq = self.__eq__(other)
+        return eq if eq is NotImplemented else not eq
+
+    def __hash__(self):
+        return hash(self.types)
+
+    def __repr__(self):
+        return f"<DBAPITypeObject {self.name}>"
+
+
+NUMBER = DBAPITypeObject(
+    "NUMBER",
+    "UInt8",
+    "UInt16",
+    "UInt32",
+    "UInt64",
+    "Int8",
+    "Int16",
+    "Int32",
+    "Int64",
+    "Float32",
+    "Float64",
+    "Decimal",
+)
+INTEGER = DBAPITypeObject(
+    "INTEGER",
+    "UInt8",
+    "UInt16",
+    "UInt32",
+    "UInt64",
+    "Int8",
+    "Int16",
+    "Int32",
+    "Int64",
+)
+FLOAT = DBAPITypeObject("FLOAT", "Float32", "Float64")
+DECIMAL = DBAPITypeObject("DECIMAL", "Decimal")
+STRING = DBAPITypeObject("STRING", "String")
+BINARY = DBAPITypeObject("BINARY", "Binary", "Bitmap")
+BOOLEAN = DBAPITypeObject("BOOLEAN", "Boolean")
+DATE = DBAPITypeObject("DATE", "Date")
+TIMESTAMP = DBAPITypeObject("TIMESTAMP", "Timestamp")
+DATETIME = DBAPITypeObject("DATETIME", "Date", "Timestamp")
+INTERVAL = DBAPITypeObject("INTERVAL", "Interval")
+ARRAY = DBAPITypeObject("ARRAY", "Array", "EmptyArray")
+MAP = DBAPITypeObject("MAP", "Map", "EmptyMap")
+TUPLE = DBAPITypeObject("TUPLE", "Tuple")
+VARIANT = DBAPITypeObject("VARIANT", "Variant")
+GEOMETRY = DBAPITypeObject("GEOMETRY", "Geometry", "Geography")
+NULL = DBAPITypeObject("NULL", "Null")
+# no row id in databend
+ROWID = DBAPITypeObject("ROWID")
@@ -16,14 +16,27 @@ use std::collections::BTreeMap;
 use std::path::Path;
 use std::sync::Arc;
 
+use bytes::Bytes;
+use pyo3::exceptions::{PyStopAsyncIteration, PyTypeError};
 use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyTuple};
 use pyo3_async_runtimes::tokio::future_into_py;
+use pyo3_async_runtimes::TaskLocals;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::StreamReader;
 
 use crate::{
-    types::{ConnectionInfo, DriverError, Row, RowIterator, ServerStats, VERSION},
-    utils::to_sql_params,
+    pool::ConnHandle,
+    types::{ConnectionInfo, DriverError, Row, RowIterator, Schema, ServerStats, VERSION},
+    utils::{
+        collect_file_rows, exec_script, format_params_csv, to_local_url, to_sql_params, CancelGuard,
+    },
 };
 
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
 #[pyclass(module = "databend_driver")]
 pub struct AsyncDatabendClient(databend_driver::Client);
 
@@ -41,18 +54,42 @@ impl AsyncDatabendClient {
         let this = self.0.clone();
         future_into_py(py, async move {
             let conn = this.get_conn().await.map_err(DriverError::new)?;
-            Ok(AsyncDatabendConnection(Arc::new(conn)))
+            Ok(AsyncDatabendConnection(ConnHandle::Owned(Arc::new(conn))))
         })
     }
 }
 
 #[pyclass(module = "databend_driver")]
-pub struct AsyncDatabendConnection(Arc<databend_driver::Connection>);
+pub struct AsyncDatabendConnection(ConnHandle);
+
+impl AsyncDatabendConnection {
+    pub(crate) fn pooled(conn: databend_driver::PooledConnection) -> Self {
+        Self(ConnHandle::pooled(conn))
+    }
+}
 
 #[pymethods]
 impl AsyncDatabendConnection {
+    /// Return the connection to the pool it is acquired from,
+    /// it should not be used anymore after released.
+    pub fn release(&self) {
+        self.0.release();
+    }
+
+    fn __aenter__<'p>(slf: Bound<'p, Self>) -> PyResult<Bound<'p, PyAny>> {
+        let py = slf.py();
+        let this = slf.unbind();
+        future_into_py(py, async move { Ok(this) })
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __aexit__<'p>(&self, py: Python<'p>, _args: &Bound<PyTuple>) -> PyResult<Bound<'p, PyAny>> {
+        self.0.release();
+        future_into_py(py, async move { Ok(()) })
+    }
+
     pub fn info<'p>(&'p self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         future_into_py(py, async move {
             let info = this.info().await;
             Ok(ConnectionInfo::new(info))
@@ -60,7 +97,7 @@ impl AsyncDatabendConnection {
     }
 
     pub fn version<'p>(&'p self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         future_into_py(py, async move {
             let version = this.version().await.map_err(DriverError::new)?;
             Ok(version)
@@ -74,9 +111,10 @@ impl AsyncDatabendConnection {
         sql: String,
         params: Option<Bound<PyAny>>,
     ) -> PyResult<String> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
-        Ok(this.format_sql(&sql, params))
+        this.format_sql(&sql, params)
+            .map_err(|e| DriverError::new(e).into())
     }
 
     #[pyo3(signature = (sql, params=None))]
@@ -86,14 +124,22 @@ impl AsyncDatabendConnection {
         sql: String,
         params: Option<Bound<'p, PyAny>>,
     ) -> PyResult<Bound<'p, PyAny>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
         future_into_py(py, async move {
-            let res = this.exec(&sql, params).await.map_err(DriverError::new)?;
-            Ok(res)
+            let guard = CancelGuard::new(this.clone());
+            let res = guard.run(this.exec(&sql, params)).await;
+            guard.disarm();
+            Ok(res.map_err(DriverError::new)?)
         })
     }
 
+    /// Split the script by `;` and execute the statements in order, returns the total affected rows.
+    pub fn exec_script<'p>(&'p self, py: Python<'p>, script: String) -> PyResult<Bound<'p, PyAny>> {
+        let this = self.0.get()?;
+        future_into_py(py, async move { exec_script(&this, &script).await })
+    }
+
     #[pyo3(signature = (sql, params=None))]
     pub fn query_row<'p>(
         &'p self,
@@ -101,14 +147,13 @@ impl AsyncDatabendConnection {
         sql: String,
         params: Option<Bound<'p, PyAny>>,
     ) -> PyResult<Bound<'p, PyAny>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
         future_into_py(py, async move {
-            let row = this
-                .query_row(&sql, params)
-                .await
-                .map_err(DriverError::new)?;
-            Ok(row.map(Row::new))
+            let guard = CancelGuard::new(this.clone());
+            let row = guard.run(this.query_row(&sql, params)).await;
+            guard.disarm();
+            Ok(row.map_err(DriverError::new)?.map(Row::new))
         })
     }
 
@@ -119,12 +164,13 @@ impl AsyncDatabendConnection {
         sql: String,
         params: Option<Bound<'p, PyAny>>,
     ) -> PyResult<Bound<'p, PyAny>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
         future_into_py(py, async move {
-            let rows: Vec<Row> = this
-                .query_all(&sql, params)
-                .await
+            let guard = CancelGuard::new(this.clone());
+            let rows = guard.run(this.query_all(&sql, params)).await;
+            guard.disarm();
+            let rows: Vec<Row> = rows
                 .map_err(DriverError::new)?
                 .into_iter()
                 .map(Row::new)
@@ -140,15 +186,59 @@ impl AsyncDatabendConnection {
         sql: String,
         params: Option<Bound<'p, PyAny>>,
     ) -> PyResult<Bound<'p, PyAny>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
 
         future_into_py(py, async move {
-            let streamer = this
+            let guard = CancelGuard::new(this.clone());
+            let streamer = guard.run(this.query_iter(&sql, params)).await;
+            let query_id = guard.last_query_id();
+            guard.disarm();
+            Ok(RowIterator::new(streamer.map_err(DriverError::new)?).with_query(this, query_id))
+        })
+    }
+
+    /// Only `INSERT` and `REPLACE` statements are supported.
+    /// Parameters are loaded in one batch as a CSV stage attachment, returns the rows written.
+    pub fn executemany<'p>(
+        &'p self,
+        py: Python<'p>,
+        sql: String,
+        seq_of_parameters: Vec<Bound<'p, PyAny>>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let this = self.0.get()?;
+        let bytes = format_params_csv(seq_of_parameters)?;
+        future_into_py(py, async move {
+            let bytes = match bytes {
+                Some(bytes) => bytes,
+                None => return Ok(0),
+            };
+            let size = bytes.len() as u64;
+            let reader = Box::new(std::io::Cursor::new(bytes));
+            let ss = this
+                .load_data(&sql, reader, size, None, None)
+                .await
+                .map_err(DriverError::new)?;
+            Ok(ss.write_rows as u64)
+        })
+    }
+
+    /// Schema of the query result, the query is not read to the end.
+    #[pyo3(signature = (sql, params=None))]
+    pub fn schema<'p>(
+        &'p self,
+        py: Python<'p>,
+        sql: String,
+        params: Option<Bound<'p, PyAny>>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let this = self.0.get()?;
+        let params = to_sql_params(params);
+        future_into_py(py, async move {
+            let it = this
                 .query_iter(&sql, params)
                 .await
                 .map_err(DriverError::new)?;
-            Ok(RowIterator::new(streamer))
+            Ok(Schema::new(it.schema()))
         })
     }
 
@@ -158,7 +248,7 @@ impl AsyncDatabendConnection {
         sql: String,
         data: Vec<Vec<String>>,
     ) -> PyResult<Bound<'p, PyAny>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         future_into_py(py, async move {
             let data = data
                 .iter()
@@ -181,20 +271,14 @@ impl AsyncDatabendConnection {
         format_options: Option<BTreeMap<String, String>>,
         copy_options: Option<BTreeMap<String, String>>,
     ) -> PyResult<Bound<'p, PyAny>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         future_into_py(py, async move {
-            let format_options = match format_options {
-                None => None,
-                Some(ref opts) => {
-                    Some(opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect())
-                }
-            };
-            let copy_options = match copy_options {
-                None => None,
-                Some(ref opts) => {
-                    Some(opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect())
-                }
-            };
+            let format_options = format_options
+                .as_ref()
+                .map(|opts| opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect());
+            let copy_options = copy_options
+                .as_ref()
+                .map(|opts| opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect());
             let ss = this
                 .load_file(&sql, Path::new(&fp), format_options, copy_options)
                 .await
@@ -202,4 +286,227 @@ impl AsyncDatabendConnection {
             Ok(ServerStats::new(ss))
         })
     }
+
+    /// Upload local files matching the pattern to stage, returns rows of file, status and size.
+    pub fn put<'p>(
+        &'p self,
+        py: Python<'p>,
+        local_pattern: String,
+        stage: String,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let this = self.0.get()?;
+        let local = to_local_url(&local_pattern)?;
+        future_into_py(py, async move {
+            let it = this
+                .put_files(&local, &stage)
+                .await
+                .map_err(DriverError::new)?;
+            collect_file_rows(it).await
+        })
+    }
+
+    /// Download all files under the stage path to the local directory.
+    pub fn get<'p>(
+        &'p self,
+        py: Python<'p>,
+        stage: String,
+        local_dir: String,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let this = self.0.get()?;
+        let local = to_local_url(&local_dir)?;
+        future_into_py(py, async move {
+            let it = this
+                .get_files(&stage, &local)
+                .await
+                .map_err(DriverError::new)?;
+            collect_file_rows(it).await
+        })
+    }
+
+    #[pyo3(signature = (sql, data, size=None, format_options=None, copy_options=None, progress=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn load_data<'p>(
+        &'p self,
+        py: Python<'p>,
+        sql: String,
+        data: Bound<'p, PyAny>,
+        size: Option<u64>,
+        format_options: Option<BTreeMap<String, String>>,
+        copy_options: Option<BTreeMap<String, String>>,
+        progress: Option<PyObject>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let this = self.0.get()?;
+        let mut source = AsyncSource::new(py, data, size, progress)?;
+        future_into_py(py, async move {
+            let format_options = format_options
+                .as_ref()
+                .map(|opts| opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect());
+            let copy_options = copy_options
+                .as_ref()
+                .map(|opts| opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect());
+            let ss = match size {
+                Some(size) => {
+                    let (tx, rx) = mpsc::channel(16);
+                    let reader = StreamReader::new(ReceiverStream::new(rx));
+                    let (pumped, ss) = tokio::join!(
+                        source.pump(tx),
+                        this.load_data(&sql, Box::new(reader), size, format_options, copy_options)
+                    );
+                    // prefer the error from python side, which fails the upload
+                    pumped?;
+                    ss
+                }
+                None => {
+                    // size is required for uploading, spool the data to a temporary file first
+                    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+                    let tmp_file = std::env::temp_dir().join(format!(
+                        "databend_load_{}_{}",
+                        std::process::id(),
+                        now
+                    ));
+                    let result = async {
+                        let size = source.spool(&tmp_file).await?;
+                        let file = tokio::fs::File::open(&tmp_file).await?;
+                        Ok::<_, PyErr>(
+                            this.load_data(
+                                &sql,
+                                Box::new(file),
+                                size,
+                                format_options,
+                                copy_options,
+                            )
+                            .await,
+                        )
+                    }
+                    .await;
+                    let _ = tokio::fs::remove_file(&tmp_file).await;
+                    result?
+                }
+            }
+            .map_err(DriverError::new)?;
+            Ok(ServerStats::new(ss))
+        })
+    }
+}
+
+/// Data source of `load_data`, either an async file-like object with `read(size)`,
+/// or an async iterator of bytes.
+struct AsyncSource {
+    obj: PyObject,
+    is_file: bool,
+    locals: TaskLocals,
+    size: Option<u64>,
+    read_bytes: u64,
+    progress: Option<PyObject>,
+}
+
+impl AsyncSource {
+    fn new(
+        py: Python,
+        data: Bound<PyAny>,
+        size: Option<u64>,
+        progress: Option<PyObject>,
+    ) -> PyResult<Self> {
+        let locals = pyo3_async_runtimes::tokio::get_current_locals(py)?;
+        let (obj, is_file) = if data.hasattr("read")? {
+            (data.unbind(), true)
+        } else if data.hasattr("__aiter__")? {
+            (data.call_method0("__aiter__")?.unbind(), false)
+        } else {
+            return Err(PyTypeError::new_err(
+                "data should be an async file-like object or an async iterator of bytes",
+            ));
+        };
+        Ok(Self {
+            obj,
+            is_file,
+            locals,
+            size,
+            read_bytes: 0,
+            progress,
+        })
+    }
+
+    /// Await the result if it is awaitable, so that sync `read` and callbacks also work.
+    async fn resolve(&self, obj: PyObject) -> PyResult<PyObject> {
+        let fut = Python::with_gil(|py| {
+            let obj = obj.bind(py);
+            if obj.hasattr("__await__")? {
+                pyo3_async_runtimes::into_future_with_locals(&self.locals, obj.clone()).map(Some)
+            } else {
+                Ok(None)
+            }
+        })?;
+        match fut {
+            Some(fut) => fut.await,
+            None => Ok(obj),
+        }
+    }
+
+    /// Read the next chunk, `None` at the end of data.
+    async fn next_chunk(&mut self) -> PyResult<Option<Bytes>> {
+        let result = Python::with_gil(|py| {
+            let obj = self.obj.bind(py);
+            if self.is_file {
+                obj.call_method1("read", (READ_CHUNK_SIZE,))
+            } else {
+                obj.call_method0("__anext__")
+            }
+            .map(Bound::unbind)
+        })?;
+        let chunk = match self.resolve(result).await {
+            Ok(chunk) => chunk,
+            Err(e) if Python::with_gil(|py| e.is_instance_of::<PyStopAsyncIteration>(py)) => {
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        let chunk = Python::with_gil(|py| {
+            let chunk = chunk.bind(py);
+            match chunk.downcast::<PyBytes>() {
+                Ok(b) => Ok(Bytes::copy_from_slice(b.as_bytes())),
+                Err(_) => chunk.extract::<Vec<u8>>().map(Bytes::from),
+            }
+        })?;
+        if self.is_file && chunk.is_empty() {
+            return Ok(None);
+        }
+        self.read_bytes += chunk.len() as u64;
+        if let Some(progress) = &self.progress {
+            let result = Python::with_gil(|py| {
+                progress
+                    .call1(py, (self.read_bytes, self.size))
+                    .map(|r| r.into_any())
+            })?;
+            self.resolve(result).await?;
+        }
+        Ok(Some(chunk))
+    }
+
+    async fn pump(&mut self, tx: mpsc::Sender<std::io::Result<Bytes>>) -> PyResult<()> {
+        loop {
+            match self.next_chunk().await {
+                Ok(Some(chunk)) => {
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        // upload aborted, the error is returned by `load_data`
+                        return Ok(());
+                    }
+                }
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    async fn spool(&mut self, path: &Path) -> PyResult<u64> {
+        let mut file = tokio::fs::File::create(path).await?;
+        while let Some(chunk) = self.next_chunk().await? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        Ok(self.read_bytes)
+    }
 }
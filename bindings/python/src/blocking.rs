@@ -16,14 +16,18 @@ use std::collections::BTreeMap;
 use std::path::Path;
 use std::sync::Arc;
 
-use pyo3::exceptions::{PyAttributeError, PyException, PyStopIteration};
+use databend_driver::{QueryKind, QueryTracker, RowWithStats};
+use pyo3::exceptions::PyStopIteration;
 use pyo3::types::{PyList, PyTuple};
 use pyo3::{prelude::*, IntoPyObjectExt};
 use tokio::sync::Mutex;
 use tokio_stream::StreamExt;
 
-use crate::types::{ConnectionInfo, DriverError, Row, RowIterator, ServerStats, VERSION};
-use crate::utils::{to_sql_params, wait_for_future};
+use crate::pool::ConnHandle;
+use crate::types::{ConnectionInfo, DriverError, Row, RowIterator, Schema, ServerStats, VERSION};
+use crate::utils::{
+    collect_file_rows, exec_script, format_params_csv, to_local_url, to_sql_params, wait_for_future,
+};
 
 #[pyclass(module = "databend_driver")]
 pub struct BlockingDatabendClient(databend_driver::Client);
@@ -43,7 +47,9 @@ impl BlockingDatabendClient {
         let conn = wait_for_future(py, async move {
             this.get_conn().await.map_err(DriverError::new)
         })?;
-        Ok(BlockingDatabendConnection(Arc::new(conn)))
+        Ok(BlockingDatabendConnection(ConnHandle::Owned(Arc::new(
+            conn,
+        ))))
     }
 
     pub fn cursor(&self, py: Python) -> PyResult<BlockingDatabendCursor> {
@@ -56,18 +62,39 @@ impl BlockingDatabendClient {
 }
 
 #[pyclass(module = "databend_driver")]
-pub struct BlockingDatabendConnection(Arc<databend_driver::Connection>);
+pub struct BlockingDatabendConnection(ConnHandle);
+
+impl BlockingDatabendConnection {
+    pub(crate) fn pooled(conn: databend_driver::PooledConnection) -> Self {
+        Self(ConnHandle::pooled(conn))
+    }
+}
 
 #[pymethods]
 impl BlockingDatabendConnection {
+    /// Return the connection to the pool it is acquired from,
+    /// it should not be used anymore after released.
+    pub fn release(&self) {
+        self.0.release();
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&self, _args: &Bound<PyTuple>) {
+        self.0.release();
+    }
+
     pub fn info(&self, py: Python) -> PyResult<ConnectionInfo> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let ret = wait_for_future(py, async move { this.info().await });
         Ok(ConnectionInfo::new(ret))
     }
 
     pub fn version(&self, py: Python) -> PyResult<String> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let ret = wait_for_future(
             py,
             async move { this.version().await.map_err(DriverError::new) },
@@ -82,14 +109,15 @@ impl BlockingDatabendConnection {
         sql: String,
         params: Option<Bound<PyAny>>,
     ) -> PyResult<String> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
-        Ok(this.format_sql(&sql, params))
+        this.format_sql(&sql, params)
+            .map_err(|e| DriverError::new(e).into())
     }
 
     #[pyo3(signature = (sql, params=None))]
     pub fn exec(&self, py: Python, sql: String, params: Option<Bound<PyAny>>) -> PyResult<i64> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
         let ret = wait_for_future(py, async move {
             this.exec(&sql, params).await.map_err(DriverError::new)
@@ -97,6 +125,12 @@ impl BlockingDatabendConnection {
         Ok(ret)
     }
 
+    /// Split the script by `;` and execute the statements in order, returns the total affected rows.
+    pub fn exec_script(&self, py: Python, script: String) -> PyResult<i64> {
+        let this = self.0.get()?;
+        wait_for_future(py, async move { exec_script(&this, &script).await })
+    }
+
     #[pyo3(signature = (sql, params=None))]
     pub fn query_row(
         &self,
@@ -104,7 +138,7 @@ impl BlockingDatabendConnection {
         sql: String,
         params: Option<Bound<PyAny>>,
     ) -> PyResult<Option<Row>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
         let ret = wait_for_future(py, async move {
             this.query_row(&sql, params).await.map_err(DriverError::new)
@@ -119,7 +153,7 @@ impl BlockingDatabendConnection {
         sql: String,
         params: Option<Bound<PyAny>>,
     ) -> PyResult<Vec<Row>> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
         let rows = wait_for_future(py, async move {
             this.query_all(&sql, params).await.map_err(DriverError::new)
@@ -134,14 +168,58 @@ impl BlockingDatabendConnection {
         sql: String,
         params: Option<Bound<PyAny>>,
     ) -> PyResult<RowIterator> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let params = to_sql_params(params);
+        let queries = QueryTracker::default();
         let it = wait_for_future(py, async {
+            queries
+                .scope(this.query_iter(&sql, params))
+                .await
+                .map_err(DriverError::new)
+        })?;
+        Ok(RowIterator::new(it).with_query(this, queries.last_query_id()))
+    }
+
+    /// Only `INSERT` and `REPLACE` statements are supported.
+    /// Parameters are loaded in one batch as a CSV stage attachment, returns the rows written.
+    pub fn executemany(
+        &self,
+        py: Python,
+        sql: String,
+        seq_of_parameters: Vec<Bound<PyAny>>,
+    ) -> PyResult<u64> {
+        let this = self.0.get()?;
+        let bytes = match format_params_csv(seq_of_parameters)? {
+            Some(bytes) => bytes,
+            None => return Ok(0),
+        };
+        let size = bytes.len() as u64;
+        let reader = Box::new(std::io::Cursor::new(bytes));
+        let ss = wait_for_future(py, async move {
+            this.load_data(&sql, reader, size, None, None)
+                .await
+                .map_err(DriverError::new)
+        })?;
+        Ok(ss.write_rows as u64)
+    }
+
+    /// Schema of the query result, the query is not read to the end.
+    #[pyo3(signature = (sql, params=None))]
+    pub fn schema(
+        &self,
+        py: Python,
+        sql: String,
+        params: Option<Bound<PyAny>>,
+    ) -> PyResult<Schema> {
+        let this = self.0.get()?;
+        let params = to_sql_params(params);
+        let schema = wait_for_future(py, async {
             this.query_iter(&sql, params)
                 .await
+                .map(|it| it.schema())
                 .map_err(DriverError::new)
         })?;
-        Ok(RowIterator::new(it))
+        Ok(Schema::new(schema))
     }
 
     pub fn stream_load(
@@ -150,7 +228,7 @@ impl BlockingDatabendConnection {
         sql: String,
         data: Vec<Vec<String>>,
     ) -> PyResult<ServerStats> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let ret = wait_for_future(py, async move {
             let data = data
                 .iter()
@@ -170,26 +248,46 @@ impl BlockingDatabendConnection {
         format_options: Option<BTreeMap<String, String>>,
         copy_options: Option<BTreeMap<String, String>>,
     ) -> PyResult<ServerStats> {
-        let this = self.0.clone();
+        let this = self.0.get()?;
         let ret = wait_for_future(py, async move {
-            let format_options = match format_options {
-                None => None,
-                Some(ref opts) => {
-                    Some(opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect())
-                }
-            };
-            let copy_options = match copy_options {
-                None => None,
-                Some(ref opts) => {
-                    Some(opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect())
-                }
-            };
+            let format_options = format_options
+                .as_ref()
+                .map(|opts| opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect());
+            let copy_options = copy_options
+                .as_ref()
+                .map(|opts| opts.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect());
             this.load_file(&sql, Path::new(&fp), format_options, copy_options)
                 .await
                 .map_err(DriverError::new)
         })?;
         Ok(ServerStats::new(ret))
     }
+
+    /// Upload local files matching the pattern to stage, returns rows of file, status and size.
+    pub fn put(&self, py: Python, local_pattern: String, stage: String) -> PyResult<Vec<Row>> {
+        let this = self.0.get()?;
+        let local = to_local_url(&local_pattern)?;
+        wait_for_future(py, async move {
+            let it = this
+                .put_files(&local, &stage)
+                .await
+                .map_err(DriverError::new)?;
+            collect_file_rows(it).await
+        })
+    }
+
+    /// Download all files under the stage path to the local directory.
+    pub fn get(&self, py: Python, stage: String, local_dir: String) -> PyResult<Vec<Row>> {
+        let this = self.0.get()?;
+        let local = to_local_url(&local_dir)?;
+        wait_for_future(py, async move {
+            let it = this
+                .get_files(&stage, &local)
+                .await
+                .map_err(DriverError::new)?;
+            collect_file_rows(it).await
+        })
+    }
 }
 
 /// BlockingDatabendCursor is an object that follows PEP 249
@@ -200,6 +298,8 @@ pub struct BlockingDatabendCursor {
     rows: Option<Arc<Mutex<databend_driver::RowIterator>>>,
     // buffer is used to store only the first row after execute
     buffer: Vec<Row>,
+    // rows written by the last update statement
+    rowcount: i64,
 }
 
 impl BlockingDatabendCursor {
@@ -208,6 +308,7 @@ impl BlockingDatabendCursor {
             conn: Arc::new(conn),
             rows: None,
             buffer: Vec::new(),
+            rowcount: -1,
         }
     }
 }
@@ -216,6 +317,7 @@ impl BlockingDatabendCursor {
     fn reset(&mut self) {
         self.rows = None;
         self.buffer.clear();
+        self.rowcount = -1;
     }
 }
 
@@ -233,13 +335,13 @@ impl BlockingDatabendCursor {
                 let mut fields = vec![];
                 for field in schema.fields() {
                     let field = (
-                        field.name.clone(),          // name
-                        field.data_type.to_string(), // type_code
-                        None::<i64>,                 // display_size
-                        None::<i64>,                 // internal_size
-                        None::<i64>,                 // precision
-                        None::<i64>,                 // scale
-                        None::<bool>,                // null_ok
+                        field.name.clone(),                  // name
+                        field.data_type.to_string(),         // type_code
+                        None::<i64>,                         // display_size
+                        None::<i64>,                         // internal_size
+                        None::<i64>,                         // precision
+                        None::<i64>,                         // scale
+                        Some(field.data_type.is_nullable()), // null_ok
                     );
                     fields.push(field.into_pyobject(py)?);
                 }
@@ -248,10 +350,11 @@ impl BlockingDatabendCursor {
         }
     }
 
-    /// Not supported currently
+    /// Rows written by the last update statement like `INSERT` or `DELETE`,
+    /// -1 for queries and other statements.
     #[getter]
     pub fn rowcount(&self, _py: Python) -> i64 {
-        -1
+        self.rowcount
     }
 
     pub fn close(&mut self, py: Python) -> PyResult<()> {
@@ -281,14 +384,27 @@ impl BlockingDatabendCursor {
         // fetch first row after execute
         // then we could finish the query directly if there's no result
         let params = to_sql_params(params);
-        let (first, rows) = wait_for_future(py, async move {
-            let mut rows = conn.query_iter(&operation, params).await?;
-            let first = rows.next().await.transpose()?;
-            Ok::<_, databend_driver::Error>((first, rows))
+        let is_update = QueryKind::from(operation.as_str()) == QueryKind::Update;
+        let (first, written, rows) = wait_for_future(py, async move {
+            let mut rows = conn.query_iter_ext(&operation, params).await?;
+            // stats before the first row, all of them for statements without rows
+            let (mut first, mut written) = (None, 0);
+            while let Some(item) = rows.next().await.transpose()? {
+                match item {
+                    RowWithStats::Stats(stats) => written = stats.write_rows,
+                    RowWithStats::Row(row) => {
+                        first = Some(row);
+                        break;
+                    }
+                }
+            }
+            Ok::<_, databend_driver::Error>((first, written, rows.filter_rows().await))
         })
         .map_err(DriverError::new)?;
-        if let Some(first) = first {
-            self.buffer.push(Row::new(first));
+        match first {
+            Some(first) => self.buffer.push(Row::new(first)),
+            None if is_update => self.rowcount = written as i64,
+            None => {}
         }
         self.rows = Some(Arc::new(Mutex::new(rows)));
         Ok(py.None())
@@ -304,9 +420,8 @@ impl BlockingDatabendCursor {
     ) -> PyResult<PyObject> {
         self.reset();
         let conn = self.conn.clone();
-        if let Some(param) = seq_of_parameters.first() {
-            if param.downcast::<PyList>().is_ok() || param.downcast::<PyTuple>().is_ok() {
-                let bytes = format_csv(seq_of_parameters)?;
+        match format_params_csv(seq_of_parameters)? {
+            Some(bytes) => {
                 let size = bytes.len() as u64;
                 let reader = Box::new(std::io::Cursor::new(bytes));
                 let stats = wait_for_future(py, async move {
@@ -315,14 +430,10 @@ impl BlockingDatabendCursor {
                         .map_err(DriverError::new)
                 })?;
                 let result = stats.write_rows.into_pyobject(py)?;
-                return Ok(result.into());
-            } else {
-                return Err(PyAttributeError::new_err(
-                    "Invalid parameter type, expected list or tuple",
-                ));
+                Ok(result.into())
             }
+            None => Ok(py.None()),
         }
-        Ok(py.None())
     }
 
     pub fn fetchone(&mut self, py: Python) -> PyResult<Option<Row>> {
@@ -397,46 +508,3 @@ impl BlockingDatabendCursor {
         slf
     }
 }
-
-fn format_csv<'p>(parameters: Vec<Bound<'p, PyAny>>) -> PyResult<Vec<u8>> {
-    let mut wtr = csv::WriterBuilder::new().from_writer(vec![]);
-    for row in parameters {
-        let iter = row.try_iter()?;
-        let data = iter
-            .map(|v| match v {
-                Ok(v) => to_csv_field(v),
-                Err(e) => Err(e.into()),
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-        wtr.write_record(data)
-            .map_err(|e| PyException::new_err(e.to_string()))
-            .unwrap();
-    }
-    let bytes = wtr
-        .into_inner()
-        .map_err(|e| PyException::new_err(e.to_string()))
-        .unwrap();
-    Ok(bytes)
-}
-
-fn to_csv_field(v: Bound<PyAny>) -> PyResult<String> {
-    match v.downcast::<PyAny>() {
-        Ok(v) => {
-            if let Ok(v) = v.extract::<String>() {
-                Ok(v)
-            } else if let Ok(v) = v.extract::<bool>() {
-                Ok(v.to_string())
-            } else if let Ok(v) = v.extract::<i64>() {
-                Ok(v.to_string())
-            } else if let Ok(v) = v.extract::<f64>() {
-                Ok(v.to_string())
-            } else {
-                Err(PyAttributeError::new_err(format!(
-                    "Invalid parameter type for: {:?}, expected str, bool, int or float",
-                    v
-                )))
-            }
-        }
-        Err(e) => Err(e.into()),
-    }
-}
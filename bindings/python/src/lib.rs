@@ -14,6 +14,7 @@
 
 mod asyncio;
 mod blocking;
+mod pool;
 mod types;
 mod utils;
 
@@ -21,8 +22,18 @@ use pyo3::prelude::*;
 
 use crate::asyncio::{AsyncDatabendClient, AsyncDatabendConnection};
 use crate::blocking::{BlockingDatabendClient, BlockingDatabendConnection, BlockingDatabendCursor};
+use crate::pool::DatabendConnectionPool;
 use crate::types::{ConnectionInfo, Field, Row, RowIterator, Schema, ServerStats};
 
+/// Split sql text by `;` out of strings, quoted identifiers and comments, like `exec_script`.
+#[pyfunction]
+fn split_statements(script: &str) -> Vec<String> {
+    databend_driver::split_statements(script)
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
 #[pymodule]
 fn _databend_driver(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<AsyncDatabendClient>()?;
@@ -30,11 +41,13 @@ fn _databend_driver(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<BlockingDatabendClient>()?;
     m.add_class::<BlockingDatabendConnection>()?;
     m.add_class::<BlockingDatabendCursor>()?;
+    m.add_class::<DatabendConnectionPool>()?;
     m.add_class::<ConnectionInfo>()?;
     m.add_class::<Schema>()?;
     m.add_class::<Row>()?;
     m.add_class::<Field>()?;
     m.add_class::<RowIterator>()?;
     m.add_class::<ServerStats>()?;
+    m.add_function(wrap_pyfunction!(split_statements, m)?)?;
     Ok(())
 }
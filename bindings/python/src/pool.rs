@@ -0,0 +1,136 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use pyo3::exceptions::{PyRuntimeError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3_async_runtimes::tokio::future_into_py;
+
+use crate::asyncio::AsyncDatabendConnection;
+use crate::blocking::BlockingDatabendConnection;
+use crate::types::{DriverError, VERSION};
+use crate::utils::wait_for_future;
+
+/// The connection of a python connection, a pooled one is only held until released,
+/// so that no handle is kept after it goes back to the pool.
+pub(crate) enum ConnHandle {
+    Owned(Arc<databend_driver::Connection>),
+    Pooled(Mutex<Option<databend_driver::PooledConnection>>),
+}
+
+impl ConnHandle {
+    pub(crate) fn pooled(conn: databend_driver::PooledConnection) -> Self {
+        Self::Pooled(Mutex::new(Some(conn)))
+    }
+
+    pub(crate) fn get(&self) -> PyResult<Arc<databend_driver::Connection>> {
+        match self {
+            Self::Owned(conn) => Ok(conn.clone()),
+            Self::Pooled(slot) => slot
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|conn| conn.shared())
+                .ok_or_else(|| PyRuntimeError::new_err("connection is released to the pool")),
+        }
+    }
+
+    pub(crate) fn release(&self) {
+        if let Self::Pooled(slot) = self {
+            slot.lock().unwrap().take();
+        }
+    }
+}
+
+#[pyclass(module = "databend_driver")]
+pub struct DatabendConnectionPool(databend_driver::ConnectionPool);
+
+impl DatabendConnectionPool {
+    async fn get(
+        pool: databend_driver::ConnectionPool,
+        timeout: Option<f64>,
+    ) -> PyResult<databend_driver::PooledConnection> {
+        let conn = match timeout {
+            Some(timeout) => pool.get_timeout(Duration::from_secs_f64(timeout)).await,
+            None => pool.get().await,
+        };
+        Ok(conn.map_err(DriverError::new)?)
+    }
+}
+
+#[pymethods]
+impl DatabendConnectionPool {
+    #[new]
+    #[pyo3(signature = (dsn, max_size=10))]
+    pub fn new(dsn: String, max_size: usize) -> PyResult<Self> {
+        let name = format!("databend-driver-python/{}", VERSION.as_str());
+        let client = databend_driver::Client::new(dsn).with_name(name);
+        let pool =
+            databend_driver::ConnectionPool::new(client, max_size).map_err(DriverError::new)?;
+        Ok(Self(pool))
+    }
+
+    /// Wait for a connection in the current thread with GIL released.
+    #[pyo3(signature = (timeout=None))]
+    pub fn acquire(
+        &self,
+        py: Python,
+        timeout: Option<f64>,
+    ) -> PyResult<BlockingDatabendConnection> {
+        let pool = self.0.clone();
+        let conn = wait_for_future(py, Self::get(pool, timeout))?;
+        Ok(BlockingDatabendConnection::pooled(conn))
+    }
+
+    #[pyo3(signature = (timeout=None))]
+    pub fn acquire_async<'p>(
+        &self,
+        py: Python<'p>,
+        timeout: Option<f64>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let pool = self.0.clone();
+        future_into_py(py, async move {
+            let conn = Self::get(pool, timeout).await?;
+            Ok(AsyncDatabendConnection::pooled(conn))
+        })
+    }
+
+    pub fn release(&self, conn: Bound<PyAny>) -> PyResult<()> {
+        if let Ok(conn) = conn.downcast::<BlockingDatabendConnection>() {
+            conn.borrow().release();
+        } else if let Ok(conn) = conn.downcast::<AsyncDatabendConnection>() {
+            conn.borrow().release();
+        } else {
+            return Err(PyTypeError::new_err("not a databend connection"));
+        }
+        Ok(())
+    }
+
+    #[getter]
+    pub fn max_size(&self) -> usize {
+        self.0.max_size()
+    }
+
+    #[getter]
+    pub fn idle(&self) -> usize {
+        self.0.idle()
+    }
+
+    #[getter]
+    pub fn in_use(&self) -> usize {
+        self.0.in_use()
+    }
+}
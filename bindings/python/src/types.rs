@@ -12,11 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use chrono::{Duration, NaiveDate, NaiveDateTime};
 use once_cell::sync::Lazy;
-use pyo3::exceptions::{PyAttributeError, PyException, PyStopAsyncIteration, PyStopIteration};
+use pyo3::exceptions::{
+    PyAttributeError, PyException, PyIndexError, PyKeyError, PyStopAsyncIteration, PyStopIteration,
+};
 use pyo3::sync::GILOnceCell;
 use pyo3::types::{PyBytes, PyDict, PyList, PyTuple, PyType};
 use pyo3::{intern, IntoPyObjectExt};
@@ -25,7 +28,7 @@ use pyo3_async_runtimes::tokio::future_into_py;
 use tokio::sync::Mutex;
 use tokio_stream::StreamExt;
 
-use crate::utils::wait_for_future;
+use crate::utils::{wait_for_future, CancelGuard};
 
 pub static VERSION: Lazy<String> = Lazy::new(|| {
     let version = option_env!("CARGO_PKG_VERSION").unwrap_or("unknown");
@@ -34,7 +37,7 @@ pub static VERSION: Lazy<String> = Lazy::new(|| {
 
 pub static DECIMAL_CLS: GILOnceCell<Py<PyType>> = GILOnceCell::new();
 
-fn get_decimal_cls(py: Python<'_>) -> PyResult<&Bound<PyType>> {
+fn get_decimal_cls(py: Python<'_>) -> PyResult<&Bound<'_, PyType>> {
     DECIMAL_CLS
         .get_or_try_init(py, || {
             py.import(intern!(py, "decimal"))?
@@ -44,6 +47,18 @@ fn get_decimal_cls(py: Python<'_>) -> PyResult<&Bound<PyType>> {
         .map(|ty| ty.bind(py))
 }
 
+pub static JSON_LOADS: GILOnceCell<PyObject> = GILOnceCell::new();
+
+/// Parse json with `json.loads`, so that variants are converted to native python objects.
+fn json_loads<'py>(py: Python<'py>, s: &str) -> PyResult<Bound<'py, PyAny>> {
+    let loads = JSON_LOADS.get_or_try_init(py, || {
+        py.import(intern!(py, "json"))?
+            .getattr(intern!(py, "loads"))
+            .map(Bound::unbind)
+    })?;
+    loads.bind(py).call1((s,))
+}
+
 pub struct Value(databend_driver::Value);
 
 impl<'py> IntoPyObject<'py> for Value {
@@ -84,7 +99,7 @@ impl<'py> IntoPyObject<'py> for Value {
                 d.into_bound_py_any(py)?
             }
             databend_driver::Value::Array(inner) => {
-                let list = PyList::new(py, inner.into_iter().map(|v| Value(v)))?;
+                let list = PyList::new(py, inner.into_iter().map(Value))?;
                 list.into_bound_py_any(py)?
             }
             databend_driver::Value::Map(inner) => {
@@ -95,11 +110,11 @@ impl<'py> IntoPyObject<'py> for Value {
                 dict.into_bound_py_any(py)?
             }
             databend_driver::Value::Tuple(inner) => {
-                let tuple = PyTuple::new(py, inner.into_iter().map(|v| Value(v)))?;
+                let tuple = PyTuple::new(py, inner.into_iter().map(Value))?;
                 tuple.into_bound_py_any(py)?
             }
             databend_driver::Value::Bitmap(s) => s.into_bound_py_any(py)?,
-            databend_driver::Value::Variant(s) => s.into_bound_py_any(py)?,
+            databend_driver::Value::Variant(s) => json_loads(py, &s)?,
             databend_driver::Value::Geometry(s) => s.into_bound_py_any(py)?,
             databend_driver::Value::Geography(s) => s.into_bound_py_any(py)?,
             databend_driver::Value::Interval(s) => {
@@ -135,18 +150,10 @@ impl<'py> IntoPyObject<'py> for NumberValue {
             databend_driver::NumberValue::Float32(i) => i.into_bound_py_any(py)?,
             databend_driver::NumberValue::Float64(i) => i.into_bound_py_any(py)?,
             databend_driver::NumberValue::Decimal128(_, _) => {
-                let dec_cls = get_decimal_cls(py).expect("failed to load decimal.Decimal");
-                let ret = dec_cls
-                    .call1((self.0.to_string(),))
-                    .expect("failed to call decimal.Decimal(value)");
-                ret.into_bound()
+                get_decimal_cls(py)?.call1((self.0.to_string(),))?
             }
             databend_driver::NumberValue::Decimal256(_, _) => {
-                let dec_cls = get_decimal_cls(py).expect("failed to load decimal.Decimal");
-                let ret = dec_cls
-                    .call1((self.0.to_string(),))
-                    .expect("failed to call decimal.Decimal(value)");
-                ret.into_bound()
+                get_decimal_cls(py)?.call1((self.0.to_string(),))?
             }
         };
         Ok(val)
@@ -165,6 +172,19 @@ impl Row {
     }
 }
 
+/// Values of a row converted to a tuple directly, without a `Row` object.
+pub struct RowValues(databend_driver::Row);
+
+impl<'py> IntoPyObject<'py> for RowValues {
+    type Target = PyTuple;
+    type Output = Bound<'py, Self::Target>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        PyTuple::new(py, self.0.into_iter().map(Value))
+    }
+}
+
 #[pymethods]
 impl Row {
     pub fn values<'p>(&'p self, py: Python<'p>) -> PyResult<Bound<'p, PyTuple>> {
@@ -177,6 +197,39 @@ impl Row {
         self.inner.len()
     }
 
+    /// Column names, so that `dict(row)` works.
+    pub fn keys(&self) -> Vec<String> {
+        let schema = self.inner.schema();
+        schema.fields().iter().map(|f| f.name.clone()).collect()
+    }
+
+    pub fn items<'p>(&'p self, py: Python<'p>) -> PyResult<Bound<'p, PyList>> {
+        let schema = self.inner.schema();
+        let items = schema
+            .fields()
+            .iter()
+            .zip(self.inner.values())
+            .map(|(field, value)| (field.name.clone(), Value(value.clone())));
+        PyList::new(py, items)
+    }
+
+    #[pyo3(signature = (key, default=None))]
+    pub fn get<'p>(
+        &'p self,
+        py: Python<'p>,
+        key: &str,
+        default: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        match self.get_by_field(key) {
+            Ok(value) => value.into_py_any(py),
+            Err(_) => Ok(default.unwrap_or_else(|| py.None())),
+        }
+    }
+
+    pub fn __contains__(&self, key: &str) -> bool {
+        self.inner.schema().fields().iter().any(|f| f.name == key)
+    }
+
     pub fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
         slf
     }
@@ -184,7 +237,7 @@ impl Row {
         if self.idx >= self.inner.len() {
             return Err(PyStopIteration::new_err("Columns exhausted"));
         }
-        let val = self.get_by_index(self.idx)?;
+        let val = self.get_by_index(self.idx as isize)?;
         self.idx += 1;
         Ok(val)
     }
@@ -198,8 +251,16 @@ impl Row {
         Ok(dict.into_bound())
     }
 
-    fn get_by_index(&self, idx: usize) -> PyResult<Value> {
-        Ok(Value(self.inner.values()[idx].clone()))
+    fn get_by_index(&self, idx: isize) -> PyResult<Value> {
+        let len = self.inner.len() as isize;
+        let pos = if idx < 0 { idx + len } else { idx };
+        if pos < 0 || pos >= len {
+            return Err(PyIndexError::new_err(format!(
+                "index {} out of range for row with {} columns",
+                idx, len
+            )));
+        }
+        Ok(Value(self.inner.values()[pos as usize].clone()))
     }
 
     fn get_by_field(&self, field: &str) -> PyResult<Value> {
@@ -208,14 +269,12 @@ impl Row {
             .fields()
             .iter()
             .position(|f| f.name == field)
-            .ok_or_else(|| {
-                PyException::new_err(format!("field '{}' not found in schema", field))
-            })?;
+            .ok_or_else(|| PyKeyError::new_err(format!("field '{}' not found in schema", field)))?;
         Ok(Value(self.inner.values()[idx].clone()))
     }
 
     pub fn __getitem__<'p>(&'p self, key: Bound<'p, PyAny>) -> PyResult<Value> {
-        if let Ok(idx) = key.extract::<usize>() {
+        if let Ok(idx) = key.extract::<isize>() {
             self.get_by_index(idx)
         } else if let Ok(field) = key.extract::<String>() {
             self.get_by_field(&field)
@@ -228,34 +287,139 @@ impl Row {
 }
 
 #[pyclass(module = "databend_driver")]
-pub struct RowIterator(Arc<Mutex<databend_driver::RowIterator>>);
+pub struct RowIterator {
+    streamer: Arc<Mutex<databend_driver::RowIterator>>,
+    // the query to kill on `close()` or cancellation before rows are exhausted
+    query: Option<(Arc<databend_driver::Connection>, String)>,
+    finished: Arc<AtomicBool>,
+}
 
 impl RowIterator {
     pub fn new(streamer: databend_driver::RowIterator) -> Self {
-        RowIterator(Arc::new(Mutex::new(streamer)))
+        RowIterator {
+            streamer: Arc::new(Mutex::new(streamer)),
+            query: None,
+            finished: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Attach the query of the rows, to kill it on `close()`.
+    pub fn with_query(
+        mut self,
+        conn: Arc<databend_driver::Connection>,
+        query_id: Option<String>,
+    ) -> Self {
+        self.query = query_id.map(|query_id| (conn, query_id));
+        self
+    }
+
+    fn guard(&self) -> Option<CancelGuard> {
+        self.query
+            .clone()
+            .map(|(conn, query_id)| CancelGuard::with_query_id(conn, query_id))
+    }
+
+    /// Read up to `n` rows without touching python objects, so the GIL is not needed.
+    async fn next_batch(
+        streamer: Arc<Mutex<databend_driver::RowIterator>>,
+        finished: Arc<AtomicBool>,
+        n: usize,
+    ) -> PyResult<Vec<RowValues>> {
+        let mut rows = Vec::with_capacity(n);
+        if finished.load(Ordering::SeqCst) {
+            return Ok(rows);
+        }
+        let mut streamer = streamer.lock().await;
+        while rows.len() < n {
+            match streamer.next().await {
+                Some(Ok(row)) => rows.push(RowValues(row)),
+                Some(Err(e)) => return Err(PyException::new_err(format!("{}", e))),
+                None => {
+                    finished.store(true, Ordering::SeqCst);
+                    break;
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    async fn kill(
+        query: Option<(Arc<databend_driver::Connection>, String)>,
+        finished: Arc<AtomicBool>,
+    ) -> PyResult<()> {
+        if finished.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        if let Some((conn, query_id)) = query {
+            conn.kill_query(&query_id)
+                .await
+                .map_err(|e| PyException::new_err(format!("{}", e)))?;
+        }
+        Ok(())
     }
 }
 
 #[pymethods]
 impl RowIterator {
     pub fn schema(&self, py: Python) -> PyResult<Schema> {
-        let streamer = self.0.clone();
+        let streamer = self.streamer.clone();
         let ret = wait_for_future(py, async move { streamer.lock().await.schema() });
         Ok(Schema::new(ret))
     }
 
+    /// Fetch up to `n` rows as a list of tuples, converted at once after all rows are read,
+    /// an empty list is returned when rows are exhausted.
+    pub fn fetch_batch(&self, py: Python, n: usize) -> PyResult<Vec<RowValues>> {
+        let streamer = self.streamer.clone();
+        let finished = self.finished.clone();
+        wait_for_future(py, Self::next_batch(streamer, finished, n))
+    }
+
+    pub fn afetch_batch<'p>(&'p self, py: Python<'p>, n: usize) -> PyResult<Bound<'p, PyAny>> {
+        let streamer = self.streamer.clone();
+        let finished = self.finished.clone();
+        let guard = self.guard();
+        future_into_py(py, async move {
+            let rows = Self::next_batch(streamer, finished, n).await;
+            if let Some(guard) = guard {
+                guard.disarm();
+            }
+            rows
+        })
+    }
+
+    /// Stop reading rows and kill the query if it is still running.
+    pub fn close(&self, py: Python) -> PyResult<()> {
+        let query = self.query.clone();
+        let finished = self.finished.clone();
+        wait_for_future(py, Self::kill(query, finished))
+    }
+
+    pub fn aclose<'p>(&'p self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+        let query = self.query.clone();
+        let finished = self.finished.clone();
+        future_into_py(py, Self::kill(query, finished))
+    }
+
     fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
         slf
     }
     fn __next__(&self, py: Python) -> PyResult<Row> {
-        let streamer = self.0.clone();
+        let streamer = self.streamer.clone();
+        let finished = self.finished.clone();
         wait_for_future(py, async move {
+            if finished.load(Ordering::SeqCst) {
+                return Err(PyStopIteration::new_err("Rows exhausted"));
+            }
             match streamer.lock().await.next().await {
                 Some(val) => match val {
                     Err(e) => Err(PyException::new_err(format!("{}", e))),
                     Ok(ret) => Ok(Row::new(ret)),
                 },
-                None => Err(PyStopIteration::new_err("Rows exhausted")),
+                None => {
+                    finished.store(true, Ordering::SeqCst);
+                    Err(PyStopIteration::new_err("Rows exhausted"))
+                }
             }
         })
     }
@@ -264,14 +428,26 @@ impl RowIterator {
         slf
     }
     fn __anext__<'p>(&'p self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
-        let streamer = self.0.clone();
+        let streamer = self.streamer.clone();
+        let finished = self.finished.clone();
+        let guard = self.guard();
         future_into_py(py, async move {
-            match streamer.lock().await.next().await {
+            if finished.load(Ordering::SeqCst) {
+                return Err(PyStopAsyncIteration::new_err("The iterator is exhausted"));
+            }
+            let next = streamer.lock().await.next().await;
+            if let Some(guard) = guard {
+                guard.disarm();
+            }
+            match next {
                 Some(val) => match val {
                     Err(e) => Err(PyException::new_err(format!("{}", e))),
                     Ok(ret) => Ok(Row::new(ret)),
                 },
-                None => Err(PyStopAsyncIteration::new_err("The iterator is exhausted")),
+                None => {
+                    finished.store(true, Ordering::SeqCst);
+                    Err(PyStopAsyncIteration::new_err("The iterator is exhausted"))
+                }
             }
         })
     }
@@ -309,6 +485,10 @@ impl Field {
     pub fn data_type(&self) -> String {
         self.0.data_type.to_string()
     }
+    #[getter]
+    pub fn nullable(&self) -> bool {
+        self.0.data_type.is_nullable()
+    }
 }
 
 #[pyclass(module = "databend_driver")]
@@ -391,6 +571,10 @@ impl ServerStats {
     pub fn running_time_ms(&self) -> f64 {
         self.0.running_time_ms
     }
+    #[getter]
+    pub fn rejected_rows(&self) -> usize {
+        self.0.rejected_rows
+    }
 }
 
 pub struct DriverError(databend_driver::Error);
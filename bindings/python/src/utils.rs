@@ -13,15 +13,23 @@
 // limitations under the License.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use databend_driver::BatchPolicy;
+use databend_driver::Connection;
 use databend_driver::Param;
 use databend_driver::Params;
-use pyo3::exceptions::PyAttributeError;
+use databend_driver::QueryTracker;
+use databend_driver::RowStatsIterator;
+use pyo3::exceptions::{PyAttributeError, PyException};
 use pyo3::types::PyTuple;
 use pyo3::{
     prelude::*,
     types::{PyDict, PyList},
 };
+use tokio_stream::StreamExt;
+
+use crate::types::{DriverError, Row};
 
 #[ctor::ctor]
 pub(crate) static RUNTIME: tokio::runtime::Runtime = tokio::runtime::Builder::new_multi_thread()
@@ -38,6 +46,70 @@ where
     py.allow_threads(|| RUNTIME.block_on(f))
 }
 
+/// Kill the queries started by [`CancelGuard::run`] if it is dropped before `disarm`,
+/// which happens when the awaiting python task is cancelled.
+pub(crate) struct CancelGuard {
+    conn: Arc<Connection>,
+    queries: QueryTracker,
+    // the query of a row iterator, started before the guard
+    query_id: Option<String>,
+    armed: bool,
+}
+
+impl CancelGuard {
+    pub(crate) fn new(conn: Arc<Connection>) -> Self {
+        Self {
+            conn,
+            queries: QueryTracker::default(),
+            query_id: None,
+            armed: true,
+        }
+    }
+
+    /// Guard a query already started, e.g. the one of a row iterator.
+    pub(crate) fn with_query_id(conn: Arc<Connection>, query_id: String) -> Self {
+        Self {
+            conn,
+            queries: QueryTracker::default(),
+            query_id: Some(query_id),
+            armed: true,
+        }
+    }
+
+    /// Run `f`, tracking the queries started by it to kill on cancellation.
+    pub(crate) async fn run<F: std::future::Future>(&self, f: F) -> F::Output {
+        self.queries.scope(f).await
+    }
+
+    /// The last query started by [`CancelGuard::run`].
+    pub(crate) fn last_query_id(&self) -> Option<String> {
+        self.queries.last_query_id()
+    }
+
+    pub(crate) fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let mut query_ids = self.queries.query_ids();
+        query_ids.extend(self.query_id.take());
+        if query_ids.is_empty() {
+            return;
+        }
+        let conn = self.conn.clone();
+        pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+            for query_id in query_ids {
+                let _ = conn.kill_query(&query_id).await;
+            }
+        });
+    }
+}
+
 //  params: Option<Bound<'p, PyAny>>
 pub(crate) fn to_sql_params(v: Option<Bound<PyAny>>) -> Params {
     match v {
@@ -93,3 +165,92 @@ fn to_sql_string(v: Bound<PyAny>) -> PyResult<String> {
         Err(e) => Err(e.into()),
     }
 }
+
+/// Format rows of parameters for `executemany` as CSV, `None` if there are no rows.
+pub(crate) fn format_params_csv(parameters: Vec<Bound<PyAny>>) -> PyResult<Option<Vec<u8>>> {
+    match parameters.first() {
+        None => Ok(None),
+        Some(param)
+            if param.downcast::<PyList>().is_ok() || param.downcast::<PyTuple>().is_ok() =>
+        {
+            format_csv(parameters).map(Some)
+        }
+        Some(_) => Err(PyAttributeError::new_err(
+            "Invalid parameter type, expected list or tuple",
+        )),
+    }
+}
+
+fn format_csv(parameters: Vec<Bound<PyAny>>) -> PyResult<Vec<u8>> {
+    let mut wtr = csv::WriterBuilder::new().from_writer(vec![]);
+    for row in parameters {
+        let iter = row.try_iter()?;
+        let data = iter
+            .map(|v| match v {
+                Ok(v) => to_csv_field(v),
+                Err(e) => Err(e),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        wtr.write_record(data)
+            .map_err(|e| PyException::new_err(e.to_string()))?;
+    }
+    let bytes = wtr
+        .into_inner()
+        .map_err(|e| PyException::new_err(e.to_string()))?;
+    Ok(bytes)
+}
+
+fn to_csv_field(v: Bound<PyAny>) -> PyResult<String> {
+    match v.downcast::<PyAny>() {
+        Ok(v) => {
+            if let Ok(v) = v.extract::<String>() {
+                Ok(v)
+            } else if let Ok(v) = v.extract::<bool>() {
+                Ok(v.to_string())
+            } else if let Ok(v) = v.extract::<i64>() {
+                Ok(v.to_string())
+            } else if let Ok(v) = v.extract::<f64>() {
+                Ok(v.to_string())
+            } else {
+                Err(PyAttributeError::new_err(format!(
+                    "Invalid parameter type for: {:?}, expected str, bool, int or float",
+                    v
+                )))
+            }
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Local paths are passed to the driver as `fs://` urls, relative ones are resolved from the current directory.
+pub(crate) fn to_local_url(path: &str) -> PyResult<String> {
+    if path.starts_with("fs://") {
+        return Ok(path.to_string());
+    }
+    let path = std::path::absolute(path)?;
+    Ok(format!("fs://{}", path.display()))
+}
+
+/// Collect the result rows of `PUT` and `GET`: file, status and size.
+pub(crate) async fn collect_file_rows(it: RowStatsIterator) -> PyResult<Vec<Row>> {
+    let mut it = it.filter_rows().await;
+    let mut rows = vec![];
+    while let Some(row) = it.next().await {
+        rows.push(Row::new(row.map_err(DriverError::new)?));
+    }
+    Ok(rows)
+}
+
+/// Execute the statements of the script one by one, stopping at the first failed one,
+/// returns the total affected rows.
+pub(crate) async fn exec_script(conn: &Connection, script: &str) -> PyResult<i64> {
+    let summaries = conn
+        .exec_script(script, BatchPolicy::FailFast)
+        .await
+        .map_err(DriverError::new)?;
+    let mut rows = 0;
+    for summary in summaries {
+        rows += summary.result.map_err(DriverError::new)?;
+    }
+    Ok(rows)
+}
@@ -15,7 +15,8 @@
 use std::collections::BTreeMap;
 
 use anyhow::{anyhow, Result};
-use databend_client::SensitiveString;
+use databend_client::{APIClient, SensitiveString};
+use databend_driver::Client;
 use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -26,10 +27,41 @@ pub struct ConnectionArgs {
     pub password: SensitiveString,
     pub database: Option<String>,
     pub flight: bool,
+    /// `databend+auto`, trying FlightSQL first and falling back to RestAPI.
+    pub auto: bool,
     pub args: BTreeMap<String, String>,
 }
 
 impl ConnectionArgs {
+    /// Client of the driver, with typed options for the RestAPI,
+    /// FlightSQL is only configured with a DSN.
+    pub fn client(self) -> Result<Client> {
+        if self.flight || self.auto {
+            return Ok(Client::new(self.get_dsn()?));
+        }
+        let mut builder = APIClient::builder()
+            .host(self.host)
+            .user(self.user)
+            .password(self.password.inner());
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+        if let Some(database) = self.database.filter(|d| !d.is_empty()) {
+            builder = builder.database(database);
+        }
+        for (k, v) in self.args {
+            builder = builder.arg(k, v);
+        }
+        Ok(Client::from_builder(builder))
+    }
+
+    /// Host, port and whether tls is enabled, the same defaults as the driver.
+    pub fn endpoint(&self) -> (String, u16, bool) {
+        let tls = self.args.get("sslmode").map(|v| v.as_str()) != Some("disable");
+        let port = self.port.unwrap_or(if tls { 443 } else { 80 });
+        (self.host.clone(), port, tls)
+    }
+
     pub fn get_dsn(self) -> Result<String> {
         let mut dsn = url::Url::parse("databend://")?;
         dsn.set_host(Some(&self.host))?;
@@ -42,6 +74,8 @@ impl ConnectionArgs {
         }
         if self.flight {
             _ = dsn.set_scheme("databend+flight");
+        } else if self.auto {
+            _ = dsn.set_scheme("databend+auto");
         }
         let mut query = url::form_urlencoded::Serializer::new(String::new());
         if !self.args.is_empty() {
@@ -62,13 +96,17 @@ impl ConnectionArgs {
             }
         }
         let flight = matches!(u.scheme(), "databend+flight");
+        let auto = matches!(u.scheme(), "databend+auto");
         let host = u.host_str().ok_or(anyhow!("missing host"))?.to_string();
         let port = u.port();
-        let user = u.username().to_string();
+        let user = percent_decode_str(u.username()).decode_utf8()?.to_string();
         let password = u.password().unwrap_or_default();
         let password = percent_decode_str(password).decode_utf8()?.to_string();
         let password = SensitiveString::from(password);
-        let database = u.path().strip_prefix('/').map(|s| s.to_string());
+        let database = u.path().strip_prefix('/').filter(|s| !s.is_empty());
+        let database = database
+            .map(|s| percent_decode_str(s).decode_utf8().map(|s| s.to_string()))
+            .transpose()?;
         Ok(Self {
             host,
             port,
@@ -76,6 +114,7 @@ impl ConnectionArgs {
             password,
             database,
             flight,
+            auto,
             args,
         })
     }
@@ -98,6 +137,7 @@ mod test {
             password: SensitiveString::from(password_str),
             database: Some("test".to_string()),
             flight: false,
+            auto: false,
             args: {
                 let mut args = BTreeMap::new();
                 args.insert("wait_time_secs".to_string(), "10".to_string());
@@ -109,6 +149,15 @@ mod test {
             },
         };
         assert_eq!(args, expected);
+
+        let args =
+            ConnectionArgs::from_dsn("databend+auto://root:@localhost:8000/?sslmode=disable")?;
+        assert!(args.auto && !args.flight);
+        assert!(args.get_dsn()?.starts_with("databend+auto://"));
+
+        let args = ConnectionArgs::from_dsn("databend://a%40b:p@localhost/sales%20data")?;
+        assert_eq!(args.user, "a@b");
+        assert_eq!(args.database.as_deref(), Some("sales data"));
         Ok(())
     }
 
@@ -122,6 +171,7 @@ mod test {
             password: SensitiveString::from(password_str),
             database: Some("test".to_string()),
             flight: false,
+            auto: false,
             args: {
                 let mut args = BTreeMap::new();
                 args.insert("wait_time_secs".to_string(), "10".to_string());
@@ -0,0 +1,65 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_common_ast::ast::{SetExpr, Statement};
+use databend_common_ast::parser::token::TokenKind;
+use databend_common_ast::parser::{parse_sql, tokenize_sql, Dialect};
+
+/// Append `LIMIT n` to a plain SELECT without limit, for `auto_limit`,
+/// returns `None` if the query is kept as is.
+pub fn auto_limit(query: &str, limit: usize) -> Option<String> {
+    let tokens = tokenize_sql(query).ok()?;
+    let (stmt, _) = parse_sql(&tokens, Dialect::PostgreSQL).ok()?;
+    let Statement::Query(query_stmt) = stmt else {
+        return None;
+    };
+    if !matches!(query_stmt.body, SetExpr::Select(_))
+        || !query_stmt.limit.is_empty()
+        || query_stmt.offset.is_some()
+        || query_stmt.ignore_result
+    {
+        return None;
+    }
+    // before trailing comments
+    let end = tokens
+        .iter()
+        .rev()
+        .find(|t| t.kind != TokenKind::EOI)?
+        .span
+        .end as usize;
+    let mut sql = query.to_string();
+    sql.insert_str(end, &format!(" LIMIT {}", limit));
+    Some(sql)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_auto_limit() {
+        assert_eq!(
+            auto_limit("SELECT * FROM t WHERE a > 1 ORDER BY a -- recent", 100),
+            Some("SELECT * FROM t WHERE a > 1 ORDER BY a LIMIT 100 -- recent".to_string())
+        );
+        assert_eq!(
+            auto_limit("WITH c AS (SELECT * FROM t LIMIT 5) SELECT * FROM c", 10),
+            Some("WITH c AS (SELECT * FROM t LIMIT 5) SELECT * FROM c LIMIT 10".to_string())
+        );
+        assert_eq!(auto_limit("SELECT * FROM t LIMIT 5", 10), None);
+        assert_eq!(auto_limit("SELECT 1 UNION ALL SELECT 2", 10), None);
+        assert_eq!(auto_limit("SHOW TABLES", 10), None);
+        assert_eq!(auto_limit("INSERT INTO t SELECT * FROM t2", 10), None);
+    }
+}
@@ -12,43 +12,122 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-mod query_kind;
-pub use query_kind::replace_newline_in_box_display;
-pub use query_kind::GenType;
-pub use query_kind::QueryKind;
+mod limit;
+mod time_travel;
+pub use databend_client_sqlparse::destructive_stmt;
+pub use databend_client_sqlparse::replace_newline_in_box_display;
+pub use databend_client_sqlparse::split_format_clause;
+pub use databend_client_sqlparse::DestructiveStmt;
+pub use databend_client_sqlparse::GenType;
+pub use databend_client_sqlparse::QueryKind;
+pub use limit::auto_limit;
+pub use time_travel::at_timestamp;
 
-use databend_common_ast::parser::{parse_sql, token::TokenKind, tokenize_sql, Dialect};
-use sqlformat::{FormatOptions, QueryParams};
+use databend_common_ast::parser::token::{Token, TokenKind};
+use databend_common_ast::parser::{parse_sql, tokenize_sql, Dialect};
+use sqlformat::{FormatOptions, Indent, QueryParams};
+
+/// Options of formatting sql, for `bendsql fmt` and `!fmt`.
+#[derive(Debug, Clone)]
+pub struct FormatConfig {
+    pub indent: u8,
+    pub uppercase: bool,
+    /// keep statements in one line if they fit in the width, 0 to always break lines
+    pub width: usize,
+    pub dialect: Dialect,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            uppercase: false,
+            width: 0,
+            dialect: Dialect::Experimental,
+        }
+    }
+}
 
 pub fn format_query(query: &str) -> String {
+    format_query_with(query, &FormatConfig::default())
+}
+
+pub fn format_query_with(query: &str, config: &FormatConfig) -> String {
     let kind = QueryKind::from(query);
     if matches!(kind, QueryKind::Get(_, _) | QueryKind::Put(_, _)) {
         return query.to_owned();
     }
 
     if let Ok(tokens) = databend_common_ast::parser::tokenize_sql(query) {
-        if let Ok((stmt, _)) = parse_sql(&tokens, Dialect::Experimental) {
-            let options = FormatOptions::default();
+        if let Ok((stmt, _)) = parse_sql(&tokens, config.dialect) {
+            if config.width > 0 {
+                let compact = compact_query(query, &tokens, config.uppercase);
+                if compact.len() <= config.width {
+                    return compact;
+                }
+            }
+            let compact = stmt.to_string();
+            let options = FormatOptions {
+                indent: Indent::Spaces(config.indent),
+                uppercase: config.uppercase.then_some(true),
+                ..Default::default()
+            };
 
             let pretty_sql = sqlformat::format(query, &QueryParams::None, &options);
             // if pretty sql could be parsed into same stmt, return pretty sql
             if let Ok(pretty_tokens) = databend_common_ast::parser::tokenize_sql(&pretty_sql) {
-                if let Ok((pretty_stmt, _)) = parse_sql(&pretty_tokens, Dialect::Experimental) {
-                    if stmt.to_string() == pretty_stmt.to_string() {
+                if let Ok((pretty_stmt, _)) = parse_sql(&pretty_tokens, config.dialect) {
+                    if compact == pretty_stmt.to_string() {
                         return pretty_sql;
                     }
                 }
             }
-
-            return stmt.to_string();
         }
     }
     query.to_string()
 }
 
+/// The query in one line with the original text of tokens and comments,
+/// only whitespace between tokens is collapsed.
+fn compact_query(query: &str, tokens: &[Token], uppercase: bool) -> String {
+    let mut output = String::new();
+    let mut last = 0;
+    for token in tokens {
+        let span = std::ops::Range::from(token.span);
+        let gap = query[last..span.start].trim();
+        if !gap.is_empty() {
+            if !output.is_empty() {
+                output.push(' ');
+            }
+            output.push_str(gap);
+            // a line comment ends the line
+            let line_comment = gap.lines().last().is_some_and(|line| line.contains("--"));
+            output.push(if line_comment { '\n' } else { ' ' });
+        } else if span.start > last && !output.is_empty() && !output.ends_with('\n') {
+            output.push(' ');
+        }
+        if uppercase && TokenKind::is_keyword(&token.kind) {
+            output.push_str(&token.text().to_uppercase());
+        } else {
+            output.push_str(token.text());
+        }
+        last = span.end;
+    }
+    let rest = query[last..].trim();
+    if !rest.is_empty() {
+        output.push(' ');
+        output.push_str(rest);
+        if rest.lines().last().is_some_and(|line| line.contains("--")) {
+            output.push('\n');
+        }
+    }
+    output
+}
+
 pub fn highlight_query(line: &str) -> String {
     let tokens = tokenize_sql(line);
     let mut line = line.to_owned();
+    let theme = crate::theme::current();
 
     if let Ok(tokens) = tokens {
         for token in tokens.iter().rev() {
@@ -58,12 +137,12 @@ pub fn highlight_query(line: &str) -> String {
             {
                 line.replace_range(
                     std::ops::Range::from(token.span),
-                    &format!("\x1b[1;32m{}\x1b[0m", token.text()),
+                    &theme.keyword(token.text()),
                 );
             } else if TokenKind::is_literal(&token.kind) {
                 line.replace_range(
                     std::ops::Range::from(token.span),
-                    &format!("\x1b[1;33m{}\x1b[0m", token.text()),
+                    &theme.literal(token.text()),
                 );
             }
         }
@@ -0,0 +1,118 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_common_ast::ast::{Statement, TableReference, CTE};
+use databend_common_ast::parser::{parse_sql, tokenize_sql, Dialect};
+use derive_visitor::{Drive, Visitor};
+
+// tables without time travel
+const SYSTEM_DATABASES: [&str; 2] = ["system", "information_schema"];
+
+#[derive(Visitor, Default)]
+#[visitor(CTE(enter), TableReference(enter))]
+struct TableVisitor {
+    ctes: Vec<String>,
+    // table name and the position to insert the AT clause
+    tables: Vec<(String, usize)>,
+}
+
+impl TableVisitor {
+    fn enter_cte(&mut self, cte: &CTE) {
+        self.ctes.push(cte.alias.name.name.to_lowercase());
+    }
+
+    fn enter_table_reference(&mut self, table: &TableReference) {
+        if let TableReference::Table {
+            table,
+            temporal: None,
+            ..
+        } = table
+        {
+            if let Some(database) = &table.database {
+                if SYSTEM_DATABASES.contains(&database.name.to_lowercase().as_str()) {
+                    return;
+                }
+            }
+            let last = table.branch.as_ref().unwrap_or(&table.table);
+            if let Some(span) = last.span {
+                self.tables
+                    .push((table.table.name.to_lowercase(), span.end as usize));
+            }
+        }
+    }
+}
+
+/// Rewrite a SELECT to read the tables at the timestamp, for `!at`.
+/// Tables with time travel already, CTEs and system tables are kept as is,
+/// returns `None` if it is not a query.
+pub fn at_timestamp(query: &str, timestamp: &str) -> Option<String> {
+    let tokens = tokenize_sql(query).ok()?;
+    let (stmt, _) = parse_sql(&tokens, Dialect::PostgreSQL).ok()?;
+    let Statement::Query(query_stmt) = stmt else {
+        return None;
+    };
+    let mut visitor = TableVisitor::default();
+    query_stmt.drive(&mut visitor);
+
+    let clause = format!(
+        " AT (TIMESTAMP => '{}'::TIMESTAMP)",
+        timestamp.replace('\\', "\\\\").replace('\'', "\\'")
+    );
+    let mut positions = visitor
+        .tables
+        .into_iter()
+        .filter(|(name, _)| !visitor.ctes.contains(name))
+        .map(|(_, pos)| pos)
+        .collect::<Vec<_>>();
+    positions.sort_unstable();
+    let mut sql = query.to_string();
+    for pos in positions.into_iter().rev() {
+        sql.insert_str(pos, &clause);
+    }
+    Some(sql)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_at_timestamp() {
+        let at = " AT (TIMESTAMP => '2024-01-01 00:00:00'::TIMESTAMP)";
+        let rewrite = |q: &str| at_timestamp(q, "2024-01-01 00:00:00");
+        assert_eq!(
+            rewrite("SELECT * FROM db.t1 AS a JOIN t2 b ON a.id = b.id"),
+            Some(format!(
+                "SELECT * FROM db.t1{} AS a JOIN t2{} b ON a.id = b.id",
+                at, at
+            ))
+        );
+        assert_eq!(
+            rewrite("WITH c AS (SELECT * FROM t) SELECT * FROM c, system.tables"),
+            Some(format!(
+                "WITH c AS (SELECT * FROM t{}) SELECT * FROM c, system.tables",
+                at
+            ))
+        );
+        assert_eq!(
+            rewrite("SELECT * FROM t AT (SNAPSHOT => 'abc') WHERE a IN (SELECT a FROM t2)"),
+            Some(format!(
+                "SELECT * FROM t AT (SNAPSHOT => 'abc') WHERE a IN (SELECT a FROM t2{})",
+                at
+            ))
+        );
+        assert_eq!(rewrite("SELECT 1"), Some("SELECT 1".to_string()));
+        assert_eq!(rewrite("INSERT INTO t SELECT * FROM t2"), None);
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{Capabilities, ServerVersion};
+
+/// Exit code of `--check` when some features are not available with the server.
+pub const EXIT_UNAVAILABLE: i32 = 2;
+
+/// Features depending on the server, with the minimum version supporting them,
+/// and what happens instead on older servers.
+const MATRIX: &[(&str, (u32, u32, u32), &str)] = &[
+    (
+        "variant",
+        (1, 0, 0),
+        "semi-structured values are returned as strings",
+    ),
+    (
+        "distinct null",
+        (1, 2, 100),
+        "NULL could not be told apart from the string 'NULL'",
+    ),
+    (
+        "query parameters",
+        (1, 2, 670),
+        "parameters are replaced in the sql by the client",
+    ),
+    (
+        "session login",
+        (1, 2, 480),
+        "session state is sent with each query",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeatureStatus {
+    Available,
+    Unavailable(String),
+    /// Server version is not detected, with the FlightSQL handler or a
+    /// version string not understood.
+    Unknown,
+}
+
+/// Version from login, or parsed from `SELECT version()` like
+/// `Databend Query v1.2.700-nightly-a1b2c3(rust-1.75.0-nightly)`.
+pub fn server_version(caps: &Capabilities, version: &str) -> Option<ServerVersion> {
+    caps.server_version.or_else(|| {
+        version
+            .split_whitespace()
+            .find(|s| s.starts_with('v') && s[1..].starts_with(|c: char| c.is_ascii_digit()))
+            .and_then(ServerVersion::parse)
+    })
+}
+
+/// Check the features in the matrix against the server version, and the ones
+/// detected at runtime, `presign` is whether uploading to presigned urls works.
+pub fn check_features(
+    version: Option<ServerVersion>,
+    caps: &Capabilities,
+    presign: bool,
+) -> Vec<(&'static str, FeatureStatus)> {
+    let mut result = vec![];
+    for (feature, (major, minor, patch), fallback) in MATRIX {
+        let status = match version {
+            None => FeatureStatus::Unknown,
+            Some(v) if !v.at_least(*major, *minor, *patch) => FeatureStatus::Unavailable(format!(
+                "requires server {}.{}.{}, {}",
+                major, minor, patch, fallback
+            )),
+            Some(_) if *feature == "query parameters" && !caps.query_params => {
+                FeatureStatus::Unavailable(format!("disabled by dsn, {}", fallback))
+            }
+            Some(_) => FeatureStatus::Available,
+        };
+        result.push((*feature, status));
+    }
+    let status = if presign {
+        FeatureStatus::Available
+    } else {
+        FeatureStatus::Unavailable("data is uploaded through the server".to_string())
+    };
+    result.push(("presign", status));
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_features() {
+        let caps = Capabilities {
+            query_params: true,
+            ..Default::default()
+        };
+        let version = server_version(&caps, "Databend Query v1.2.200-nightly-a1b2c3(rust-1.75.0)");
+        assert_eq!(version, ServerVersion::parse("1.2.200"));
+
+        let result = check_features(version, &caps, true);
+        assert_eq!(result[0], ("variant", FeatureStatus::Available));
+        assert_eq!(result[1], ("distinct null", FeatureStatus::Available));
+        assert_eq!(
+            result[2],
+            (
+                "query parameters",
+                FeatureStatus::Unavailable(
+                    "requires server 1.2.670, parameters are replaced in the sql by the client"
+                        .to_string()
+                )
+            )
+        );
+        assert_eq!(result[4], ("presign", FeatureStatus::Available));
+
+        let caps = Capabilities::default();
+        let result = check_features(ServerVersion::parse("v1.2.700"), &caps, false);
+        assert!(
+            matches!(result[2].1, FeatureStatus::Unavailable(ref s) if s.starts_with("disabled by dsn"))
+        );
+        assert!(matches!(result[4].1, FeatureStatus::Unavailable(_)));
+
+        let result = check_features(server_version(&caps, "unknown"), &caps, true);
+        assert_eq!(result[0].1, FeatureStatus::Unknown);
+    }
+}
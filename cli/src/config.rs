@@ -14,12 +14,18 @@
 
 // Loading from `$HOME/.config/bendsql/config.toml`
 
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::anyhow;
 use anyhow::Result;
 use clap::ValueEnum;
-use serde::Deserialize;
+use databend_client::SensitiveString;
+use serde::{Deserialize, Serialize};
+
+use crate::theme::{self, ColorMode, Theme, ThemeName};
 
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct Config {
@@ -29,6 +35,9 @@ pub struct Config {
     pub settings: SettingsConfig,
     #[serde(default)]
     pub server: ServerConfig,
+    /// Named connections selected by `--profile`.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ConnectionConfig>,
 }
 
 #[derive(Clone, Debug, Deserialize, Default)]
@@ -39,12 +48,19 @@ pub struct SettingsConfig {
     pub progress_color: Option<String>,
     pub no_auto_complete: Option<bool>,
     pub show_progress: Option<bool>,
+    pub progress_interval: Option<u64>,
     pub show_stats: Option<bool>,
     pub expand: Option<String>,
     pub replace_newline: Option<bool>,
+    pub confirm_paste: Option<bool>,
+    pub confirm_drop: Option<bool>,
     pub max_display_rows: Option<usize>,
     pub max_col_width: Option<usize>,
     pub max_width: Option<usize>,
+    pub row_numbers: Option<bool>,
+    pub auto_limit: Option<usize>,
+    pub theme: Option<String>,
+    pub color: Option<String>,
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -73,8 +89,10 @@ pub struct Settings {
     pub no_auto_complete: bool,
 
     /// Show progress [bar] when executing queries.
-    /// Only works with output format `table` and `null`.
     pub show_progress: bool,
+    /// Seconds between plain text progress lines, used instead of the bar
+    /// when stderr is not a terminal.
+    pub progress_interval: u64,
 
     /// Show stats after executing queries.
     /// Only works with non-interactive mode.
@@ -85,6 +103,10 @@ pub struct Settings {
     pub max_col_width: usize,
     /// limit display render box max width, 0 means default to the size of the terminal
     pub max_width: usize,
+    /// Prefix rows with their index (only works in table output format)
+    pub row_numbers: bool,
+    /// Append `LIMIT` to plain SELECTs without one, 0 means disabled
+    pub auto_limit: usize,
     /// Output format is set by the flag.
     pub output_format: OutputFormat,
     // Output Quote Style.
@@ -101,10 +123,19 @@ pub struct Settings {
     pub multi_line: bool,
     /// whether replace '\n' with '\\n', default true.
     pub replace_newline: bool,
+    /// Ask before running multiple statements pasted at once in REPL, default true.
+    pub confirm_paste: bool,
+    /// Ask before running `DROP` and `TRUNCATE` in REPL, default true.
+    pub confirm_drop: bool,
 
     pub bind_address: String,
     pub bind_port: u16,
     pub auto_open_browser: bool,
+
+    /// Color theme for tables, errors and highlighted sql, dark/light/none.
+    pub theme: ThemeName,
+    /// Color mode, auto/ansi/truecolor/never, auto respects `NO_COLOR`.
+    pub color: ColorMode,
 }
 
 #[derive(ValueEnum, Clone, Debug, PartialEq, Deserialize)]
@@ -115,6 +146,19 @@ pub enum OutputFormat {
     Null,
 }
 
+impl OutputFormat {
+    /// Output format for the ClickHouse style `FORMAT` clause of a query, if any.
+    pub fn from_clause(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "pretty" | "prettycompact" | "table" => Some(Self::Table),
+            "csv" => Some(Self::CSV),
+            "tsv" | "tabseparated" => Some(Self::TSV),
+            "null" => Some(Self::Null),
+            _ => None,
+        }
+    }
+}
+
 #[derive(ValueEnum, Clone, Debug, PartialEq, Deserialize)]
 pub enum OutputQuoteStyle {
     Always,
@@ -151,26 +195,52 @@ impl Settings {
             .unwrap_or_else(|| self.progress_color.clone());
         self.no_auto_complete = cfg.no_auto_complete.unwrap_or(self.no_auto_complete);
         self.show_progress = cfg.show_progress.unwrap_or(self.show_progress);
+        self.progress_interval = cfg.progress_interval.unwrap_or(self.progress_interval);
         self.show_stats = cfg.show_stats.unwrap_or(self.show_stats);
         self.expand = cfg
             .expand
             .map(|expand| expand.as_str().into())
             .unwrap_or_else(|| self.expand);
         self.replace_newline = cfg.replace_newline.unwrap_or(self.replace_newline);
+        self.confirm_paste = cfg.confirm_paste.unwrap_or(self.confirm_paste);
+        self.confirm_drop = cfg.confirm_drop.unwrap_or(self.confirm_drop);
         self.max_width = cfg.max_width.unwrap_or(self.max_width);
         self.max_col_width = cfg.max_col_width.unwrap_or(self.max_col_width);
         self.max_display_rows = cfg.max_display_rows.unwrap_or(self.max_display_rows);
+        self.row_numbers = cfg.row_numbers.unwrap_or(self.row_numbers);
+        self.auto_limit = cfg.auto_limit.unwrap_or(self.auto_limit);
         self.auto_open_browser = c.server.auto_open_browser;
         self.bind_address.clone_from(&c.server.bind_address);
         self.bind_port = c.server.bind_port;
+        // invalid values are reported by `Config::validate`
+        if let Some(theme) = cfg.theme.as_deref().and_then(|t| t.try_into().ok()) {
+            self.theme = theme;
+        }
+        if let Some(color) = cfg.color.as_deref().and_then(|c| c.try_into().ok()) {
+            self.color = color;
+        }
+    }
+
+    pub fn apply_theme(&self) {
+        theme::set(Theme::new(self.theme, self.color));
     }
 
     pub fn inject_ctrl_cmd(&mut self, cmd_name: &str, cmd_value: &str) -> Result<()> {
         match cmd_name {
             "display_pretty_sql" => self.display_pretty_sql = cmd_value.parse()?,
             "prompt" => self.prompt = cmd_value.to_string(),
-            "progress_color" => self.progress_color = cmd_value.to_string(),
+            "progress_color" => {
+                if !COLORS.contains(&cmd_value) {
+                    return Err(anyhow!(
+                        "Unknown progress color: {}, expected one of {}",
+                        cmd_value,
+                        COLORS.join(", ")
+                    ));
+                }
+                self.progress_color = cmd_value.to_string()
+            }
             "show_progress" => self.show_progress = cmd_value.parse()?,
+            "progress_interval" => self.progress_interval = cmd_value.parse()?,
             "show_stats" => self.show_stats = cmd_value.parse()?,
             "output_format" => {
                 self.output_format = match cmd_value.to_ascii_lowercase().as_str() {
@@ -197,20 +267,38 @@ impl Settings {
             "max_width" => self.max_width = cmd_value.parse()?,
             "max_col_width" => self.max_col_width = cmd_value.parse()?,
             "replace_newline" => self.replace_newline = cmd_value.parse()?,
+            "confirm_paste" => self.confirm_paste = cmd_value.parse()?,
+            "confirm_drop" => self.confirm_drop = cmd_value.parse()?,
+            "row_numbers" => self.row_numbers = cmd_value.parse()?,
+            "auto_limit" => self.auto_limit = cmd_value.parse()?,
+            "theme" => {
+                self.theme = cmd_value.try_into()?;
+                self.apply_theme();
+            }
+            "color" => {
+                self.color = cmd_value.try_into()?;
+                self.apply_theme();
+            }
             _ => return Err(anyhow!("Unknown command: {}", cmd_name)),
         }
         Ok(())
     }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct ConnectionConfig {
     pub host: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<u16>,
     pub user: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<SensitiveString>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub database: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tls: Option<bool>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     pub args: BTreeMap<String, String>,
 }
 
@@ -223,8 +311,8 @@ pub struct ServerConfig {
 }
 
 impl Config {
-    pub fn load() -> Self {
-        let paths = [
+    fn paths() -> [String; 2] {
+        [
             format!(
                 "{}/.bendsql/config.toml",
                 std::env::var("HOME").unwrap_or_else(|_| ".".to_string())
@@ -233,23 +321,384 @@ impl Config {
                 "{}/.config/bendsql/config.toml",
                 std::env::var("HOME").unwrap_or_else(|_| ".".to_string())
             ),
-        ];
-        let path = paths.iter().find(|p| Path::new(p).exists());
-        match path {
-            Some(path) => Self::load_from_file(path),
+        ]
+    }
+
+    /// Path of the config file to load, the first existing one.
+    pub fn find() -> Option<String> {
+        Self::paths().into_iter().find(|p| Path::new(p).exists())
+    }
+
+    pub fn load() -> Self {
+        match Self::find() {
+            Some(path) => Self::load_from_file(&path),
             None => Self::default(),
         }
     }
 
     fn load_from_file(path: &str) -> Self {
-        match toml::from_str(&std::fs::read_to_string(path).unwrap()) {
-            Ok(config) => config,
+        match Self::parse(path) {
+            Ok((config, unknown, invalid)) => {
+                for warning in unknown {
+                    eprintln!("{}, ignored", warning);
+                }
+                for warning in invalid {
+                    eprintln!("{}, using default", warning);
+                }
+                config
+            }
             Err(e) => {
-                eprintln!("failed to load config file {}: {}, using defaults", path, e);
+                eprintln!("{}", e);
+                eprintln!("failed to load config file, using defaults");
                 Self::default()
             }
         }
     }
+
+    /// Parse and validate the config file, errors come with the line and
+    /// suggestions for misspelled keys.
+    pub fn check_file(path: &str) -> Result<Self> {
+        let (config, unknown, invalid) = Self::parse(path)?;
+        let errors = unknown.into_iter().chain(invalid).collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(anyhow!(errors.join("\n")));
+        }
+        Ok(config)
+    }
+
+    /// Returns the config along with unknown keys and invalid values found,
+    /// unknown keys should not fail loading, they may come from newer versions.
+    /// Invalid values are reset to their defaults in the returned config.
+    fn parse(path: &str) -> Result<(Self, Vec<String>, Vec<String>)> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read config file {}: {}", path, e))?;
+        let mut config: Config = toml::from_str(&content).map_err(|e| {
+            let line = e
+                .span()
+                .map(|span| format!(":{}", line_of(&content, span.start)))
+                .unwrap_or_default();
+            anyhow!("{}{}: {}", path, line, e.message().trim())
+        })?;
+        let line_in = |section: &str, key: &str| {
+            locate(&content, section, key)
+                .map(|line| format!(":{}", line))
+                .unwrap_or_default()
+        };
+        let unknown = unknown_keys(&content)
+            .into_iter()
+            .map(|(section, key, expected)| {
+                let mut msg = format!(
+                    "{}{}: unknown field `{}`",
+                    path,
+                    line_in(&section, &key),
+                    key
+                );
+                if !section.is_empty() {
+                    msg.push_str(&format!(" in [{}]", section));
+                }
+                if let Some(suggestion) = suggest_field(&key, expected) {
+                    msg.push_str(&format!(", did you mean `{}`?", suggestion));
+                }
+                msg
+            })
+            .collect();
+        let invalid = config
+            .validate()
+            .into_iter()
+            .map(|(section, key, msg)| {
+                config.reset(&section, key);
+                format!(
+                    "{}{}: invalid {}.{}: {}",
+                    path,
+                    line_in(&section, key),
+                    section,
+                    key,
+                    msg
+                )
+            })
+            .collect();
+        Ok((config, unknown, invalid))
+    }
+
+    /// Reset a key reported by `validate` to its default.
+    fn reset(&mut self, section: &str, key: &str) {
+        if section == "settings" {
+            let settings = &mut self.settings;
+            match key {
+                "expand" => settings.expand = None,
+                "progress_color" => settings.progress_color = None,
+                "theme" => settings.theme = None,
+                "color" => settings.color = None,
+                "max_display_rows" => settings.max_display_rows = None,
+                _ => {}
+            }
+            return;
+        }
+        let conn = match section.strip_prefix("profiles.") {
+            Some(name) => self.profiles.get_mut(name),
+            None => Some(&mut self.connection),
+        };
+        if let Some(conn) = conn {
+            match key {
+                "host" => conn.host = ConnectionConfig::default().host,
+                "port" => conn.port = None,
+                _ => {}
+            }
+        }
+    }
+
+    /// Check values not covered by types, returns (section, key, message).
+    fn validate(&self) -> Vec<(String, &'static str, String)> {
+        let mut errors = vec![];
+        let settings = &self.settings;
+        if let Some(expand) = &settings.expand {
+            if !["on", "off", "auto"].contains(&expand.to_ascii_lowercase().as_str()) {
+                errors.push((
+                    "settings".to_string(),
+                    "expand",
+                    format!("`{}`, expected one of on, off, auto", expand),
+                ));
+            }
+        }
+        if let Some(color) = &settings.progress_color {
+            if !COLORS.contains(&color.as_str()) {
+                errors.push((
+                    "settings".to_string(),
+                    "progress_color",
+                    format!("`{}`, expected one of {}", color, COLORS.join(", ")),
+                ));
+            }
+        }
+        if let Some(theme) = &settings.theme {
+            if let Err(e) = ThemeName::try_from(theme.as_str()) {
+                errors.push(("settings".to_string(), "theme", e.to_string()));
+            }
+        }
+        if let Some(color) = &settings.color {
+            if let Err(e) = ColorMode::try_from(color.as_str()) {
+                errors.push(("settings".to_string(), "color", e.to_string()));
+            }
+        }
+        if settings.max_display_rows == Some(0) {
+            errors.push((
+                "settings".to_string(),
+                "max_display_rows",
+                "should be greater than 0".to_string(),
+            ));
+        }
+        let connections = std::iter::once(("connection".to_string(), &self.connection)).chain(
+            self.profiles
+                .iter()
+                .map(|(name, conn)| (format!("profiles.{}", name), conn)),
+        );
+        for (section, conn) in connections {
+            if conn.host.is_empty() {
+                errors.push((section.clone(), "host", "should not be empty".to_string()));
+            }
+            if conn.port == Some(0) {
+                errors.push((section, "port", "should be greater than 0".to_string()));
+            }
+        }
+        errors
+    }
+
+    /// Write connection into the config file being loaded, or
+    /// `$HOME/.config/bendsql/config.toml` if none exists. Profile `default`
+    /// is written to `[connection]`, others to `[profiles.<name>]`.
+    /// Other content of the file is kept as is, including comments.
+    pub fn save_profile(name: &str, conn: &ConnectionConfig) -> Result<PathBuf> {
+        let [legacy, path] = Self::paths();
+        let path = if Path::new(&legacy).exists() {
+            PathBuf::from(legacy)
+        } else {
+            PathBuf::from(path)
+        };
+        save_profile_to(&path, name, conn)?;
+        Ok(path)
+    }
+}
+
+fn save_profile_to(path: &Path, name: &str, conn: &ConnectionConfig) -> Result<()> {
+    let mut doc: toml_edit::DocumentMut = match std::fs::read_to_string(path) {
+        Ok(content) => content
+            .parse()
+            .map_err(|e| anyhow!("failed to parse config file {}: {}", path.display(), e))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml_edit::DocumentMut::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let value = toml_edit::ser::to_document(conn)?;
+    let section = if name == "default" {
+        doc.entry("connection")
+    } else {
+        let profiles = doc
+            .entry("profiles")
+            .or_insert_with(|| {
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                toml_edit::Item::Table(table)
+            })
+            .as_table_mut()
+            .ok_or_else(|| anyhow!("invalid profiles in config file {}", path.display()))?;
+        profiles.entry(name)
+    };
+    let table = section
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .ok_or_else(|| anyhow!("invalid profile {} in config file {}", name, path.display()))?;
+    // update keys one by one, so that comments of the kept keys stay
+    let keys = table
+        .iter()
+        .map(|(k, _)| k.to_string())
+        .filter(|k| !value.contains_key(k))
+        .collect::<Vec<_>>();
+    for key in keys {
+        table.remove(&key);
+    }
+    for (key, item) in value.iter() {
+        table.insert(key, item.clone());
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // the config may contain password, so it is never readable by others
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let mut file = options.open(path)?;
+    std::io::Write::write_all(&mut file, doc.to_string().as_bytes())?;
+    Ok(())
+}
+
+const COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+/// Line of `key` in `[section]`, for reporting invalid values.
+fn locate(content: &str, section: &str, key: &str) -> Option<usize> {
+    let mut current = String::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = header.trim().replace(['"', '\''], "");
+            // unknown tables are reported as keys of their parent
+            if section.is_empty() && current.split('.').next() == Some(key) {
+                return Some(i + 1);
+            }
+        } else if current == section && line.split_once('=').is_some_and(|(k, _)| k.trim() == key) {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// Keys of the config not known by this version, returns
+/// (section, key, expected keys of the section).
+fn unknown_keys(content: &str) -> Vec<(String, String, &'static [&'static str])> {
+    let Ok(table) = content.parse::<toml::Table>() else {
+        return vec![];
+    };
+    let mut unknown = vec![];
+    let mut check = |section: &str, table: &toml::Table, expected: &'static [&'static str]| {
+        for key in table.keys() {
+            if !expected.contains(&key.as_str()) {
+                unknown.push((section.to_string(), key.clone(), expected));
+            }
+        }
+    };
+    check("", &table, fields_of::<Config>());
+    for (key, value) in &table {
+        let Some(value) = value.as_table() else {
+            continue;
+        };
+        match key.as_str() {
+            "connection" => check(key, value, fields_of::<ConnectionConfig>()),
+            "settings" => check(key, value, fields_of::<SettingsConfig>()),
+            "server" => check(key, value, fields_of::<ServerConfig>()),
+            "profiles" => {
+                for (name, conn) in value {
+                    if let Some(conn) = conn.as_table() {
+                        let section = format!("profiles.{}", name);
+                        check(&section, conn, fields_of::<ConnectionConfig>());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    unknown
+}
+
+/// Field names of a struct, taken from its `Deserialize` impl so that they
+/// never get out of sync.
+fn fields_of<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct Fields<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> serde::Deserializer<'de> for Fields<'_> {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: serde::de::Visitor<'de>>(
+            self,
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: serde::de::Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(serde::de::Error::custom("fields only"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Fields(&mut fields));
+    fields
+}
+
+/// Suggest the closest expected field for the unknown one.
+fn suggest_field(unknown: &str, expected: &[&'static str]) -> Option<&'static str> {
+    expected
+        .iter()
+        .map(|field| (edit_distance(unknown, field), *field))
+        .filter(|(d, _)| *d <= 2.max(unknown.len() / 3))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, field)| field)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
 }
 
 impl Default for Settings {
@@ -263,16 +712,23 @@ impl Default for Settings {
             quote_style: OutputQuoteStyle::Necessary,
             expand: ExpandMode::Auto,
             show_progress: false,
+            progress_interval: 10,
             max_display_rows: 1000,
             max_col_width: 1024 * 1024,
             max_width: 1024 * 1024,
+            row_numbers: false,
+            auto_limit: 0,
             show_stats: false,
             time: None,
             multi_line: true,
             replace_newline: true,
+            confirm_paste: true,
+            confirm_drop: true,
             auto_open_browser: false,
             bind_address: "127.0.0.1".to_string(),
             bind_port: 8080,
+            theme: ThemeName::Dark,
+            color: ColorMode::Auto,
         }
     }
 }
@@ -283,6 +739,7 @@ impl Default for ConnectionConfig {
             host: "localhost".to_string(),
             port: Some(8000),
             user: "root".to_string(),
+            password: None,
             database: None,
             tls: None,
             args: BTreeMap::new(),
@@ -299,3 +756,100 @@ impl Default for ServerConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(content: &str) -> Result<Config> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        std::io::Write::write_all(&mut file, content.as_bytes())?;
+        Config::check_file(file.path().to_str().unwrap())
+    }
+
+    #[test]
+    fn check_config() -> Result<()> {
+        let config =
+            check("[settings]\nexpand = \"on\"\n\n[profiles.dev]\nhost = \"a.com\"\n").unwrap();
+        assert_eq!(config.profiles["dev"].host, "a.com");
+
+        let err = check("[settings]\nshow_stats = true\npromt = \"> \"\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(":3: unknown field `promt`"), "{}", err);
+        assert!(
+            err.ends_with("in [settings], did you mean `prompt`?"),
+            "{}",
+            err
+        );
+
+        // keys from newer versions are only warned when loading
+        let content = "[settings]\nprompt = \"> \"\nnew_setting = 1\n\n[cloud]\nregion = \"us\"\n";
+        let mut file = tempfile::NamedTempFile::new()?;
+        std::io::Write::write_all(&mut file, content.as_bytes())?;
+        let path = file.path().to_str().unwrap();
+        let config = Config::load_from_file(path);
+        assert_eq!(config.settings.prompt.as_deref(), Some("> "));
+        let err = Config::check_file(path).unwrap_err().to_string();
+        assert!(
+            err.contains(":3: unknown field `new_setting` in [settings]"),
+            "{}",
+            err
+        );
+        assert!(err.contains(":5: unknown field `cloud`\n"), "{}", err);
+
+        let err = check("[settings]\n\nexpand = \"yes\"\n\n[connection]\nport = 0\n")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains(":3: invalid settings.expand: `yes`, expected one of on, off, auto"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains(":6: invalid connection.port: should be greater than 0"),
+            "{}",
+            err
+        );
+
+        // invalid values only reset their own keys when loading
+        let content = "[settings]
+prompt = \"> \"
+progress_color = \"pink\"
+";
+        let mut file = tempfile::NamedTempFile::new()?;
+        std::io::Write::write_all(&mut file, content.as_bytes())?;
+        let config = Config::load_from_file(file.path().to_str().unwrap());
+        assert_eq!(config.settings.prompt.as_deref(), Some("> "));
+        assert_eq!(config.settings.progress_color, None);
+        Ok(())
+    }
+
+    #[test]
+    fn save_profile_keeps_comments() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "# my settings\n[settings]\nprompt = \"> \" # short\n\n[connection]\nhost = \"old.com\"\n",
+        )?;
+        let conn = ConnectionConfig {
+            host: "a.com".to_string(),
+            ..Default::default()
+        };
+        save_profile_to(&path, "default", &conn)?;
+        save_profile_to(&path, "dev", &conn)?;
+        let content = std::fs::read_to_string(&path)?;
+        assert!(content.starts_with("# my settings\n[settings]\nprompt = \"> \" # short\n"));
+        let config: Config = toml::from_str(&content)?;
+        assert_eq!(config.connection.host, "a.com");
+        assert_eq!(config.profiles["dev"].host, "a.com");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        Ok(())
+    }
+}
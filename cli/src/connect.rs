@@ -0,0 +1,172 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Write};
+
+use anyhow::{anyhow, Result};
+use clap::{ArgAction, Args};
+use databend_client::SensitiveString;
+
+use crate::args::ConnectionArgs;
+use crate::config::{Config, ConnectionConfig};
+
+#[derive(Args, Debug, PartialEq)]
+pub struct ConnectArgs {
+    #[clap(long, action = ArgAction::Help, help = "Print help information")]
+    help: Option<bool>,
+
+    #[clap(
+        long,
+        help = "Profile name to save, `default` is used without --profile, prompted if not set"
+    )]
+    name: Option<String>,
+}
+
+fn prompt(label: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => eprint!("{} [{}]: ", label, default),
+        None => eprint!("{}: ", label),
+    }
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line)? == 0 {
+        return Err(anyhow!("aborted"));
+    }
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(default.unwrap_or_default().to_string());
+    }
+    Ok(line.to_string())
+}
+
+fn prompt_bool(label: &str, default: bool) -> Result<bool> {
+    let hint = if default { "Y/n" } else { "y/N" };
+    loop {
+        let answer = prompt(&format!("{} ({})", label, hint), None)?;
+        match answer.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => eprintln!("please answer y or n"),
+        }
+    }
+}
+
+/// Read without echo from terminal, or as a normal line when piped.
+fn prompt_secret(label: &str) -> Result<String> {
+    if !std::io::stdin().is_terminal() {
+        return prompt(label, None);
+    }
+    Ok(rpassword::prompt_password(format!("{}: ", label))?)
+}
+
+fn optional(s: String) -> Option<String> {
+    (!s.is_empty()).then_some(s)
+}
+
+/// Prompt for connection options, test the connection, then save it as
+/// a profile in the config file.
+pub async fn connect_wizard(args: ConnectArgs) -> Result<()> {
+    let name = match args.name {
+        Some(name) => name,
+        None => prompt("Profile name", Some("default"))?,
+    };
+    let host = prompt("Host", Some("localhost"))?;
+    let tls = prompt_bool(
+        "Enable TLS",
+        !matches!(host.as_str(), "localhost" | "127.0.0.1"),
+    )?;
+    let default_port = if tls { "443" } else { "8000" };
+    let port: u16 = prompt("Port", Some(default_port))?
+        .parse()
+        .map_err(|e| anyhow!("invalid port: {}", e))?;
+
+    let mut conn_args = BTreeMap::new();
+    let (user, password) = loop {
+        match prompt("Auth method, password or token", Some("password"))?.as_str() {
+            "password" => {
+                let user = prompt("User", Some("root"))?;
+                let password = prompt_secret("Password")?;
+                break (user, optional(password).map(SensitiveString::from));
+            }
+            "token" => {
+                let token = prompt_secret("Access token")?;
+                conn_args.insert("access_token".to_string(), token);
+                break ("token".to_string(), None);
+            }
+            other => eprintln!("unknown auth method: {}", other),
+        }
+    };
+    let database = optional(prompt("Database (optional)", None)?);
+    if let Some(warehouse) = optional(prompt("Warehouse (optional)", None)?) {
+        conn_args.insert("warehouse".to_string(), warehouse);
+    }
+
+    let profile = ConnectionConfig {
+        host,
+        port: Some(port),
+        user,
+        password,
+        database,
+        tls: Some(tls),
+        args: conn_args,
+    };
+
+    eprintln!("testing connection to {}:{} ...", profile.host, port);
+    match test_connection(&profile).await {
+        Ok(version) => eprintln!("connected to {}", version),
+        Err(e) => {
+            eprintln!("failed to connect: {}", e);
+            if !prompt_bool("Save the profile anyway", false)? {
+                return Ok(());
+            }
+        }
+    }
+
+    let path = Config::save_profile(&name, &profile)?;
+    if name == "default" {
+        eprintln!("saved default connection to {}", path.display());
+    } else {
+        eprintln!(
+            "saved profile {} to {}, connect with `bendsql --profile {}`",
+            name,
+            path.display(),
+            name
+        );
+    }
+    Ok(())
+}
+
+async fn test_connection(profile: &ConnectionConfig) -> Result<String> {
+    let mut args = profile.args.clone();
+    if profile.tls == Some(false) {
+        args.insert("sslmode".to_string(), "disable".to_string());
+    }
+    let client = ConnectionArgs {
+        host: profile.host.clone(),
+        port: profile.port,
+        user: profile.user.clone(),
+        password: profile.password.clone().unwrap_or_default(),
+        database: profile.database.clone(),
+        flight: false,
+        auto: false,
+        args,
+    }
+    .client()?;
+    let conn = client.get_conn().await?;
+    let version = conn.version().await?;
+    conn.close().await?;
+    Ok(version)
+}
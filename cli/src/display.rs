@@ -13,16 +13,20 @@
 // limitations under the License.
 
 use std::fmt::Write;
+use std::io::IsTerminal;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::{collections::HashSet, env};
 
 use anyhow::{anyhow, Result};
 use comfy_table::{Cell, CellAlignment, Table};
-use databend_driver::{Row, RowStatsIterator, RowWithStats, SchemaRef, ServerStats};
+use databend_driver::{
+    Capabilities, ConnectionInfo, DataType, Field, NumberDataType, Row, RowStatsIterator,
+    RowWithStats, Schema, SchemaRef, ServerStats,
+};
 use indicatif::{HumanBytes, ProgressBar, ProgressState, ProgressStyle};
 use terminal_size::{terminal_size, Width};
-use tokio::time::Instant;
+use tokio::time::{Duration, Instant};
 use tokio_stream::StreamExt;
 use unicode_segmentation::UnicodeSegmentation;
 
@@ -30,6 +34,7 @@ use crate::ast::QueryKind;
 use crate::{
     ast::{format_query, highlight_query},
     config::{ExpandMode, OutputFormat, OutputQuoteStyle, Settings},
+    theme,
     web::set_data,
 };
 
@@ -51,9 +56,60 @@ pub struct FormatDisplay<'a> {
 
     rows: usize,
     progress: Option<ProgressBar>,
+    // plain text lines instead of the bar when stderr is not a terminal,
+    // with the time of the last line printed
+    plain_progress: bool,
+    last_progress: Option<Instant>,
     start: Instant,
     stats: Option<ServerStats>,
     interrupted: Arc<AtomicBool>,
+    // rows of the last table display, kept for `!show rows`
+    result: Option<(SchemaRef, Vec<Row>)>,
+    tee: Option<&'a mut Tee>,
+}
+
+/// Copy of the results written to a csv file besides the normal display,
+/// enabled with `!tee file` until `!tee off`.
+pub struct Tee {
+    path: String,
+    writer: csv::Writer<std::fs::File>,
+}
+
+impl Tee {
+    /// Results are appended if the file exists, each with a header line.
+    pub fn open(path: &str) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow!("Failed to open tee file {}: {}", path, e))?;
+        let writer = csv::WriterBuilder::new().flexible(true).from_writer(file);
+        Ok(Self {
+            path: path.to_string(),
+            writer,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn write_header(&mut self, schema: &Schema) -> Result<()> {
+        self.writer
+            .write_record(schema.fields().iter().map(|f| f.name.as_str()))?;
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &Row) -> Result<()> {
+        self.writer
+            .write_record(row.values().iter().map(|v| v.to_string()))?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
 }
 
 impl<'a> FormatDisplay<'a> {
@@ -73,14 +129,42 @@ impl<'a> FormatDisplay<'a> {
             data,
             rows: 0,
             progress: None,
+            plain_progress: !std::io::stderr().is_terminal(),
+            last_progress: None,
             start,
             stats: None,
             interrupted,
+            result: None,
+            tee: None,
         }
     }
+
+    pub fn with_tee(mut self, tee: Option<&'a mut Tee>) -> Self {
+        self.tee = tee;
+        self
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn take_result(&mut self) -> Option<(SchemaRef, Vec<Row>)> {
+        self.result.take()
+    }
 }
 
 impl FormatDisplay<'_> {
+    /// Called after `self.rows` is counted, the header goes before the first row.
+    fn tee_row(&mut self, row: &Row) -> Result<()> {
+        if let Some(tee) = self.tee.as_mut() {
+            if self.rows == 1 {
+                tee.write_header(&self.data.schema())?;
+            }
+            tee.write_row(row)?;
+        }
+        Ok(())
+    }
+
     fn running_secs(&self) -> f64 {
         // prefer to show server running time
         if let Some(ref stats) = self.stats {
@@ -92,15 +176,33 @@ impl FormatDisplay<'_> {
 
     async fn display_progress(&mut self, ss: &ServerStats) {
         if self.settings.show_progress {
-            let pb = self.progress.take();
-            match self.kind {
-                QueryKind::Get(_, _) | QueryKind::Query => {
-                    self.progress = Some(display_progress(pb, ss, "read"));
-                }
-                QueryKind::Put(_, _) | QueryKind::Update => {
-                    self.progress = Some(display_progress(pb, ss, "write"));
+            let kind = match self.kind {
+                QueryKind::Get(_, _) | QueryKind::Query => "read",
+                QueryKind::Put(_, _) | QueryKind::Update => "write",
+                _ => return,
+            };
+            if self.plain_progress {
+                let interval = Duration::from_secs(self.settings.progress_interval);
+                let now = Instant::now();
+                if self
+                    .last_progress
+                    .is_some_and(|last| now.duration_since(last) < interval)
+                {
+                    return;
                 }
-                _ => {}
+                self.last_progress = Some(now);
+                eprintln!(
+                    "{}",
+                    plain_progress_line(ss, kind, self.start.elapsed().as_secs_f64())
+                );
+            } else {
+                let pb = self.progress.take();
+                self.progress = Some(display_progress(
+                    pb,
+                    ss,
+                    kind,
+                    &self.settings.progress_color,
+                ));
             }
         }
     }
@@ -146,6 +248,7 @@ impl FormatDisplay<'_> {
             match line {
                 Ok(RowWithStats::Row(row)) => {
                     self.rows += 1;
+                    self.tee_row(&row)?;
                     rows.push(row);
                 }
                 Ok(RowWithStats::Stats(ss)) => {
@@ -190,36 +293,23 @@ impl FormatDisplay<'_> {
         let expand = expand.unwrap_or(self.settings.expand);
         match expand {
             ExpandMode::On => {
-                print_expanded(schema, &rows)?;
-            }
-            ExpandMode::Off => {
-                println!(
-                    "{}",
-                    create_table(
-                        schema,
-                        &rows,
-                        self.replace_newline,
-                        self.settings.max_display_rows,
-                        self.settings.max_width,
-                        self.settings.max_col_width
-                    )?
-                );
+                print_expanded(schema.clone(), &rows)?;
             }
-            ExpandMode::Auto => {
-                // FIXME: depends on terminal size
-                println!(
-                    "{}",
-                    create_table(
-                        schema,
-                        &rows,
-                        self.replace_newline,
-                        self.settings.max_display_rows,
-                        self.settings.max_width,
-                        self.settings.max_col_width
-                    )?
-                );
+            ExpandMode::Off | ExpandMode::Auto => {
+                // FIXME: auto depends on terminal size
+                let table = create_table(
+                    schema.clone(),
+                    &rows,
+                    self.replace_newline,
+                    self.settings.max_display_rows,
+                    self.settings.max_width,
+                    self.settings.max_col_width,
+                    self.settings.row_numbers.then_some(0),
+                )?;
+                println!("{}", theme::current().paint_table(&table.to_string()));
             }
         }
+        self.result = Some((schema, rows));
 
         Ok(())
     }
@@ -241,10 +331,12 @@ impl FormatDisplay<'_> {
             match line {
                 Ok(RowWithStats::Row(row)) => {
                     self.rows += 1;
+                    self.tee_row(&row)?;
                     let record = row.into_iter().map(|v| v.to_string()).collect::<Vec<_>>();
                     wtr.write_record(record)?;
                 }
                 Ok(RowWithStats::Stats(ss)) => {
+                    self.display_progress(&ss).await;
                     self.stats = Some(ss);
                 }
                 Err(err) => {
@@ -252,6 +344,9 @@ impl FormatDisplay<'_> {
                 }
             }
         }
+        if let Some(pb) = self.progress.take() {
+            pb.finish_and_clear();
+        }
         Ok(())
     }
 
@@ -274,10 +369,12 @@ impl FormatDisplay<'_> {
             match line {
                 Ok(RowWithStats::Row(row)) => {
                     self.rows += 1;
+                    self.tee_row(&row)?;
                     let record = row.into_iter().map(|v| v.to_string()).collect::<Vec<_>>();
                     wtr.write_record(record)?;
                 }
                 Ok(RowWithStats::Stats(ss)) => {
+                    self.display_progress(&ss).await;
                     self.stats = Some(ss);
                 }
                 Err(err) => {
@@ -285,6 +382,9 @@ impl FormatDisplay<'_> {
                 }
             }
         }
+        if let Some(pb) = self.progress.take() {
+            pb.finish_and_clear();
+        }
         Ok(())
     }
 
@@ -295,8 +395,9 @@ impl FormatDisplay<'_> {
                 return Err(anyhow!(INTERRUPTED_MESSAGE));
             }
             match line {
-                Ok(RowWithStats::Row(_)) => {
+                Ok(RowWithStats::Row(row)) => {
                     self.rows += 1;
+                    self.tee_row(&row)?;
                 }
                 Ok(RowWithStats::Stats(ss)) => {
                     self.display_progress(&ss).await;
@@ -331,6 +432,11 @@ impl FormatDisplay<'_> {
 
             let (rows, mut rows_str, kind, total_rows, total_bytes) = match self.kind {
                 QueryKind::Graphical => (self.rows, "rows", "graphical", 0, 0),
+                QueryKind::Command => {
+                    eprintln!("executed in {:.3} sec.", self.running_secs());
+                    eprintln!();
+                    return;
+                }
                 QueryKind::Explain => (self.rows, "rows", "explain", 0, 0),
                 QueryKind::ShowCreate => (self.rows, "rows", "showcreate", 0, 0),
                 QueryKind::Query => (self.rows, "rows", "read", stats.read_rows, stats.read_bytes),
@@ -389,20 +495,17 @@ impl ChunkDisplay for FormatDisplay<'_> {
             return Err(anyhow!(INTERRUPTED_MESSAGE));
         }
 
-        match self.settings.output_format {
-            OutputFormat::Table => {
-                self.display_table(expand).await?;
-            }
-            OutputFormat::CSV => {
-                self.display_csv().await?;
-            }
-            OutputFormat::TSV => {
-                self.display_tsv().await?;
-            }
-            OutputFormat::Null => {
-                self.display_null().await?;
-            }
+        let result = match self.settings.output_format {
+            OutputFormat::Table => self.display_table(expand).await,
+            OutputFormat::CSV => self.display_csv().await,
+            OutputFormat::TSV => self.display_tsv().await,
+            OutputFormat::Null => self.display_null().await,
+        };
+        // keep the rows written before an error
+        if let Some(tee) = self.tee.as_mut() {
+            tee.flush()?;
         }
+        result?;
         self.display_stats().await;
         let stats = self.stats.take().unwrap_or_default();
         Ok(stats)
@@ -449,10 +552,22 @@ pub fn format_write_progress(ss: &ServerStats, elapsed: f64) -> String {
     )
 }
 
-fn display_progress(pb: Option<ProgressBar>, current: &ServerStats, kind: &str) -> ProgressBar {
+fn plain_progress_line(ss: &ServerStats, kind: &str, elapsed: f64) -> String {
+    let progress = match kind {
+        "read" => format_read_progress(ss, elapsed),
+        _ => format_write_progress(ss, elapsed),
+    };
+    format!("==> [{:.1}s] {}", elapsed, progress)
+}
+
+fn display_progress(
+    pb: Option<ProgressBar>,
+    current: &ServerStats,
+    kind: &str,
+    progress_color: &str,
+) -> ProgressBar {
     let pb = pb.unwrap_or_else(|| {
         let pbn = ProgressBar::new(current.total_bytes as u64);
-        let progress_color = "green";
         let template = "{spinner:.${progress_color}} [{elapsed_precise}] {msg} {wide_bar:.${progress_color}/blue} ({eta})".replace("${progress_color}", progress_color);
         pbn.set_style(
             ProgressStyle::with_template(&template)
@@ -571,6 +686,7 @@ fn create_table(
     max_rows: usize,
     mut max_width: usize,
     max_col_width: usize,
+    row_offset: Option<usize>,
 ) -> Result<Table> {
     let mut table = Table::new();
     table.load_preset("││──├─┼┤│    ──┌┐└┘");
@@ -605,14 +721,17 @@ fn create_table(
     let (top_rows, bottom_rows) = if rows_to_render == row_count {
         (row_count, 0usize)
     } else {
-        let top_rows = rows_to_render / 2 + (rows_to_render % 2 != 0) as usize;
+        let top_rows = rows_to_render / 2 + !rows_to_render.is_multiple_of(2) as usize;
         (top_rows, rows_to_render - top_rows)
     };
 
     let mut res_vec: Vec<Vec<String>> = vec![];
-    for row in results.iter().take(top_rows) {
+    for (i, row) in results.iter().enumerate().take(top_rows) {
         let values = row.values();
         let mut v = vec![];
+        if let Some(offset) = row_offset {
+            v.push((offset + i + 1).to_string());
+        }
         for value in values {
             if replace_newline {
                 v.push(value.to_string().replace('\n', "\\n"));
@@ -624,9 +743,12 @@ fn create_table(
     }
 
     if bottom_rows != 0 {
-        for row in results.iter().skip(row_count - bottom_rows) {
+        for (i, row) in results.iter().enumerate().skip(row_count - bottom_rows) {
             let values = row.values();
             let mut v = vec![];
+            if let Some(offset) = row_offset {
+                v.push((offset + i + 1).to_string());
+            }
             for value in values {
                 if replace_newline {
                     v.push(value.to_string().replace('\n', "\\n"));
@@ -638,6 +760,18 @@ fn create_table(
         }
     }
 
+    let schema = match row_offset {
+        Some(_) => {
+            let mut fields = vec![Field {
+                name: "#".to_string(),
+                data_type: DataType::Number(NumberDataType::UInt64),
+            }];
+            fields.extend(schema.fields().iter().cloned());
+            Arc::new(Schema::from_vec(fields))
+        }
+        None => schema,
+    };
+
     // "..." take up three lengths
     if max_width > 0 {
         (widths, column_map) =
@@ -671,7 +805,7 @@ fn create_table(
                 } else {
                     let mut value = values[*col_index as usize].clone();
                     if value.len() + 3 > widths[idx] {
-                        let element_size = if widths[idx] >= 6 { widths[idx] - 6 } else { 0 };
+                        let element_size = widths[idx].saturating_sub(6);
                         value = String::from_utf8(
                             value
                                 .graphemes(true)
@@ -724,7 +858,7 @@ fn create_table(
                     } else {
                         let mut value = values[*col_index as usize].clone();
                         if value.len() + 3 > widths[idx] {
-                            let element_size = if widths[idx] >= 6 { widths[idx] - 6 } else { 0 };
+                            let element_size = widths[idx].saturating_sub(6);
                             value = String::from_utf8(
                                 value
                                     .graphemes(true)
@@ -785,7 +919,7 @@ fn render_head(
                 let width = widths[idx];
                 let mut field_name = field.name.to_string();
                 let mut field_data_type = field.data_type.to_string();
-                let element_size = if width >= 6 { width - 6 } else { 0 };
+                let element_size = width.saturating_sub(6);
 
                 if field_name.len() + 3 > width {
                     field_name = String::from_utf8(
@@ -827,7 +961,96 @@ fn render_head(
     }
 }
 
+/// Render rows `start..end` of a buffered result, prefixed with their index.
+pub fn print_rows(
+    settings: &Settings,
+    schema: SchemaRef,
+    rows: &[Row],
+    start: usize,
+    end: usize,
+) -> Result<()> {
+    let table = create_table(
+        schema,
+        &rows[start..end],
+        settings.replace_newline,
+        usize::MAX,
+        settings.max_width,
+        settings.max_col_width,
+        Some(start),
+    )?;
+    println!("{}", theme::current().paint_table(&table.to_string()));
+    Ok(())
+}
+
+fn connection_info_rows(info: &ConnectionInfo, caps: &Capabilities) -> Vec<(String, String)> {
+    let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+    let mut rows = vec![
+        ("handler".to_string(), info.handler.clone()),
+        ("host".to_string(), info.host.clone()),
+        ("port".to_string(), info.port.to_string()),
+        ("tls".to_string(), info.tls.to_string()),
+        ("user".to_string(), info.user.clone()),
+        ("tenant".to_string(), opt(&info.tenant)),
+        ("warehouse".to_string(), opt(&info.warehouse)),
+        ("database".to_string(), opt(&info.database)),
+        ("role".to_string(), opt(&info.role)),
+        ("server_version".to_string(), opt(&info.server_version)),
+    ];
+    let features = [
+        ("login", caps.login),
+        ("session_token", caps.session_token),
+        ("presign", caps.presign),
+        ("query_params", caps.query_params),
+        ("distinct_null", caps.distinct_null),
+    ];
+    let features = features
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    rows.push(("capabilities".to_string(), features.join(", ")));
+    for (k, v) in &info.settings {
+        rows.push((format!("settings.{}", k), v.clone()));
+    }
+    rows
+}
+
+/// Print the connection info, capabilities and session settings in a table, for `!info`.
+pub fn print_connection_info(info: &ConnectionInfo, caps: &Capabilities) {
+    let mut table = Table::new();
+    table.load_preset("││──├─┼┤│    ──┌┐└┘");
+    let theme = theme::current();
+    table.set_header(vec![
+        Cell::new(theme.header("name")),
+        Cell::new(theme.header("value")),
+    ]);
+    for (k, v) in connection_info_rows(info, caps) {
+        table.add_row(vec![k, v]);
+    }
+    println!("{}", theme.paint_table(&table.to_string()));
+}
+
+/// Parse a 1-based inclusive row range like `100-200`, `100-` or `100`
+/// into a 0-based half-open range of `total` rows.
+pub fn parse_row_range(range: &str, total: usize) -> Result<(usize, usize)> {
+    let err = || anyhow!("Invalid row range: {}, should be like 100-200", range);
+    let parse = |s: &str| s.trim().parse::<usize>().map_err(|_| err());
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) if end.trim().is_empty() => (parse(start)?, total),
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => (parse(range)?, parse(range)?),
+    };
+    if start == 0 || start > end {
+        return Err(err());
+    }
+    if start > total {
+        return Err(anyhow!("Row {} out of range, only {} rows", start, total));
+    }
+    Ok((start - 1, end.min(total)))
+}
+
 fn print_expanded(schema: SchemaRef, results: &[Row]) -> Result<()> {
+    let theme = theme::current();
     let mut head_width = 0;
     for field in schema.fields() {
         if field.name.len() > head_width {
@@ -840,7 +1063,8 @@ fn print_expanded(schema: SchemaRef, results: &[Row]) -> Result<()> {
             row + 1
         );
         for (idx, field) in schema.fields().iter().enumerate() {
-            println!("{: >head_width$}: {}", field.name, result.values()[idx]);
+            let name = format!("{: >head_width$}", field.name);
+            println!("{}: {}", theme.header(&name), result.values()[idx]);
         }
     }
     println!();
@@ -887,3 +1111,129 @@ pub fn humanize_count(num: f64) -> String {
     let unit = units[exponent as usize];
     format!("{}{}{}", negative, pretty_bytes, unit)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use databend_driver::{NumberValue, Value};
+
+    #[tokio::test]
+    async fn tee_results() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("bendsql_tee_{}.csv", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let schema = Arc::new(Schema::from_vec(vec![
+            Field {
+                name: "a".to_string(),
+                data_type: DataType::Number(NumberDataType::Int32),
+            },
+            Field {
+                name: "b".to_string(),
+                data_type: DataType::String,
+            },
+        ]));
+        let rows = vec![
+            Row::from_vec(
+                schema.clone(),
+                vec![
+                    Value::Number(NumberValue::Int32(1)),
+                    Value::String("x".to_string()),
+                ],
+            ),
+            Row::from_vec(
+                schema.clone(),
+                vec![
+                    Value::Number(NumberValue::Int32(2)),
+                    Value::String("y, z".to_string()),
+                ],
+            ),
+        ];
+        let settings = Settings {
+            output_format: OutputFormat::Null,
+            show_stats: false,
+            ..Default::default()
+        };
+        let mut tee = Tee::open(&path)?;
+        for _ in 0..2 {
+            let stream =
+                tokio_stream::iter(rows.clone().into_iter().map(|r| Ok(RowWithStats::Row(r))));
+            let data = RowStatsIterator::new(schema.clone(), Box::pin(stream));
+            let mut displayer = FormatDisplay::new(
+                &settings,
+                "SELECT a, b FROM t",
+                false,
+                Instant::now(),
+                data,
+                Arc::new(AtomicBool::new(false)),
+            )
+            .with_tee(Some(&mut tee));
+            displayer.display(None).await?;
+        }
+        let content = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(content, "a,b\n1,x\n2,\"y, z\"\na,b\n1,x\n2,\"y, z\"\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_plain_progress_line() {
+        let ss = ServerStats {
+            total_rows: 1000,
+            read_rows: 500,
+            total_bytes: 2048,
+            read_bytes: 1024,
+            ..Default::default()
+        };
+        assert_eq!(
+            plain_progress_line(&ss, "read", 2.0),
+            "==> [2.0s] Processing 500/1 thousand (250 rows/s), 1.00 KiB/2.00 KiB (512 B/s)"
+        );
+    }
+
+    #[test]
+    fn test_parse_row_range() {
+        assert_eq!(parse_row_range("100-200", 1000).unwrap(), (99, 200));
+        assert_eq!(parse_row_range("100-2000", 1000).unwrap(), (99, 1000));
+        assert_eq!(parse_row_range("990-", 1000).unwrap(), (989, 1000));
+        assert_eq!(parse_row_range("5", 1000).unwrap(), (4, 5));
+        assert!(parse_row_range("0-10", 1000).is_err());
+        assert!(parse_row_range("20-10", 1000).is_err());
+        assert!(parse_row_range("1001-1002", 1000).is_err());
+        assert!(parse_row_range("a-b", 1000).is_err());
+    }
+
+    #[test]
+    fn test_connection_info_rows() {
+        let info = ConnectionInfo {
+            handler: "RestAPI".to_string(),
+            host: "localhost".to_string(),
+            port: 8000,
+            user: "root".to_string(),
+            database: Some("db1".to_string()),
+            warehouse: None,
+            role: Some("analyst".to_string()),
+            tenant: None,
+            tls: false,
+            settings: [("timezone".to_string(), "UTC".to_string())].into(),
+            server_version: Some("v1.2.700".to_string()),
+        };
+        let caps = Capabilities {
+            login: true,
+            query_params: true,
+            ..Default::default()
+        };
+        let rows = connection_info_rows(&info, &caps);
+        assert_eq!(rows.len(), 12);
+        assert_eq!(rows[7], ("database".to_string(), "db1".to_string()));
+        assert_eq!(
+            rows[10],
+            (
+                "capabilities".to_string(),
+                "login, query_params".to_string()
+            )
+        );
+        assert_eq!(
+            rows[11],
+            ("settings.timezone".to_string(), "UTC".to_string())
+        );
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, Result};
+use databend_driver::Row;
+
+/// Expectation of the next statement in `--test` mode, annotated in comments like
+/// `-- expect_rows: 3`, `-- expect_value: 42` or `-- expect_error: Unknown table`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+    /// number of rows returned
+    Rows(usize),
+    /// first column of the first row
+    Value(String),
+    /// the statement fails, with the message containing the text if any
+    Error(Option<String>),
+}
+
+impl Expectation {
+    /// Parse a comment line, `None` if it is not an annotation.
+    pub fn parse(line: &str) -> Result<Option<Self>> {
+        let comment = match line.trim().strip_prefix("--") {
+            Some(comment) => comment.trim(),
+            None => return Ok(None),
+        };
+        let (key, value) = match comment.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => (comment, ""),
+        };
+        match key {
+            "expect_rows" => {
+                let rows = value
+                    .parse()
+                    .map_err(|_| anyhow!("invalid expect_rows: {}", value))?;
+                Ok(Some(Self::Rows(rows)))
+            }
+            "expect_value" => Ok(Some(Self::Value(value.to_string()))),
+            "expect_error" => Ok(Some(Self::Error(
+                (!value.is_empty()).then(|| value.to_string()),
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Check the result of the statement, return the reason if mismatched.
+    pub fn check(&self, result: &std::result::Result<Vec<Row>, String>) -> Option<String> {
+        match (self, result) {
+            (Self::Error(None), Err(_)) => None,
+            (Self::Error(Some(text)), Err(e)) => {
+                (!e.contains(text.as_str())).then(|| format!("expect error {}, got: {}", text, e))
+            }
+            (Self::Error(_), Ok(rows)) => Some(format!("expect error, got {} rows", rows.len())),
+            (_, Err(e)) => Some(format!("unexpected error: {}", e)),
+            (Self::Rows(n), Ok(rows)) => {
+                (rows.len() != *n).then(|| format!("expect {} rows, got {}", n, rows.len()))
+            }
+            (Self::Value(v), Ok(rows)) => {
+                let got = rows
+                    .first()
+                    .and_then(|row| row.values().first())
+                    .map(|value| value.to_string());
+                match got {
+                    Some(got) if got == *v => None,
+                    Some(got) => Some(format!("expect value {}, got {}", v, got)),
+                    None => Some(format!("expect value {}, got no rows", v)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use databend_client_mock::{MockResult, MockServer};
+
+    use crate::config::Settings;
+    use crate::session::Session;
+
+    #[test]
+    fn parse_expectations() -> Result<()> {
+        assert_eq!(
+            Expectation::parse("-- expect_rows: 3")?,
+            Some(Expectation::Rows(3))
+        );
+        assert_eq!(
+            Expectation::parse("--expect_value:abc def")?,
+            Some(Expectation::Value("abc def".to_string()))
+        );
+        assert_eq!(
+            Expectation::parse("-- expect_error")?,
+            Some(Expectation::Error(None))
+        );
+        assert_eq!(
+            Expectation::parse("-- expect_error: Unknown table")?,
+            Some(Expectation::Error(Some("Unknown table".to_string())))
+        );
+        assert_eq!(Expectation::parse("-- just a comment")?, None);
+        assert_eq!(Expectation::parse("SELECT 1")?, None);
+        assert!(Expectation::parse("-- expect_rows: many").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_test_script() -> Result<()> {
+        let server = MockServer::start().await;
+        server.on_query(
+            "SELECT a",
+            MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")], vec![Some("2")]]),
+        );
+        server.on_query(
+            "SELECT * FROM missing",
+            MockResult::error(1025, "Unknown table"),
+        );
+        let mut session = Session::try_new(
+            databend_driver::Client::new(server.dsn()),
+            Settings::default(),
+            false,
+        )
+        .await?;
+
+        let script = "
+-- expect_rows: 2
+SELECT a FROM t;
+-- expect_value: 1
+SELECT a FROM t;
+-- expect_error: Unknown table
+SELECT * FROM missing;
+CREATE TABLE t2 (a INT);
+";
+        session.handle_test(std::io::Cursor::new(script)).await?;
+
+        let script = "
+-- expect_rows: 3
+SELECT a FROM t;
+-- expect_value: 2
+SELECT a FROM t;
+";
+        let err = session
+            .handle_test(std::io::Cursor::new(script))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "2 of 2 statements failed");
+        Ok(())
+    }
+}
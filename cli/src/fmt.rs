@@ -0,0 +1,220 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::{ArgAction, Args, ValueEnum};
+use databend_client_sqlparse::split_statements;
+use databend_common_ast::parser::{parse_sql, tokenize_sql, Dialect};
+
+use crate::ast::{format_query_with, FormatConfig};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SqlDialect {
+    Experimental,
+    #[clap(name = "postgresql")]
+    PostgreSQL,
+    #[clap(name = "mysql")]
+    MySQL,
+    Hive,
+}
+
+impl From<SqlDialect> for Dialect {
+    fn from(d: SqlDialect) -> Self {
+        match d {
+            SqlDialect::Experimental => Dialect::Experimental,
+            SqlDialect::PostgreSQL => Dialect::PostgreSQL,
+            SqlDialect::MySQL => Dialect::MySQL,
+            SqlDialect::Hive => Dialect::Hive,
+        }
+    }
+}
+
+#[derive(Args, Debug, PartialEq)]
+pub struct FmtArgs {
+    #[clap(long, action = ArgAction::Help, help = "Print help information")]
+    help: Option<bool>,
+
+    #[clap(help = "Sql files to format, Default: stdin")]
+    files: Vec<PathBuf>,
+
+    #[clap(short = 'w', long, help = "Write the formatted sql back to the files")]
+    write: bool,
+
+    #[clap(
+        long,
+        help = "Check the files are formatted and valid without writing, exit non-zero if not"
+    )]
+    check: bool,
+
+    #[clap(long, default_value = "2", help = "Number of spaces to indent")]
+    indent: u8,
+
+    #[clap(long, help = "Convert keywords to uppercase")]
+    uppercase: bool,
+
+    #[clap(
+        long,
+        default_value = "0",
+        help = "Keep statements in one line if they fit in the width, 0 to always break lines"
+    )]
+    width: usize,
+
+    #[clap(
+        long,
+        default_value = "experimental",
+        help = "SQL dialect to parse with"
+    )]
+    dialect: SqlDialect,
+}
+
+impl FmtArgs {
+    fn config(&self) -> FormatConfig {
+        FormatConfig {
+            indent: self.indent,
+            uppercase: self.uppercase,
+            width: self.width,
+            dialect: self.dialect.into(),
+        }
+    }
+}
+
+pub fn format_sql(content: &str, config: &FormatConfig) -> String {
+    let mut output = String::new();
+    for (i, statement) in split_statements(content).into_iter().enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        output.push_str(&format_query_with(statement, config));
+        output.push_str(";\n");
+    }
+    output
+}
+
+/// Errors of statements failed to parse, with the position in statement.
+pub fn lint_sql(content: &str, dialect: Dialect) -> Vec<String> {
+    let mut errors = vec![];
+    for statement in split_statements(content) {
+        let result = tokenize_sql(statement).and_then(|tokens| parse_sql(&tokens, dialect));
+        if let Err(e) = result {
+            errors.push(e.display_with_source(statement).to_string());
+        }
+    }
+    errors
+}
+
+/// Format the files or stdin, print the result unless writing back to files.
+pub fn run(args: FmtArgs) -> Result<()> {
+    let config = args.config();
+    let inputs = if args.files.is_empty() {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content)?;
+        vec![("<stdin>".to_string(), None, content)]
+    } else {
+        let mut inputs = vec![];
+        for path in &args.files {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))?;
+            inputs.push((path.display().to_string(), Some(path), content));
+        }
+        inputs
+    };
+
+    let mut failed = 0;
+    for (name, path, content) in inputs {
+        let errors = lint_sql(&content, config.dialect);
+        for error in &errors {
+            eprintln!("{}: {}", name, error);
+        }
+        let formatted = format_sql(&content, &config);
+        if args.check {
+            if !errors.is_empty() || formatted != content {
+                if formatted != content {
+                    eprintln!("{}: not formatted", name);
+                }
+                failed += 1;
+            }
+        } else if let (true, Some(path)) = (args.write, path) {
+            if formatted != content {
+                std::fs::write(path, &formatted)?;
+            }
+        } else {
+            print!("{}", formatted);
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} files not formatted or invalid", failed));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_statements() {
+        let content = "-- count\nselect a, count(*) from t group by a; select 1;\n";
+        let config = FormatConfig::default();
+        assert_eq!(
+            format_sql(content, &config),
+            "-- count\nselect\n  a,\n  count(*)\nfrom\n  t\ngroup by\n  a;\n\nselect\n  1;\n"
+        );
+
+        let config = FormatConfig {
+            width: 40,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_sql("select a from t;", &config),
+            "select a from t;\n"
+        );
+        let formatted = format_sql(content, &config);
+        assert_eq!(format_sql(&formatted, &config), formatted);
+    }
+
+    #[test]
+    fn format_keeps_comments() {
+        let config = FormatConfig {
+            width: 80,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_sql("-- count rows\nselect a  from t;", &config),
+            "-- count rows\nselect a from t;\n"
+        );
+        assert_eq!(
+            format_sql("select a, /* the id */ b\n  from t -- all\n;", &config),
+            "select a, /* the id */ b from t -- all\n;\n"
+        );
+        let config = FormatConfig {
+            uppercase: true,
+            ..config
+        };
+        assert_eq!(
+            format_sql("select a from t where a > 1;", &config),
+            "SELECT a FROM t WHERE a > 1;\n"
+        );
+    }
+
+    #[test]
+    fn lint_statements() {
+        assert!(lint_sql("select 1; select * from t;", Dialect::Experimental).is_empty());
+        let errors = lint_sql("select 1; selec * from t;", Dialect::Experimental);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("selec"), "{}", errors[0]);
+    }
+}
@@ -21,6 +21,7 @@ use rustyline::completion::Pair;
 use rustyline::error::ReadlineError;
 use rustyline::highlight::Highlighter;
 use rustyline::hint::Hinter;
+use rustyline::hint::HistoryHinter;
 use rustyline::validate::ValidationContext;
 use rustyline::validate::ValidationResult;
 use rustyline::validate::Validator;
@@ -32,6 +33,7 @@ use crate::ast::highlight_query;
 
 pub struct CliHelper {
     completer: FilenameCompleter,
+    history: HistoryHinter,
     keywords: Option<Arc<sled::Db>>,
 }
 
@@ -39,6 +41,7 @@ impl CliHelper {
     pub fn new(keywords: Option<Arc<sled::Db>>) -> Self {
         Self {
             completer: FilenameCompleter::new(),
+            history: HistoryHinter {},
             keywords,
         }
     }
@@ -60,7 +63,7 @@ impl Highlighter for CliHelper {
     }
 
     fn highlight_hint<'h>(&self, hint: &'h str) -> std::borrow::Cow<'h, str> {
-        std::borrow::Cow::Owned("\x1b[1m".to_owned() + hint + "\x1b[m")
+        std::borrow::Cow::Owned("\x1b[2m".to_owned() + hint + "\x1b[m")
     }
 
     fn highlight_candidate<'c>(
@@ -80,10 +83,15 @@ impl Highlighter for CliHelper {
 impl Hinter for CliHelper {
     type Hint = String;
 
-    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<Self::Hint> {
+    /// Suggest the most recent history entry starting with the line first,
+    /// then the keyword of the last word, accepted with right arrow.
+    fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<Self::Hint> {
+        if let Some(hint) = self.history.hint(line, pos, ctx) {
+            return Some(hint);
+        }
         let last_word = line
             .split(|p: char| p.is_whitespace() || p == '.')
-            .last()
+            .next_back()
             .unwrap_or(line);
 
         if last_word.is_empty() {
@@ -141,7 +149,7 @@ impl KeyWordCompleter {
     fn complete(s: &str, pos: usize, db: &sled::Db) -> (usize, Vec<Pair>) {
         let hint = s
             .split(|p: char| p.is_whitespace() || p == '.')
-            .last()
+            .next_back()
             .unwrap_or(s)
             .to_ascii_lowercase();
 
@@ -164,3 +172,32 @@ impl KeyWordCompleter {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rustyline::history::{DefaultHistory, History};
+
+    #[test]
+    fn hint_from_history() {
+        let mut history = DefaultHistory::new();
+        history.add("select * from orders;").unwrap();
+        history.add("select count(*) from t;").unwrap();
+        history.add("show tables;").unwrap();
+        let ctx = Context::new(&history);
+        let helper = CliHelper::new(None);
+
+        assert_eq!(
+            helper.hint("sel", 3, &ctx).as_deref(),
+            Some("ect count(*) from t;")
+        );
+        assert_eq!(
+            helper.hint("select *", 8, &ctx).as_deref(),
+            Some(" from orders;")
+        );
+        // only at the end of line
+        assert_eq!(helper.hint("sel", 1, &ctx), None);
+        assert_eq!(helper.hint("drop", 4, &ctx), None);
+        assert_eq!(helper.hint("show tables;", 12, &ctx), None);
+    }
+}
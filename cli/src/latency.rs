@@ -0,0 +1,138 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::args::ConnectionArgs;
+
+/// Latencies of each step to connect, measured with fresh connections for
+/// the network steps since the driver does not expose them.
+#[derive(Debug, Default)]
+pub struct ConnectLatency {
+    pub addr: Option<SocketAddr>,
+    pub dns: Duration,
+    pub tcp: Duration,
+    pub tls: Option<Result<Duration, (Duration, String)>>,
+    pub auth: Duration,
+    pub first_query: Duration,
+}
+
+impl std::fmt::Display for ConnectLatency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let addr = self.addr.map(|a| a.to_string()).unwrap_or_default();
+        writeln!(f, "  dns          {:>10.2?}  {}", self.dns, addr)?;
+        writeln!(f, "  tcp connect  {:>10.2?}", self.tcp)?;
+        match &self.tls {
+            Some(Ok(tls)) => writeln!(f, "  tls          {:>10.2?}", tls)?,
+            Some(Err((tls, e))) => writeln!(f, "  tls          {:>10.2?}  failed: {}", tls, e)?,
+            None => writeln!(f, "  tls          {:>10}", "disabled")?,
+        }
+        writeln!(f, "  auth         {:>10.2?}", self.auth)?;
+        write!(f, "  first query  {:>10.2?}", self.first_query)
+    }
+}
+
+fn tls_connector() -> Result<TlsConnector> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Measure dns, tcp and tls handshake with a probe connection, then login
+/// and run the first query with the driver.
+pub async fn measure(args: &ConnectionArgs) -> Result<ConnectLatency> {
+    let (host, port, tls) = args.endpoint();
+    let mut latency = ConnectLatency::default();
+
+    let start = Instant::now();
+    let addr = tokio::net::lookup_host((host.as_str(), port))
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("no address found for {}", host))?;
+    latency.dns = start.elapsed();
+    latency.addr = Some(addr);
+
+    let start = Instant::now();
+    let stream = TcpStream::connect(addr).await?;
+    latency.tcp = start.elapsed();
+
+    if tls {
+        let start = Instant::now();
+        let result = match ServerName::try_from(host.clone()) {
+            Ok(name) => tls_connector()?
+                .connect(name, stream)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        latency.tls = Some(match result {
+            Ok(_) => Ok(start.elapsed()),
+            Err(e) => Err((start.elapsed(), e)),
+        });
+    }
+
+    let start = Instant::now();
+    let conn = args.clone().client()?.get_conn().await?;
+    latency.auth = start.elapsed();
+
+    let start = Instant::now();
+    conn.exec("SELECT 1", ()).await?;
+    latency.first_query = start.elapsed();
+    conn.close().await?;
+    Ok(latency)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use databend_client_mock::MockServer;
+
+    #[test]
+    fn endpoint_defaults() -> Result<()> {
+        let endpoint = |dsn| ConnectionArgs::from_dsn(dsn).map(|args| args.endpoint());
+        assert_eq!(
+            endpoint("databend://root:@app.databend.com/db")?,
+            ("app.databend.com".to_string(), 443, true)
+        );
+        assert_eq!(
+            endpoint("databend+flight://root:@localhost:8900/db?sslmode=disable")?,
+            ("localhost".to_string(), 8900, false)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn measure_mock() -> Result<()> {
+        let server = MockServer::start().await;
+        let latency = measure(&ConnectionArgs::from_dsn(&server.dsn())?).await?;
+        assert_eq!(latency.addr, Some(server.addr()));
+        assert!(latency.tls.is_none());
+        assert!(server.queries().contains(&"SELECT 1".to_string()));
+        Ok(())
+    }
+}
@@ -0,0 +1,541 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{anyhow, Result};
+use clap::{ArgAction, Args};
+use databend_driver::query_builder::quote_ident;
+use databend_driver::rest_api::parse_on_error;
+use databend_driver::{Connection, Param};
+use futures::stream::{self, StreamExt};
+use log::info;
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+
+use crate::{parse_key_val, InputFormat};
+
+#[derive(Args, Debug, PartialEq)]
+pub struct LoadArgs {
+    #[clap(long, action = ArgAction::Help, help = "Print help information")]
+    help: Option<bool>,
+
+    #[clap(long, help = "Table to load data into")]
+    table: String,
+
+    #[clap(long, help = "Directory containing the files to load")]
+    path: PathBuf,
+
+    #[clap(short = 'f', long, default_value = "csv", help = "Data format to load")]
+    format: InputFormat,
+
+    #[clap(long, value_parser = parse_key_val::<String, String>, help = "Data format options")]
+    format_opt: Vec<(String, String)>,
+
+    #[clap(
+        long,
+        value_parser = parse_on_error,
+        help = "How to handle malformed rows when loading data: continue, abort or abort_N"
+    )]
+    on_error: Option<String>,
+
+    #[clap(
+        long,
+        help = "Manifest file to track loaded files, Default: <path>/.bendsql_load_<table>.manifest"
+    )]
+    manifest: Option<PathBuf>,
+
+    #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..), help = "Files to copy in one COPY statement")]
+    batch_size: u32,
+
+    #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..), help = "COPY statements to run at once")]
+    concurrency: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum FileState {
+    Uploaded,
+    Loaded,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ManifestEntry {
+    size: u64,
+    modified: u64,
+    state: FileState,
+}
+
+/// Local record of the files already uploaded and loaded, so an interrupted
+/// load can be resumed with the same stage.
+#[derive(Serialize, Deserialize, Debug)]
+struct Manifest {
+    table: String,
+    stage: String,
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    fn open(path: &Path, table: &str) -> Result<Self> {
+        if path.exists() {
+            let content = std::fs::read_to_string(path)?;
+            let manifest: Manifest = serde_json::from_str(&content)
+                .map_err(|e| anyhow!("invalid manifest {}: {}", path.display(), e))?;
+            if manifest.table != table {
+                return Err(anyhow!(
+                    "manifest {} belongs to table {}",
+                    path.display(),
+                    manifest.table
+                ));
+            }
+            return Ok(manifest);
+        }
+        let now = chrono::Utc::now().timestamp_nanos_opt().ok_or_else(|| {
+            anyhow!("Failed to get timestamp, please check your system time is correct and retry.")
+        })?;
+        Ok(Self {
+            table: table.to_string(),
+            stage: format!("@~/client/load/{}", now),
+            files: BTreeMap::new(),
+        })
+    }
+
+    /// Write to a temporary file first, so that the manifest is never left half written.
+    fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn state(&self, name: &str, size: u64, modified: u64) -> Option<FileState> {
+        self.files
+            .get(name)
+            .filter(|e| e.size == size && e.modified == modified)
+            .map(|e| e.state)
+    }
+
+    fn set_state(&mut self, name: &str, size: u64, modified: u64, state: FileState) {
+        self.files.insert(
+            name.to_string(),
+            ManifestEntry {
+                size,
+                modified,
+                state,
+            },
+        );
+    }
+}
+
+impl InputFormat {
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            InputFormat::CSV => &["csv"],
+            InputFormat::TSV => &["tsv"],
+            InputFormat::NDJSON => &["ndjson", "jsonl", "json"],
+            InputFormat::Parquet => &["parquet"],
+            InputFormat::XML => &["xml"],
+        }
+    }
+}
+
+fn collect_files(dir: &Path, extensions: &[&str], files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, extensions, files)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn copy_sql(
+    table: &str,
+    stage: &str,
+    files: &[&str],
+    format_options: &BTreeMap<&str, &str>,
+    on_error: Option<&str>,
+) -> String {
+    let format_options = format_options
+        .iter()
+        .map(|(k, v)| match *k {
+            "type" => format!("type = {}", v),
+            _ => format!("{} = {}", k, v.as_sql_string()),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let files = files
+        .iter()
+        .map(|f| f.as_sql_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut sql = format!(
+        "COPY INTO {} FROM {} FILES = ({}) FILE_FORMAT = ({}) PURGE = TRUE",
+        quote_ident(table),
+        stage,
+        files,
+        format_options
+    );
+    if let Some(on_error) = on_error {
+        sql.push_str(&format!(" ON_ERROR = {}", on_error));
+    }
+    sql
+}
+
+struct PendingFile {
+    path: PathBuf,
+    name: String,
+    size: u64,
+    modified: u64,
+    uploaded: bool,
+}
+
+/// Files of a batch uploaded, which are kept in the stage even if the COPY fails.
+struct BatchResult<'a> {
+    batch: &'a [PendingFile],
+    uploaded: Vec<&'a PendingFile>,
+    rows: Result<i64>,
+}
+
+/// Upload the files of a batch not in the stage yet, then copy them into the table.
+async fn load_batch<'a>(
+    conn: &Connection,
+    stage: &str,
+    batch: &'a [PendingFile],
+    sql: String,
+) -> BatchResult<'a> {
+    let mut uploaded = vec![];
+    for file in batch.iter().filter(|f| !f.uploaded) {
+        let data = match File::open(&file.path).await {
+            Ok(data) => data,
+            Err(e) => {
+                return BatchResult {
+                    batch,
+                    uploaded,
+                    rows: Err(e.into()),
+                }
+            }
+        };
+        let stage_file = format!("{}/{}", stage, file.name);
+        if let Err(e) = conn
+            .upload_to_stage(&stage_file, Box::new(data), file.size)
+            .await
+        {
+            return BatchResult {
+                batch,
+                uploaded,
+                rows: Err(e.into()),
+            };
+        }
+        uploaded.push(file);
+    }
+    let rows = conn.exec(&sql, ()).await.map_err(anyhow::Error::from);
+    BatchResult {
+        batch,
+        uploaded,
+        rows,
+    }
+}
+
+/// Upload all matching files under a directory to a stage and copy them
+/// into the table in batches of `--batch-size` files, with up to `--concurrency`
+/// batches at once, so files are uploaded while other batches are copied.
+/// Progress is recorded in a manifest, files are skipped on resume once loaded,
+/// and `COPY INTO` dedups files already loaded from the stage in case the
+/// manifest was not updated in time. After a batch fails, no more batches are
+/// started, the running ones are waited for and recorded before returning the error.
+pub async fn load_dir(conn: &Connection, args: LoadArgs) -> Result<()> {
+    if !args.path.is_dir() {
+        return Err(anyhow!("not a directory: {}", args.path.display()));
+    }
+    let manifest_path = args.manifest.clone().unwrap_or_else(|| {
+        args.path
+            .join(format!(".bendsql_load_{}.manifest", args.table))
+    });
+    let mut manifest = Manifest::open(&manifest_path, &args.table)?;
+    let format_options = args.format.get_options(&args.format_opt);
+
+    let mut files = vec![];
+    collect_files(&args.path, args.format.extensions(), &mut files)?;
+    files.sort();
+    info!(
+        "load {} files from {} into {} with stage {}",
+        files.len(),
+        args.path.display(),
+        args.table,
+        manifest.stage
+    );
+
+    let mut pending = vec![];
+    let mut skipped = 0;
+    for file in files {
+        let name = file
+            .strip_prefix(&args.path)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let metadata = std::fs::metadata(&file)?;
+        let size = metadata.len();
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let state = manifest.state(&name, size, modified);
+        if state == Some(FileState::Loaded) {
+            skipped += 1;
+            continue;
+        }
+        pending.push(PendingFile {
+            path: file,
+            name,
+            size,
+            modified,
+            uploaded: state == Some(FileState::Uploaded),
+        });
+    }
+
+    let total = pending.len();
+    let stage = manifest.stage.clone();
+    let failed = Cell::new(false);
+    let batches = pending
+        .chunks(args.batch_size as usize)
+        .take_while(|_| !failed.get())
+        .map(|batch| {
+            let names = batch.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+            let sql = copy_sql(
+                &args.table,
+                &stage,
+                &names,
+                &format_options,
+                args.on_error.as_deref(),
+            );
+            load_batch(conn, &stage, batch, sql)
+        });
+    let mut results = stream::iter(batches).buffer_unordered(args.concurrency as usize);
+    let (mut loaded, mut total_rows) = (0, 0);
+    let mut error = None;
+    while let Some(result) = results.next().await {
+        for file in &result.uploaded {
+            manifest.set_state(&file.name, file.size, file.modified, FileState::Uploaded);
+        }
+        let rows = match result.rows {
+            Ok(rows) => rows,
+            Err(e) => {
+                manifest.save(&manifest_path)?;
+                failed.set(true);
+                error.get_or_insert(e);
+                continue;
+            }
+        };
+        for file in result.batch {
+            manifest.set_state(&file.name, file.size, file.modified, FileState::Loaded);
+        }
+        manifest.save(&manifest_path)?;
+        loaded += result.batch.len();
+        total_rows += rows;
+        let names = result
+            .batch
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>();
+        eprintln!(
+            "==> loaded {}: {} rows ({}/{} files)",
+            names.join(", "),
+            rows,
+            loaded,
+            total
+        );
+    }
+    if let Some(e) = error {
+        return Err(e);
+    }
+    // files with rejected rows are not purged by COPY
+    if total > 0 {
+        conn.exec(&format!("REMOVE {}/", manifest.stage), ())
+            .await?;
+    }
+    eprintln!(
+        "==> loaded {} files with {} rows into {}, skipped {} loaded files",
+        loaded, total_rows, args.table, skipped
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use databend_client_mock::{MockResult, MockServer};
+    use databend_driver::Client;
+
+    #[test]
+    fn test_copy_sql() {
+        let options = vec![
+            ("type", "CSV"),
+            ("field_delimiter", ","),
+            ("record_delimiter", "\n"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            copy_sql("t", "@~/client/load/1", &["a/b's.csv"], &options, Some("continue")),
+            "COPY INTO t FROM @~/client/load/1 FILES = ('a/b\\'s.csv') FILE_FORMAT = (field_delimiter = ',', record_delimiter = '\n', type = CSV) PURGE = TRUE ON_ERROR = continue"
+        );
+        assert!(
+            copy_sql("db.`my t`", "@s", &[], &options, None).starts_with("COPY INTO db.`my t` ")
+        );
+        assert!(copy_sql("a`b", "@s", &[], &options, None).starts_with("COPY INTO `a``b` "));
+    }
+
+    #[tokio::test]
+    async fn load_dir_resume() -> Result<()> {
+        let server = MockServer::start().await;
+        server.on_query("COPY INTO", MockResult::empty().with_write_rows(2));
+        let conn = Client::new(server.dsn()).get_conn().await?;
+
+        let dir = std::env::temp_dir().join(format!("bendsql_load_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub"))?;
+        std::fs::write(dir.join("a.csv"), "1,a\n2,b\n")?;
+        std::fs::write(dir.join("sub/b.csv"), "3,c\n4,d\n")?;
+        std::fs::write(dir.join("c.txt"), "ignored")?;
+        let args = || LoadArgs {
+            help: None,
+            table: "t".to_string(),
+            path: dir.clone(),
+            format: InputFormat::CSV,
+            format_opt: vec![],
+            on_error: None,
+            manifest: None,
+            batch_size: 1,
+            concurrency: 1,
+        };
+        load_dir(&conn, args()).await?;
+        let uploads = server.uploads();
+        assert_eq!(uploads.len(), 2);
+        assert!(uploads[1].path.ends_with("/sub/b.csv"));
+        assert_eq!(uploads[1].data, b"3,c\n4,d\n");
+
+        // loaded files are skipped on resume
+        load_dir(&conn, args()).await?;
+        assert_eq!(server.uploads().len(), 2);
+        let copies = server
+            .queries()
+            .into_iter()
+            .filter(|sql| sql.starts_with("COPY INTO t "))
+            .count();
+        assert_eq!(copies, 2);
+        let removes = server
+            .queries()
+            .into_iter()
+            .filter(|sql| sql.starts_with("REMOVE @~/client/load/"))
+            .count();
+        assert_eq!(removes, 1);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn load_dir_batches() -> Result<()> {
+        let server = MockServer::start().await;
+        server.on_query("COPY INTO", MockResult::empty().with_write_rows(2));
+        let conn = Client::new(server.dsn()).get_conn().await?;
+
+        let dir = std::env::temp_dir().join(format!("bendsql_load_batch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        for name in ["a", "b", "c"] {
+            std::fs::write(dir.join(format!("{name}.csv")), "1,a\n2,b\n")?;
+        }
+        let args = LoadArgs {
+            help: None,
+            table: "t".to_string(),
+            path: dir.clone(),
+            format: InputFormat::CSV,
+            format_opt: vec![],
+            on_error: None,
+            manifest: None,
+            batch_size: 2,
+            concurrency: 2,
+        };
+        load_dir(&conn, args).await?;
+        assert_eq!(server.uploads().len(), 3);
+        let mut copies = server
+            .queries()
+            .into_iter()
+            .filter(|sql| sql.starts_with("COPY INTO t "))
+            .collect::<Vec<_>>();
+        copies.sort();
+        assert_eq!(copies.len(), 2);
+        assert!(copies[0].contains("FILES = ('a.csv', 'b.csv')"));
+        assert!(copies[1].contains("FILES = ('c.csv')"));
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn load_dir_failed_batch() -> Result<()> {
+        let server = MockServer::start().await;
+        server.on_query("COPY INTO", MockResult::empty().with_write_rows(2));
+        server.on_query(
+            "COPY INTO t FROM @~/client/load/1 FILES = ('b.csv')",
+            MockResult::error(1046, "bad file"),
+        );
+        let conn = Client::new(server.dsn()).get_conn().await?;
+
+        let dir = std::env::temp_dir().join(format!("bendsql_load_fail_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        for name in ["a", "b", "c"] {
+            std::fs::write(dir.join(format!("{name}.csv")), "1,a\n2,b\n")?;
+        }
+        let manifest_path = dir.join("load.manifest");
+        std::fs::write(
+            &manifest_path,
+            r#"{"table": "t", "stage": "@~/client/load/1", "files": {}}"#,
+        )?;
+        let args = || LoadArgs {
+            help: None,
+            table: "t".to_string(),
+            path: dir.clone(),
+            format: InputFormat::CSV,
+            format_opt: vec![],
+            on_error: None,
+            manifest: Some(manifest_path.clone()),
+            batch_size: 1,
+            concurrency: 2,
+        };
+        let err = load_dir(&conn, args()).await.unwrap_err();
+        assert!(err.to_string().contains("bad file"), "{}", err);
+        let manifest = Manifest::open(&manifest_path, "t")?;
+        assert_eq!(manifest.files["a.csv"].state, FileState::Loaded);
+        assert_eq!(manifest.files["b.csv"].state, FileState::Uploaded);
+        assert!(!server.queries().iter().any(|q| q.starts_with("REMOVE")));
+
+        // resumed without uploading the failed file again
+        server.on_query("COPY INTO", MockResult::empty().with_write_rows(2));
+        load_dir(&conn, args()).await?;
+        assert_eq!(server.uploads().len(), 3);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -16,21 +16,30 @@
 
 mod args;
 mod ast;
+mod compat;
 mod config;
+mod connect;
 mod display;
+mod expect;
+mod fmt;
 mod gendata;
 mod helper;
+mod latency;
+mod load;
+mod prompt;
 mod session;
+mod theme;
 mod trace;
 mod web;
 
 use std::{
     collections::BTreeMap,
     io::{stdin, IsTerminal},
+    path::PathBuf,
 };
 
 use anyhow::{anyhow, Result};
-use clap::{ArgAction, CommandFactory, Parser, ValueEnum};
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
 use databend_client::SensitiveString;
 use log::info;
 use once_cell::sync::Lazy;
@@ -111,6 +120,13 @@ struct Args {
     #[clap(long, help = "Print help information")]
     help: bool,
 
+    #[clap(
+        long,
+        env = "BENDSQL_PROFILE",
+        help = "Connection profile in config file, saved by `bendsql connect`"
+    )]
+    profile: Option<String>,
+
     #[clap(long, help = "Using flight sql protocol, ignored when --dsn is set")]
     flight: bool,
 
@@ -170,12 +186,40 @@ struct Args {
     )]
     no_auto_complete: bool,
 
-    #[clap(long, help = "Check for server status and exit")]
+    #[clap(
+        long,
+        help = "File of statements and commands to run on REPL startup, Default: ~/.bendsqlrc"
+    )]
+    init_file: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "Check for server status and features, exit with 2 if some features are not available"
+    )]
     check: bool,
 
+    #[clap(
+        long,
+        help = "Show latencies of dns, tcp, tls handshake, auth and the first query in stderr when connecting"
+    )]
+    verbose_connect: bool,
+
+    #[clap(
+        long,
+        help = "Run statements as tests, check annotations like `-- expect_rows: 3` and exit non-zero on mismatch"
+    )]
+    test: bool,
+
     #[clap(long, require_equals = true, help = "Query to execute")]
     query: Option<String>,
 
+    #[clap(
+        long = "param",
+        value_parser = parse_key_val::<String, String>,
+        help = "Bind `:name` in the query or statements from stdin, numbers as is and others as strings"
+    )]
+    params: Vec<(String, String)>,
+
     #[clap(short = 'd', long, help = "Data to load, @file or @- for stdin")]
     data: Option<String>,
 
@@ -185,6 +229,31 @@ struct Args {
     #[clap(long, value_parser = parse_key_val::<String, String>, help = "Data format options")]
     format_opt: Vec<(String, String)>,
 
+    #[clap(
+        long,
+        value_parser = databend_driver::rest_api::parse_on_error,
+        help = "How to handle malformed rows when loading data: continue, abort or abort_N"
+    )]
+    on_error: Option<String>,
+
+    #[clap(
+        long,
+        help = "Compress data with zstd on client side while loading, overrides load_compression in DSN"
+    )]
+    compress: bool,
+
+    #[clap(
+        long,
+        help = "Limit the bandwidth of stage transfers, e.g. 50MB/s, overrides limit_rate in DSN"
+    )]
+    limit_rate: Option<String>,
+
+    #[clap(
+        long,
+        help = "Abort loading data after N malformed rows, same as `--on-error=abort_N`"
+    )]
+    max_errors: Option<u64>,
+
     #[clap(short = 'o', long, help = "Output format")]
     output: Option<OutputFormat>,
 
@@ -196,7 +265,7 @@ struct Args {
 
     #[clap(
         long,
-        help = "Show progress for query execution in stderr, only works with output format `table` and `null`."
+        help = "Show progress for query execution in stderr, including statements like INSERT and DELETE."
     )]
     progress: bool,
 
@@ -214,8 +283,60 @@ struct Args {
     )]
     time: Option<TimeOption>,
 
+    #[clap(
+        long,
+        help = "Append LIMIT to SELECTs without one for exploring, overrides auto_limit in config"
+    )]
+    sample: Option<usize>,
+
     #[clap(short = 'l', default_value = "info", long)]
     log_level: String,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand, PartialEq)]
+enum Command {
+    /// Load all files in a directory into a table, resume from the last run if interrupted
+    Load(load::LoadArgs),
+    /// Prompt for connection options, test and save them as a profile in the config file
+    Connect(connect::ConnectArgs),
+    /// Manage the config file
+    Config {
+        #[clap(long, action = ArgAction::Help, help = "Print help information")]
+        help: Option<bool>,
+
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Format sql files or stdin, or check them with --check
+    Fmt(fmt::FmtArgs),
+    /// Generate shell completions to stdout
+    Completions {
+        #[clap(long, action = ArgAction::Help, help = "Print help information")]
+        help: Option<bool>,
+
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Generate man page to stdout
+    Man {
+        #[clap(long, action = ArgAction::Help, help = "Print help information")]
+        help: Option<bool>,
+    },
+}
+
+#[derive(Debug, Subcommand, PartialEq)]
+enum ConfigCommand {
+    /// Validate the config file and print errors with line numbers
+    Check {
+        #[clap(long, action = ArgAction::Help, help = "Print help information")]
+        help: Option<bool>,
+
+        #[clap(long, help = "Config file to check, Default: the one being loaded")]
+        path: Option<String>,
+    },
 }
 
 /// Parse a single key-value pair
@@ -236,7 +357,7 @@ where
 
 #[tokio::main]
 pub async fn main() -> Result<()> {
-    let config = Config::load();
+    let mut config = Config::load();
 
     let args = Args::parse();
     let mut cmd = Args::command();
@@ -244,6 +365,43 @@ pub async fn main() -> Result<()> {
         cmd.print_help()?;
         return Ok(());
     }
+    match args.command {
+        Some(Command::Completions { shell, .. }) => {
+            clap_complete::generate(shell, &mut cmd, "bendsql", &mut std::io::stdout());
+            return Ok(());
+        }
+        Some(Command::Man { .. }) => {
+            clap_mangen::Man::new(cmd).render(&mut std::io::stdout())?;
+            return Ok(());
+        }
+        Some(Command::Connect(connect_args)) => {
+            return connect::connect_wizard(connect_args).await;
+        }
+        Some(Command::Fmt(fmt_args)) => {
+            return fmt::run(fmt_args);
+        }
+        Some(Command::Config {
+            command: ConfigCommand::Check { path, .. },
+            ..
+        }) => {
+            let path = path
+                .or_else(Config::find)
+                .ok_or_else(|| anyhow!("no config file found"))?;
+            Config::check_file(&path)?;
+            println!("config file {} is valid", path);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    if let Some(profile) = &args.profile {
+        match config.profiles.remove(profile) {
+            Some(conn) => config.connection = conn,
+            // profile `default` is saved as `[connection]`
+            None if profile == "default" => {}
+            None => return Err(anyhow!("profile {} not found in config file", profile)),
+        }
+    }
 
     let mut conn_args = match args.dsn {
         Some(ref dsn) => {
@@ -279,7 +437,10 @@ pub async fn main() -> Result<()> {
             }
 
             let user = args.user.unwrap_or_else(|| config.connection.user.clone());
-            let password = args.password.unwrap_or_else(|| SensitiveString::from(""));
+            let password = args
+                .password
+                .or_else(|| config.connection.password.clone())
+                .unwrap_or_else(|| SensitiveString::from(""));
 
             ConnectionArgs {
                 host,
@@ -288,6 +449,7 @@ pub async fn main() -> Result<()> {
                 password,
                 database: config.connection.database.clone(),
                 flight: args.flight,
+                auto: false,
                 args: config.connection.args.clone(),
             }
         }
@@ -330,13 +492,27 @@ pub async fn main() -> Result<()> {
         if let Some(role) = args.role {
             conn_args.args.insert("role".to_string(), role);
         }
+
+        if let Some(limit_rate) = args.limit_rate {
+            conn_args.args.insert("limit_rate".to_string(), limit_rate);
+        }
+
+        if args.compress {
+            conn_args
+                .args
+                .insert("load_compression".to_string(), "zstd".to_string());
+        }
     }
 
     let user = conn_args.user.clone();
-    let dsn = conn_args.get_dsn()?;
     let mut settings = Settings::default();
     let is_terminal = stdin().is_terminal();
-    let is_repl = is_terminal && !args.non_interactive && !args.check && args.query.is_none();
+    let is_repl = is_terminal
+        && !args.non_interactive
+        && !args.check
+        && !args.test
+        && args.query.is_none()
+        && args.command.is_none();
     if is_repl {
         settings.display_pretty_sql = true;
         settings.show_progress = true;
@@ -367,6 +543,10 @@ pub async fn main() -> Result<()> {
         settings.output_format = OutputFormat::Null;
     }
     settings.time = args.time;
+    if let Some(sample) = args.sample {
+        settings.auto_limit = sample;
+    }
+    settings.apply_theme();
 
     let log_dir = format!(
         "{}/.bendsql",
@@ -376,7 +556,15 @@ pub async fn main() -> Result<()> {
     let _guards = trace::init_logging(&log_dir, &args.log_level).await?;
     info!("-> bendsql version: {}", VERSION.as_str());
 
-    let mut session = match session::Session::try_new(dsn, settings, is_repl).await {
+    if args.verbose_connect {
+        match latency::measure(&conn_args).await {
+            Ok(latency) => eprintln!("==> connect latency:\n{}", latency),
+            Err(e) => eprintln!("==> connect latency: failed: {}", e),
+        }
+    }
+
+    let mut session = match session::Session::try_new(conn_args.client()?, settings, is_repl).await
+    {
         Ok(session) => session,
         Err(err) => {
             // Exit client if user login failed.
@@ -386,11 +574,11 @@ pub async fn main() -> Result<()> {
                         println!("Authenticate failed wrong password user {}", user);
                         return Ok(());
                     }
-                    databend_driver::Error::Arrow(arrow::error::ArrowError::IpcError(ipc_err)) => {
-                        if ipc_err.contains("Unauthenticated") {
-                            println!("Authenticate failed wrong password user {}", user);
-                            return Ok(());
-                        }
+                    databend_driver::Error::Arrow(arrow::error::ArrowError::IpcError(ipc_err))
+                        if ipc_err.contains("Unauthenticated") =>
+                    {
+                        println!("Authenticate failed wrong password user {}", user);
+                        return Ok(());
                     }
                     _ => {}
                 }
@@ -399,12 +587,38 @@ pub async fn main() -> Result<()> {
         }
     };
 
+    session.set_params(&args.params);
+
     if args.check {
-        session.check().await?;
+        if !session.check().await? {
+            std::process::exit(compat::EXIT_UNAVAILABLE);
+        }
+        return Ok(());
+    }
+
+    if let Some(command) = args.command {
+        match command {
+            Command::Load(load_args) => load::load_dir(&session.conn, load_args).await?,
+            Command::Connect(_)
+            | Command::Fmt(_)
+            | Command::Config { .. }
+            | Command::Completions { .. }
+            | Command::Man { .. } => {
+                unreachable!("handled before connecting")
+            }
+        }
         return Ok(());
     }
 
     if is_repl {
+        let init_file = args.init_file.or_else(|| {
+            let path = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()))
+                .join(".bendsqlrc");
+            path.exists().then_some(path)
+        });
+        if let Some(path) = init_file {
+            session.handle_init_file(&path).await?;
+        }
         session.handle_repl().await;
         return Ok(());
     }
@@ -414,23 +628,44 @@ pub async fn main() -> Result<()> {
             if args.non_interactive {
                 return Err(anyhow!("no query specified"));
             }
-            session.handle_reader(stdin().lock()).await?;
+            if args.test {
+                session.handle_test(stdin().lock()).await?;
+            } else {
+                session.handle_reader(stdin().lock()).await?;
+            }
         }
         Some(query) => match args.data {
+            None if args.test => {
+                session.handle_test(std::io::Cursor::new(query)).await?;
+            }
             None => {
                 session.handle_reader(std::io::Cursor::new(query)).await?;
             }
             Some(data) => {
                 let options = args.format.get_options(&args.format_opt);
+                let max_errors = args.max_errors.map(|n| n.to_string());
+                let mut copy_options = BTreeMap::new();
+                if let Some(on_error) = &args.on_error {
+                    copy_options.insert("on_error", on_error.as_str());
+                }
+                if let Some(max_errors) = &max_errors {
+                    copy_options.insert("max_errors", max_errors.as_str());
+                }
                 if data.starts_with('@') {
                     match data.strip_prefix('@') {
-                        Some("-") => session.stream_load_stdin(&query, options).await?,
+                        Some("-") => {
+                            session
+                                .stream_load_stdin(&query, options, copy_options)
+                                .await?
+                        }
                         Some(fname) => {
                             let path = std::path::Path::new(fname);
                             if !path.exists() {
                                 return Err(anyhow!("file not found: {}", fname));
                             }
-                            session.stream_load_file(&query, path, options).await?
+                            session
+                                .stream_load_file(&query, path, options, copy_options)
+                                .await?
                         }
                         None => {
                             return Err(anyhow!("invalid data input: {}", data));
@@ -0,0 +1,176 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Prompt template, with tokens like `{user}`, colors like `{red}` and
+//! conditional sections like `{?role: as {role}}` or `{?warehouse=prod:{red}}`,
+//! which are rendered only when the value is set or equals to the given one.
+
+use std::collections::BTreeMap;
+
+use databend_driver::ConnectionInfo;
+
+const COLORS: [(&str, &str); 10] = [
+    ("reset", "\x1b[0m"),
+    ("bold", "\x1b[1m"),
+    ("black", "\x1b[30m"),
+    ("red", "\x1b[31m"),
+    ("green", "\x1b[32m"),
+    ("yellow", "\x1b[33m"),
+    ("blue", "\x1b[34m"),
+    ("magenta", "\x1b[35m"),
+    ("cyan", "\x1b[36m"),
+    ("white", "\x1b[37m"),
+];
+
+/// Values of tokens, conditional sections check the raw values, while
+/// `{database}` and `{warehouse}` have defaults when rendered.
+pub struct PromptContext {
+    values: BTreeMap<&'static str, String>,
+}
+
+impl PromptContext {
+    pub fn new(info: &ConnectionInfo) -> Self {
+        let mut values = BTreeMap::new();
+        values.insert("handler", info.handler.clone());
+        values.insert("host", info.host.clone());
+        values.insert("port", info.port.to_string());
+        values.insert("user", info.user.clone());
+        for (k, v) in [
+            ("database", &info.database),
+            ("warehouse", &info.warehouse),
+            ("role", &info.role),
+            ("tenant", &info.tenant),
+        ] {
+            if let Some(v) = v.as_ref().filter(|v| !v.is_empty()) {
+                values.insert(k, v.clone());
+            }
+        }
+        values.insert("time", chrono::Local::now().format("%H:%M:%S").to_string());
+        Self { values }
+    }
+
+    fn raw(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|v| v.as_str())
+    }
+
+    fn token(&self, name: &str) -> Option<String> {
+        if let Some((_, code)) = COLORS.iter().find(|(c, _)| *c == name) {
+            return Some(code.to_string());
+        }
+        match name {
+            "database" => Some(self.raw("database").unwrap_or("default").to_string()),
+            "warehouse" => Some(match self.raw("warehouse") {
+                Some(warehouse) => format!("({})", warehouse),
+                None => format!("{}:{}", self.values["host"], self.values["port"]),
+            }),
+            "role" | "tenant" => Some(self.raw(name).unwrap_or_default().to_string()),
+            _ => self.raw(name).map(|v| v.to_string()),
+        }
+    }
+
+    pub fn render(&self, template: &str) -> String {
+        let mut out = String::new();
+        let chars: Vec<char> = template.chars().collect();
+        self.render_until(&chars, &mut 0, false, &mut out);
+        out
+    }
+
+    /// Render from `pos` until the end, or the closing `}` of a section,
+    /// unknown tokens are kept as is.
+    fn render_until(&self, chars: &[char], pos: &mut usize, nested: bool, out: &mut String) {
+        while *pos < chars.len() {
+            let c = chars[*pos];
+            if c == '}' && nested {
+                *pos += 1;
+                return;
+            }
+            if c != '{' {
+                out.push(c);
+                *pos += 1;
+                continue;
+            }
+            let start = *pos;
+            if chars.get(start + 1) == Some(&'?') {
+                if let Some(colon) = chars[start..].iter().position(|c| *c == ':') {
+                    let cond: String = chars[start + 2..start + colon].iter().collect();
+                    let enabled = match cond.split_once('=') {
+                        Some((name, value)) => self.raw(name.trim()) == Some(value.trim()),
+                        None => self.raw(cond.trim()).is_some(),
+                    };
+                    *pos = start + colon + 1;
+                    let mut section = String::new();
+                    self.render_until(chars, pos, true, &mut section);
+                    if enabled {
+                        out.push_str(&section);
+                    }
+                    continue;
+                }
+            }
+            match chars[start..].iter().position(|c| *c == '}') {
+                Some(end) => {
+                    let name: String = chars[start + 1..start + end].iter().collect();
+                    match self.token(&name) {
+                        Some(value) => out.push_str(&value),
+                        None => out.extend(&chars[start..=start + end]),
+                    }
+                    *pos = start + end + 1;
+                }
+                None => {
+                    out.extend(&chars[start..]);
+                    *pos = chars.len();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_prompt() {
+        let mut info = ConnectionInfo {
+            handler: "RestAPI".to_string(),
+            host: "localhost".to_string(),
+            port: 8000,
+            user: "root".to_string(),
+            database: None,
+            warehouse: None,
+            role: None,
+            tenant: None,
+            tls: false,
+            settings: Default::default(),
+            server_version: None,
+        };
+        let ctx = PromptContext::new(&info);
+        assert_eq!(
+            ctx.render("{user}@{warehouse}/{database}> "),
+            "root@localhost:8000/default> "
+        );
+        assert_eq!(
+            ctx.render("{user}{?role: as {role}}> {unknown}"),
+            "root> {unknown}"
+        );
+
+        info.database = Some("prod".to_string());
+        info.role = Some("admin".to_string());
+        let ctx = PromptContext::new(&info);
+        assert_eq!(
+            ctx.render("{?database=prod:{red}}{database}{reset}{?role: as {role}}> "),
+            "\x1b[31mprod\x1b[0m as admin> "
+        );
+        assert_eq!(ctx.render("{?database=dev:dev}{database"), "{database");
+    }
+}
@@ -12,7 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::io::BufRead;
 use std::path::Path;
 use std::sync::Arc;
@@ -21,11 +22,11 @@ use anyhow::anyhow;
 use anyhow::Result;
 use async_recursion::async_recursion;
 use chrono::NaiveDateTime;
+use databend_client_sqlparse::take_statements;
 use databend_common_ast::parser::all_reserved_keywords;
-use databend_common_ast::parser::token::TokenKind;
-use databend_common_ast::parser::token::Tokenizer;
-use databend_driver::ServerStats;
-use databend_driver::{Client, Connection};
+use databend_common_ast::parser::Dialect;
+use databend_driver::{Client, Connection, Param, Params};
+use databend_driver::{Row, SchemaRef, ServerStats};
 use log::error;
 use once_cell::sync::Lazy;
 use rustyline::config::Builder;
@@ -41,13 +42,26 @@ use tokio::time::Instant;
 use tokio_stream::StreamExt;
 
 use crate::ast::replace_newline_in_box_display;
+use crate::ast::FormatConfig;
 use crate::ast::QueryKind;
+use crate::ast::{
+    at_timestamp, auto_limit, destructive_stmt, split_format_clause, DestructiveStmt,
+};
+use crate::compat::{self, FeatureStatus};
 use crate::config::ExpandMode;
+use crate::config::OutputFormat;
 use crate::config::Settings;
 use crate::config::TimeOption;
 use crate::display::INTERRUPTED_MESSAGE;
-use crate::display::{format_write_progress, ChunkDisplay, FormatDisplay};
+use crate::display::{
+    format_write_progress, parse_row_range, print_connection_info, print_rows, ChunkDisplay,
+    FormatDisplay, Tee,
+};
+use crate::expect::Expectation;
+use crate::fmt::{format_sql, lint_sql};
 use crate::helper::CliHelper;
+use crate::prompt::PromptContext;
+use crate::theme;
 use crate::web::find_available_port;
 use crate::web::start_server;
 use crate::VERSION;
@@ -74,11 +88,18 @@ pub struct Session {
     server_handle: Option<JoinHandle<std::io::Result<()>>>,
     keywords: Option<Arc<sled::Db>>,
     interrupted: Arc<AtomicBool>,
+    last_result: Option<(SchemaRef, Vec<Row>)>,
+    last_query: Option<String>,
+    tee: Option<Tee>,
+    // named params from `--param`, rendered as sql literals
+    params: HashMap<String, String>,
+    // timestamp of `!at` for time travel of the following queries
+    at: Option<String>,
 }
 
 impl Session {
-    pub async fn try_new(dsn: String, mut settings: Settings, is_repl: bool) -> Result<Self> {
-        let client = Client::new(dsn).with_name(format!("bendsql/{}", VERSION_SHORT.as_str()));
+    pub async fn try_new(client: Client, mut settings: Settings, is_repl: bool) -> Result<Self> {
+        let client = client.with_name(format!("bendsql/{}", VERSION_SHORT.as_str()));
         let conn = client.get_conn().await?;
         let info = conn.info().await;
         let mut keywords: Option<Arc<sled::Db>> = None;
@@ -108,19 +129,16 @@ impl Session {
                         }
                         databend_driver::Error::Arrow(arrow::error::ArrowError::IpcError(
                             ref ipc_err,
-                        )) => {
-                            if ipc_err.contains("Unauthenticated")
-                                || ipc_err.contains("Connection refused")
-                            {
-                                return Err(err.into());
-                            }
+                        )) if ipc_err.contains("Unauthenticated")
+                            || ipc_err.contains("Connection refused") =>
+                        {
+                            return Err(err.into());
                         }
-                        databend_driver::Error::Api(databend_client::Error::Request(
-                            ref resp_err,
-                        )) => {
-                            if resp_err.contains("error sending request for url") {
-                                return Err(err.into());
-                            }
+                        databend_driver::Error::Api(
+                            databend_client::Error::Request(ref resp_err)
+                            | databend_client::Error::Connect(ref resp_err),
+                        ) if resp_err.contains("error sending request for url") => {
+                            return Err(err.into());
                         }
                         _ => {}
                     }
@@ -202,6 +220,11 @@ impl Session {
             keywords,
             server_handle,
             interrupted,
+            last_result: None,
+            last_query: None,
+            tee: None,
+            params: HashMap::new(),
+            at: None,
         })
     }
 
@@ -210,25 +233,14 @@ impl Session {
             "> ".to_owned()
         } else {
             let info = self.conn.info().await;
-            let mut prompt = self.settings.prompt.clone();
-            prompt = prompt.replace("{host}", &info.host);
-            prompt = prompt.replace("{user}", &info.user);
-            prompt = prompt.replace("{port}", &info.port.to_string());
-            if let Some(database) = &info.database {
-                prompt = prompt.replace("{database}", database);
-            } else {
-                prompt = prompt.replace("{database}", "default");
-            }
-            if let Some(warehouse) = &info.warehouse {
-                prompt = prompt.replace("{warehouse}", &format!("({})", warehouse));
-            } else {
-                prompt = prompt.replace("{warehouse}", &format!("{}:{}", info.host, info.port));
-            }
+            let prompt = PromptContext::new(&info).render(&self.settings.prompt);
             format!("{} ", prompt.trim_end())
         }
     }
 
-    pub async fn check(&mut self) -> Result<()> {
+    /// Check the server and the features available with it,
+    /// returns false if some features are not available.
+    pub async fn check(&mut self) -> Result<bool> {
         // bendsql version
         {
             println!("BendSQL {}", VERSION.as_str());
@@ -252,10 +264,8 @@ impl Session {
         }
 
         // server version
-        {
-            let version = self.conn.version().await.unwrap_or_default();
-            println!("Server version: {}", version);
-        }
+        let version = self.conn.version().await.unwrap_or_default();
+        println!("Server version: {}", version);
 
         // license info
         match self.conn.query_iter("call admin$license_info()", ()).await {
@@ -279,6 +289,7 @@ impl Session {
         }
 
         // backend storage
+        let mut presign = false;
         {
             let stage_file = "@~/bendsql/.check";
             match self.conn.get_presigned_url("UPLOAD", stage_file).await {
@@ -301,19 +312,41 @@ impl Session {
                             let u = url::Url::parse(&resp.url)?;
                             let host = u.host_str().unwrap_or("unknown");
                             println!("Backend storage OK: {}", host);
+                            presign = true;
                         }
                     };
                 }
             }
         }
 
-        Ok(())
+        // features depending on server version
+        let caps = self.conn.capabilities();
+        let server_version = compat::server_version(&caps, &version);
+        let mut all_available = true;
+        for (feature, status) in compat::check_features(server_version, &caps, presign) {
+            match status {
+                FeatureStatus::Available => println!("Feature {}: OK", feature),
+                FeatureStatus::Unavailable(reason) => {
+                    all_available = false;
+                    eprintln!("-> WARN: Feature {} not available, {}.", feature, reason);
+                }
+                FeatureStatus::Unknown => {
+                    eprintln!(
+                        "-> WARN: Feature {} unknown, server version not detected.",
+                        feature
+                    );
+                }
+            }
+        }
+
+        Ok(all_available)
     }
 
     pub async fn handle_repl(&mut self) {
         let config = Builder::new()
             .completion_prompt_limit(10)
             .completion_type(CompletionType::List)
+            .bracketed_paste(true)
             .build();
         let mut rl = Editor::<CliHelper, DefaultHistory>::with_config(config).unwrap();
 
@@ -324,8 +357,28 @@ impl Session {
             match rl.readline(&self.prompt().await) {
                 Ok(line) => {
                     let queries = self.append_query(&line);
+                    // a multi-line input with several statements is pasted,
+                    // confirm before running a script pasted by accident
+                    if self.settings.confirm_paste
+                        && line.contains('\n')
+                        && queries.len() > 1
+                        && !confirm_paste(&mut rl, &queries, &self.query)
+                    {
+                        eprintln!("cancelled");
+                        self.query.clear();
+                        continue;
+                    }
                     for query in queries {
                         let _ = rl.add_history_entry(&query);
+                        if self.settings.confirm_drop {
+                            if let Some(stmt) = destructive_stmt(&query) {
+                                if !confirm_destructive(&mut rl, &stmt) {
+                                    eprintln!("cancelled");
+                                    self.query.clear();
+                                    break;
+                                }
+                            }
+                        }
                         match self.handle_query(true, &query).await {
                             Ok(None) => {
                                 break 'F;
@@ -336,10 +389,16 @@ impl Session {
                                     if let Err(e) = self.reconnect().await {
                                         eprintln!("reconnect error: {}", e);
                                     } else if let Err(e) = self.handle_query(true, &query).await {
-                                        eprintln!("error: {}", e);
+                                        eprintln!(
+                                            "{}",
+                                            theme::current().error(&format!("error: {}", e))
+                                        );
                                     }
                                 } else {
-                                    eprintln!("error: {}", e);
+                                    eprintln!(
+                                        "{}",
+                                        theme::current().error(&format!("error: {}", e))
+                                    );
                                     if e.to_string().contains(INTERRUPTED_MESSAGE) {
                                         if let Some(query_id) = self.conn.last_query_id() {
                                             println!("killing query: {}", query_id);
@@ -385,6 +444,38 @@ impl Session {
         let _ = rl.save_history(&get_history_path());
     }
 
+    /// Run the statements and commands of the startup file before the REPL starts,
+    /// failed ones are reported and the rest of the file still runs.
+    pub async fn handle_init_file(&mut self, path: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read init file {}: {}", path.display(), e))?;
+        let mut queries = vec![];
+        for line in content.lines() {
+            let line = line.trim();
+            if self.query.is_empty() && line.starts_with("--") {
+                continue;
+            }
+            queries.extend(self.append_query(line));
+        }
+        let rest = self.query.trim().to_owned();
+        if !rest.is_empty() {
+            self.query.clear();
+            queries.push(rest);
+        }
+        for query in queries {
+            if query == "exit" || query == "quit" {
+                continue;
+            }
+            if let Err(e) = self.handle_query(true, &query).await {
+                eprintln!(
+                    "{}",
+                    theme::current().error(&format!("error in {}: {}", path.display(), e))
+                );
+            }
+        }
+        Ok(())
+    }
+
     pub async fn handle_reader<R: BufRead>(&mut self, r: R) -> Result<()> {
         let start = Instant::now();
         let mut lines = r.lines();
@@ -398,7 +489,7 @@ impl Session {
                     }
                 }
                 Some(Err(e)) => {
-                    return Err(anyhow!("read lines err: {}", e.to_string()));
+                    return Err(anyhow!("read lines err: {}", e));
                 }
                 None => break,
             }
@@ -427,6 +518,68 @@ impl Session {
         Ok(())
     }
 
+    /// Run statements as tests for `--test`, statements annotated by comments like
+    /// `-- expect_rows: 3` are checked without printing results, failed ones are
+    /// reported and the rest still runs, with an error at the end if any failed.
+    pub async fn handle_test<R: BufRead>(&mut self, r: R) -> Result<()> {
+        let mut queries: Vec<(String, Vec<Expectation>)> = vec![];
+        let mut expectations = vec![];
+        for line in r.lines() {
+            let line = line?;
+            if self.query.is_empty() {
+                if let Some(expectation) = Expectation::parse(&line)? {
+                    expectations.push(expectation);
+                    continue;
+                }
+            }
+            for query in self.append_query(line.trim()) {
+                queries.push((query, std::mem::take(&mut expectations)));
+            }
+        }
+        let rest = self.query.trim().to_owned();
+        if !rest.is_empty() {
+            self.query.clear();
+            queries.push((rest, expectations));
+        }
+
+        let mut failed = 0;
+        for (query, expectations) in &queries {
+            let failures = if expectations.is_empty() {
+                match self.handle_query(false, query).await {
+                    Ok(_) => vec![],
+                    Err(e) => vec![format!("unexpected error: {}", e)],
+                }
+            } else {
+                let query = query.trim_end_matches(';');
+                let result = match self.query_params(query) {
+                    Ok(params) => self.conn.query_all(query, params).await,
+                    Err(e) => Err(e),
+                }
+                .map_err(|e| e.to_string());
+                expectations
+                    .iter()
+                    .filter_map(|expectation| expectation.check(&result))
+                    .collect()
+            };
+            if !failures.is_empty() {
+                failed += 1;
+                eprintln!(
+                    "{}",
+                    theme::current().error(&format!("FAIL: {}", query.trim()))
+                );
+                for failure in failures {
+                    eprintln!("  {}", failure);
+                }
+            }
+        }
+        self.conn.close().await.ok();
+        if failed > 0 {
+            return Err(anyhow!("{} of {} statements failed", failed, queries.len()));
+        }
+        eprintln!("{} statements passed", queries.len());
+        Ok(())
+    }
+
     pub fn append_query(&mut self, line: &str) -> Vec<String> {
         if line.is_empty() {
             return vec![];
@@ -449,51 +602,25 @@ impl Session {
             }
         }
 
-        // consume self.query and get the result
-        let mut queries = Vec::new();
-
         if !self.query.is_empty() {
             self.query.push('\n');
         }
         self.query.push_str(line);
+        // `\G` shows the result vertically in the REPL
+        take_statements(&mut self.query, self.is_repl)
+    }
 
-        'Parser: loop {
-            let mut is_valid = true;
-            let tokenizer = Tokenizer::new(&self.query);
-            let mut previous_token_backslash = false;
-            for token in tokenizer {
-                match token {
-                    Ok(token) => {
-                        // SQL end with `;` or `\G` in repl
-                        let is_end_query = matches!(token.kind, TokenKind::SemiColon);
-                        let is_slash_g = self.is_repl
-                            && (previous_token_backslash
-                                && token.kind == TokenKind::Ident
-                                && token.text() == "G")
-                            || (token.text().ends_with("\\G"));
-
-                        if is_end_query || is_slash_g {
-                            // push to current and continue the tokenizer
-                            let (sql, remain) = self.query.split_at(token.span.end as usize);
-                            if is_valid && !sql.is_empty() {
-                                queries.push(sql.to_string());
-                            }
-                            self.query = remain.to_string();
-                            continue 'Parser;
-                        }
-                        previous_token_backslash = matches!(token.kind, TokenKind::Backslash);
-                    }
-                    Err(_) => {
-                        // ignore current query if have invalid token.
-                        is_valid = false;
-                        continue;
-                    }
-                }
-            }
-            break;
+    /// Bind `:name` in the following queries, numbers are bound as is
+    /// and other values as quoted strings.
+    pub fn set_params(&mut self, params: &[(String, String)]) {
+        for (name, value) in params {
+            self.params.insert(name.clone(), param_literal(value));
         }
+    }
 
-        queries
+    /// Bound params referenced by the query, others are not sent.
+    fn query_params(&self, query: &str) -> databend_driver::Result<Params> {
+        Params::NamedParams(self.params.clone()).used_by(query)
     }
 
     #[async_recursion]
@@ -517,8 +644,36 @@ impl Session {
                 query = query.trim_end_matches("\\G");
                 expand = Some(ExpandMode::On);
             }
+            self.last_query = Some(query.to_string());
         }
 
+        // not supported by the server, used as the output format of this query if possible
+        let mut output_format = None;
+        if let Some((sql, format)) = split_format_clause(query) {
+            output_format = OutputFormat::from_clause(format);
+            if output_format.is_none() {
+                eprintln!(
+                    "FORMAT {} is not supported, removed from the query, use `--output` or `!set output_format` instead",
+                    format
+                );
+            }
+            query = sql;
+        }
+
+        let mut rewritten = None;
+        if let Some(timestamp) = &self.at {
+            rewritten = at_timestamp(query, timestamp);
+        }
+        let mut limited = false;
+        if self.settings.auto_limit > 0 {
+            let sql = rewritten.as_deref().unwrap_or(query);
+            if let Some(sql) = auto_limit(sql, self.settings.auto_limit) {
+                rewritten = Some(sql);
+                limited = true;
+            }
+        }
+        let query = rewritten.as_deref().unwrap_or(query);
+
         let start = Instant::now();
         let kind = QueryKind::from(query);
         match kind {
@@ -539,18 +694,38 @@ impl Session {
                     QueryKind::Put(l, r) => self.conn.put_files(&l, &r).await?,
                     QueryKind::Get(l, r) => self.conn.get_files(&l, &r).await?,
                     QueryKind::GenData(t, s, o) => self.gendata(t, s, o).await?,
-                    _ => self.conn.query_iter_ext(query, ()).await?,
+                    _ => {
+                        let params = self.query_params(query)?;
+                        self.conn.query_iter_ext(query, params).await?
+                    }
                 };
 
+                let settings = match output_format {
+                    Some(output_format) => Cow::Owned(Settings {
+                        output_format,
+                        ..self.settings.clone()
+                    }),
+                    None => Cow::Borrowed(&self.settings),
+                };
                 let mut displayer = FormatDisplay::new(
-                    &self.settings,
+                    &settings,
                     query,
                     replace_newline,
                     start,
                     data,
                     self.interrupted.clone(),
-                );
+                )
+                .with_tee(self.tee.as_mut());
                 let stats = displayer.display(expand).await?;
+                if limited && displayer.rows() >= self.settings.auto_limit {
+                    eprintln!(
+                        "results are limited to {} rows by auto_limit",
+                        self.settings.auto_limit
+                    );
+                }
+                if let Some(result) = displayer.take_result() {
+                    self.last_result = Some(result);
+                }
                 Ok(Some(stats))
             }
         }
@@ -565,6 +740,9 @@ impl Session {
             "!configs" => {
                 println!("{:#?}", self.settings);
             }
+            "!info" => {
+                print_connection_info(&self.conn.info().await, &self.conn.capabilities());
+            }
             other => {
                 if other.starts_with("!set") {
                     let query = query[4..].split_whitespace().collect::<Vec<_>>();
@@ -574,6 +752,13 @@ impl Session {
                         ));
                     }
                     self.settings.inject_ctrl_cmd(query[0], query[1])?;
+                } else if let Some(range) = other.strip_prefix("!show rows") {
+                    let (schema, rows) = self
+                        .last_result
+                        .as_ref()
+                        .ok_or_else(|| anyhow!("No result to show, run a query first"))?;
+                    let (start, end) = parse_row_range(range.trim(), rows.len())?;
+                    print_rows(&self.settings, schema.clone(), rows, start, end)?;
                 } else if other.starts_with("!source") {
                     let query = query[7..].trim();
                     let path = Path::new(query);
@@ -583,6 +768,49 @@ impl Session {
                     let file = std::fs::File::open(path)?;
                     let reader = std::io::BufReader::new(file);
                     self.handle_reader(reader).await?;
+                } else if let Some(timestamp) = other.strip_prefix("!at") {
+                    match timestamp.trim() {
+                        "" => {
+                            return Err(anyhow!("Usage: `!at '2024-01-01 00:00:00'` or `!at off`"));
+                        }
+                        "off" => {
+                            self.at = None;
+                            eprintln!("Stopped time travel");
+                        }
+                        timestamp => {
+                            let timestamp = timestamp.trim_matches('\'').to_string();
+                            eprintln!("Querying tables at {}", timestamp);
+                            self.at = Some(timestamp);
+                        }
+                    }
+                } else if let Some(path) = other.strip_prefix("!tee") {
+                    match path.trim() {
+                        "" => {
+                            return Err(anyhow!("Usage: `!tee file.csv` or `!tee off`"));
+                        }
+                        "off" => {
+                            if let Some(tee) = self.tee.take() {
+                                eprintln!("Stopped writing results to {}", tee.path());
+                            }
+                        }
+                        path => {
+                            let tee = Tee::open(path)?;
+                            eprintln!("Writing results to {} in csv", tee.path());
+                            self.tee = Some(tee);
+                        }
+                    }
+                } else if let Some(sql) = other.strip_prefix("!fmt") {
+                    let sql = match sql.trim() {
+                        "" => self
+                            .last_query
+                            .as_deref()
+                            .ok_or_else(|| anyhow!("No query to format, run a query first"))?,
+                        sql => sql,
+                    };
+                    for error in lint_sql(sql, Dialect::Experimental) {
+                        eprintln!("{}", error);
+                    }
+                    print!("{}", format_sql(sql, &FormatConfig::default()));
                 } else {
                     return Err(anyhow!("Unknown commands: {}", other));
                 }
@@ -595,6 +823,7 @@ impl Session {
         &mut self,
         query: &str,
         options: BTreeMap<&str, &str>,
+        copy_options: BTreeMap<&str, &str>,
     ) -> Result<()> {
         let dir = std::env::temp_dir();
         // TODO:(everpcpc) write by chunks
@@ -612,14 +841,15 @@ impl Session {
                         file.write_all(b"\n").await?;
                     }
                     Some(Err(e)) => {
-                        return Err(anyhow!("stream load stdin err: {}", e.to_string()));
+                        return Err(anyhow!("stream load stdin err: {}", e));
                     }
                     None => break,
                 }
             }
             file.flush().await?;
         }
-        self.stream_load_file(query, &tmp_file, options).await?;
+        self.stream_load_file(query, &tmp_file, options, copy_options)
+            .await?;
         remove_file(tmp_file).await?;
         Ok(())
     }
@@ -629,6 +859,7 @@ impl Session {
         query: &str,
         file_path: &Path,
         options: BTreeMap<&str, &str>,
+        copy_options: BTreeMap<&str, &str>,
     ) -> Result<()> {
         let start = Instant::now();
         let file = File::open(file_path).await?;
@@ -636,7 +867,13 @@ impl Session {
 
         let ss = self
             .conn
-            .load_data(query, Box::new(file), metadata.len(), Some(options), None)
+            .load_data(
+                query,
+                Box::new(file),
+                metadata.len(),
+                Some(options),
+                Some(copy_options),
+            )
             .await?;
 
         // TODO:(everpcpc) show progress
@@ -647,6 +884,21 @@ impl Session {
                 format_write_progress(&ss, start.elapsed().as_secs_f64())
             );
         }
+        if ss.rejected_rows > 0 {
+            eprintln!("==> rejected {} rows:", ss.rejected_rows);
+            for f in &ss.rejected_files {
+                eprintln!(
+                    "    {}: {} rows rejected, {} rows loaded",
+                    f.file, f.errors_seen, f.rows_loaded
+                );
+                if let Some(err) = &f.first_error {
+                    match f.first_error_line {
+                        Some(line) => eprintln!("    first error at line {}: {}", line, err),
+                        None => eprintln!("    first error: {}", err),
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
@@ -666,6 +918,59 @@ impl Session {
     }
 }
 
+/// Value of `--param` as a sql literal, strings are escaped by the driver.
+fn param_literal(value: &str) -> String {
+    // `007` is more likely a code than a number
+    let digits = value.trim_start_matches(['-', '+']);
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    let is_number = !leading_zero
+        && (value.parse::<i64>().is_ok() || value.parse::<f64>().is_ok_and(|f| f.is_finite()));
+    if is_number {
+        value.to_string()
+    } else {
+        value.as_sql_string()
+    }
+}
+
+/// Summary of pasted statements, with the first line of each and the incomplete rest.
+fn paste_summary(queries: &[String], rest: &str) -> String {
+    let mut summary = format!("pasted {} statements:\n", queries.len());
+    for (i, query) in queries.iter().enumerate() {
+        let query = query.trim();
+        let first = query.lines().next().unwrap_or_default();
+        let more = if first.len() < query.len() {
+            " ..."
+        } else {
+            ""
+        };
+        summary.push_str(&format!("  {}. {}{}\n", i + 1, first, more));
+    }
+    if !rest.trim().is_empty() {
+        summary.push_str("  (incomplete statement left in buffer)\n");
+    }
+    summary
+}
+
+fn confirm_paste(
+    rl: &mut Editor<CliHelper, DefaultHistory>,
+    queries: &[String],
+    rest: &str,
+) -> bool {
+    eprint!("{}", paste_summary(queries, rest));
+    match rl.readline(&format!("Run {} statements? [y/N] ", queries.len())) {
+        Ok(answer) => matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"),
+        Err(_) => false,
+    }
+}
+
+fn confirm_destructive(rl: &mut Editor<CliHelper, DefaultHistory>, stmt: &DestructiveStmt) -> bool {
+    eprintln!("{} {}", stmt.action, stmt.objects.join(", "));
+    match rl.readline("Confirm? [y/N] ") {
+        Ok(answer) => matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"),
+        Err(_) => false,
+    }
+}
+
 fn get_history_path() -> String {
     format!(
         "{}/.bendsql_history",
@@ -680,3 +985,135 @@ impl Drop for Session {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::OutputFormat;
+    use databend_client_mock::{MockResult, MockServer};
+
+    #[test]
+    fn test_paste_summary() {
+        let queries = vec![
+            "DELETE FROM t\nWHERE id > 10;".to_string(),
+            "\nDROP TABLE t2;".to_string(),
+        ];
+        assert_eq!(
+            paste_summary(&queries, "\nDROP"),
+            "pasted 2 statements:\n  1. DELETE FROM t ...\n  2. DROP TABLE t2;\n  (incomplete statement left in buffer)\n"
+        );
+    }
+
+    #[test]
+    fn test_param_literal() {
+        assert_eq!(param_literal("42"), "42");
+        assert_eq!(param_literal("-1.5"), "-1.5");
+        assert_eq!(param_literal("nan"), "'nan'");
+        assert_eq!(param_literal("2024-01-01"), "'2024-01-01'");
+        assert_eq!(param_literal("it's"), "'it\\'s'");
+        assert_eq!(param_literal("007"), "'007'");
+        assert_eq!(param_literal("0"), "0");
+        assert_eq!(param_literal("0.5"), "0.5");
+    }
+
+    #[tokio::test]
+    async fn append_query_like_script() -> Result<()> {
+        let server = MockServer::start().await;
+        let settings = Settings {
+            output_format: OutputFormat::Null,
+            ..Default::default()
+        };
+        let mut session = Session::try_new(Client::new(server.dsn()), settings, false).await?;
+        let script = "-- books\nSELECT 'a;\nb' FROM t; SELECT 1;\n/* x; */ SELECT 2;";
+        let mut queries = vec![];
+        for line in script.lines() {
+            queries.extend(session.append_query(line));
+        }
+        let queries = queries
+            .iter()
+            .map(|q| q.trim_end_matches(';'))
+            .collect::<Vec<_>>();
+        assert_eq!(queries, databend_client_sqlparse::split_statements(script));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn query_at_timestamp() -> Result<()> {
+        let server = MockServer::start().await;
+        server.on_query("SELECT", MockResult::empty());
+        let settings = Settings {
+            output_format: OutputFormat::Null,
+            ..Default::default()
+        };
+        let mut session = Session::try_new(Client::new(server.dsn()), settings, false).await?;
+        session
+            .handle_query(true, "!at '2024-01-01 00:00:00'")
+            .await?;
+        session.handle_query(true, "SELECT * FROM t;").await?;
+        session.handle_query(true, "!at off").await?;
+        session.handle_query(true, "SELECT * FROM t;").await?;
+        let queries = server.queries();
+        assert_eq!(
+            queries[queries.len() - 2..],
+            [
+                "SELECT * FROM t AT (TIMESTAMP => '2024-01-01 00:00:00'::TIMESTAMP)",
+                "SELECT * FROM t"
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn query_format_clause() -> Result<()> {
+        let server = MockServer::start().await;
+        server.on_query("SELECT", MockResult::empty());
+        let settings = Settings {
+            output_format: OutputFormat::Null,
+            auto_limit: 10,
+            ..Default::default()
+        };
+        let mut session = Session::try_new(Client::new(server.dsn()), settings, false).await?;
+        session
+            .handle_query(true, "SELECT * FROM t FORMAT CSV;")
+            .await?;
+        session
+            .handle_query(false, "SELECT * FROM t FORMAT JSONEachRow")
+            .await?;
+        let queries = server.queries();
+        assert_eq!(
+            queries[queries.len() - 2..],
+            ["SELECT * FROM t LIMIT 10", "SELECT * FROM t LIMIT 10"]
+        );
+        assert_eq!(session.settings.output_format, OutputFormat::Null);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn query_with_params() -> Result<()> {
+        let server = MockServer::start().await;
+        server.on_query("SELECT", MockResult::empty());
+        let settings = Settings {
+            output_format: OutputFormat::Null,
+            ..Default::default()
+        };
+        // replace params in sql instead of sending them to server
+        let dsn = format!("{}&query_params=disable", server.dsn());
+        let mut session = Session::try_new(Client::new(dsn), settings, false).await?;
+        session.set_params(&[
+            ("id".to_string(), "5".to_string()),
+            ("name".to_string(), "a'b".to_string()),
+        ]);
+        session
+            .handle_reader(std::io::Cursor::new(
+                "SELECT * FROM t WHERE id = :id AND name = :name",
+            ))
+            .await?;
+        assert!(server
+            .queries()
+            .contains(&"SELECT * FROM t WHERE id = 5 AND name = 'a\\'b'".to_string()));
+
+        // malformed sql is an error instead of a panic
+        assert!(session.handle_query(false, "SELECT 'a").await.is_err());
+        Ok(())
+    }
+}
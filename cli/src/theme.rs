@@ -0,0 +1,270 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::IsTerminal;
+use std::sync::RwLock;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+
+static THEME: Lazy<RwLock<Theme>> = Lazy::new(|| RwLock::new(Theme::default()));
+
+const BORDER_CHARS: &str = "│─├┼┤┌┐└┘┬┴";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThemeName {
+    Dark,
+    Light,
+    None,
+}
+
+impl TryFrom<&str> for ThemeName {
+    type Error = anyhow::Error;
+    fn try_from(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "dark" => Ok(ThemeName::Dark),
+            "light" => Ok(ThemeName::Light),
+            "none" => Ok(ThemeName::None),
+            _ => Err(anyhow!(
+                "Unknown theme: {}, expected dark, light or none",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMode {
+    /// Colors if stdout is a terminal and `NO_COLOR` is not set,
+    /// truecolor if `COLORTERM` says so.
+    Auto,
+    Ansi,
+    TrueColor,
+    Never,
+}
+
+impl TryFrom<&str> for ColorMode {
+    type Error = anyhow::Error;
+    fn try_from(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "ansi" => Ok(ColorMode::Ansi),
+            "truecolor" => Ok(ColorMode::TrueColor),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(anyhow!(
+                "Unknown color mode: {}, expected auto, ansi, truecolor or never",
+                s
+            )),
+        }
+    }
+}
+
+impl ColorMode {
+    fn resolve(self) -> ColorMode {
+        match self {
+            ColorMode::Auto => {
+                let no_color = std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty());
+                if no_color || !std::io::stdout().is_terminal() {
+                    ColorMode::Never
+                } else if std::env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit")
+                {
+                    ColorMode::TrueColor
+                } else {
+                    ColorMode::Ansi
+                }
+            }
+            mode => mode,
+        }
+    }
+}
+
+/// A color as ANSI SGR parameters and truecolor rgb.
+struct Color(&'static str, (u8, u8, u8), bool);
+
+impl Color {
+    fn code(&self, mode: ColorMode) -> String {
+        let bold = if self.2 { "1;" } else { "" };
+        match mode {
+            ColorMode::Ansi => format!("{}{}", bold, self.0),
+            ColorMode::TrueColor => {
+                let (r, g, b) = self.1;
+                format!("{}38;2;{};{};{}", bold, r, g, b)
+            }
+            ColorMode::Auto | ColorMode::Never => String::new(),
+        }
+    }
+}
+
+struct Palette {
+    border: Color,
+    header: Color,
+    error: Color,
+    keyword: Color,
+    literal: Color,
+}
+
+const DARK: Palette = Palette {
+    border: Color("90", (92, 99, 112), false),
+    header: Color("36", (97, 175, 239), true),
+    error: Color("31", (224, 108, 117), true),
+    keyword: Color("32", (152, 195, 121), true),
+    literal: Color("33", (229, 192, 123), true),
+};
+
+const LIGHT: Palette = Palette {
+    border: Color("37", (160, 161, 167), false),
+    header: Color("34", (1, 132, 188), true),
+    error: Color("31", (202, 18, 67), true),
+    keyword: Color("35", (166, 38, 164), true),
+    literal: Color("32", (80, 161, 79), true),
+};
+
+/// SGR codes of each element, empty for no color.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Theme {
+    border: String,
+    header: String,
+    error: String,
+    keyword: String,
+    literal: String,
+}
+
+impl Theme {
+    pub fn new(name: ThemeName, mode: ColorMode) -> Self {
+        let mode = mode.resolve();
+        let palette = match name {
+            ThemeName::Dark => &DARK,
+            ThemeName::Light => &LIGHT,
+            ThemeName::None => return Self::default(),
+        };
+        Self {
+            border: palette.border.code(mode),
+            header: palette.header.code(mode),
+            error: palette.error.code(mode),
+            keyword: palette.keyword.code(mode),
+            literal: palette.literal.code(mode),
+        }
+    }
+
+    fn paint(code: &str, text: &str) -> String {
+        if code.is_empty() {
+            text.to_string()
+        } else {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        }
+    }
+
+    pub fn header(&self, text: &str) -> String {
+        Self::paint(&self.header, text)
+    }
+
+    pub fn error(&self, text: &str) -> String {
+        Self::paint(&self.error, text)
+    }
+
+    pub fn keyword(&self, text: &str) -> String {
+        Self::paint(&self.keyword, text)
+    }
+
+    pub fn literal(&self, text: &str) -> String {
+        Self::paint(&self.literal, text)
+    }
+
+    /// Color borders and the header of a rendered table, the header is the
+    /// lines before the first separator line.
+    pub fn paint_table(&self, table: &str) -> String {
+        if self.border.is_empty() && self.header.is_empty() {
+            return table.to_string();
+        }
+        let has_header = table.lines().any(|l| l.starts_with('├'));
+        let mut in_header = has_header;
+        let mut out = String::with_capacity(table.len() * 2);
+        for (i, line) in table.lines().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            if line.starts_with('├') {
+                in_header = false;
+            }
+            let mut segment = String::new();
+            let flush = |segment: &mut String, out: &mut String| {
+                if !segment.is_empty() {
+                    if in_header && i > 0 && !segment.trim().is_empty() {
+                        out.push_str(&self.header(segment));
+                    } else {
+                        out.push_str(segment);
+                    }
+                    segment.clear();
+                }
+            };
+            let mut border = String::new();
+            for c in line.chars() {
+                if BORDER_CHARS.contains(c) {
+                    flush(&mut segment, &mut out);
+                    border.push(c);
+                } else {
+                    if !border.is_empty() {
+                        out.push_str(&Self::paint(&self.border, &border));
+                        border.clear();
+                    }
+                    segment.push(c);
+                }
+            }
+            flush(&mut segment, &mut out);
+            if !border.is_empty() {
+                out.push_str(&Self::paint(&self.border, &border));
+            }
+        }
+        out
+    }
+}
+
+pub fn current() -> Theme {
+    THEME.read().unwrap().clone()
+}
+
+pub fn set(theme: Theme) {
+    *THEME.write().unwrap() = theme;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn paint_table() {
+        let table = "┌──────┐\n│  a   │\n│ Int8 │\n├──────┤\n│ 1    │\n└──────┘";
+        assert_eq!(
+            Theme::new(ThemeName::Dark, ColorMode::Never).paint_table(table),
+            table
+        );
+        assert_eq!(
+            Theme::new(ThemeName::None, ColorMode::Ansi).paint_table(table),
+            table
+        );
+
+        let theme = Theme::new(ThemeName::Dark, ColorMode::Ansi);
+        let painted = theme.paint_table(table);
+        let lines: Vec<&str> = painted.lines().collect();
+        assert_eq!(lines[0], "\x1b[90m┌──────┐\x1b[0m");
+        assert_eq!(
+            lines[1],
+            "\x1b[90m│\x1b[0m\x1b[1;36m  a   \x1b[0m\x1b[90m│\x1b[0m"
+        );
+        assert_eq!(lines[4], "\x1b[90m│\x1b[0m 1    \x1b[90m│\x1b[0m");
+
+        let theme = Theme::new(ThemeName::Light, ColorMode::TrueColor);
+        assert_eq!(theme.error("x"), "\x1b[1;38;2;202;18;67mx\x1b[0m");
+    }
+}
@@ -12,11 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use async_trait::async_trait;
 use reqwest::RequestBuilder;
 
 use crate::error::{Error, Result};
 
+#[async_trait]
 pub trait Auth: Sync + Send {
+    /// Called before wrapping each request, to fetch rotated credentials.
+    async fn refresh(&self) -> Result<()> {
+        Ok(())
+    }
+    /// Drop cached credentials after rejected by the server.
+    fn invalidate(&self) {}
     fn wrap(&self, builder: RequestBuilder) -> Result<RequestBuilder>;
     fn can_reload(&self) -> bool {
         false
@@ -39,6 +47,7 @@ impl BasicAuth {
     }
 }
 
+#[async_trait]
 impl Auth for BasicAuth {
     fn wrap(&self, builder: RequestBuilder) -> Result<RequestBuilder> {
         Ok(builder.basic_auth(&self.username, Some(self.password.inner())))
@@ -62,6 +71,7 @@ impl AccessTokenAuth {
     }
 }
 
+#[async_trait]
 impl Auth for AccessTokenAuth {
     fn wrap(&self, builder: RequestBuilder) -> Result<RequestBuilder> {
         Ok(builder.bearer_auth(self.token.inner()))
@@ -84,6 +94,7 @@ impl AccessTokenFileAuth {
     }
 }
 
+#[async_trait]
 impl Auth for AccessTokenFileAuth {
     fn wrap(&self, builder: RequestBuilder) -> Result<RequestBuilder> {
         let token = std::fs::read_to_string(&self.token_file).map_err(|e| {
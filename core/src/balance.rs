@@ -0,0 +1,143 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::error::{Error, Result};
+
+/// A node is skipped for new queries for a while after failed to connect.
+const NODE_DOWN_DURATION: Duration = Duration::from_secs(30);
+
+/// How new queries are spread over the hosts of a DSN, by DSN `load_balance`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LoadBalanceMode {
+    RoundRobin,
+    Random,
+}
+
+impl LoadBalanceMode {
+    /// `disable` for all queries on the first reachable host.
+    pub(crate) fn parse(s: &str) -> Result<Option<Self>> {
+        match s {
+            "disable" | "none" => Ok(None),
+            "round_robin" => Ok(Some(Self::RoundRobin)),
+            "random" => Ok(Some(Self::Random)),
+            _ => Err(Error::BadArgument(format!(
+                "Invalid value for load_balance: {}, should be one of disable/round_robin/random",
+                s
+            ))),
+        }
+    }
+}
+
+/// Picks the node of new queries, pages of a query are always fetched from
+/// the node it is started on.
+#[derive(Debug)]
+pub(crate) struct LoadBalancer {
+    mode: LoadBalanceMode,
+    nodes: Vec<Url>,
+    next: AtomicUsize,
+    down_until: parking_lot::Mutex<Vec<Option<Instant>>>,
+}
+
+impl LoadBalancer {
+    pub(crate) fn new(mode: LoadBalanceMode, nodes: Vec<Url>) -> Self {
+        let down_until = parking_lot::Mutex::new(vec![None; nodes.len()]);
+        Self {
+            mode,
+            nodes,
+            next: AtomicUsize::new(0),
+            down_until,
+        }
+    }
+
+    pub(crate) fn nodes(&self) -> &[Url] {
+        &self.nodes
+    }
+
+    /// Nodes to try in order for a new query, the ones down are moved to the
+    /// end, in case all of them are down.
+    pub(crate) fn candidates(&self) -> Vec<Url> {
+        let n = self.nodes.len();
+        let start = match self.mode {
+            LoadBalanceMode::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            LoadBalanceMode::Random => uuid::Uuid::new_v4().as_u128() as usize,
+        } % n;
+        let now = Instant::now();
+        let down_until = self.down_until.lock();
+        let (up, down): (Vec<usize>, Vec<usize>) = (0..n)
+            .map(|i| (start + i) % n)
+            .partition(|i| down_until[*i].is_none_or(|t| t <= now));
+        up.into_iter()
+            .chain(down)
+            .map(|i| self.nodes[i].clone())
+            .collect()
+    }
+
+    pub(crate) fn mark_down(&self, node: &Url) {
+        if let Some(i) = self.nodes.iter().position(|u| u == node) {
+            self.down_until.lock()[i] = Some(Instant::now() + NODE_DOWN_DURATION);
+        }
+    }
+
+    pub(crate) fn mark_up(&self, node: &Url) {
+        if let Some(i) = self.nodes.iter().position(|u| u == node) {
+            self.down_until.lock()[i] = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn skip_nodes_down() -> Result<()> {
+        let nodes = vec![
+            Url::parse("http://h1:8000")?,
+            Url::parse("http://h2:8000")?,
+            Url::parse("http://h3:8000")?,
+        ];
+        let lb = LoadBalancer::new(LoadBalanceMode::RoundRobin, nodes.clone());
+        assert_eq!(lb.candidates()[0], nodes[0]);
+        assert_eq!(lb.candidates()[0], nodes[1]);
+        assert_eq!(lb.candidates()[0], nodes[2]);
+
+        lb.mark_down(&nodes[1]);
+        assert_eq!(
+            lb.candidates(),
+            vec![nodes[0].clone(), nodes[2].clone(), nodes[1].clone()]
+        );
+        assert_eq!(
+            lb.candidates(),
+            vec![nodes[2].clone(), nodes[0].clone(), nodes[1].clone()]
+        );
+        lb.mark_up(&nodes[1]);
+        lb.candidates();
+        assert_eq!(lb.candidates(), nodes);
+
+        let lb = LoadBalancer::new(LoadBalanceMode::Random, nodes.clone());
+        assert_eq!(lb.candidates().len(), 3);
+        assert_eq!(
+            LoadBalanceMode::parse("random")?,
+            Some(LoadBalanceMode::Random)
+        );
+        assert_eq!(LoadBalanceMode::parse("disable")?, None);
+        assert!(LoadBalanceMode::parse("least_conn").is_err());
+        Ok(())
+    }
+}
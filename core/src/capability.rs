@@ -0,0 +1,95 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Version of the server like `v1.2.700-nightly-a1b2c3(rust-1.75.0-nightly)`,
+/// compared by the leading numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ServerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ServerVersion {
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().trim_start_matches('v');
+        let end = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let mut parts = s[..end].split('.').map(|p| p.parse::<u32>().ok());
+        Some(Self {
+            major: parts.next()??,
+            minor: parts.next().flatten().unwrap_or(0),
+            patch: parts.next().flatten().unwrap_or(0),
+        })
+    }
+
+    pub fn at_least(&self, major: u32, minor: u32, patch: u32) -> bool {
+        *self
+            >= Self {
+                major,
+                minor,
+                patch,
+            }
+    }
+}
+
+impl std::fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Features of the server detected while connecting, for degrading
+/// gracefully on older servers instead of failing in the middle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// None if the server does not support login, or login is disabled.
+    pub server_version: Option<ServerVersion>,
+    /// Session is kept by the server with `/v1/session/login`.
+    pub login: bool,
+    pub session_token: bool,
+    /// Data is uploaded to presigned urls instead of through the server.
+    pub presign: bool,
+    /// Query parameters are sent to server, by the server version or DSN `query_params`.
+    pub query_params: bool,
+    /// NULL is sent as json null instead of the string `NULL` in results,
+    /// so that it is distinct from strings.
+    pub distinct_null: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_version() {
+        let v = ServerVersion::parse("v1.2.700-nightly-a1b2c3(rust-1.75.0-nightly-2023-12-01)");
+        assert_eq!(
+            v,
+            Some(ServerVersion {
+                major: 1,
+                minor: 2,
+                patch: 700
+            })
+        );
+        let v = v.unwrap();
+        assert!(v.at_least(1, 2, 700));
+        assert!(v.at_least(1, 1, 900));
+        assert!(!v.at_least(1, 2, 701));
+        assert_eq!(v.to_string(), "1.2.700");
+        assert_eq!(ServerVersion::parse("1.3").map(|v| v.minor), Some(3));
+        assert_eq!(ServerVersion::parse("nightly"), None);
+    }
+}
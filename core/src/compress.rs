@@ -0,0 +1,127 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write;
+
+use bytes::Bytes;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::{StreamReader, SyncIoBridge};
+
+use crate::error::{Error, Result};
+use crate::presign::Reader;
+
+const ZSTD_LEVEL: i32 = 3;
+const CHANNEL_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadCompression {
+    Zstd,
+}
+
+impl LoadCompression {
+    pub fn parse(s: &str) -> Result<Option<Self>> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(None),
+            "zstd" => Ok(Some(Self::Zstd)),
+            _ => Err(Error::BadArgument(format!(
+                "Invalid value for load_compression: {}, should be one of none/zstd",
+                s
+            ))),
+        }
+    }
+
+    /// The value of file format option `compression` for data compressed with this.
+    pub fn file_format(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zstd",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zst",
+        }
+    }
+}
+
+struct ChannelWriter {
+    tx: mpsc::Sender<std::io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "upload aborted"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Compress data with zstd in a blocking thread with `workers` compression threads,
+/// the returned reader yields compressed data as soon as each frame is ready,
+/// so that compression overlaps with the upload reading from it.
+pub(crate) fn compress(data: Reader, compression: LoadCompression, workers: u32) -> Reader {
+    let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
+    match compression {
+        LoadCompression::Zstd => {
+            tokio::task::spawn_blocking(move || {
+                let writer = ChannelWriter { tx: tx.clone() };
+                let result = (|| {
+                    let mut input = SyncIoBridge::new(data);
+                    let mut encoder = zstd::stream::write::Encoder::new(writer, ZSTD_LEVEL)?;
+                    if workers > 1 {
+                        encoder.multithread(workers)?;
+                    }
+                    std::io::copy(&mut input, &mut encoder)?;
+                    encoder.finish()?.flush()
+                })();
+                if let Err(e) = result {
+                    let _ = tx.blocking_send(Err(e));
+                }
+            });
+        }
+    }
+    Box::new(StreamReader::new(ReceiverStream::new(rx)))
+}
+
+pub(crate) fn default_workers() -> u32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get() as u32)
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn compress_zstd() -> Result<()> {
+        let data = "1,abc,2024-01-01\n".repeat(100000);
+        let reader = Box::new(std::io::Cursor::new(data.clone().into_bytes()));
+        let mut compressed = vec![];
+        compress(reader, LoadCompression::Zstd, 4)
+            .read_to_end(&mut compressed)
+            .await?;
+        assert!(compressed.len() < data.len());
+        let decompressed = zstd::decode_all(compressed.as_slice())?;
+        assert_eq!(decompressed, data.as_bytes());
+        Ok(())
+    }
+}
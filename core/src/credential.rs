@@ -0,0 +1,360 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use reqwest::{Client as HttpClient, RequestBuilder};
+
+use crate::auth::{Auth, SensitiveString};
+use crate::error::{Error, Result};
+
+#[derive(Clone)]
+pub enum Credential {
+    Password {
+        username: String,
+        password: SensitiveString,
+    },
+    Token(SensitiveString),
+}
+
+impl Credential {
+    fn wrap(&self, builder: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credential::Password { username, password } => {
+                builder.basic_auth(username, Some(password.inner()))
+            }
+            Credential::Token(token) => builder.bearer_auth(token.inner()),
+        }
+    }
+}
+
+/// Source of credentials consulted by `APIClient` before each request,
+/// so that credentials can be rotated without reconnecting.
+/// Providers fetching credentials remotely should cache them until expired.
+/// There is no AWS IAM provider, since the server does not verify IAM auth tokens.
+#[async_trait]
+pub trait CredentialProvider: Send + Sync {
+    async fn credential(&self) -> Result<Credential>;
+
+    /// Drop any cached credential, called after it is rejected by the server.
+    fn invalidate(&self) {}
+
+    fn username(&self) -> String;
+}
+
+/// Credential cached until the deadline.
+#[derive(Default)]
+struct CredentialCache(parking_lot::Mutex<Option<(Credential, Instant)>>);
+
+impl CredentialCache {
+    fn get(&self) -> Option<Credential> {
+        let cache = self.0.lock();
+        cache
+            .as_ref()
+            .filter(|(_, deadline)| Instant::now() < *deadline)
+            .map(|(c, _)| c.clone())
+    }
+
+    fn set(&self, credential: Credential, ttl: Duration) {
+        *self.0.lock() = Some((credential, Instant::now() + ttl));
+    }
+
+    fn clear(&self) {
+        *self.0.lock() = None;
+    }
+}
+
+pub struct StaticCredentialProvider {
+    credential: Credential,
+}
+
+impl StaticCredentialProvider {
+    pub fn password(username: impl ToString, password: impl ToString) -> Self {
+        Self {
+            credential: Credential::Password {
+                username: username.to_string(),
+                password: SensitiveString::from(password.to_string()),
+            },
+        }
+    }
+
+    pub fn token(token: impl ToString) -> Self {
+        Self {
+            credential: Credential::Token(SensitiveString::from(token.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for StaticCredentialProvider {
+    async fn credential(&self) -> Result<Credential> {
+        Ok(self.credential.clone())
+    }
+
+    fn username(&self) -> String {
+        match &self.credential {
+            Credential::Password { username, .. } => username.clone(),
+            Credential::Token(_) => "token".to_string(),
+        }
+    }
+}
+
+/// Read access token from a file before each request.
+pub struct TokenFileCredentialProvider {
+    token_file: String,
+}
+
+impl TokenFileCredentialProvider {
+    pub fn new(token_file: impl ToString) -> Self {
+        Self {
+            token_file: token_file.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for TokenFileCredentialProvider {
+    async fn credential(&self) -> Result<Credential> {
+        let token = tokio::fs::read_to_string(&self.token_file)
+            .await
+            .map_err(|e| {
+                Error::IO(format!(
+                    "cannot read access token from file {}: {}",
+                    self.token_file, e
+                ))
+            })?;
+        Ok(Credential::Token(SensitiveString::from(token.trim())))
+    }
+
+    fn username(&self) -> String {
+        "token".to_string()
+    }
+}
+
+/// Run an external command and use its stdout as access token, cached for `ttl`.
+pub struct CommandCredentialProvider {
+    program: String,
+    args: Vec<String>,
+    ttl: Duration,
+    cache: CredentialCache,
+}
+
+impl CommandCredentialProvider {
+    pub fn new(program: impl ToString, args: Vec<String>, ttl: Duration) -> Self {
+        Self {
+            program: program.to_string(),
+            args,
+            ttl,
+            cache: CredentialCache::default(),
+        }
+    }
+
+    /// Parse a command line split by whitespace.
+    pub fn parse(command: &str, ttl: Duration) -> Result<Self> {
+        let mut parts = command.split_whitespace().map(|s| s.to_string());
+        let program = parts.next().ok_or_else(|| {
+            Error::BadArgument("Invalid value for credential_command: empty".to_string())
+        })?;
+        Ok(Self::new(program, parts.collect(), ttl))
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for CommandCredentialProvider {
+    async fn credential(&self) -> Result<Credential> {
+        if let Some(credential) = self.cache.get() {
+            return Ok(credential);
+        }
+        let output = tokio::process::Command::new(&self.program)
+            .args(&self.args)
+            .output()
+            .await
+            .map_err(|e| Error::IO(format!("cannot run credential command: {}", e)))?;
+        if !output.status.success() {
+            return Err(Error::IO(format!(
+                "credential command failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if token.is_empty() {
+            return Err(Error::IO(
+                "credential command returned empty token".to_string(),
+            ));
+        }
+        let credential = Credential::Token(SensitiveString::from(token));
+        self.cache.set(credential.clone(), self.ttl);
+        Ok(credential)
+    }
+
+    fn invalidate(&self) {
+        self.cache.clear();
+    }
+
+    fn username(&self) -> String {
+        "token".to_string()
+    }
+}
+
+/// Fetch access token from a cloud metadata endpoint, such as
+/// `http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token`
+/// with header `Metadata-Flavor: Google`. Responses in JSON with `access_token`
+/// and `expires_in` are cached until expired, otherwise the body is used as token
+/// and cached for `ttl`.
+pub struct MetadataCredentialProvider {
+    cli: HttpClient,
+    url: String,
+    headers: Vec<(String, String)>,
+    ttl: Duration,
+    cache: CredentialCache,
+}
+
+impl MetadataCredentialProvider {
+    pub fn new(url: impl ToString, headers: Vec<(String, String)>, ttl: Duration) -> Self {
+        Self {
+            cli: HttpClient::new(),
+            url: url.to_string(),
+            headers,
+            ttl,
+            cache: CredentialCache::default(),
+        }
+    }
+}
+
+fn parse_metadata_token(body: &str) -> Option<(String, Option<Duration>)> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let token = value.get("access_token")?.as_str()?.to_string();
+    let expires_in = value.get("expires_in").and_then(|v| match v {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    });
+    Some((token, expires_in.map(Duration::from_secs)))
+}
+
+#[async_trait]
+impl CredentialProvider for MetadataCredentialProvider {
+    async fn credential(&self) -> Result<Credential> {
+        if let Some(credential) = self.cache.get() {
+            return Ok(credential);
+        }
+        let mut builder = self.cli.get(&self.url).timeout(Duration::from_secs(10));
+        for (k, v) in &self.headers {
+            builder = builder.header(k, v);
+        }
+        let response = builder.send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(Error::Response { status, msg: body });
+        }
+        let (token, ttl) = match parse_metadata_token(&body) {
+            // refresh a minute before expiring
+            Some((token, Some(expires_in))) => {
+                (token, expires_in.saturating_sub(Duration::from_secs(60)))
+            }
+            Some((token, None)) => (token, self.ttl),
+            None => (body.trim().to_string(), self.ttl),
+        };
+        let credential = Credential::Token(SensitiveString::from(token));
+        self.cache.set(credential.clone(), ttl);
+        Ok(credential)
+    }
+
+    fn invalidate(&self) {
+        self.cache.clear();
+    }
+
+    fn username(&self) -> String {
+        "token".to_string()
+    }
+}
+
+/// Auth with credentials from a provider, refreshed before each request.
+pub(crate) struct ProviderAuth {
+    provider: std::sync::Arc<dyn CredentialProvider>,
+    current: parking_lot::Mutex<Option<Credential>>,
+}
+
+impl ProviderAuth {
+    pub(crate) fn new(provider: std::sync::Arc<dyn CredentialProvider>) -> Self {
+        Self {
+            provider,
+            current: parking_lot::Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl Auth for ProviderAuth {
+    async fn refresh(&self) -> Result<()> {
+        let credential = self.provider.credential().await?;
+        *self.current.lock() = Some(credential);
+        Ok(())
+    }
+
+    fn invalidate(&self) {
+        self.provider.invalidate();
+    }
+
+    fn wrap(&self, builder: RequestBuilder) -> Result<RequestBuilder> {
+        match &*self.current.lock() {
+            Some(credential) => Ok(credential.wrap(builder)),
+            None => Err(Error::BadArgument(
+                "credential not fetched from provider".to_string(),
+            )),
+        }
+    }
+
+    fn can_reload(&self) -> bool {
+        true
+    }
+
+    fn username(&self) -> String {
+        self.provider.username()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn metadata_token() {
+        let body = r#"{"access_token":"ya29.abc","expires_in":3599,"token_type":"Bearer"}"#;
+        assert_eq!(
+            parse_metadata_token(body),
+            Some(("ya29.abc".to_string(), Some(Duration::from_secs(3599))))
+        );
+        let body = r#"{"access_token":"eyJ0","expires_in":"86399"}"#;
+        assert_eq!(
+            parse_metadata_token(body),
+            Some(("eyJ0".to_string(), Some(Duration::from_secs(86399))))
+        );
+        assert_eq!(parse_metadata_token("plain-token"), None);
+    }
+
+    #[tokio::test]
+    async fn command_provider() -> Result<()> {
+        let provider = CommandCredentialProvider::parse("echo my-token", Duration::from_secs(60))?;
+        match provider.credential().await? {
+            Credential::Token(token) => assert_eq!(token.inner(), "my-token"),
+            _ => panic!("expected token"),
+        }
+        assert!(CommandCredentialProvider::parse(" ", Duration::from_secs(60)).is_err());
+        Ok(())
+    }
+}
@@ -29,6 +29,8 @@ pub enum Error {
 
     /// send request error
     Request(String),
+    /// failed to connect to the server, so the request is not sent
+    Connect(String),
 
     /// http handler return 200, but body is invalid
     /// 1. failed to decode body to Utf8 or JSON
@@ -58,6 +60,8 @@ pub enum Error {
     /// TODO: try to distinguish them
     QueryNotFound(String),
     AuthFailure(ErrorCode),
+    /// http handler return 429, still after waiting for `rate_limit_timeout_secs`
+    RateLimited(String),
 }
 
 impl Error {
@@ -72,6 +76,15 @@ impl Error {
         Error::WithContext(Box::new(self), ctx.to_string())
     }
 
+    /// Failed to connect to the server, the request is surely not handled.
+    pub(crate) fn is_connect_error(&self) -> bool {
+        match self {
+            Error::Connect(_) => true,
+            Error::WithContext(err, _) => err.is_connect_error(),
+            _ => false,
+        }
+    }
+
     pub fn status_code(&self) -> Option<StatusCode> {
         match self {
             Error::Logic(status, ..) => Some(*status),
@@ -87,13 +100,14 @@ impl std::fmt::Display for Error {
         match self {
             Error::Decode(msg) => write!(f, "DecodeError: {msg}"),
             Error::BadArgument(msg) => write!(f, "BadArgument: {msg}"),
-            Error::Request(msg) => write!(f, "{msg}"),
+            Error::Request(msg) | Error::Connect(msg) => write!(f, "{msg}"),
             Error::Response { msg, status } => write!(f, "ResponseError: ({status}){msg}"),
             Error::IO(msg) => write!(f, "IOError: {msg}"),
             Error::Logic(status_code, ec) => write!(f, "BadRequest:({status_code}){ec}"),
             Error::QueryNotFound(msg) => write!(f, "QueryNotFound: {msg}"),
             Error::QueryFailed(ec) => write!(f, "QueryFailed: {ec}"),
             Error::AuthFailure(ec) => write!(f, "AuthFailure: {ec}"),
+            Error::RateLimited(msg) => write!(f, "RateLimited: {msg}"),
 
             Error::WithContext(err, ctx) => write!(f, "fail to {ctx}: {err}"),
         }
@@ -131,7 +145,11 @@ impl From<serde_json::Error> for Error {
 
 impl From<reqwest::Error> for Error {
     fn from(e: reqwest::Error) -> Self {
-        Error::Request(e.to_string())
+        if e.is_connect() {
+            Error::Connect(e.to_string())
+        } else {
+            Error::Request(e.to_string())
+        }
     }
 }
 
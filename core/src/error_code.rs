@@ -17,6 +17,8 @@ use std::fmt::{Display, Formatter};
 
 const SESSION_TOKEN_EXPIRED: u16 = 5101;
 const SESSION_TOKEN_NOT_FOUND: u16 = 5103;
+// `Internal` of the server, for queries failed without an error
+const INTERNAL: u16 = 1001;
 
 pub fn need_refresh_token(code: u16) -> bool {
     code == SESSION_TOKEN_EXPIRED || code == SESSION_TOKEN_NOT_FOUND
@@ -29,6 +31,17 @@ pub struct ErrorCode {
     pub detail: Option<String>,
 }
 
+impl ErrorCode {
+    /// Error of a query in `Failed` state without an error in the response.
+    pub(crate) fn failed_without_error(query_id: &str) -> Self {
+        Self {
+            code: INTERNAL,
+            message: format!("query {} failed without error", query_id),
+            detail: None,
+        }
+    }
+}
+
 /// try to decode to this when status code is not 200.
 /// so the error field is expect to exist.
 #[derive(Deserialize, Debug)]
@@ -15,24 +15,62 @@
 mod client;
 
 mod auth;
+mod balance;
+mod builder;
+mod capability;
+mod compress;
+mod credential;
 mod error;
 mod error_code;
 mod global_cookie_store;
 mod login;
+mod pool;
 mod presign;
+mod query;
+mod query_limit;
+mod rate_limit;
+mod redact;
 mod request;
 mod response;
+mod retry;
 
 mod session;
+mod sign;
 mod stage;
+mod tls;
+mod tracker;
+mod transport;
+mod vcr;
 
 pub use auth::SensitiveString;
+pub use builder::APIClientBuilder;
+pub use capability::{Capabilities, ServerVersion};
 pub use client::APIClient;
+pub use client::CLIENT_ARGS;
+pub use compress::LoadCompression;
+pub use credential::{
+    CommandCredentialProvider, Credential, CredentialProvider, MetadataCredentialProvider,
+    StaticCredentialProvider, TokenFileCredentialProvider,
+};
 pub use error::Error;
+pub use pool::ConnectionStats;
 pub use presign::presign_download_from_stage;
 pub use presign::presign_upload_to_stage;
 pub use presign::PresignedResponse;
+pub use query::{QueryClient, QueryHandle};
+pub use query_limit::QueryLimitStats;
+pub use query_limit::QuerySlot;
+pub use query_limit::StartedQuery;
+pub use rate_limit::RateLimiter;
+pub use redact::redact_sql;
 pub use response::QueryResponse;
+pub use response::QueryState;
 pub use response::QueryStats;
 pub use response::SchemaField;
+pub use retry::RetryPolicy;
+pub use sign::RequestSigner;
 pub use stage::StageLocation;
+pub use tls::TlsBackend;
+pub use tracker::QueryTracker;
+pub use transport::{Transport, TransportError};
+pub use vcr::{vcr_transport, RecordingTransport, ReplayTransport, VCR_MODE_ENV};
@@ -0,0 +1,255 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use reqwest::ClientBuilder;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Http2Mode {
+    /// http/1.1 only
+    Disable,
+    /// negotiate http/2 with ALPN over https, with prior knowledge over plain http,
+    /// so that pages and concurrent queries are multiplexed on one connection
+    Enable,
+    /// http/2 without negotiation, also for plain http
+    PriorKnowledge,
+}
+
+impl Http2Mode {
+    pub(crate) fn parse(s: &str) -> Result<Self> {
+        match s {
+            "disable" | "0" | "false" => Ok(Self::Disable),
+            "enable" | "1" | "true" => Ok(Self::Enable),
+            "prior_knowledge" => Ok(Self::PriorKnowledge),
+            _ => Err(Error::BadArgument(format!(
+                "Invalid value for http2: {}, should be one of disable/enable/prior_knowledge",
+                s
+            ))),
+        }
+    }
+}
+
+/// Proxy of the http clients, for both the server and presigned urls.
+#[derive(Debug, Clone)]
+pub(crate) enum ProxyMode {
+    /// `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY` from environment
+    Env,
+    Disable,
+    /// proxy of the url for all requests, except hosts in `NO_PROXY`
+    Url(Box<reqwest::Proxy>),
+}
+
+impl ProxyMode {
+    pub(crate) fn parse(s: &str) -> Result<Self> {
+        match s {
+            "env" => Ok(Self::Env),
+            "disable" | "none" => Ok(Self::Disable),
+            url => {
+                let proxy = reqwest::Proxy::all(url).map_err(|e| {
+                    Error::BadArgument(format!("Invalid value for proxy: {}, {}", url, e))
+                })?;
+                Ok(Self::Url(Box::new(
+                    proxy.no_proxy(reqwest::NoProxy::from_env()),
+                )))
+            }
+        }
+    }
+}
+
+/// Keep-alive, http/2 and proxy settings of the http clients of a client.
+#[derive(Debug, Clone)]
+pub(crate) struct PoolConfig {
+    /// `None` to keep idle connections until the server closes them
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) max_idle_per_host: usize,
+    /// `None` to disable tcp keepalive
+    pub(crate) tcp_keepalive: Option<Duration>,
+    pub(crate) http2: Http2Mode,
+    pub(crate) proxy: ProxyMode,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Some(Duration::from_secs(30)),
+            max_idle_per_host: usize::MAX,
+            // same as reqwest
+            tcp_keepalive: Some(Duration::from_secs(15)),
+            http2: Http2Mode::Disable,
+            proxy: ProxyMode::Env,
+        }
+    }
+}
+
+impl PoolConfig {
+    pub(crate) fn parse_idle_timeout(&mut self, v: &str) -> Result<()> {
+        let secs: u64 = v.parse()?;
+        self.idle_timeout = match secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        Ok(())
+    }
+
+    pub(crate) fn parse_tcp_keepalive(&mut self, v: &str) -> Result<()> {
+        let secs: u64 = v.parse()?;
+        self.tcp_keepalive = match secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        Ok(())
+    }
+
+    /// Apply to the client of the server, which can only speak http/2 over
+    /// plain http with prior knowledge.
+    pub(crate) fn apply(&self, builder: ClientBuilder, plain_http: bool) -> ClientBuilder {
+        let builder = self.apply_pool(builder);
+        match self.http2 {
+            Http2Mode::Disable => builder.http1_only(),
+            Http2Mode::Enable if !plain_http => builder,
+            Http2Mode::Enable | Http2Mode::PriorKnowledge => builder.http2_prior_knowledge(),
+        }
+    }
+
+    /// Apply to the client of presigned urls, which are not known to support
+    /// http/2, so it is only negotiated.
+    pub(crate) fn apply_presign(&self, builder: ClientBuilder) -> ClientBuilder {
+        let builder = self.apply_pool(builder);
+        match self.http2 {
+            Http2Mode::Disable => builder.http1_only(),
+            Http2Mode::Enable | Http2Mode::PriorKnowledge => builder,
+        }
+    }
+
+    fn apply_pool(&self, builder: ClientBuilder) -> ClientBuilder {
+        let builder = builder
+            .pool_idle_timeout(self.idle_timeout)
+            .pool_max_idle_per_host(self.max_idle_per_host)
+            .tcp_keepalive(self.tcp_keepalive);
+        match &self.proxy {
+            // used by reqwest unless disabled
+            ProxyMode::Env => builder,
+            ProxyMode::Disable => builder.no_proxy(),
+            ProxyMode::Url(proxy) => builder.proxy(*proxy.clone()),
+        }
+    }
+}
+
+/// Requests sent by a client and connections opened for them,
+/// the others are sent on connections reused from the pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub requests: u64,
+    pub connections: u64,
+}
+
+impl ConnectionStats {
+    pub fn reused(&self) -> u64 {
+        self.requests.saturating_sub(self.connections)
+    }
+}
+
+/// Connection reuse metrics of a client, shared by its clones.
+#[derive(Debug, Default)]
+pub(crate) struct PoolMetrics {
+    requests: AtomicU64,
+    connections: AtomicU64,
+}
+
+impl PoolMetrics {
+    pub(crate) fn on_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ConnectionStats {
+        ConnectionStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            connections: self.connections.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Connector layer counting the connections opened by the pool.
+#[derive(Clone)]
+pub(crate) struct CountConnections(pub(crate) Arc<PoolMetrics>);
+
+impl<S> Layer<S> for CountConnections {
+    type Service = CountingConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountingConnector {
+            inner,
+            metrics: self.0.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct CountingConnector<S> {
+    inner: S,
+    metrics: Arc<PoolMetrics>,
+}
+
+impl<S: Service<R>, R> Service<R> for CountingConnector<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        self.metrics.connections.fetch_add(1, Ordering::Relaxed);
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_pool_options() -> Result<()> {
+        let mut config = PoolConfig::default();
+        config.parse_idle_timeout("0")?;
+        assert_eq!(config.idle_timeout, None);
+        config.parse_idle_timeout("90")?;
+        assert_eq!(config.idle_timeout, Some(Duration::from_secs(90)));
+        assert!(config.parse_idle_timeout("forever").is_err());
+        assert_eq!(
+            Http2Mode::parse("prior_knowledge")?,
+            Http2Mode::PriorKnowledge
+        );
+        assert_eq!(Http2Mode::parse("1")?, Http2Mode::Enable);
+        assert_eq!(Http2Mode::parse("false")?, Http2Mode::Disable);
+        assert!(Http2Mode::parse("on").is_err());
+        assert!(matches!(ProxyMode::parse("env")?, ProxyMode::Env));
+        assert!(matches!(ProxyMode::parse("none")?, ProxyMode::Disable));
+        assert!(matches!(
+            ProxyMode::parse("http://proxy.internal:3128")?,
+            ProxyMode::Url(_)
+        ));
+        assert!(ProxyMode::parse("proxy internal").is_err());
+        Ok(())
+    }
+}
@@ -12,16 +12,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::BTreeMap, path::Path};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use log::info;
+use parking_lot::Mutex;
 use reqwest::{Body, Client as HttpClient, StatusCode};
+use serde::{Deserialize, Serialize};
 use tokio::io::AsyncRead;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 use tokio_stream::StreamExt;
 use tokio_util::io::ReaderStream;
 
 use crate::error::{Error, Result};
+use crate::rate_limit::RateLimiter;
+
+/// Size of the ranges downloaded concurrently for large files.
+const DOWNLOAD_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
 
 pub type Reader = Box<dyn AsyncRead + Send + Sync + Unpin + 'static>;
 
@@ -43,9 +52,17 @@ pub async fn presign_upload_to_stage(
     presigned: PresignedResponse,
     data: Reader,
     size: u64,
+) -> Result<()> {
+    presign_upload_with_client(&HttpClient::new(), presigned, data, size).await
+}
+
+pub(crate) async fn presign_upload_with_client(
+    client: &HttpClient,
+    presigned: PresignedResponse,
+    data: Reader,
+    size: u64,
 ) -> Result<()> {
     info!("upload to stage with presigned url, size: {}", size);
-    let client = HttpClient::new();
     let mut builder = client.put(presigned.url);
     for (k, v) in presigned.headers {
         if k.to_lowercase() == "content-length" {
@@ -70,11 +87,19 @@ pub async fn presign_upload_to_stage(
 pub async fn presign_download_from_stage(
     presigned: PresignedResponse,
     local_path: &Path,
+) -> Result<u64> {
+    presign_download_with_limit(&HttpClient::new(), presigned, local_path, None).await
+}
+
+pub(crate) async fn presign_download_with_limit(
+    client: &HttpClient,
+    presigned: PresignedResponse,
+    local_path: &Path,
+    limiter: Option<&RateLimiter>,
 ) -> Result<u64> {
     if let Some(p) = local_path.parent() {
         tokio::fs::create_dir_all(p).await?;
     }
-    let client = HttpClient::new();
     let mut builder = client.get(presigned.url);
     for (k, v) in presigned.headers {
         builder = builder.header(k, v);
@@ -87,7 +112,11 @@ pub async fn presign_download_from_stage(
             let mut file = tokio::fs::File::create(local_path).await?;
             let mut body = resp.bytes_stream();
             while let Some(chunk) = body.next().await {
-                file.write_all(&chunk?).await?;
+                let chunk = chunk?;
+                if let Some(limiter) = limiter {
+                    limiter.acquire(chunk.len()).await;
+                }
+                file.write_all(&chunk).await?;
             }
             file.flush().await?;
             let metadata = file.metadata().await?;
@@ -99,3 +128,301 @@ pub async fn presign_download_from_stage(
         ))),
     }
 }
+
+/// Ranges of a large download completed so far, kept next to the local file
+/// until all are done, so that an interrupted download is resumed.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct DownloadState {
+    size: u64,
+    chunk_size: u64,
+    /// etag of the remote file in the first response, the download is restarted
+    /// if it is changed, instead of mixing the ranges of both
+    #[serde(default)]
+    etag: Option<String>,
+    done: BTreeSet<u64>,
+}
+
+impl DownloadState {
+    fn new(size: u64) -> Self {
+        Self {
+            size,
+            chunk_size: DOWNLOAD_CHUNK_SIZE,
+            ..Default::default()
+        }
+    }
+
+    fn sidecar(local_path: &Path) -> PathBuf {
+        let mut name = local_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".bendsql-download");
+        local_path.with_file_name(name)
+    }
+
+    /// The state of the last download if it is for the same size and the
+    /// local file is still there, ranges done without etag are not resumed.
+    async fn load(local_path: &Path, size: u64) -> Option<Self> {
+        let content = tokio::fs::read(Self::sidecar(local_path)).await.ok()?;
+        let state: Self = serde_json::from_slice(&content).ok()?;
+        let len = tokio::fs::metadata(local_path).await.ok()?.len();
+        (state.size == size
+            && state.chunk_size == DOWNLOAD_CHUNK_SIZE
+            && len == size
+            && (state.etag.is_some() || state.done.is_empty()))
+        .then_some(state)
+    }
+
+    async fn save(&self, local_path: &Path) -> Result<()> {
+        let sidecar = Self::sidecar(local_path);
+        let tmp = sidecar.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec(self)?).await?;
+        tokio::fs::rename(&tmp, &sidecar).await?;
+        Ok(())
+    }
+}
+
+enum RangeOutcome {
+    Done,
+    /// the server responds with the whole file, not supporting ranges
+    Whole,
+    /// the size or etag of the remote file differs from the ones of the state
+    Changed,
+}
+
+/// Download a file of `size` bytes in ranges with `concurrency` requests at once,
+/// into a preallocated sparse file, resuming from the ranges already done.
+/// Small files and servers not supporting ranges are downloaded in one request.
+/// The download is restarted once if the remote file is changed.
+pub(crate) async fn presign_download_ranged(
+    client: &HttpClient,
+    presigned: PresignedResponse,
+    local_path: &Path,
+    size: u64,
+    concurrency: usize,
+    limiter: Option<Arc<RateLimiter>>,
+) -> Result<u64> {
+    if size <= DOWNLOAD_CHUNK_SIZE || concurrency <= 1 {
+        return presign_download_with_limit(client, presigned, local_path, limiter.as_deref())
+            .await;
+    }
+    if let Some(p) = local_path.parent() {
+        tokio::fs::create_dir_all(p).await?;
+    }
+    let presigned = Arc::new(presigned);
+    let mut resumed = DownloadState::load(local_path, size).await;
+    let mut restarted = false;
+    loop {
+        let state = match resumed.take() {
+            Some(state) => {
+                info!(
+                    "resume download of {} with {} ranges done",
+                    local_path.display(),
+                    state.done.len()
+                );
+                state
+            }
+            None => {
+                let file = tokio::fs::File::create(local_path).await?;
+                file.set_len(size).await?;
+                let state = DownloadState::new(size);
+                state.save(local_path).await?;
+                state
+            }
+        };
+        let outcome = download_ranges(
+            client,
+            &presigned,
+            local_path,
+            state,
+            concurrency,
+            limiter.clone(),
+        )
+        .await?;
+        match outcome {
+            RangeOutcome::Done => return Ok(size),
+            RangeOutcome::Whole => {
+                info!(
+                    "range requests not supported, download {} at once",
+                    local_path.display()
+                );
+                let presigned = PresignedResponse {
+                    method: presigned.method.clone(),
+                    headers: presigned.headers.clone(),
+                    url: presigned.url.clone(),
+                };
+                return presign_download_with_limit(
+                    client,
+                    presigned,
+                    local_path,
+                    limiter.as_deref(),
+                )
+                .await;
+            }
+            RangeOutcome::Changed if !restarted => {
+                info!(
+                    "remote file of {} changed, restart the download",
+                    local_path.display()
+                );
+                restarted = true;
+            }
+            RangeOutcome::Changed => {
+                return Err(Error::IO(format!(
+                    "Download of {} failed, the remote file changed during download",
+                    local_path.display()
+                )))
+            }
+        }
+    }
+}
+
+/// Download the ranges not done of the state with the workers, the sidecar is
+/// removed once all are done, or kept for resume on errors.
+async fn download_ranges(
+    client: &HttpClient,
+    presigned: &Arc<PresignedResponse>,
+    local_path: &Path,
+    state: DownloadState,
+    concurrency: usize,
+    limiter: Option<Arc<RateLimiter>>,
+) -> Result<RangeOutcome> {
+    let size = state.size;
+    let chunks = size.div_ceil(DOWNLOAD_CHUNK_SIZE);
+    let queue: VecDeque<u64> = (0..chunks).filter(|i| !state.done.contains(i)).collect();
+    let queue = Arc::new(Mutex::new(queue));
+    // locked across the writes of the sidecar, which are in order then
+    let state = Arc::new(tokio::sync::Mutex::new(state));
+
+    let mut workers = vec![];
+    for _ in 0..concurrency {
+        let client = client.clone();
+        let presigned = presigned.clone();
+        let local_path = local_path.to_path_buf();
+        let limiter = limiter.clone();
+        let queue = queue.clone();
+        let state = state.clone();
+        workers.push(tokio::spawn(async move {
+            loop {
+                let Some(chunk) = queue.lock().pop_front() else {
+                    return Ok(RangeOutcome::Done);
+                };
+                let start = chunk * DOWNLOAD_CHUNK_SIZE;
+                let end = (start + DOWNLOAD_CHUNK_SIZE).min(size);
+                let outcome = download_range(
+                    &client,
+                    &presigned,
+                    &local_path,
+                    &state,
+                    start,
+                    end,
+                    limiter.as_deref(),
+                )
+                .await?;
+                if !matches!(outcome, RangeOutcome::Done) {
+                    queue.lock().clear();
+                    return Ok(outcome);
+                }
+                let mut state = state.lock().await;
+                state.done.insert(chunk);
+                state.save(&local_path).await?;
+            }
+        }));
+    }
+    // wait for all workers before failing, the ranges done are kept for resume
+    let mut results = vec![];
+    for worker in workers {
+        results.push(match worker.await {
+            Ok(result) => result,
+            Err(e) => Err(Error::IO(format!("Download task failed: {}", e))),
+        });
+    }
+    let mut outcome = RangeOutcome::Done;
+    for result in results {
+        match result? {
+            RangeOutcome::Done => {}
+            RangeOutcome::Whole => {
+                if matches!(outcome, RangeOutcome::Done) {
+                    outcome = RangeOutcome::Whole;
+                }
+            }
+            RangeOutcome::Changed => outcome = RangeOutcome::Changed,
+        }
+    }
+    if !matches!(outcome, RangeOutcome::Changed) {
+        tokio::fs::remove_file(DownloadState::sidecar(local_path)).await?;
+    }
+    Ok(outcome)
+}
+
+/// Download bytes `[start, end)` into the same range of the local file, after
+/// checking the remote file is the one of the state, the etag of the first
+/// response is kept in the state.
+async fn download_range(
+    client: &HttpClient,
+    presigned: &PresignedResponse,
+    local_path: &Path,
+    state: &tokio::sync::Mutex<DownloadState>,
+    start: u64,
+    end: u64,
+    limiter: Option<&RateLimiter>,
+) -> Result<RangeOutcome> {
+    let mut builder = client.get(&presigned.url);
+    for (k, v) in &presigned.headers {
+        builder = builder.header(k, v);
+    }
+    let resp = builder
+        .header("Range", format!("bytes={}-{}", start, end - 1))
+        .send()
+        .await?;
+    match resp.status() {
+        StatusCode::PARTIAL_CONTENT => {}
+        StatusCode::OK => return Ok(RangeOutcome::Whole),
+        status => {
+            return Err(Error::IO(format!(
+                "Download with presigned url failed: {}",
+                status
+            )))
+        }
+    }
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+    };
+    // `bytes start-end/total`
+    let total = header("content-range")
+        .and_then(|v| v.rsplit_once('/'))
+        .and_then(|(_, total)| total.parse::<u64>().ok());
+    let etag = header("etag").map(|v| v.to_string());
+    {
+        let mut state = state.lock().await;
+        if total.is_some_and(|total| total != state.size) {
+            return Ok(RangeOutcome::Changed);
+        }
+        match (&state.etag, etag) {
+            (Some(expected), Some(etag)) if *expected != etag => return Ok(RangeOutcome::Changed),
+            (None, Some(etag)) => state.etag = Some(etag),
+            _ => {}
+        }
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(local_path)
+        .await?;
+    file.seek(SeekFrom::Start(start)).await?;
+    let mut written = 0;
+    let mut body = resp.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        if let Some(limiter) = limiter {
+            limiter.acquire(chunk.len()).await;
+        }
+        file.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+    }
+    file.flush().await?;
+    if written != end - start {
+        return Err(Error::IO(format!(
+            "Download range {}-{} incomplete, got {} bytes",
+            start, end, written
+        )));
+    }
+    Ok(RangeOutcome::Done)
+}
@@ -0,0 +1,191 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::client::APIClient;
+use crate::error::Result;
+use crate::query_limit::{QuerySlot, StartedQuery};
+use crate::response::{QueryResponse, QueryState, QueryStats, SchemaField};
+
+/// Low level access to the http query protocol, for driving the pages of a query by hand.
+///
+/// A query is started with [`QueryClient::start_query`], which returns the first page,
+/// then [`QueryClient::poll`] fetches the following pages until it returns `None`.
+/// Queries not read to the end should be released with [`QueryClient::close`],
+/// or cancelled with [`QueryClient::kill`] while still running.
+/// With `max_concurrent_queries`, the query started holds [`StartedQuery::slot`]
+/// until it is dropped, keep it while polling the following pages.
+#[derive(Clone)]
+pub struct QueryClient {
+    client: Arc<APIClient>,
+}
+
+impl QueryClient {
+    pub async fn new(dsn: &str, name: Option<String>) -> Result<Self> {
+        let client = APIClient::new(dsn, name).await?;
+        Ok(Self::with_client(Arc::new(client)))
+    }
+
+    /// Share the session of a client, e.g. the one used by the driver.
+    pub fn with_client(client: Arc<APIClient>) -> Self {
+        Self { client }
+    }
+
+    pub fn api_client(&self) -> &Arc<APIClient> {
+        &self.client
+    }
+
+    pub async fn start_query(&self, sql: &str) -> Result<StartedQuery> {
+        self.client.start_query(sql).await
+    }
+
+    /// Start a query without waiting for it, poll the returned handle for its status.
+    pub async fn submit(&self, sql: &str) -> Result<QueryHandle> {
+        let started = self.client.start_query(sql).await?;
+        Ok(QueryHandle::new(self.clone(), started))
+    }
+
+    /// Parameters are sent to server as sql literals, see [`APIClient::start_query_with_params`].
+    pub async fn start_query_with_params(
+        &self,
+        sql: &str,
+        params: serde_json::Value,
+    ) -> Result<StartedQuery> {
+        self.client.start_query_with_params(sql, params).await
+    }
+
+    /// Fetch the page after `resp`, `None` if `resp` is the last page of the query.
+    /// Pages of a running query may have no data yet.
+    pub async fn poll(&self, resp: &QueryResponse) -> Result<Option<QueryResponse>> {
+        match resp.next_uri {
+            Some(ref next_uri) => {
+                let page = self
+                    .client
+                    .query_page(&resp.id, next_uri, &resp.node_id)
+                    .await?;
+                Ok(Some(page))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Fetch all pages after `resp`, with data of all pages in the returned one.
+    pub async fn wait(&self, resp: QueryResponse) -> Result<QueryResponse> {
+        self.client.wait_for_query(resp).await
+    }
+
+    pub async fn kill(&self, query_id: &str) -> Result<()> {
+        self.client.kill_query(query_id).await
+    }
+
+    /// Release the resources of the query on server, pages after `resp` can not be read anymore.
+    pub async fn close(&self, resp: &QueryResponse) -> Result<()> {
+        let final_uri = match resp.final_uri {
+            Some(ref final_uri) => final_uri,
+            None => return Ok(()),
+        };
+        self.client
+            .finalize_query(&resp.id, final_uri, &resp.node_id)
+            .await
+    }
+}
+
+/// A query started by [`QueryClient::submit`], for long running statements to be polled
+/// from time to time instead of waiting on them.
+pub struct QueryHandle {
+    client: QueryClient,
+    // the last page polled
+    resp: QueryResponse,
+    schema: Vec<SchemaField>,
+    data: Vec<Vec<Option<String>>>,
+    // released when all pages are read or the handle is dropped
+    slot: Option<QuerySlot>,
+}
+
+impl QueryHandle {
+    fn new(client: QueryClient, started: StartedQuery) -> Self {
+        let StartedQuery { mut resp, slot } = started;
+        let schema = std::mem::take(&mut resp.schema);
+        let data = std::mem::take(&mut resp.data);
+        Self {
+            client,
+            resp,
+            schema,
+            data,
+            slot,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.resp.id
+    }
+
+    /// State of the last page polled, without requesting the server.
+    pub fn state(&self) -> QueryState {
+        self.resp.state
+    }
+
+    pub fn stats(&self) -> &QueryStats {
+        &self.resp.stats
+    }
+
+    /// Schema of the result, may be empty before the query starts running.
+    pub fn schema(&self) -> &[SchemaField] {
+        &self.schema
+    }
+
+    /// All pages are read, no more data and stats would come.
+    pub fn is_finished(&self) -> bool {
+        self.resp.next_uri.is_none()
+    }
+
+    /// Poll the server for the next page once, and return the state of the query after it.
+    /// Data of the pages polled are kept until [`QueryHandle::take_data`].
+    pub async fn status(&mut self) -> Result<QueryState> {
+        if let Some(mut page) = self.client.poll(&self.resp).await? {
+            if !page.schema.is_empty() {
+                self.schema = std::mem::take(&mut page.schema);
+            }
+            self.data.append(&mut page.data);
+            self.resp = page;
+            if self.is_finished() {
+                self.slot = None;
+            }
+        }
+        Ok(self.resp.state)
+    }
+
+    pub fn take_data(&mut self) -> Vec<Vec<Option<String>>> {
+        std::mem::take(&mut self.data)
+    }
+
+    /// Poll until all pages are read, with data not taken yet in the returned response.
+    pub async fn wait(mut self) -> Result<QueryResponse> {
+        while !self.is_finished() {
+            self.status().await?;
+        }
+        self.resp.schema = self.schema;
+        self.resp.data = self.data;
+        Ok(self.resp)
+    }
+
+    pub async fn kill(&self) -> Result<()> {
+        self.client.kill(&self.resp.id).await
+    }
+
+    pub async fn close(&self) -> Result<()> {
+        self.client.close(&self.resp).await
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::{Error, Result};
+use crate::response::QueryResponse;
+
+/// Limits the queries running at once on a client, excess queries wait in a queue
+/// until a running one finishes, or fail after `timeout`.
+/// A query is running from start until its last page is fetched, or the stream of its pages
+/// is dropped, which holds the [`QuerySlot`].
+#[derive(Debug)]
+pub(crate) struct QueryLimiter {
+    max: usize,
+    timeout: Duration,
+    semaphore: Arc<Semaphore>,
+    waiting: AtomicU64,
+    queued_total: AtomicU64,
+    timeout_total: AtomicU64,
+    wait_micros_total: AtomicU64,
+}
+
+/// A slot of the concurrent query limit, taken by a running query and released on drop.
+#[derive(Debug)]
+pub struct QuerySlot {
+    _permit: OwnedSemaphorePermit,
+}
+
+/// The first page of a query just started, with the slot it takes under
+/// `max_concurrent_queries` if there are pages to fetch. Keep the slot along
+/// with whatever fetches the following pages, it is released on drop.
+#[derive(Debug)]
+pub struct StartedQuery {
+    pub resp: QueryResponse,
+    pub slot: Option<QuerySlot>,
+}
+
+/// Snapshot of the metrics of the concurrent query limit of a client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryLimitStats {
+    pub max_concurrent_queries: usize,
+    /// queries holding a slot now
+    pub running: usize,
+    /// queries waiting for a slot now
+    pub waiting: u64,
+    /// queries that had to wait for a slot
+    pub queued_total: u64,
+    /// queries failed after waiting for `query_queue_timeout_secs`
+    pub timeout_total: u64,
+    /// total time spent waiting for slots
+    pub wait_time_total: Duration,
+}
+
+impl QueryLimiter {
+    pub(crate) fn new(max: usize, timeout: Duration) -> Self {
+        Self {
+            max,
+            timeout,
+            semaphore: Arc::new(Semaphore::new(max)),
+            waiting: AtomicU64::new(0),
+            queued_total: AtomicU64::new(0),
+            timeout_total: AtomicU64::new(0),
+            wait_micros_total: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) async fn acquire(&self) -> Result<QuerySlot> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(QuerySlot { _permit: permit });
+        }
+        self.queued_total.fetch_add(1, Ordering::Relaxed);
+        let waiting = self.waiting.fetch_add(1, Ordering::Relaxed) + 1;
+        info!(
+            "query queued, {} running, {} waiting",
+            self.max - self.semaphore.available_permits(),
+            waiting
+        );
+        let start = Instant::now();
+        let permit =
+            tokio::time::timeout(self.timeout, self.semaphore.clone().acquire_owned()).await;
+        self.waiting.fetch_sub(1, Ordering::Relaxed);
+        self.wait_micros_total
+            .fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
+        match permit {
+            Ok(Ok(permit)) => Ok(QuerySlot { _permit: permit }),
+            Ok(Err(_)) => Err(Error::Request("query limiter closed".to_string())),
+            Err(_) => {
+                self.timeout_total.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "query timed out in queue after {:?}, with {} queries running",
+                    self.timeout, self.max
+                );
+                Err(Error::Request(format!(
+                    "timed out after {:?} waiting for a query slot, max_concurrent_queries is {}",
+                    self.timeout, self.max
+                )))
+            }
+        }
+    }
+
+    pub(crate) fn stats(&self) -> QueryLimitStats {
+        QueryLimitStats {
+            max_concurrent_queries: self.max,
+            running: self.max - self.semaphore.available_permits(),
+            waiting: self.waiting.load(Ordering::Relaxed),
+            queued_total: self.queued_total.load(Ordering::Relaxed),
+            timeout_total: self.timeout_total.load(Ordering::Relaxed),
+            wait_time_total: Duration::from_micros(self.wait_micros_total.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn queue_and_timeout() -> Result<()> {
+        let limiter = Arc::new(QueryLimiter::new(1, Duration::from_millis(200)));
+        let slot = limiter.acquire().await?;
+        assert!(limiter.acquire().await.is_err());
+
+        let waiter = limiter.clone();
+        let handle = tokio::spawn(async move { waiter.acquire().await.map(|_| ()) });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(limiter.stats().waiting, 1);
+        drop(slot);
+        handle.await.unwrap()?;
+
+        let stats = limiter.stats();
+        assert_eq!(stats.running, 0);
+        assert_eq!(stats.waiting, 0);
+        assert_eq!(stats.queued_total, 2);
+        assert_eq!(stats.timeout_total, 1);
+        assert!(stats.wait_time_total >= Duration::from_millis(200));
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::time::Sleep;
+
+use crate::error::{Error, Result};
+
+/// Max bytes to wait for at once, to keep transfers smooth under low rates.
+const MAX_CHUNK: u64 = 64 * 1024;
+
+/// Token bucket limiting the bytes per second of stage transfers,
+/// shared by all transfers of a client.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: u64,
+    state: parking_lot::Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            rate: bytes_per_sec,
+            state: parking_lot::Mutex::new((bytes_per_sec as f64, Instant::now())),
+        }
+    }
+
+    /// Parse rates like `50MB/s`, `512K` or `1048576`, units are in 1024.
+    pub fn parse(s: &str) -> Result<Self> {
+        let err = || Error::BadArgument(format!("Invalid value for limit_rate: {}", s));
+        let v = s.trim();
+        let v = v.strip_suffix("/s").unwrap_or(v);
+        let pos = v
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(v.len());
+        let (num, unit) = v.split_at(pos);
+        let num: f64 = num.parse().map_err(|_| err())?;
+        let unit = match unit.trim().to_uppercase().as_str() {
+            "" | "B" => 1,
+            "K" | "KB" | "KIB" => 1024,
+            "M" | "MB" | "MIB" => 1024 * 1024,
+            "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+            _ => return Err(err()),
+        };
+        let rate = (num * unit as f64) as u64;
+        if rate == 0 {
+            return Err(err());
+        }
+        Ok(Self::new(rate))
+    }
+
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Take up to `want` bytes, or return how long to wait before trying again.
+    fn try_take(&self, want: usize) -> std::result::Result<usize, Duration> {
+        let mut state = self.state.lock();
+        let (tokens, last) = &mut *state;
+        let now = Instant::now();
+        *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.rate as f64)
+            .min(self.rate as f64);
+        *last = now;
+        let min = (want as u64).min(MAX_CHUNK).min(self.rate) as f64;
+        if *tokens < min {
+            return Err(Duration::from_secs_f64((min - *tokens) / self.rate as f64));
+        }
+        let n = (want as f64).min(*tokens) as usize;
+        *tokens -= n as f64;
+        Ok(n)
+    }
+
+    fn give_back(&self, n: usize) {
+        let mut state = self.state.lock();
+        state.0 = (state.0 + n as f64).min(self.rate as f64);
+    }
+
+    pub async fn acquire(&self, mut n: usize) {
+        while n > 0 {
+            match self.try_take(n) {
+                Ok(taken) => n -= taken,
+                Err(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+}
+
+pub struct LimitedReader<R> {
+    inner: R,
+    limiter: Arc<RateLimiter>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<R> LimitedReader<R> {
+    pub fn new(inner: R, limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            inner,
+            limiter,
+            sleep: None,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for LimitedReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        loop {
+            if let Some(sleep) = self.sleep.as_mut() {
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                self.sleep = None;
+            }
+            match self.limiter.try_take(buf.remaining()) {
+                Ok(n) => {
+                    let this = &mut *self;
+                    let mut limited = ReadBuf::new(buf.initialize_unfilled_to(n));
+                    let poll = Pin::new(&mut this.inner).poll_read(cx, &mut limited);
+                    let read = limited.filled().len();
+                    this.limiter.give_back(n - read);
+                    buf.advance(read);
+                    return poll;
+                }
+                Err(wait) => {
+                    self.sleep = Some(Box::pin(tokio::time::sleep(wait)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn parse_rate() -> Result<()> {
+        assert_eq!(RateLimiter::parse("50MB/s")?.rate(), 50 * 1024 * 1024);
+        assert_eq!(RateLimiter::parse("512k")?.rate(), 512 * 1024);
+        assert_eq!(RateLimiter::parse("1.5M")?.rate(), 1536 * 1024);
+        assert_eq!(RateLimiter::parse("1000")?.rate(), 1000);
+        assert!(RateLimiter::parse("fast").is_err());
+        assert!(RateLimiter::parse("10TB/s").is_err());
+        assert!(RateLimiter::parse("0").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn limited_read() -> Result<()> {
+        let limiter = Arc::new(RateLimiter::new(100 * 1024));
+        let data = vec![1u8; 200 * 1024];
+        let start = Instant::now();
+        let mut reader = LimitedReader::new(std::io::Cursor::new(data.clone()), limiter);
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf).await?;
+        assert_eq!(buf, data);
+        // the first 100KB is in the bucket already
+        assert!(start.elapsed() >= Duration::from_millis(900));
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+
+const MASK: &str = "'***'";
+
+/// Replace string literals in sql with `'***'` for logging, including
+/// `$$` quoted strings. Quotes escaped by doubling or backslash are kept
+/// inside the literal, and an unterminated literal is masked to the end.
+pub fn redact_sql(sql: &str) -> Cow<'_, str> {
+    if !sql.contains('\'') && !sql.contains("$$") {
+        return Cow::Borrowed(sql);
+    }
+    let mut out = String::with_capacity(sql.len());
+    let mut rest = sql;
+    while let Some(pos) = rest.find(['\'', '$']) {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if let Some(body) = rest.strip_prefix("$$") {
+            rest = body.find("$$").map_or("", |end| &body[end + 2..]);
+            out.push_str(MASK);
+        } else if rest.starts_with('\'') {
+            rest = skip_quoted(&rest[1..]);
+            out.push_str(MASK);
+        } else {
+            out.push('$');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+/// Return the remaining sql after the closing quote.
+fn skip_quoted(s: &str) -> &str {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\'' if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+            b'\'' => return &s[i + 1..],
+            _ => i += 1,
+        }
+    }
+    ""
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redact() {
+        assert_eq!(redact_sql("select 1"), "select 1");
+        assert_eq!(
+            redact_sql("select * from users where email = 'a@b.com' and id = 1"),
+            "select * from users where email = '***' and id = 1"
+        );
+        assert_eq!(
+            redact_sql(r"insert into t values ('it''s', 'a\'b', $$token$$), ('x')"),
+            "insert into t values ('***', '***', '***'), ('***')"
+        );
+        assert_eq!(redact_sql("select $1, 'abc"), "select $1, '***'");
+    }
+}
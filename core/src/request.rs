@@ -15,13 +15,7 @@
 use std::collections::BTreeMap;
 
 use crate::session::SessionState;
-use serde::{Deserialize, Serialize};
-
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
-pub struct ServerInfo {
-    pub id: String,
-    pub start_time: String,
-}
+use serde::Serialize;
 
 #[derive(Serialize, Debug)]
 pub struct QueryRequest<'a> {
@@ -32,6 +26,8 @@ pub struct QueryRequest<'a> {
     pagination: Option<PaginationConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     stage_attachment: Option<StageAttachmentConfig<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    params: Option<serde_json::Value>,
 }
 
 #[derive(Serialize, Debug)]
@@ -60,6 +56,7 @@ impl<'r, 't: 'r> QueryRequest<'r> {
             sql,
             pagination: None,
             stage_attachment: None,
+            params: None,
         }
     }
 
@@ -80,6 +77,11 @@ impl<'r, 't: 'r> QueryRequest<'r> {
         self.stage_attachment = stage_attachment;
         self
     }
+
+    pub fn with_params(mut self, params: Option<serde_json::Value>) -> Self {
+        self.params = params;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -107,6 +109,13 @@ mod test {
             serde_json::to_string(&req)?,
             r#"{"session":{"database":"default"},"sql":"select 1","pagination":{"wait_time_secs":1,"max_rows_in_buffer":1,"max_rows_per_page":1},"stage_attachment":{"location":"@~/my_location"}}"#
         );
+
+        let req =
+            QueryRequest::new("select ?, ?").with_params(Some(serde_json::json!(["1", "'a'"])));
+        assert_eq!(
+            serde_json::to_string(&req)?,
+            r#"{"sql":"select ?, ?","params":["1","'a'"]}"#
+        );
         Ok(())
     }
 }
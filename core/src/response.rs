@@ -14,16 +14,17 @@
 
 use crate::error_code::ErrorCode;
 use crate::session::SessionState;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::value::RawValue;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 pub struct QueryStats {
     #[serde(flatten)]
     pub progresses: Progresses,
     pub running_time_ms: f64,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 pub struct Progresses {
     pub scan_progress: ProgressValues,
     pub write_progress: ProgressValues,
@@ -49,7 +50,7 @@ impl Progresses {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 pub struct ProgressValues {
     pub rows: usize,
     pub bytes: usize,
@@ -68,15 +69,35 @@ pub struct SchemaField {
     pub data_type: String,
 }
 
-#[derive(Deserialize, Debug)]
+/// State of a query, states added by newer servers are `Unknown`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueryState {
+    Starting,
+    #[default]
+    Running,
+    Succeeded,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+impl QueryState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
+}
+
+/// Default is only for resuming a query from the page uris saved before.
+#[derive(Deserialize, Debug, Default)]
 pub struct QueryResponse {
     pub id: String,
     pub node_id: Option<String>,
     pub session_id: Option<String>,
     pub session: Option<SessionState>,
     pub schema: Vec<SchemaField>,
+    #[serde(deserialize_with = "deserialize_data")]
     pub data: Vec<Vec<Option<String>>>,
-    pub state: String,
+    pub state: QueryState,
     pub error: Option<ErrorCode>,
     // make it optional for backward compatibility
     pub warnings: Option<Vec<String>>,
@@ -88,12 +109,73 @@ pub struct QueryResponse {
     pub kill_uri: Option<String>,
 }
 
+/// Cells are sent as strings, but numbers and other json values are also
+/// accepted as their literal text, so that big integers and decimals are not
+/// rounded through `f64` before they are parsed by the column type.
+/// Cells are taken as raw json instead of enabling `arbitrary_precision`,
+/// which breaks numbers in the flattened and untagged responses.
+fn deserialize_data<'de, D>(deserializer: D) -> Result<Vec<Vec<Option<String>>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let rows: Vec<Vec<Option<Box<RawValue>>>> = Deserialize::deserialize(deserializer)?;
+    rows.into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|cell| cell.map(|raw| raw_to_string(&raw)).transpose())
+                .collect()
+        })
+        .collect::<serde_json::Result<_>>()
+        .map_err(serde::de::Error::custom)
+}
+
+fn raw_to_string(raw: &RawValue) -> serde_json::Result<String> {
+    let text = raw.get();
+    match text.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(s) if !s.contains('\\') => Ok(s.to_string()),
+        Some(_) => serde_json::from_str(text),
+        None => Ok(text.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::BTreeMap;
 
     use super::*;
 
+    #[test]
+    fn deserialize_query_state() {
+        let state: QueryState = serde_json::from_str(r#""Running""#).unwrap();
+        assert_eq!(state, QueryState::Running);
+        assert!(!state.is_finished());
+        let state: QueryState = serde_json::from_str(r#""Failed""#).unwrap();
+        assert!(state.is_finished());
+        let state: QueryState = serde_json::from_str(r#""Paused""#).unwrap();
+        assert_eq!(state, QueryState::Unknown);
+    }
+
+    #[test]
+    fn deserialize_big_numbers() {
+        let body = r#"{"id": "q1", "schema": [], "state": "Succeeded",
+            "stats": {"scan_progress": {"rows": 1, "bytes": 1}, "write_progress": {"rows": 0, "bytes": 0},
+                "result_progress": {"rows": 1, "bytes": 1}, "running_time_ms": 1.5},
+            "data": [["a\"b", 123456789012345678901234567890, 0.1000000000000000055511151231257827, null, true, {"k": [1]}]]}"#;
+        let resp: QueryResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            resp.data[0],
+            vec![
+                Some("a\"b".to_string()),
+                Some("123456789012345678901234567890".to_string()),
+                Some("0.1000000000000000055511151231257827".to_string()),
+                None,
+                Some("true".to_string()),
+                Some(r#"{"k": [1]}"#.to_string()),
+            ]
+        );
+        assert_eq!(resp.stats.progresses.scan_progress.rows, 1);
+    }
+
     #[test]
     fn deserialize_session_config() {
         let session_json = r#"{"database":"default","settings":{}}"#;
@@ -0,0 +1,150 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use tokio_retry::strategy::jitter;
+
+use crate::error::{Error, Result};
+
+/// How requests of queries are retried on network errors and the retryable
+/// status codes, with exponential backoff from `base_backoff` up to
+/// `max_backoff`, jittered so that clients do not retry together.
+///
+/// Set with DSN args `retry_max_attempts`, `retry_base_backoff_ms`,
+/// `retry_max_backoff_ms` and `retry_status_codes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total including the first one, `1` to never retry.
+    pub max_attempts: u32,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Retried when starting queries, `503` while the server is starting by default.
+    pub retryable_status: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_backoff: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(10),
+            retryable_status: vec![503],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry, for workloads failing over by themselves.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Wait before the retry after `retries` retries.
+    pub fn backoff(&self, retries: u32) -> Duration {
+        let backoff = self
+            .base_backoff
+            .saturating_mul(1 << retries.min(16))
+            .min(self.max_backoff);
+        jitter(backoff)
+    }
+
+    pub fn is_retryable_status(&self, status: u16) -> bool {
+        self.retryable_status.contains(&status)
+    }
+
+    pub(crate) fn parse_max_attempts(v: &str) -> Result<u32> {
+        match v.parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(Error::BadArgument(format!(
+                "Invalid value for retry_max_attempts: {}, should be at least 1",
+                v
+            ))),
+        }
+    }
+
+    /// Parse comma separated status codes like `502,503,504`, empty for none.
+    pub(crate) fn parse_status_codes(v: &str) -> Result<Vec<u16>> {
+        v.split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| match s.parse::<u16>() {
+                Ok(code) if (100..600).contains(&code) => Ok(code),
+                _ => Err(Error::BadArgument(format!(
+                    "Invalid status code for retry_status_codes: {}",
+                    s
+                ))),
+            })
+            .collect()
+    }
+
+    /// The DSN args of the policy, for DSN builders.
+    pub fn dsn_args(&self) -> Vec<(&'static str, String)> {
+        let codes = self
+            .retryable_status
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        vec![
+            ("retry_max_attempts", self.max_attempts.to_string()),
+            (
+                "retry_base_backoff_ms",
+                self.base_backoff.as_millis().to_string(),
+            ),
+            (
+                "retry_max_backoff_ms",
+                self.max_backoff.as_millis().to_string(),
+            ),
+            ("retry_status_codes", codes),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_to_max() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+            retryable_status: vec![502, 503],
+        };
+        for (retries, max) in [(0, 100), (1, 200), (2, 300), (10, 300), (40, 300)] {
+            assert!(policy.backoff(retries) <= Duration::from_millis(max));
+        }
+        assert!(policy.is_retryable_status(502));
+        assert!(!policy.is_retryable_status(500));
+    }
+
+    #[test]
+    fn parse_args() -> Result<()> {
+        assert_eq!(
+            RetryPolicy::parse_status_codes(" 502, 503,504 ")?,
+            vec![502, 503, 504]
+        );
+        assert!(RetryPolicy::parse_status_codes("")?.is_empty());
+        assert!(RetryPolicy::parse_status_codes("5xx").is_err());
+        assert!(RetryPolicy::parse_status_codes("700").is_err());
+        assert_eq!(RetryPolicy::parse_max_attempts("1")?, 1);
+        assert!(RetryPolicy::parse_max_attempts("0").is_err());
+        Ok(())
+    }
+}
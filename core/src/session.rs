@@ -32,7 +32,8 @@ pub struct SessionState {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub need_keep_alive: Option<bool>,
 
-    // hide fields of no interest (but need to send back to server in next query)
+    // hide fields of no interest (but need to send back to server in next query),
+    // such as tokens for read-your-writes consistency across nodes
     #[serde(flatten)]
     additional_fields: HashMap<String, serde_json::Value>,
 }
@@ -53,3 +54,25 @@ impl SessionState {
         self
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keep_unknown_fields() -> serde_json::Result<()> {
+        let session: SessionState =
+            serde_json::from_str(r#"{"database":"db1","last_commit_seq":{"node":"n1","seq":42}}"#)?;
+        assert_eq!(session.database.as_deref(), Some("db1"));
+        let value = serde_json::to_value(session.with_role(Some("analyst".to_string())))?;
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "database": "db1",
+                "role": "analyst",
+                "last_commit_seq": {"node": "n1", "seq": 42}
+            })
+        );
+        Ok(())
+    }
+}
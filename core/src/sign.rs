@@ -0,0 +1,30 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use async_trait::async_trait;
+use reqwest::Request;
+
+use crate::error::Result;
+
+/// Signs the requests of [`crate::APIClient`] to the server before they are sent,
+/// such as with AWS SigV4 or HMAC headers required by an authenticating gateway,
+/// set with [`crate::APIClientBuilder::request_signer`].
+///
+/// Requests are signed again on each retry. The body is available with
+/// `request.body().and_then(|b| b.as_bytes())`, except for uploads to stage,
+/// which are streamed. Requests to presigned urls are not signed.
+#[async_trait]
+pub trait RequestSigner: Send + Sync {
+    async fn sign(&self, request: &mut Request) -> Result<()>;
+}
@@ -0,0 +1,153 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+use log::warn;
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+use reqwest::ClientBuilder;
+
+use crate::error::{Error, Result};
+
+/// TLS implementation used by the http client, selected at runtime with
+/// `tls_backend` among the compiled ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsBackend {
+    Rustls,
+    NativeTls,
+}
+
+impl TlsBackend {
+    pub fn parse(s: &str) -> Result<Self> {
+        let backend = match s.to_lowercase().as_str() {
+            "rustls" => Self::Rustls,
+            "native-tls" | "native_tls" | "native" => Self::NativeTls,
+            _ => {
+                return Err(Error::BadArgument(format!(
+                    "Invalid value for tls_backend: {}, should be one of rustls/native-tls",
+                    s
+                )))
+            }
+        };
+        if !backend.is_compiled() {
+            return Err(Error::BadArgument(format!(
+                "tls_backend {} is not compiled in, rebuild with feature {}",
+                s,
+                backend.feature()
+            )));
+        }
+        Ok(backend)
+    }
+
+    fn feature(&self) -> &'static str {
+        match self {
+            Self::Rustls => "rustls",
+            Self::NativeTls => "native-tls",
+        }
+    }
+
+    fn is_compiled(&self) -> bool {
+        match self {
+            Self::Rustls => cfg!(feature = "rustls"),
+            Self::NativeTls => cfg!(feature = "native-tls"),
+        }
+    }
+}
+
+/// Parse `tls_skip_verify`, accepting the usual boolean spellings.
+pub(crate) fn parse_skip_verify(s: &str) -> Result<bool> {
+    match s.to_lowercase().as_str() {
+        "1" | "true" | "enable" => Ok(true),
+        "0" | "false" | "disable" => Ok(false),
+        _ => Err(Error::BadArgument(format!(
+            "Invalid value for tls_skip_verify: {}",
+            s
+        ))),
+    }
+}
+
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+pub(crate) fn apply_tls(
+    mut builder: ClientBuilder,
+    backend: Option<TlsBackend>,
+    skip_verify: bool,
+    verify_hostname: bool,
+) -> ClientBuilder {
+    match backend {
+        #[cfg(feature = "rustls")]
+        Some(TlsBackend::Rustls) => builder = builder.use_rustls_tls(),
+        #[cfg(feature = "native-tls")]
+        Some(TlsBackend::NativeTls) => builder = builder.use_native_tls(),
+        // backends not compiled in are rejected when parsing
+        _ => {}
+    }
+    if skip_verify {
+        warn!("tls_skip_verify is enabled, server certificates are not verified");
+        builder = builder.danger_accept_invalid_certs(true);
+    } else if !verify_hostname {
+        // sslmode=verify-ca, the certificate chain is still verified
+        builder = builder.danger_accept_invalid_hostnames(true);
+    }
+    builder
+}
+
+/// Client certificate for mutual TLS from the PEM files of certificate and key,
+/// native-tls requires the key in PKCS#8.
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+pub(crate) fn client_identity(
+    backend: Option<TlsBackend>,
+    cert: &[u8],
+    key: &[u8],
+) -> Result<reqwest::Identity> {
+    // same as reqwest, native-tls is the default if compiled in
+    let backend = backend.unwrap_or(if cfg!(feature = "native-tls") {
+        TlsBackend::NativeTls
+    } else {
+        TlsBackend::Rustls
+    });
+    let identity = match backend {
+        #[cfg(feature = "rustls")]
+        TlsBackend::Rustls => {
+            let mut pem = cert.to_vec();
+            pem.push(b'\n');
+            pem.extend_from_slice(key);
+            reqwest::Identity::from_pem(&pem)
+        }
+        #[cfg(feature = "native-tls")]
+        TlsBackend::NativeTls => reqwest::Identity::from_pkcs8_pem(cert, key),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!("tls backend {:?} not compiled", backend),
+    };
+    identity.map_err(|e| Error::BadArgument(format!("Invalid client certificate: {}", e)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_tls_options() -> Result<()> {
+        assert_eq!(TlsBackend::parse("RusTLS")?, TlsBackend::Rustls);
+        assert!(TlsBackend::parse("openssl").is_err());
+        assert_eq!(
+            TlsBackend::parse("native-tls").is_ok(),
+            cfg!(feature = "native-tls")
+        );
+        assert!(parse_skip_verify("1")?);
+        assert!(!parse_skip_verify("false")?);
+        assert!(parse_skip_verify("yes").is_err());
+        #[cfg(any(feature = "rustls", feature = "native-tls"))]
+        assert!(client_identity(None, b"not a cert", b"not a key").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+tokio::task_local! {
+    static TRACKER: QueryTracker;
+}
+
+/// Records the ids of queries started inside [`QueryTracker::scope`],
+/// so the caller knows exactly which queries it started, even with other
+/// queries running concurrently on the same client.
+#[derive(Clone, Debug, Default)]
+pub struct QueryTracker {
+    ids: Arc<Mutex<Vec<String>>>,
+}
+
+impl QueryTracker {
+    /// Run `f`, recording the queries started by it in this tracker.
+    pub async fn scope<F: Future>(&self, f: F) -> F::Output {
+        TRACKER.scope(self.clone(), f).await
+    }
+
+    /// Ids of the queries started so far, in order.
+    pub fn query_ids(&self) -> Vec<String> {
+        self.ids.lock().clone()
+    }
+
+    /// Id of the last query started.
+    pub fn last_query_id(&self) -> Option<String> {
+        self.ids.lock().last().cloned()
+    }
+
+    pub(crate) fn record(query_id: &str) {
+        let _ = TRACKER.try_with(|tracker| tracker.ids.lock().push(query_id.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn track_in_scope() {
+        let tracker = QueryTracker::default();
+        QueryTracker::record("outside");
+        tracker
+            .scope(async {
+                QueryTracker::record("q1");
+                QueryTracker::record("q2");
+            })
+            .await;
+        assert_eq!(tracker.query_ids(), vec!["q1", "q2"]);
+        assert_eq!(tracker.last_query_id(), Some("q2".to_string()));
+    }
+}
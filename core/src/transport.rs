@@ -0,0 +1,107 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use async_trait::async_trait;
+use reqwest::{Client as HttpClient, Request, Response};
+
+use crate::error::Error;
+
+/// Sends the http requests of [`crate::APIClient`], by default with a reqwest client.
+///
+/// A custom transport could be set with [`crate::APIClientBuilder::transport`],
+/// to send requests with another http stack or runtime, or to answer requests
+/// with canned responses in tests, which could be built from `http::Response`.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn execute(&self, request: Request) -> Result<Response, TransportError>;
+}
+
+/// Error of sending a request, the request is retried if the error is retryable.
+#[derive(Debug, Clone)]
+pub struct TransportError {
+    message: String,
+    retryable: bool,
+    connect: bool,
+}
+
+impl TransportError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: false,
+            connect: false,
+        }
+    }
+
+    /// Errors like timeout or connection failure, which could succeed on retry.
+    pub fn retryable(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: true,
+            connect: false,
+        }
+    }
+
+    /// Failed to connect, so the request is not sent and could go to another node.
+    pub fn connect(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: true,
+            connect: true,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+
+    pub fn is_connect(&self) -> bool {
+        self.connect
+    }
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+impl From<reqwest::Error> for TransportError {
+    fn from(e: reqwest::Error) -> Self {
+        Self {
+            message: e.to_string(),
+            retryable: e.is_timeout() || e.is_connect(),
+            connect: e.is_connect(),
+        }
+    }
+}
+
+impl From<TransportError> for Error {
+    fn from(e: TransportError) -> Self {
+        if e.connect {
+            Error::Connect(e.message)
+        } else {
+            Error::Request(e.message)
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for HttpClient {
+    async fn execute(&self, request: Request) -> Result<Response, TransportError> {
+        Ok(HttpClient::execute(self, request).await?)
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::{Client as HttpClient, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::transport::{Transport, TransportError};
+
+/// Env to choose the mode of [`vcr_transport`], `record` to record, otherwise replay.
+pub const VCR_MODE_ENV: &str = "DATABEND_VCR_MODE";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct RecordedRequest {
+    method: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Fixture {
+    interactions: Vec<Interaction>,
+}
+
+impl RecordedRequest {
+    fn from_request(request: &Request) -> Self {
+        Self {
+            method: request.method().to_string(),
+            path: request.url().path().to_string(),
+            query: request.url().query().map(|q| q.to_string()),
+            body: request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(|b| String::from_utf8_lossy(b).to_string()),
+        }
+    }
+
+    fn matches(&self, other: &Self) -> bool {
+        self.method == other.method && self.path == other.path
+    }
+}
+
+impl RecordedResponse {
+    fn to_response(&self) -> std::result::Result<Response, TransportError> {
+        let mut builder = http::Response::builder().status(self.status);
+        for (k, v) in &self.headers {
+            builder = builder.header(k, v);
+        }
+        let resp = builder
+            .body(self.body.clone())
+            .map_err(|e| TransportError::new(format!("invalid recorded response: {}", e)))?;
+        Ok(resp.into())
+    }
+}
+
+/// Records the http interactions sent with the inner transport to a fixture file,
+/// which is rewritten after each request, to be replayed by [`ReplayTransport`].
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    path: PathBuf,
+    fixture: tokio::sync::Mutex<Fixture>,
+}
+
+impl RecordingTransport {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self::with_inner(path, Arc::new(HttpClient::new()))
+    }
+
+    pub fn with_inner(path: impl AsRef<Path>, inner: Arc<dyn Transport>) -> Self {
+        Self {
+            inner,
+            path: path.as_ref().to_path_buf(),
+            fixture: Default::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for RecordingTransport {
+    async fn execute(&self, request: Request) -> std::result::Result<Response, TransportError> {
+        let recorded = RecordedRequest::from_request(&request);
+        let resp = self.inner.execute(request).await?;
+        let status = resp.status().as_u16();
+        let headers = resp
+            .headers()
+            .iter()
+            .filter(|(k, _)| *k != reqwest::header::CONTENT_LENGTH)
+            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().to_string()))
+            .collect();
+        let body = resp.bytes().await?;
+        let response = RecordedResponse {
+            status,
+            headers,
+            body: String::from_utf8_lossy(&body).to_string(),
+        };
+        let mut fixture = self.fixture.lock().await;
+        fixture.interactions.push(Interaction {
+            request: recorded,
+            response: response.clone(),
+        });
+        let content = serde_json::to_vec_pretty(&*fixture)
+            .map_err(|e| TransportError::new(format!("failed to encode fixture: {}", e)))?;
+        tokio::fs::write(&self.path, content).await.map_err(|e| {
+            TransportError::new(format!(
+                "failed to write fixture {}: {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+        response.to_response()
+    }
+}
+
+/// Replays the interactions of a fixture file in order, requests are matched
+/// by method and path, so that tests are deterministic without a server.
+pub struct ReplayTransport {
+    path: PathBuf,
+    interactions: parking_lot::Mutex<VecDeque<Interaction>>,
+}
+
+impl ReplayTransport {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = std::fs::read(&path)
+            .map_err(|e| Error::IO(format!("failed to read fixture {}: {}", path.display(), e)))?;
+        let fixture: Fixture = serde_json::from_slice(&content)
+            .map_err(|e| Error::Decode(format!("invalid fixture {}: {}", path.display(), e)))?;
+        Ok(Self {
+            path,
+            interactions: parking_lot::Mutex::new(fixture.interactions.into()),
+        })
+    }
+
+    /// Interactions not replayed yet, which should be 0 at the end of a test.
+    pub fn remaining(&self) -> usize {
+        self.interactions.lock().len()
+    }
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn execute(&self, request: Request) -> std::result::Result<Response, TransportError> {
+        let recorded = RecordedRequest::from_request(&request);
+        let interaction = match self.interactions.lock().pop_front() {
+            Some(interaction) => interaction,
+            None => {
+                return Err(TransportError::new(format!(
+                    "no more interactions in {} for {} {}",
+                    self.path.display(),
+                    recorded.method,
+                    recorded.path
+                )))
+            }
+        };
+        if !interaction.request.matches(&recorded) {
+            return Err(TransportError::new(format!(
+                "unexpected request {} {}, expected {} {} in {}",
+                recorded.method,
+                recorded.path,
+                interaction.request.method,
+                interaction.request.path,
+                self.path.display()
+            )));
+        }
+        interaction.response.to_response()
+    }
+}
+
+/// Record to the fixture if env `DATABEND_VCR_MODE` is `record`, otherwise replay from it.
+pub fn vcr_transport(path: impl AsRef<Path>) -> Result<Arc<dyn Transport>> {
+    match std::env::var(VCR_MODE_ENV).as_deref() {
+        Ok("record") => Ok(Arc::new(RecordingTransport::new(path))),
+        _ => Ok(Arc::new(ReplayTransport::open(path)?)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Answers with the path of the request.
+    struct Echo;
+
+    #[async_trait]
+    impl Transport for Echo {
+        async fn execute(&self, request: Request) -> std::result::Result<Response, TransportError> {
+            let resp = http::Response::builder()
+                .status(200)
+                .header("x-path", request.url().path())
+                .body(request.url().path().to_string())
+                .unwrap();
+            Ok(resp.into())
+        }
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("databend_vcr_{}.json", std::process::id()));
+        let cli = HttpClient::new();
+        let recorder = RecordingTransport::with_inner(&path, Arc::new(Echo));
+        for p in ["/v1/query", "/v1/query/q1/page/1"] {
+            let req = cli.get(format!("http://localhost:8000{}", p)).build()?;
+            let resp = recorder.execute(req).await?;
+            assert_eq!(resp.text().await?, p);
+        }
+
+        let replay = ReplayTransport::open(&path)?;
+        assert_eq!(replay.remaining(), 2);
+        let req = cli.get("http://localhost:8000/v1/query").build()?;
+        let resp = replay.execute(req).await?;
+        assert_eq!(resp.headers()["x-path"], "/v1/query");
+        assert_eq!(resp.text().await?, "/v1/query");
+        let req = cli.post("http://localhost:8000/v1/query/q1/kill").build()?;
+        assert!(replay.execute(req).await.is_err());
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -14,5 +14,6 @@
 
 mod common;
 
+mod query;
 mod simple;
 mod stage;
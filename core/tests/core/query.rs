@@ -0,0 +1,96 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_client::{QueryClient, QueryState};
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn query_client_pages() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = QueryClient::new(dsn, None).await.unwrap();
+    let mut resp = client
+        .start_query("select * from numbers(100000)")
+        .await
+        .unwrap()
+        .resp;
+    let mut rows = resp.data.len();
+    while let Some(page) = client.poll(&resp).await.unwrap() {
+        rows += page.data.len();
+        resp = page;
+    }
+    assert_eq!(rows, 100000);
+    assert_eq!(resp.state, QueryState::Succeeded);
+    client.close(&resp).await.unwrap();
+}
+
+#[tokio::test]
+async fn query_client_kill() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = QueryClient::new(dsn, None).await.unwrap();
+    let resp = client
+        .start_query("select sleep(1) from numbers(60)")
+        .await
+        .unwrap()
+        .resp;
+    client.kill(&resp.id).await.unwrap();
+    let mut resp = resp;
+    let mut failed = false;
+    loop {
+        match client.poll(&resp).await {
+            Ok(Some(page)) => resp = page,
+            Ok(None) => break,
+            Err(_) => {
+                failed = true;
+                break;
+            }
+        }
+    }
+    assert!(failed || resp.state != QueryState::Succeeded);
+}
+
+#[tokio::test]
+async fn query_handle_status() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = QueryClient::new(dsn, None).await.unwrap();
+    let mut handle = client
+        .submit("select sleep(1), number from numbers(3)")
+        .await
+        .unwrap();
+    let mut rows = handle.take_data().len();
+    while !handle.is_finished() {
+        let state = handle.status().await.unwrap();
+        assert_ne!(state, QueryState::Failed);
+        rows += handle.take_data().len();
+    }
+    assert_eq!(rows, 3);
+    assert_eq!(handle.state(), QueryState::Succeeded);
+    assert_eq!(handle.schema().len(), 2);
+}
+
+#[tokio::test]
+async fn finalize_query_twice() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = QueryClient::new(dsn, None).await.unwrap();
+    let resp = client.start_query("select 1").await.unwrap().resp;
+    let final_uri = resp.final_uri.clone().unwrap();
+    let api = client.api_client();
+    api.finalize_query(&resp.id, &final_uri, &resp.node_id)
+        .await
+        .unwrap();
+    // already released by server
+    api.finalize_query(&resp.id, &final_uri, &resp.node_id)
+        .await
+        .unwrap();
+}
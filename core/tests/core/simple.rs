@@ -20,6 +20,6 @@ use crate::common::DEFAULT_DSN;
 async fn select_simple() {
     let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
     let client = APIClient::new(dsn, None).await.unwrap();
-    let resp = client.start_query("select 15532").await.unwrap();
+    let resp = client.start_query("select 15532").await.unwrap().resp;
     assert_eq!(resp.data, [[Some("15532".to_string())]]);
 }
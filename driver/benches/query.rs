@@ -0,0 +1,140 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rows per second and allocations of reading query results, with the mock
+//! server for the RestAPI decode path, and with a real server for both RestAPI
+//! and FlightSQL if available, which is started in docker by `make -C tests bench`.
+//!
+//! ```sh
+//! cargo bench -p databend-driver --features flight-sql --bench query
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use databend_client_mock::{MockResult, MockServer};
+use databend_driver::{Client, Connection, Error};
+use tokio::runtime::Runtime;
+use tokio_stream::StreamExt;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const ROWS: u64 = 100_000;
+const SQL: &str = "SELECT number, number::String AS s, number / 3 AS f FROM numbers(100000)";
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+async fn fetch_all(conn: &Connection) -> u64 {
+    let mut rows = conn.query_iter(SQL, ()).await.unwrap();
+    let mut n = 0;
+    while let Some(row) = rows.next().await {
+        row.unwrap();
+        n += 1;
+    }
+    n
+}
+
+fn bench_conn(c: &mut Criterion, rt: &Runtime, name: &str, conn: &Connection) {
+    // counted over one run, including allocations of the runtime and http client
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let n = rt.block_on(fetch_all(conn));
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    eprintln!(
+        "{}: {} allocations for {} rows, {:.1} per row",
+        name,
+        allocations,
+        n,
+        allocations as f64 / n as f64
+    );
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(ROWS));
+    group.sample_size(10);
+    group.bench_function("query_iter", |b| b.to_async(rt).iter(|| fetch_all(conn)));
+    group.finish();
+}
+
+fn bench_mock(c: &mut Criterion) {
+    let rt = runtime();
+    let server = rt.block_on(MockServer::start());
+    let data: Vec<[String; 3]> = (0..ROWS)
+        .map(|i| [i.to_string(), i.to_string(), (i as f64 / 3.0).to_string()])
+        .collect();
+    let data = data
+        .iter()
+        .map(|row| row.iter().map(|v| Some(v.as_str())).collect())
+        .collect();
+    server.on_query(
+        "SELECT",
+        MockResult::rows(
+            &[("number", "UInt64"), ("s", "String"), ("f", "Float64")],
+            data,
+        ),
+    );
+    let conn = rt.block_on(Client::new(server.dsn()).get_conn()).unwrap();
+    bench_conn(c, &rt, "mock_rest", &conn);
+}
+
+fn bench_server(c: &mut Criterion) {
+    let rt = runtime();
+    let targets = [
+        (
+            "rest",
+            "BENCH_DATABEND_DSN",
+            "databend://root:@localhost:8000/default?sslmode=disable",
+        ),
+        (
+            "flight",
+            "BENCH_DATABEND_FLIGHT_DSN",
+            "databend+flight://root:@localhost:8900/default?sslmode=disable",
+        ),
+    ];
+    for (name, env, default) in targets {
+        let dsn = std::env::var(env).unwrap_or_else(|_| default.to_string());
+        let conn = rt.block_on(async {
+            let conn = Client::new(dsn).get_conn().await?;
+            conn.version().await?;
+            Ok::<_, Error>(conn)
+        });
+        match conn {
+            Ok(conn) => bench_conn(c, &rt, name, &conn),
+            Err(e) => eprintln!("skip {}: {}", name, e),
+        }
+    }
+}
+
+criterion_group!(benches, bench_mock, bench_server);
+criterion_main!(benches);
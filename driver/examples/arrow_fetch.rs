@@ -0,0 +1,53 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fetch results with FlightSQL, where data is transferred in arrow record
+//! batches and decoded into rows while streaming.
+//!
+//! ```sh
+//! BENDSQL_DSN="databend+flight://root:@localhost:8900/default?sslmode=disable" \
+//!     cargo run --example arrow_fetch --features flight-sql
+//! ```
+
+use databend_driver::{Client, Error, Result, TryFromRow};
+use tokio_stream::StreamExt;
+
+#[derive(TryFromRow)]
+struct Number {
+    number: u64,
+    square: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let dsn = std::env::var("BENDSQL_DSN").unwrap_or_else(|_| {
+        "databend+flight://root:@localhost:8900/default?sslmode=disable".to_string()
+    });
+    let conn = Client::new(dsn).get_conn().await?;
+
+    let mut rows = conn
+        .query_iter(
+            "SELECT number, number * number AS square FROM numbers(10)",
+            (),
+        )
+        .await?;
+    for field in rows.schema().fields() {
+        println!("{}: {}", field.name, field.data_type);
+    }
+    while let Some(row) = rows.next().await {
+        let number = Number::try_from(row?).map_err(Error::Parsing)?;
+        println!("{} * {} = {}", number.number, number.number, number.square);
+    }
+    Ok(())
+}
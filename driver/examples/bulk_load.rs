@@ -0,0 +1,51 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Load a local csv file into a table, and rows built in memory with stream load.
+//!
+//! ```sh
+//! cargo run --example bulk_load -- books.csv
+//! ```
+
+use std::path::Path;
+
+use databend_driver::{Client, Result};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let dsn = std::env::var("BENDSQL_DSN")
+        .unwrap_or_else(|_| "databend://root:@localhost:8000/default?sslmode=disable".to_string());
+    let conn = Client::new(dsn).get_conn().await?;
+    conn.exec(
+        "CREATE TABLE IF NOT EXISTS books (title VARCHAR, author VARCHAR, year INT)",
+        (),
+    )
+    .await?;
+
+    if let Some(file) = std::env::args().nth(1) {
+        // uploaded to a stage, then copied into the table
+        let stats = conn
+            .load_file("INSERT INTO books VALUES", Path::new(&file), None, None)
+            .await?;
+        println!("loaded {}: {} rows", file, stats.write_rows);
+    }
+
+    let rows = vec![
+        vec!["The Little Prince", "Antoine de Saint-Exupéry", "1943"],
+        vec!["The Rust Programming Language", "Steve Klabnik", "2018"],
+    ];
+    let stats = conn.stream_load("INSERT INTO books VALUES", rows).await?;
+    println!("stream loaded {} rows", stats.write_rows);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cancel a long running query after a timeout, the server side query is
+//! killed too, instead of only dropping the client side stream.
+//!
+//! ```sh
+//! cargo run --example cancel
+//! ```
+
+use std::time::Duration;
+
+use databend_driver::{Client, Result};
+use tokio_stream::StreamExt;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let dsn = std::env::var("BENDSQL_DSN")
+        .unwrap_or_else(|_| "databend://root:@localhost:8000/default?sslmode=disable".to_string());
+    let client = Client::new(dsn);
+    let conn = client.get_conn().await?;
+
+    let query_id = conn
+        .submit("SELECT count(*) FROM numbers(100000000000) WHERE sleep(1)")
+        .await?;
+    println!("submitted query {}", query_id.id);
+
+    let mut rows = conn.fetch(query_id.clone()).await?;
+    match tokio::time::timeout(Duration::from_secs(3), rows.next()).await {
+        Ok(row) => println!("finished: {:?}", row),
+        Err(_) => {
+            // kill it with another connection, this one may be busy with the query
+            let killer = client.get_conn().await?;
+            killer.kill_query(&query_id.id).await?;
+            println!("killed query {} after timeout", query_id.id);
+        }
+    }
+    Ok(())
+}
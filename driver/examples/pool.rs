@@ -0,0 +1,48 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Share a bounded pool of connections between tasks.
+//!
+//! ```sh
+//! BENDSQL_DSN="databend://root:@localhost:8000/default?sslmode=disable" cargo run --example pool
+//! ```
+
+use databend_driver::{Client, ConnectionPool, Result};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let dsn = std::env::var("BENDSQL_DSN")
+        .unwrap_or_else(|_| "databend://root:@localhost:8000/default?sslmode=disable".to_string());
+    let pool = ConnectionPool::new(Client::new(dsn), 4)?;
+
+    let mut handles = vec![];
+    for i in 0..16 {
+        let pool = pool.clone();
+        handles.push(tokio::spawn(async move {
+            // waits for a free connection when all 4 are in use
+            let conn = pool.get().await?;
+            let sum: i64 = conn
+                .query_scalar("SELECT sum(number) FROM numbers(?)", (i * 1000,))
+                .await?;
+            // released back to the pool on drop
+            Ok::<_, databend_driver::Error>(sum)
+        }));
+    }
+    for (i, handle) in handles.into_iter().enumerate() {
+        let sum = handle.await.expect("task panicked")?;
+        println!("sum of numbers({}): {}", i * 1000, sum);
+    }
+    println!("idle connections: {}", pool.idle());
+    Ok(())
+}
@@ -0,0 +1,83 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Move money between accounts in an explicit transaction, which only spans
+//! the statements sent on the same connection.
+//!
+//! ```sh
+//! cargo run --example transaction
+//! ```
+
+use databend_driver::{Client, Connection, Error, Result};
+
+async fn transfer(conn: &Connection, from: i32, to: i32, amount: i64) -> Result<()> {
+    conn.exec("BEGIN", ()).await?;
+    let result = async {
+        conn.exec(
+            "UPDATE accounts SET balance = balance - ? WHERE id = ?",
+            (amount, from),
+        )
+        .await?;
+        conn.exec(
+            "UPDATE accounts SET balance = balance + ? WHERE id = ?",
+            (amount, to),
+        )
+        .await?;
+        let balance: i64 = conn
+            .query_scalar("SELECT balance FROM accounts WHERE id = ?", (from,))
+            .await?;
+        if balance < 0 {
+            return Err(Error::BadArgument(format!(
+                "insufficient balance of account {}",
+                from
+            )));
+        }
+        Ok(())
+    }
+    .await;
+    match result {
+        Ok(()) => conn.exec("COMMIT", ()).await.map(|_| ()),
+        Err(e) => {
+            conn.exec("ROLLBACK", ()).await?;
+            Err(e)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let dsn = std::env::var("BENDSQL_DSN")
+        .unwrap_or_else(|_| "databend://root:@localhost:8000/default?sslmode=disable".to_string());
+    let conn = Client::new(dsn).get_conn().await?;
+    conn.exec(
+        "CREATE OR REPLACE TABLE accounts (id INT, balance BIGINT)",
+        (),
+    )
+    .await?;
+    conn.exec("INSERT INTO accounts VALUES (1, 100), (2, 0)", ())
+        .await?;
+
+    transfer(&conn, 1, 2, 60).await?;
+    if let Err(e) = transfer(&conn, 1, 2, 60).await {
+        println!("rolled back: {}", e);
+    }
+    let rows = conn
+        .query_all("SELECT id, balance FROM accounts ORDER BY id", ())
+        .await?;
+    for row in rows {
+        let (id, balance): (i32, i64) = row.try_into().map_err(Error::Parsing)?;
+        println!("account {}: {}", id, balance);
+    }
+    Ok(())
+}
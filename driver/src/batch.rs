@@ -0,0 +1,87 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::{Duration, Instant};
+
+use databend_client_sqlparse::{split_statements, QueryKind};
+use databend_driver_core::error::{Error, Result};
+
+use crate::Connection;
+
+/// What to do with the rest of the statements of a batch after one fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchPolicy {
+    #[default]
+    FailFast,
+    ContinueOnError,
+}
+
+/// Result of a statement in [`Connection::exec_batch`].
+#[derive(Debug)]
+pub struct StatementSummary {
+    pub sql: String,
+    /// kind of the statement, to tell queries from updates and commands
+    pub kind: QueryKind,
+    /// affected rows, or the error of the statement
+    pub result: Result<i64>,
+    pub elapsed: Duration,
+}
+
+impl StatementSummary {
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+impl Connection {
+    /// Execute statements one by one in the session of this connection, so that
+    /// `USE` and `SET` take effect for the statements after them.
+    /// With `FailFast` the statements after the first failed one are not sent,
+    /// the summaries end with the failed one.
+    pub async fn exec_batch(
+        &self,
+        sqls: &[&str],
+        policy: BatchPolicy,
+    ) -> Result<Vec<StatementSummary>> {
+        if sqls.is_empty() {
+            return Err(Error::BadArgument("empty batch".to_string()));
+        }
+        let mut summaries = Vec::with_capacity(sqls.len());
+        for sql in sqls {
+            let start = Instant::now();
+            let result = self.exec(sql, ()).await;
+            let failed = result.is_err();
+            summaries.push(StatementSummary {
+                sql: sql.to_string(),
+                kind: QueryKind::from(*sql),
+                result,
+                elapsed: start.elapsed(),
+            });
+            if failed && policy == BatchPolicy::FailFast {
+                break;
+            }
+        }
+        Ok(summaries)
+    }
+
+    /// Split the script into statements with [`split_statements`], then execute them
+    /// like [`Connection::exec_batch`].
+    pub async fn exec_script(
+        &self,
+        script: &str,
+        policy: BatchPolicy,
+    ) -> Result<Vec<StatementSummary>> {
+        self.exec_batch(&split_statements(script), policy).await
+    }
+}
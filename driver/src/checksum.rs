@@ -0,0 +1,118 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use md5::{Digest, Md5};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+use databend_driver_core::error::{Error, Result};
+
+/// Computes md5 of the data read through it, for verifying uploads without reading twice.
+pub(crate) struct Md5Reader<R> {
+    inner: R,
+    hasher: Arc<Mutex<Md5>>,
+}
+
+impl<R> Md5Reader<R> {
+    pub(crate) fn new(inner: R) -> (Self, Arc<Mutex<Md5>>) {
+        let hasher = Arc::new(Mutex::new(Md5::new()));
+        (
+            Self {
+                inner,
+                hasher: hasher.clone(),
+            },
+            hasher,
+        )
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Md5Reader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            self.hasher.lock().unwrap().update(&buf.filled()[before..]);
+        }
+        poll
+    }
+}
+
+pub(crate) fn md5_hex(hasher: &Mutex<Md5>) -> String {
+    format!("{:x}", hasher.lock().unwrap().clone().finalize())
+}
+
+pub(crate) async fn file_md5(path: &Path) -> Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Md5::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Compare local md5 with the one from stage listing, which is the etag of the object.
+/// Etags not looking like a plain md5, such as `<md5>-<parts>` of multipart uploads,
+/// are skipped since they are not md5 of the content. Etags of objects encrypted by
+/// the storage, like SSE-KMS of S3, look the same but differ from the content md5,
+/// verification would fail for them and is not supported.
+pub(crate) fn verify_md5(file: &str, local: &str, remote: Option<&str>) -> Result<()> {
+    let remote = match remote {
+        Some(remote) => remote.trim_matches('"'),
+        None => return Ok(()),
+    };
+    if remote.len() != 32 || !remote.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(());
+    }
+    if !remote.eq_ignore_ascii_case(local) {
+        return Err(Error::IO(format!(
+            "checksum mismatch for {}: local md5 {}, stage md5 {}",
+            file, local, remote
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_md5_reader() {
+        let (mut reader, hasher) = Md5Reader::new(std::io::Cursor::new(b"hello world".to_vec()));
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf).await.unwrap();
+        let md5 = md5_hex(&hasher);
+        assert_eq!(md5, "5eb63bbbe01eeed093cb22bb8f5acdc3");
+
+        assert!(verify_md5("f", &md5, Some("\"5EB63BBBE01EEED093CB22BB8F5ACDC3\"")).is_ok());
+        assert!(verify_md5("f", &md5, Some("d41d8cd98f00b204e9800998ecf8427e-2")).is_ok());
+        assert!(verify_md5("f", &md5, None).is_ok());
+        assert!(verify_md5("f", &md5, Some("W/\"0x8D9F2A3B4C5D6E7\"")).is_ok());
+        assert!(verify_md5("f", &md5, Some("")).is_ok());
+        assert!(verify_md5("f", &md5, Some("d41d8cd98f00b204e9800998ecf8427e")).is_err());
+    }
+}
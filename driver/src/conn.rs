@@ -12,20 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use tokio::fs::File;
 use tokio::io::AsyncRead;
 use tokio::io::BufReader;
 use tokio_stream::StreamExt;
 
+use crate::checksum::{file_md5, md5_hex, verify_md5, Md5Reader};
+use crate::params::{quote_string, Params};
+use databend_client::Capabilities;
 use databend_client::StageLocation;
 use databend_client::{presign_download_from_stage, PresignedResponse};
+use databend_client::{QueryResponse, SchemaField};
 use databend_driver_core::error::{Error, Result};
-use databend_driver_core::raw_rows::{RawRow, RawRowIterator};
+use databend_driver_core::raw_rows::{RawRow, RawRowIterator, StringRowIterator};
 use databend_driver_core::rows::{Row, RowIterator, RowStatsIterator, RowWithStats, ServerStats};
 use databend_driver_core::schema::{DataType, Field, NumberDataType, Schema};
 use databend_driver_core::value::{NumberValue, Value};
@@ -37,6 +43,72 @@ pub struct ConnectionInfo {
     pub user: String,
     pub database: Option<String>,
     pub warehouse: Option<String>,
+    pub role: Option<String>,
+    pub tenant: Option<String>,
+    /// Whether the connection is over tls.
+    pub tls: bool,
+    /// Snapshot of the session settings, only tracked by RestAPI.
+    pub settings: BTreeMap<String, String>,
+    pub server_version: Option<String>,
+}
+
+/// Query started with [`Connection::submit`](crate::Connection::submit) and not read yet,
+/// which could be saved with [`QueryId::encode`] and fetched later by another client
+/// with the same credentials, e.g. in another process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryId {
+    pub id: String,
+    pub node_id: Option<String>,
+    pub next_uri: Option<String>,
+    // the first page is not available on server anymore, so it is kept here
+    schema: Vec<SchemaField>,
+    data: Vec<Vec<Option<String>>>,
+}
+
+impl QueryId {
+    pub(crate) fn from_response(resp: QueryResponse) -> Self {
+        Self {
+            id: resp.id,
+            node_id: resp.node_id,
+            next_uri: resp.next_uri,
+            schema: resp.schema,
+            data: resp.data,
+        }
+    }
+
+    pub(crate) fn into_response(self) -> QueryResponse {
+        QueryResponse {
+            id: self.id,
+            node_id: self.node_id,
+            next_uri: self.next_uri,
+            schema: self.schema,
+            data: self.data,
+            ..Default::default()
+        }
+    }
+
+    pub fn encode(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn decode(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl std::fmt::Display for QueryId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+/// Table listed by [`Connection::list_tables`](crate::Connection::list_tables).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableInfo {
+    pub database: String,
+    pub name: String,
+    /// `BASE TABLE`, `VIEW` or `SYSTEM VIEW`.
+    pub table_type: String,
 }
 
 pub type Reader = Box<dyn AsyncRead + Send + Sync + Unpin + 'static>;
@@ -50,6 +122,25 @@ pub trait IConnection: Send + Sync {
 
     fn last_query_id(&self) -> Option<String>;
 
+    /// Features of the server detected while connecting.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Database of the session, kept up to date after `USE`.
+    async fn current_database(&self) -> Option<String> {
+        self.info().await.database
+    }
+
+    async fn current_warehouse(&self) -> Option<String> {
+        self.info().await.warehouse
+    }
+
+    /// Role of the session, kept up to date after `SET ROLE`.
+    async fn current_role(&self) -> Option<String> {
+        self.info().await.role
+    }
+
     async fn version(&self) -> Result<String> {
         let row = self.query_row("SELECT version()").await?;
         let version = match row {
@@ -62,11 +153,64 @@ pub trait IConnection: Send + Sync {
         Ok(version)
     }
 
+    async fn list_databases(&self) -> Result<Vec<String>> {
+        let rows = self
+            .query_all("SELECT schema_name FROM information_schema.schemata ORDER BY schema_name")
+            .await?;
+        rows.into_iter()
+            .map(|row| {
+                let (name,): (String,) = row.try_into().map_err(Error::Parsing)?;
+                Ok(name)
+            })
+            .collect()
+    }
+
+    /// Tables in the database, or in all databases if `database` is none.
+    async fn list_tables(&self, database: Option<&str>) -> Result<Vec<TableInfo>> {
+        let filter = match database {
+            Some(database) => format!(" WHERE table_schema = {}", quote_string(database)),
+            None => "".to_string(),
+        };
+        let sql = format!(
+            "SELECT table_schema, table_name, table_type FROM information_schema.tables{} ORDER BY table_schema, table_name",
+            filter
+        );
+        let rows = self.query_all(&sql).await?;
+        rows.into_iter()
+            .map(|row| {
+                let (database, name, table_type): (String, String, String) =
+                    row.try_into().map_err(Error::Parsing)?;
+                Ok(TableInfo {
+                    database,
+                    name,
+                    table_type,
+                })
+            })
+            .collect()
+    }
+
     async fn exec(&self, sql: &str) -> Result<i64>;
     async fn kill_query(&self, query_id: &str) -> Result<()>;
     async fn query_iter(&self, sql: &str) -> Result<RowIterator>;
     async fn query_iter_ext(&self, sql: &str) -> Result<RowStatsIterator>;
 
+    /// Execute with params, sent to server if supported, otherwise replaced in sql.
+    async fn exec_with_params(&self, sql: &str, params: Params) -> Result<i64> {
+        self.exec(&params.replace(sql)?).await
+    }
+
+    async fn query_iter_with_params(&self, sql: &str, params: Params) -> Result<RowIterator> {
+        self.query_iter(&params.replace(sql)?).await
+    }
+
+    async fn query_iter_ext_with_params(
+        &self,
+        sql: &str,
+        params: Params,
+    ) -> Result<RowStatsIterator> {
+        self.query_iter_ext(&params.replace(sql)?).await
+    }
+
     async fn query_row(&self, sql: &str) -> Result<Option<Row>> {
         let rows = self.query_all(sql).await?;
         let row = rows.into_iter().next();
@@ -78,6 +222,20 @@ pub trait IConnection: Send + Sync {
         rows.collect().await
     }
 
+    /// Start a query without reading its result, see [`QueryId`].
+    async fn submit(&self, _sql: &str) -> Result<QueryId> {
+        Err(Error::BadArgument(
+            "Unsupported implement submit".to_string(),
+        ))
+    }
+
+    /// Read the result of a query started with [`IConnection::submit`].
+    async fn fetch(&self, _query_id: QueryId) -> Result<RowIterator> {
+        Err(Error::BadArgument(
+            "Unsupported implement fetch".to_string(),
+        ))
+    }
+
     // raw data response query, only for test
     async fn query_raw_iter(&self, _sql: &str) -> Result<RawRowIterator> {
         Err(Error::BadArgument(
@@ -85,6 +243,13 @@ pub trait IConnection: Send + Sync {
         ))
     }
 
+    /// Rows as the strings sent by the server, see [`crate::Connection::query_iter_raw`].
+    async fn query_iter_raw(&self, _sql: &str) -> Result<StringRowIterator> {
+        Err(Error::BadArgument(
+            "Unsupported implement query_iter_raw".to_string(),
+        ))
+    }
+
     // raw data response query, only for test
     async fn query_raw_all(&self, sql: &str) -> Result<Vec<RawRow>> {
         let rows = self.query_raw_iter(sql).await?;
@@ -97,6 +262,31 @@ pub trait IConnection: Send + Sync {
 
     async fn upload_to_stage(&self, stage: &str, data: Reader, size: u64) -> Result<()>;
 
+    /// Get md5 of a file in stage from the listing, which is the etag of the object,
+    /// not the content md5 for multipart or encrypted uploads, see `verify_md5`.
+    async fn stage_file_md5(&self, stage_file: &str) -> Result<Option<String>> {
+        let location = StageLocation::try_from(stage_file)?;
+        let mut rows = self.query_iter(&format!("LIST {}", location)).await?;
+        while let Some(row) = rows.next().await {
+            let (name, _, md5, _, _): (String, u64, Option<String>, String, Option<String>) =
+                row?.try_into().map_err(Error::Parsing)?;
+            if name.trim_start_matches('/') == location.path.trim_start_matches('/') {
+                return Ok(md5);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Download a file of `size` bytes from the presigned url.
+    async fn download_presigned(
+        &self,
+        presigned: PresignedResponse,
+        local_file: &Path,
+        _size: u64,
+    ) -> Result<u64> {
+        Ok(presign_download_from_stage(presigned, local_file).await?)
+    }
+
     async fn load_data(
         &self,
         sql: &str,
@@ -116,6 +306,34 @@ pub trait IConnection: Send + Sync {
 
     async fn stream_load(&self, sql: &str, data: Vec<Vec<&str>>) -> Result<ServerStats>;
 
+    /// Remove stages left by `load_data` with failed clients, which are older than `older_than`.
+    /// Stages of loads still running are kept as long as `older_than` is longer than the loads.
+    /// Returns the number of stages removed.
+    async fn purge_orphaned_client_stages(&self, older_than: Duration) -> Result<usize> {
+        let prefix = "client/load/";
+        let deadline = chrono::Utc::now()
+            .timestamp_nanos_opt()
+            .ok_or_else(|| Error::IO("Failed to get current timestamp".to_string()))?
+            - older_than.as_nanos() as i64;
+        let mut rows = self.query_iter(&format!("LIST @~/{}", prefix)).await?;
+        let mut stages = BTreeSet::new();
+        while let Some(row) = rows.next().await {
+            let (name, _, _, _, _): (String, u64, Option<String>, String, Option<String>) =
+                row?.try_into().map_err(Error::Parsing)?;
+            let Some(rest) = name.trim_start_matches('/').strip_prefix(prefix) else {
+                continue;
+            };
+            let ts = rest.split('/').next().unwrap_or_default();
+            if ts.parse::<i64>().is_ok_and(|ts| ts < deadline) {
+                stages.insert(ts.to_string());
+            }
+        }
+        for ts in stages.iter() {
+            self.exec(&format!("REMOVE @~/{}{}", prefix, ts)).await?;
+        }
+        Ok(stages.len())
+    }
+
     // PUT file://<path_to_file>/<filename> internalStage|externalStage
     async fn put_files(&self, local_file: &str, stage: &str) -> Result<RowStatsIterator> {
         let mut total_count: usize = 0;
@@ -137,11 +355,17 @@ pub trait IConnection: Send + Sync {
             let stage_file = stage_location.file_path(filename);
             let file = File::open(&entry).await?;
             let size = file.metadata().await?.len();
-            let data = BufReader::new(file);
-            let (fname, status) = match self
+            let (data, hasher) = Md5Reader::new(BufReader::new(file));
+            let uploaded = match self
                 .upload_to_stage(&stage_file, Box::new(data), size)
                 .await
             {
+                Ok(_) => self.stage_file_md5(&stage_file).await.and_then(|remote| {
+                    verify_md5(&stage_file, &md5_hex(&hasher), remote.as_deref())
+                }),
+                Err(e) => Err(e),
+            };
+            let (fname, status) = match uploaded {
                 Ok(_) => {
                     total_count += 1;
                     total_size += size as usize;
@@ -185,7 +409,7 @@ pub trait IConnection: Send + Sync {
         let mut results = Vec::new();
         let schema = Arc::new(put_get_schema());
         while let Some(row) = response.next().await {
-            let (mut name, _, _, _, _): (String, u64, Option<String>, String, Option<String>) =
+            let (mut name, size, md5, _, _): (String, u64, Option<String>, String, Option<String>) =
                 row?.try_into().map_err(Error::Parsing)?;
             if !location.path.is_empty() && name.starts_with(&location.path) {
                 name = name[location.path.len()..].to_string();
@@ -193,7 +417,13 @@ pub trait IConnection: Send + Sync {
             let stage_file = format!("{}/{}", location, name);
             let presign = self.get_presigned_url("DOWNLOAD", &stage_file).await?;
             let local_file = Path::new(local_dsn.path()).join(&name);
-            let status = presign_download_from_stage(presign, &local_file).await;
+            let status = match self.download_presigned(presign, &local_file, size).await {
+                Ok(size) => {
+                    let local = file_md5(&local_file).await?;
+                    verify_md5(&stage_file, &local, md5.as_deref()).map(|_| size)
+                }
+                Err(e) => Err(e),
+            };
             let (status, size) = match status {
                 Ok(size) => {
                     total_count += 1;
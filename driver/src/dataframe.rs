@@ -0,0 +1,216 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BinaryArray, BooleanArray, Date32Array, Float32Array, Float64Array, Int16Array,
+    Int32Array, Int64Array, Int8Array, NullArray, StringArray, TimestampMicrosecondArray,
+    UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+};
+use arrow::datatypes::{Field as ArrowField, Schema as ArrowSchema};
+use arrow::ipc::writer::StreamWriter;
+use arrow::record_batch::RecordBatch;
+use databend_driver_core::error::{ConvertError, Error, Result};
+use databend_driver_core::rows::Row;
+use databend_driver_core::schema::{DataType, NumberDataType, Schema};
+use databend_driver_core::value::{NumberValue, Value};
+use polars::prelude::{DataFrame, IpcStreamReader, SerReader};
+use tokio_stream::StreamExt;
+
+use crate::{Connection, Params};
+
+impl Connection {
+    /// Collect the result into a polars `DataFrame`, converted with an arrow record batch.
+    ///
+    /// Numbers, booleans, strings, binaries, dates and timestamps are kept in their types,
+    /// decimals and the others are strings as displayed.
+    pub async fn query_polars<P: Into<Params> + Send>(
+        &self,
+        sql: &str,
+        params: P,
+    ) -> Result<DataFrame> {
+        let mut rows = self.query_iter(sql, params).await?;
+        let schema = rows.schema();
+        let mut data = vec![];
+        while let Some(row) = rows.next().await {
+            data.push(row?);
+        }
+        to_dataframe(&schema, &data)
+    }
+}
+
+fn to_dataframe(schema: &Schema, rows: &[Row]) -> Result<DataFrame> {
+    let batch = record_batch(schema, rows)?;
+    let mut buf = vec![];
+    let mut writer = StreamWriter::try_new(&mut buf, &batch.schema()).map_err(convert_error)?;
+    writer.write(&batch).map_err(convert_error)?;
+    writer.finish().map_err(convert_error)?;
+    drop(writer);
+    IpcStreamReader::new(Cursor::new(buf))
+        .finish()
+        .map_err(convert_error)
+}
+
+fn convert_error(e: impl std::fmt::Display) -> Error {
+    Error::Convert(ConvertError::new("DataFrame", "arrow".to_string()).with_message(e.to_string()))
+}
+
+macro_rules! column {
+    ($rows:expr, $i:expr, $array:ty, $pattern:pat => $value:expr) => {
+        Arc::new(
+            $rows
+                .iter()
+                .map(|row| match &row.values()[$i] {
+                    Value::Null => Ok(None),
+                    $pattern => Ok(Some($value)),
+                    other => Err(Error::Convert(ConvertError::new(
+                        stringify!($array),
+                        other.to_string(),
+                    ))),
+                })
+                .collect::<Result<$array>>()?,
+        ) as ArrayRef
+    };
+}
+
+fn record_batch(schema: &Schema, rows: &[Row]) -> Result<RecordBatch> {
+    let mut fields = vec![];
+    let mut columns = vec![];
+    for (i, field) in schema.fields().iter().enumerate() {
+        let data_type = match &field.data_type {
+            DataType::Nullable(inner) => inner.as_ref(),
+            other => other,
+        };
+        let column = match data_type {
+            DataType::Null => Arc::new(NullArray::new(rows.len())) as ArrayRef,
+            DataType::Boolean => column!(rows, i, BooleanArray, Value::Boolean(v) => *v),
+            DataType::Binary => {
+                column!(rows, i, BinaryArray, Value::Binary(v) => v.as_slice())
+            }
+            DataType::String => column!(rows, i, StringArray, Value::String(v) => v.as_str()),
+            DataType::Date => column!(rows, i, Date32Array, Value::Date(v) => *v),
+            DataType::Timestamp => {
+                column!(rows, i, TimestampMicrosecondArray, Value::Timestamp(v) => *v)
+            }
+            DataType::Number(number) => match number {
+                NumberDataType::Int8 => {
+                    column!(rows, i, Int8Array, Value::Number(NumberValue::Int8(v)) => *v)
+                }
+                NumberDataType::Int16 => {
+                    column!(rows, i, Int16Array, Value::Number(NumberValue::Int16(v)) => *v)
+                }
+                NumberDataType::Int32 => {
+                    column!(rows, i, Int32Array, Value::Number(NumberValue::Int32(v)) => *v)
+                }
+                NumberDataType::Int64 => {
+                    column!(rows, i, Int64Array, Value::Number(NumberValue::Int64(v)) => *v)
+                }
+                NumberDataType::UInt8 => {
+                    column!(rows, i, UInt8Array, Value::Number(NumberValue::UInt8(v)) => *v)
+                }
+                NumberDataType::UInt16 => {
+                    column!(rows, i, UInt16Array, Value::Number(NumberValue::UInt16(v)) => *v)
+                }
+                NumberDataType::UInt32 => {
+                    column!(rows, i, UInt32Array, Value::Number(NumberValue::UInt32(v)) => *v)
+                }
+                NumberDataType::UInt64 => {
+                    column!(rows, i, UInt64Array, Value::Number(NumberValue::UInt64(v)) => *v)
+                }
+                NumberDataType::Float32 => {
+                    column!(rows, i, Float32Array, Value::Number(NumberValue::Float32(v)) => *v)
+                }
+                NumberDataType::Float64 => {
+                    column!(rows, i, Float64Array, Value::Number(NumberValue::Float64(v)) => *v)
+                }
+            },
+            _ => Arc::new(
+                rows.iter()
+                    .map(|row| match &row.values()[i] {
+                        Value::Null => None,
+                        v => Some(v.to_string()),
+                    })
+                    .collect::<StringArray>(),
+            ) as ArrayRef,
+        };
+        fields.push(ArrowField::new(
+            &field.name,
+            column.data_type().clone(),
+            true,
+        ));
+        columns.push(column);
+    }
+    RecordBatch::try_new(Arc::new(ArrowSchema::new(fields)), columns).map_err(convert_error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use databend_driver_core::schema::Field;
+
+    #[test]
+    fn rows_to_dataframe() -> Result<()> {
+        let schema = Arc::new(Schema::from_vec(vec![
+            Field {
+                name: "id".to_string(),
+                data_type: DataType::Number(NumberDataType::UInt64),
+            },
+            Field {
+                name: "name".to_string(),
+                data_type: DataType::Nullable(Box::new(DataType::String)),
+            },
+            Field {
+                name: "tags".to_string(),
+                data_type: DataType::Array(Box::new(DataType::String)),
+            },
+        ]));
+        let rows = vec![
+            Row::from_vec(
+                schema.clone(),
+                vec![
+                    Value::Number(NumberValue::UInt64(1)),
+                    Value::String("a".to_string()),
+                    Value::Array(vec![Value::String("x".to_string())]),
+                ],
+            ),
+            Row::from_vec(
+                schema.clone(),
+                vec![
+                    Value::Number(NumberValue::UInt64(2)),
+                    Value::Null,
+                    Value::EmptyArray,
+                ],
+            ),
+        ];
+        let batch = record_batch(&schema, &rows)?;
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.column(1).null_count(), 1);
+        assert_eq!(
+            batch.column(2).data_type(),
+            &arrow::datatypes::DataType::Utf8
+        );
+        let df = to_dataframe(&schema, &rows)?;
+        assert_eq!(df.shape(), (2, 3));
+        assert_eq!(df.get_column_names(), ["id", "name", "tags"]);
+
+        let bad = vec![Row::from_vec(
+            schema.clone(),
+            vec![Value::Boolean(true), Value::Null, Value::Null],
+        )];
+        assert!(record_batch(&schema, &bad).is_err());
+        Ok(())
+    }
+}
@@ -21,17 +21,26 @@ use std::time::Duration;
 
 use arrow::ipc::{convert::fb_to_schema, root_as_message};
 use arrow_flight::decode::FlightDataDecoder;
+use arrow_flight::flight_service_client::FlightServiceClient;
 use arrow_flight::sql::client::FlightSqlServiceClient;
+use arrow_flight::sql::{CommandGetDbSchemas, CommandGetTables};
 use arrow_flight::utils::flight_data_to_arrow_batch;
+use arrow_flight::FlightInfo;
 use arrow_schema::SchemaRef as ArrowSchemaRef;
 use async_trait::async_trait;
 use percent_encoding::percent_decode_str;
 use tokio::sync::Mutex;
-use tokio_stream::{Stream, StreamExt};
-use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tokio::task::JoinSet;
+use tokio_stream::{Stream, StreamExt, StreamMap};
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+use tonic::transport::ClientTlsConfig;
+use tonic::transport::{Channel, Endpoint};
 use url::Url;
 
+use databend_client::Capabilities;
 use databend_client::SensitiveString;
+use databend_client::TlsBackend;
+use databend_client::CLIENT_ARGS;
 use databend_client::{presign_upload_to_stage, PresignedResponse};
 use databend_driver_core::error::{Error, Result};
 use databend_driver_core::rows::{
@@ -39,7 +48,9 @@ use databend_driver_core::rows::{
 };
 use databend_driver_core::schema::Schema;
 
-use crate::conn::{ConnectionInfo, IConnection, Reader};
+use crate::conn::{ConnectionInfo, IConnection, Reader, TableInfo};
+use crate::params::quote_string;
+use crate::query_builder::quote_exact;
 
 #[derive(Clone)]
 pub struct FlightSQLConnection {
@@ -58,6 +69,11 @@ impl IConnection for FlightSQLConnection {
             user: self.args.user.clone(),
             database: self.args.database.clone(),
             warehouse: self.args.warehouse.clone(),
+            role: self.args.role.clone(),
+            tenant: self.args.tenant.clone(),
+            tls: self.args.tls,
+            settings: BTreeMap::new(),
+            server_version: None,
         }
     }
 
@@ -65,6 +81,73 @@ impl IConnection for FlightSQLConnection {
         None
     }
 
+    fn capabilities(&self) -> Capabilities {
+        // data is always uploaded with presigned urls, and params are replaced in sql,
+        // arrow arrays keep NULL apart from values
+        Capabilities {
+            presign: true,
+            distinct_null: true,
+            ..Default::default()
+        }
+    }
+
+    /// The session is kept on server side, so ask the server for the current one.
+    async fn current_database(&self) -> Option<String> {
+        match self.query_row("SELECT current_database()").await {
+            Ok(Some(row)) => row.try_into().ok().map(|(db,): (String,)| db),
+            _ => self.args.database.clone(),
+        }
+    }
+
+    async fn current_role(&self) -> Option<String> {
+        match self.query_row("SELECT current_role()").await {
+            Ok(Some(row)) => row.try_into().ok().map(|(role,): (String,)| role),
+            _ => None,
+        }
+    }
+
+    async fn list_databases(&self) -> Result<Vec<String>> {
+        self.handshake().await?;
+        let mut client = self.client.lock().await;
+        let flight_info = client
+            .get_db_schemas(CommandGetDbSchemas::default())
+            .await?;
+        let rows = Self::fetch_rows(&mut client, flight_info).await?;
+        rows.into_iter()
+            .map(|row| {
+                let (_catalog, database): (Option<String>, String) =
+                    row.try_into().map_err(Error::Parsing)?;
+                Ok(database)
+            })
+            .collect()
+    }
+
+    async fn list_tables(&self, database: Option<&str>) -> Result<Vec<TableInfo>> {
+        self.handshake().await?;
+        let mut client = self.client.lock().await;
+        let command = CommandGetTables {
+            db_schema_filter_pattern: database.map(|db| db.to_string()),
+            ..Default::default()
+        };
+        let flight_info = client.get_tables(command).await?;
+        let rows = Self::fetch_rows(&mut client, flight_info).await?;
+        rows.into_iter()
+            .map(|row| {
+                let (_catalog, database, name, table_type): (
+                    Option<String>,
+                    Option<String>,
+                    String,
+                    String,
+                ) = row.try_into().map_err(Error::Parsing)?;
+                Ok(TableInfo {
+                    database: database.unwrap_or_default(),
+                    name,
+                    table_type,
+                })
+            })
+            .collect()
+    }
+
     async fn exec(&self, sql: &str) -> Result<i64> {
         self.handshake().await?;
         let mut client = self.client.lock().await;
@@ -85,16 +168,49 @@ impl IConnection for FlightSQLConnection {
 
     async fn query_iter_ext(&self, sql: &str) -> Result<RowStatsIterator> {
         self.handshake().await?;
-        let mut client = self.client.lock().await;
-        let mut stmt = client.prepare(sql.to_string(), None).await?;
-        let flight_info = stmt.execute().await?;
-        let ticket = flight_info.endpoint[0]
-            .ticket
-            .as_ref()
-            .ok_or_else(|| Error::Protocol("Ticket is empty".to_string()))?;
-        let flight_data = client.do_get(ticket.clone()).await?.into_inner();
-        let (schema, rows) = FlightSQLRows::try_from_flight_data(flight_data).await?;
-        Ok(RowStatsIterator::new(Arc::new(schema), Box::pin(rows)))
+        let (flight_info, client) = {
+            let mut client = self.client.lock().await;
+            let mut stmt = client.prepare(sql.to_string(), None).await?;
+            (stmt.execute().await?, client.clone())
+        };
+        let ordered = match self.args.endpoint_order {
+            EndpointOrder::Auto => flight_info.ordered,
+            EndpointOrder::Ordered => true,
+            EndpointOrder::Unordered => false,
+        };
+        // start fetching all endpoints at once, each stream is buffered
+        // up to the flow control window until it is read, the fetches
+        // are aborted with the set on errors or if the caller is dropped
+        let mut fetches = JoinSet::new();
+        let endpoints = flight_info.endpoint.len();
+        for (i, endpoint) in flight_info.endpoint.into_iter().enumerate() {
+            let ticket = endpoint
+                .ticket
+                .ok_or_else(|| Error::Protocol("Ticket is empty".to_string()))?;
+            let mut client = client.clone();
+            fetches.spawn(async move {
+                let flight_data = client.do_get(ticket).await?.into_inner();
+                let result = FlightSQLRows::try_from_flight_data(flight_data).await?;
+                Ok::<_, Error>((i, result))
+            });
+        }
+        let mut fetched = (0..endpoints).map(|_| None).collect::<Vec<_>>();
+        while let Some(result) = fetches.join_next().await {
+            let (i, result) = result.map_err(|e| {
+                Error::Transport(format!("fetch flight sql endpoint failed: {e}"))
+            })??;
+            fetched[i] = Some(result);
+        }
+        let mut schema = None;
+        let mut streams = vec![];
+        for (endpoint_schema, rows) in fetched.into_iter().flatten() {
+            schema.get_or_insert(endpoint_schema);
+            streams.push(rows);
+        }
+        let schema =
+            schema.ok_or_else(|| Error::Protocol("No endpoint in flight info".to_string()))?;
+        let rows = merge_endpoints(streams, ordered);
+        Ok(RowStatsIterator::new(Arc::new(schema), rows))
     }
 
     async fn get_presigned_url(&self, operation: &str, stage: &str) -> Result<PresignedResponse> {
@@ -155,7 +271,11 @@ impl FlightSQLConnection {
     pub async fn try_create(dsn: &str, name: String) -> Result<Self> {
         let (args, endpoint) = Self::parse_dsn(dsn, name).await?;
         let channel = endpoint.connect_lazy();
-        let mut client = FlightSqlServiceClient::new(channel);
+        let mut inner = FlightServiceClient::new(channel);
+        if let Some(size) = args.max_message_size {
+            inner = inner.max_decoding_message_size(size);
+        }
+        let mut client = FlightSqlServiceClient::new_from_inner(inner);
         // enable progress
         client.set_header("bendsql", "1");
         if let Some(tenant) = args.tenant.as_ref() {
@@ -171,7 +291,7 @@ impl FlightSQLConnection {
         })
     }
 
-    async fn handshake(&self) -> Result<()> {
+    pub(crate) async fn handshake(&self) -> Result<()> {
         let mut handshaked = self.handshaked.lock().await;
         if *handshaked {
             return Ok(());
@@ -180,13 +300,42 @@ impl FlightSQLConnection {
         let _token = client
             .handshake(&self.args.user, self.args.password.inner())
             .await?;
+        // the session is kept on server side after handshake
+        for sql in self.args.session_sqls() {
+            client
+                .execute_update(sql.clone(), None)
+                .await
+                .map_err(|e| Error::from(e).with_context(&sql))?;
+        }
         *handshaked = true;
         Ok(())
     }
 
+    /// Read the results of the catalog RPCs, which are small enough to collect.
+    async fn fetch_rows(
+        client: &mut FlightSqlServiceClient<Channel>,
+        flight_info: FlightInfo,
+    ) -> Result<Vec<Row>> {
+        let mut rows = vec![];
+        for endpoint in flight_info.endpoint {
+            let ticket = endpoint
+                .ticket
+                .ok_or_else(|| Error::Protocol("Ticket is empty".to_string()))?;
+            let mut batches = client.do_get(ticket).await?;
+            while let Some(batch) = batches.next().await {
+                let batch = batch.map_err(|err| {
+                    Error::Transport(format!("fetch flight sql catalog failed: {err:?}"))
+                })?;
+                rows.extend(Rows::try_from(batch)?);
+            }
+        }
+        Ok(rows)
+    }
+
     async fn parse_dsn(dsn: &str, name: String) -> Result<(Args, Endpoint)> {
         let u = Url::parse(dsn)?;
         let args = Args::from_url(&u)?;
+        #[allow(unused_mut)]
         let mut endpoint = Endpoint::new(args.uri.clone())?
             .user_agent(name)?
             .connect_timeout(args.connect_timeout)
@@ -195,10 +344,13 @@ impl FlightSQLConnection {
             .tcp_keepalive(args.tcp_keepalive)
             .http2_keep_alive_interval(args.http2_keep_alive_interval)
             .keep_alive_timeout(args.keep_alive_timeout)
-            .keep_alive_while_idle(args.keep_alive_while_idle);
+            .keep_alive_while_idle(args.keep_alive_while_idle)
+            .initial_stream_window_size(args.http2_stream_window_size)
+            .initial_connection_window_size(args.http2_connection_window_size)
+            .http2_adaptive_window(args.http2_adaptive_window);
         #[cfg(any(feature = "rustls", feature = "native-tls"))]
         if args.tls {
-            let tls_config = match args.tls_ca_file {
+            let mut tls_config = match args.tls_ca_file {
                 None => ClientTlsConfig::new(),
                 Some(ref ca_file) => {
                     let pem = tokio::fs::read(ca_file).await?;
@@ -206,6 +358,15 @@ impl FlightSQLConnection {
                     ClientTlsConfig::new().ca_certificate(cert)
                 }
             };
+            if let Some(ref server_name) = args.tls_server_name {
+                tls_config = tls_config.domain_name(server_name);
+            }
+            if let (Some(cert_file), Some(key_file)) = (&args.tls_client_cert, &args.tls_client_key)
+            {
+                let cert = tokio::fs::read(cert_file).await?;
+                let key = tokio::fs::read(key_file).await?;
+                tls_config = tls_config.identity(tonic::transport::Identity::from_pem(cert, key));
+            }
             endpoint = endpoint.tls_config(tls_config)?;
         }
         Ok((args, endpoint))
@@ -220,10 +381,16 @@ struct Args {
     user: String,
     password: SensitiveString,
     database: Option<String>,
+    role: Option<String>,
+    /// Session settings, applied with `SET` after handshake.
+    settings: BTreeMap<String, String>,
     tenant: Option<String>,
     warehouse: Option<String>,
     tls: bool,
     tls_ca_file: Option<String>,
+    tls_client_cert: Option<String>,
+    tls_client_key: Option<String>,
+    tls_server_name: Option<String>,
     connect_timeout: Duration,
     query_timeout: Duration,
     tcp_nodelay: bool,
@@ -232,6 +399,13 @@ struct Args {
     http2_keep_alive_interval: Duration,
     keep_alive_timeout: Duration,
     keep_alive_while_idle: bool,
+    // http2 flow control windows in bytes, limiting the data buffered before read
+    http2_stream_window_size: Option<u32>,
+    http2_connection_window_size: Option<u32>,
+    http2_adaptive_window: bool,
+    /// Max size of a message in bytes, such as a record batch, default to 4MB by tonic.
+    max_message_size: Option<usize>,
+    endpoint_order: EndpointOrder,
 }
 
 impl Default for Args {
@@ -241,10 +415,15 @@ impl Default for Args {
             host: "localhost".to_string(),
             port: 8900,
             database: None,
+            role: None,
+            settings: BTreeMap::new(),
             tenant: None,
             warehouse: None,
             tls: true,
             tls_ca_file: None,
+            tls_client_cert: None,
+            tls_client_key: None,
+            tls_server_name: None,
             user: "root".to_string(),
             password: SensitiveString::from(""),
             connect_timeout: Duration::from_secs(20),
@@ -254,11 +433,63 @@ impl Default for Args {
             http2_keep_alive_interval: Duration::from_secs(300),
             keep_alive_timeout: Duration::from_secs(20),
             keep_alive_while_idle: true,
+            http2_stream_window_size: None,
+            http2_connection_window_size: None,
+            http2_adaptive_window: false,
+            max_message_size: None,
+            endpoint_order: EndpointOrder::Auto,
         }
     }
 }
 
+/// Args of the DSN taken by the FlightSQL client, the others except the ones
+/// of the RestAPI client are session settings.
+pub(crate) const FLIGHT_ARGS: &[&str] = &[
+    "tenant",
+    "warehouse",
+    "role",
+    "sslmode",
+    "tls_ca_file",
+    "sslrootcert",
+    "tls_client_cert",
+    "sslcert",
+    "tls_client_key",
+    "sslkey",
+    "tls_server_name",
+    "tls_backend",
+    "tls_skip_verify",
+    "connect_timeout",
+    "connect_timeout_secs",
+    "query_timeout",
+    "tcp_nodelay",
+    "tcp_keepalive",
+    "tcp_keepalive_secs",
+    "http2_keep_alive_interval",
+    "keep_alive_timeout",
+    "keep_alive_while_idle",
+    "http2_stream_window_size",
+    "http2_connection_window_size",
+    "http2_adaptive_window",
+    "max_message_size",
+    "endpoint_order",
+];
+
 impl Args {
+    /// Statements to set up the session like the RestAPI does with the DSN.
+    fn session_sqls(&self) -> Vec<String> {
+        let mut sqls = vec![];
+        if let Some(database) = &self.database {
+            sqls.push(format!("USE {}", quote_exact(database)));
+        }
+        if let Some(role) = &self.role {
+            sqls.push(format!("SET ROLE {}", quote_exact(role)));
+        }
+        for (k, v) in &self.settings {
+            sqls.push(format!("SET {} = {}", k, quote_string(v)));
+        }
+        sqls
+    }
+
     fn from_url(u: &Url) -> Result<Self> {
         let mut args = Self::default();
         let mut scheme = "https";
@@ -266,15 +497,21 @@ impl Args {
             match k.as_ref() {
                 "tenant" => args.tenant = Some(v.to_string()),
                 "warehouse" => args.warehouse = Some(v.to_string()),
+                "role" => args.role = Some(v.to_string()),
                 "sslmode" => match v.as_ref() {
                     "disable" => {
                         scheme = "http";
                         args.tls = false;
                     }
-                    "require" | "enable" => {
+                    "require" | "enable" | "verify-full" => {
                         scheme = "https";
                         args.tls = true;
                     }
+                    "verify-ca" => {
+                        return Err(Error::BadArgument(
+                            "sslmode=verify-ca is not supported by Flight SQL".to_string(),
+                        ))
+                    }
                     _ => {
                         return Err(Error::BadArgument(format!(
                             "Invalid value for sslmode: {}",
@@ -282,11 +519,24 @@ impl Args {
                         )))
                     }
                 },
-                "tls_ca_file" => args.tls_ca_file = Some(v.to_string()),
-                "connect_timeout" => args.connect_timeout = Duration::from_secs(v.parse()?),
+                "tls_ca_file" | "sslrootcert" => args.tls_ca_file = Some(v.to_string()),
+                "tls_client_cert" | "sslcert" => args.tls_client_cert = Some(v.to_string()),
+                "tls_client_key" | "sslkey" => args.tls_client_key = Some(v.to_string()),
+                "tls_server_name" => args.tls_server_name = Some(v.to_string()),
+                "tls_backend" if TlsBackend::parse(&v)? != TlsBackend::Rustls => {
+                    return Err(Error::BadArgument(
+                        "Flight SQL only supports tls_backend=rustls".to_string(),
+                    ));
+                }
+                "tls_skip_verify" if !matches!(v.as_ref(), "0" | "false" | "disable") => {
+                    return Err(Error::BadArgument(
+                        "tls_skip_verify is not supported by Flight SQL".to_string(),
+                    ));
+                }
+                "connect_timeout" | "connect_timeout_secs" => args.connect_timeout = Duration::from_secs(v.parse()?),
                 "query_timeout" => args.query_timeout = Duration::from_secs(v.parse()?),
                 "tcp_nodelay" => args.tcp_nodelay = v.parse()?,
-                "tcp_keepalive" => {
+                "tcp_keepalive" | "tcp_keepalive_secs" => {
                     args.tcp_keepalive = {
                         match v.as_ref() {
                             "0" | "close" => None,
@@ -299,9 +549,43 @@ impl Args {
                 }
                 "keep_alive_timeout" => args.keep_alive_timeout = Duration::from_secs(v.parse()?),
                 "keep_alive_while_idle" => args.keep_alive_while_idle = v.parse()?,
-                _ => {}
+                "http2_stream_window_size" => args.http2_stream_window_size = Some(v.parse()?),
+                "http2_connection_window_size" => {
+                    args.http2_connection_window_size = Some(v.parse()?)
+                }
+                "http2_adaptive_window" => args.http2_adaptive_window = v.parse()?,
+                "max_message_size" => args.max_message_size = Some(v.parse()?),
+                "endpoint_order" => {
+                    args.endpoint_order = match v.as_ref() {
+                        "auto" => EndpointOrder::Auto,
+                        "ordered" => EndpointOrder::Ordered,
+                        "unordered" => EndpointOrder::Unordered,
+                        _ => {
+                            return Err(Error::BadArgument(format!(
+                                "Invalid value for endpoint_order: {}, should be one of auto/ordered/unordered",
+                                v
+                            )))
+                        }
+                    }
+                }
+                k if FLIGHT_ARGS.contains(&k) || CLIENT_ARGS.contains(&k) => {}
+                _ => {
+                    args.settings.insert(k.to_string(), v.to_string());
+                }
             }
         }
+        if args.tls_client_cert.is_some() != args.tls_client_key.is_some() {
+            return Err(Error::BadArgument(
+                "tls_client_cert and tls_client_key should be set together".to_string(),
+            ));
+        }
+        #[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+        if args.tls {
+            return Err(Error::BadArgument(
+                "TLS is not supported without feature rustls or native-tls, use sslmode=disable"
+                    .to_string(),
+            ));
+        }
         u.path().split('/').filter(|s| !s.is_empty()).for_each(|s| {
             if args.database.is_none() {
                 args.database = Some(s.to_string());
@@ -327,6 +611,35 @@ impl Args {
     }
 }
 
+type RowStatsStream = Pin<Box<dyn Stream<Item = Result<RowWithStats>> + Send>>;
+
+/// Read the endpoints one after another if ordered, otherwise rows
+/// are yielded from any endpoint as soon as they arrive.
+fn merge_endpoints(streams: Vec<FlightSQLRows>, ordered: bool) -> RowStatsStream {
+    if ordered {
+        let mut merged: RowStatsStream = Box::pin(tokio_stream::empty());
+        for stream in streams {
+            merged = Box::pin(merged.chain(stream));
+        }
+        merged
+    } else {
+        let mut map = StreamMap::new();
+        for (i, stream) in streams.into_iter().enumerate() {
+            map.insert(i, stream);
+        }
+        Box::pin(map.map(|(_, item)| item))
+    }
+}
+
+/// How rows of multiple endpoints are merged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EndpointOrder {
+    /// Ordered if the server says the endpoints are in the order of data.
+    Auto,
+    Ordered,
+    Unordered,
+}
+
 pub struct FlightSQLRows {
     schema: ArrowSchemaRef,
     data: FlightDataDecoder,
@@ -390,3 +703,78 @@ impl Stream for FlightSQLRows {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_args_not_settings() -> Result<()> {
+        for arg in FLIGHT_ARGS.iter().chain(CLIENT_ARGS) {
+            let u = Url::parse(&format!(
+                "databend+flight://root:@localhost:8900/?{}=x",
+                arg
+            ))?;
+            // invalid values are rejected, the others are not sent as settings
+            if let Ok(args) = Args::from_url(&u) {
+                assert!(!args.settings.contains_key(*arg), "{}", arg);
+            }
+        }
+        let u = Url::parse(
+            "databend+flight://root:@localhost:8900/?sslmode=disable&tls_backend=rustls&tls_skip_verify=false",
+        )?;
+        assert!(Args::from_url(&u)?.session_sqls().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn flow_control_args() -> Result<()> {
+        let u = Url::parse(
+            "databend+flight://root:@localhost:8900?sslmode=disable&max_message_size=67108864&http2_stream_window_size=1048576&http2_adaptive_window=true",
+        )?;
+        let args = Args::from_url(&u)?;
+        assert_eq!(args.max_message_size, Some(64 * 1024 * 1024));
+        assert_eq!(args.http2_stream_window_size, Some(1024 * 1024));
+        assert_eq!(args.http2_connection_window_size, None);
+        assert!(args.http2_adaptive_window);
+        assert!(args.settings.is_empty());
+        assert_eq!(args.endpoint_order, EndpointOrder::Auto);
+
+        let u = Url::parse("databend+flight://root:@localhost:8900?endpoint_order=unordered")?;
+        assert_eq!(Args::from_url(&u)?.endpoint_order, EndpointOrder::Unordered);
+        let u = Url::parse("databend+flight://root:@localhost:8900?endpoint_order=random")?;
+        assert!(Args::from_url(&u).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn client_cert_args() -> Result<()> {
+        let u = Url::parse(
+            "databend+flight://root:@localhost:8900?tls_client_cert=/tmp/client.pem&sslkey=/tmp/client.key",
+        )?;
+        let args = Args::from_url(&u)?;
+        assert_eq!(args.tls_client_cert.as_deref(), Some("/tmp/client.pem"));
+        assert_eq!(args.tls_client_key.as_deref(), Some("/tmp/client.key"));
+        assert!(args.settings.is_empty());
+        let u = Url::parse("databend+flight://root:@localhost:8900?sslkey=/tmp/client.key")?;
+        assert!(Args::from_url(&u).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn session_sqls() -> Result<()> {
+        let u = Url::parse(
+            "databend+flight://root:@localhost:8900/books?sslmode=disable&role=analyst&timezone=Europe/London&presign=off",
+        )?;
+        let args = Args::from_url(&u)?;
+        assert_eq!(
+            args.session_sqls(),
+            vec![
+                "USE books",
+                "SET ROLE analyst",
+                "SET timezone = 'Europe/London'",
+            ]
+        );
+        Ok(())
+    }
+}
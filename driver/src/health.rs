@@ -0,0 +1,112 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Health checks of the server for the `/healthz` endpoints of services using the driver.
+//!
+//! ```no_run
+//! # use databend_driver::Client;
+//! # #[tokio::main]
+//! # async fn main() {
+//! let client = Client::new("databend://root:@localhost:8000/default".to_string());
+//! let report = databend_driver::health::check(&client).await;
+//! let status = if report.healthy { 200 } else { 503 };
+//! println!("{} {}", status, serde_json::to_string(&report).unwrap());
+//! # }
+//! ```
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use databend_driver_core::error::{Error, Result};
+use serde::{Serialize, Serializer};
+
+use crate::Client;
+
+/// Timeout of each step of [`check`].
+pub const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Result of [`check`], serialized with latencies in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub version: Option<String>,
+    pub checks: Vec<HealthCheck>,
+}
+
+/// A step of the health check, steps after a failed one are not run.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthCheck {
+    pub name: &'static str,
+    #[serde(rename = "latency_ms", serialize_with = "as_millis")]
+    pub latency: Duration,
+    pub error: Option<String>,
+}
+
+fn as_millis<S: Serializer>(latency: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(latency.as_secs_f64() * 1000.0)
+}
+
+/// Connect to the server as `ping`, then ask for its `version` and run a trivial `query`,
+/// each step bounded by [`DEFAULT_CHECK_TIMEOUT`].
+pub async fn check(client: &Client) -> HealthReport {
+    check_with_timeout(client, DEFAULT_CHECK_TIMEOUT).await
+}
+
+pub async fn check_with_timeout(client: &Client, timeout: Duration) -> HealthReport {
+    let mut report = HealthReport {
+        healthy: false,
+        version: None,
+        checks: vec![],
+    };
+    let Some(conn) = step(&mut report, "ping", timeout, client.get_conn()).await else {
+        return report;
+    };
+    let Some(version) = step(&mut report, "version", timeout, conn.version()).await else {
+        return report;
+    };
+    report.version = Some(version);
+    if step(&mut report, "query", timeout, conn.exec("SELECT 1", ()))
+        .await
+        .is_some()
+    {
+        report.healthy = true;
+    }
+    if let Err(e) = conn.close().await {
+        log::warn!("failed to close health check connection: {}", e);
+    }
+    report
+}
+
+async fn step<T>(
+    report: &mut HealthReport,
+    name: &'static str,
+    timeout: Duration,
+    fut: impl Future<Output = Result<T>>,
+) -> Option<T> {
+    let start = Instant::now();
+    let result = match tokio::time::timeout(timeout, fut).await {
+        Ok(result) => result,
+        Err(_) => Err(Error::IO(format!("timed out after {:?}", timeout))),
+    };
+    let (value, error) = match result {
+        Ok(value) => (Some(value), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    report.checks.push(HealthCheck {
+        name,
+        latency: start.elapsed(),
+        error,
+    });
+    value
+}
@@ -12,24 +12,52 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod batch;
+mod checksum;
 mod client;
 pub mod conn;
+#[cfg(feature = "polars")]
+mod dataframe;
+mod dsn;
 #[cfg(feature = "flight-sql")]
 mod flight_sql;
+pub mod health;
+pub mod migrations;
 mod params;
 mod placeholder;
+mod pool;
+pub mod query_builder;
 pub mod rest_api;
+mod rewrite;
+mod temp_table;
 
+pub use batch::{BatchPolicy, StatementSummary};
 pub use client::Client;
 pub use client::Connection;
 pub use conn::ConnectionInfo;
+pub use conn::QueryId;
+pub use conn::TableInfo;
+pub use dsn::{DsnBuilder, SslMode};
 pub use params::Param;
 pub use params::Params;
+pub use pool::ConnectionPool;
+pub use pool::PooledConnection;
+pub use rewrite::QueryRewriter;
+pub use temp_table::TempTable;
 
 // pub use for convenience
+pub use databend_client::{
+    APIClientBuilder, Capabilities, CommandCredentialProvider, Credential, CredentialProvider,
+    MetadataCredentialProvider, QueryTracker, RecordingTransport, ReplayTransport, RequestSigner,
+    RetryPolicy, ServerVersion, StaticCredentialProvider, TokenFileCredentialProvider, Transport,
+    TransportError,
+};
+pub use databend_client_sqlparse::split_statements;
+pub use databend_client_sqlparse::QueryKind;
 pub use databend_driver_core::error::{Error, Result};
+pub use databend_driver_core::raw_rows::StringRowIterator;
 pub use databend_driver_core::rows::{
-    Row, RowIterator, RowStatsIterator, RowWithStats, ServerStats,
+    RejectedFile, Row, RowIterator, RowStatsIterator, RowWithStats, ServerStats,
 };
 pub use databend_driver_core::schema::{
     DataType, DecimalSize, Field, NumberDataType, Schema, SchemaRef,
@@ -0,0 +1,264 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Schema migrations from a directory of sql files named like `0001_create_users.sql`,
+//! applied in the order of the leading version numbers and recorded in a table.
+//!
+//! ```no_run
+//! # use databend_driver::Client;
+//! # use databend_driver::migrations::Migrator;
+//! # #[tokio::main]
+//! # async fn main() -> databend_driver::Result<()> {
+//! let conn = Client::new("databend://root:@localhost:8000/default".to_string())
+//!     .get_conn()
+//!     .await?;
+//! let applied = Migrator::new("./migrations").run(&conn).await?;
+//! for m in applied {
+//!     println!("applied {} {}", m.version, m.name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use md5::{Digest, Md5};
+
+use databend_driver_core::error::{Error, Result};
+
+use crate::query_builder::quote_ident;
+use crate::{split_statements, Connection};
+
+const DEFAULT_TABLE: &str = "_migrations";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub version: u64,
+    pub name: String,
+    pub sql: String,
+    /// md5 of the sql, to detect files changed after they were applied
+    pub checksum: String,
+}
+
+impl Migration {
+    fn parse(path: &Path) -> Result<Option<Self>> {
+        if path.extension().and_then(|e| e.to_str()) != Some("sql") {
+            return Ok(None);
+        }
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let (version, name) = stem.split_once('_').unwrap_or((stem, ""));
+        let version = version.parse().map_err(|_| {
+            Error::BadArgument(format!(
+                "invalid migration file {}, should be named like 0001_name.sql",
+                path.display()
+            ))
+        })?;
+        let sql = std::fs::read_to_string(path)?;
+        let checksum = format!("{:x}", Md5::digest(sql.as_bytes()));
+        Ok(Some(Self {
+            version,
+            name: name.to_string(),
+            sql,
+            checksum,
+        }))
+    }
+
+    /// Statements split by `;`, migrations are not in a transaction, since DDL is not transactional.
+    /// A migration failed partway is recorded as failed, and blocks the following runs until
+    /// the database is fixed and the record is deleted.
+    pub fn statements(&self) -> Vec<&str> {
+        split_statements(&self.sql)
+    }
+}
+
+pub struct Migrator {
+    dir: PathBuf,
+    table: String,
+    dry_run: bool,
+}
+
+impl Migrator {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            table: DEFAULT_TABLE.to_string(),
+            dry_run: false,
+        }
+    }
+
+    /// Table recording the applied migrations, default to `_migrations`, could be
+    /// `db.table` and is quoted as needed.
+    pub fn table(mut self, table: &str) -> Self {
+        self.table = table.to_string();
+        self
+    }
+
+    /// Validate and return the pending migrations without applying them,
+    /// nothing is written, not even the table recording migrations.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Migrations in the directory ordered by version.
+    pub fn load(&self) -> Result<Vec<Migration>> {
+        let mut migrations = BTreeMap::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if let Some(m) = Migration::parse(&path)? {
+                if let Some(dup) = migrations.insert(m.version, m) {
+                    return Err(Error::BadArgument(format!(
+                        "duplicated migration version {} in {}",
+                        dup.version,
+                        self.dir.display()
+                    )));
+                }
+            }
+        }
+        Ok(migrations.into_values().collect())
+    }
+
+    /// Versions recorded with their checksums and whether they succeeded,
+    /// the table is created if not exists unless in dry run.
+    pub async fn applied(&self, conn: &Connection) -> Result<BTreeMap<u64, Applied>> {
+        let table = quote_ident(&self.table);
+        if self.dry_run {
+            if !self.table_exists(conn).await? {
+                return Ok(BTreeMap::new());
+            }
+        } else {
+            let sql = format!(
+                "CREATE TABLE IF NOT EXISTS {} (version UInt64, name String, checksum String, success Boolean, applied_at Timestamp DEFAULT now())",
+                table
+            );
+            conn.exec(&sql, ()).await?;
+        }
+        let sql = format!(
+            "SELECT version, checksum, success FROM {} ORDER BY version",
+            table
+        );
+        let mut applied = BTreeMap::new();
+        for row in conn.query_all(&sql, ()).await? {
+            let (version, checksum, success): (u64, String, bool) =
+                row.try_into().map_err(Error::Parsing)?;
+            applied.insert(version, Applied { checksum, success });
+        }
+        Ok(applied)
+    }
+
+    async fn table_exists(&self, conn: &Connection) -> Result<bool> {
+        let row = match self.table.split_once('.') {
+            Some((db, name)) => {
+                let sql = "SELECT count(*) FROM system.tables WHERE database = ? AND name = ?";
+                conn.query_row(sql, (db, name)).await?
+            }
+            None => {
+                let sql = "SELECT count(*) FROM system.tables WHERE database = currentDatabase() AND name = ?";
+                conn.query_row(sql, (self.table.as_str(),)).await?
+            }
+        };
+        let count = match row {
+            Some(row) => {
+                let (count,): (u64,) = row.try_into().map_err(Error::Parsing)?;
+                count
+            }
+            None => 0,
+        };
+        Ok(count > 0)
+    }
+
+    /// Apply the pending migrations in order, after checking the applied ones are
+    /// unchanged, and return them.
+    pub async fn run(&self, conn: &Connection) -> Result<Vec<Migration>> {
+        let migrations = self.load()?;
+        let applied = self.applied(conn).await?;
+        let pending = pending(migrations, &applied)?;
+        if self.dry_run {
+            return Ok(pending);
+        }
+        let sql = format!(
+            "INSERT INTO {} (version, name, checksum, success) VALUES (?, ?, ?, ?)",
+            quote_ident(&self.table)
+        );
+        for m in &pending {
+            info!("apply migration {} {}", m.version, m.name);
+            for (i, statement) in m.statements().into_iter().enumerate() {
+                if let Err(e) = conn.exec(statement, ()).await {
+                    // statements before are already applied, so the failure is recorded
+                    // for it not being applied again over them
+                    conn.exec(
+                        &sql,
+                        (m.version, m.name.as_str(), m.checksum.as_str(), false),
+                    )
+                    .await?;
+                    return Err(e.with_context(&format!(
+                        "apply migration {} {}, statement {}",
+                        m.version,
+                        m.name,
+                        i + 1
+                    )));
+                }
+            }
+            conn.exec(
+                &sql,
+                (m.version, m.name.as_str(), m.checksum.as_str(), true),
+            )
+            .await?;
+        }
+        Ok(pending)
+    }
+}
+
+/// A migration recorded in the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Applied {
+    pub checksum: String,
+    /// false if the migration failed partway
+    pub success: bool,
+}
+
+fn pending(migrations: Vec<Migration>, applied: &BTreeMap<u64, Applied>) -> Result<Vec<Migration>> {
+    for (version, Applied { checksum, success }) in applied {
+        match migrations.iter().find(|m| m.version == *version) {
+            None => {
+                return Err(Error::BadArgument(format!(
+                    "applied migration {} not found",
+                    version
+                )))
+            }
+            Some(m) if &m.checksum != checksum => {
+                return Err(Error::BadArgument(format!(
+                    "checksum mismatch for applied migration {} {}, it was changed after applied",
+                    m.version, m.name
+                )))
+            }
+            Some(m) if !success => {
+                return Err(Error::BadArgument(format!(
+                    "migration {} {} failed partway, fix the database and delete its record to run it again",
+                    m.version, m.name
+                )))
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(migrations
+        .into_iter()
+        .filter(|m| !applied.contains_key(&m.version))
+        .collect())
+}
@@ -16,6 +16,7 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 
 use databend_common_ast::parser::Dialect;
+use databend_driver_core::error::{Error, Result};
 
 pub trait Param: Debug {
     fn as_sql_string(&self) -> String;
@@ -77,18 +78,67 @@ impl Params {
         }
     }
 
-    pub fn replace(&self, sql: &str) -> String {
+    /// Params sent to server along with the query, an array for `?` and an object
+    /// for `:name`, values are kept as sql literals.
+    pub fn to_json_value(&self) -> serde_json::Value {
+        match self {
+            Params::QuestionParams(vec) => serde_json::Value::Array(
+                vec.iter()
+                    .map(|v| serde_json::Value::String(v.clone()))
+                    .collect(),
+            ),
+            Params::NamedParams(map) => serde_json::Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Replace placeholders in sql with the params, sql that fails to
+    /// tokenize is an error, and kept as is if not parsed.
+    pub fn replace(&self, sql: &str) -> Result<String> {
         if !self.is_empty() {
-            let tokens = databend_common_ast::parser::tokenize_sql(sql).unwrap();
-            if let Ok((stmt, _)) =
-                databend_common_ast::parser::parse_sql(&tokens, Dialect::PostgreSQL)
-            {
+            if let Some(stmt) = parse_statement(sql)? {
                 let mut v = super::placeholder::PlaceholderVisitor::new();
-                return v.replace_sql(self, &stmt, sql);
+                return Ok(v.replace_sql(self, &stmt, sql));
             }
         }
-        sql.to_string()
+        Ok(sql.to_string())
     }
+
+    /// Named params referenced by `:name` in sql, so that params bound for a
+    /// session are not sent along with every statement. All params are kept
+    /// if the sql is not parsed, to let the server decide.
+    pub fn used_by(&self, sql: &str) -> Result<Params> {
+        let map = match self {
+            Params::QuestionParams(vec) => return Ok(Params::QuestionParams(vec.clone())),
+            Params::NamedParams(map) => map,
+        };
+        if map.is_empty() {
+            return Ok(Params::NamedParams(HashMap::new()));
+        }
+        let Some(stmt) = parse_statement(sql)? else {
+            return Ok(Params::NamedParams(map.clone()));
+        };
+        let names = super::placeholder::PlaceholderVisitor::new().names(&stmt);
+        Ok(Params::NamedParams(
+            map.iter()
+                .filter(|(k, _)| names.contains(k))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        ))
+    }
+}
+
+fn parse_statement(sql: &str) -> Result<Option<databend_common_ast::ast::Statement>> {
+    let tokens = databend_common_ast::parser::tokenize_sql(sql)
+        .map_err(|e| Error::BadArgument(format!("failed to tokenize sql: {}", e)))?;
+    Ok(
+        databend_common_ast::parser::parse_sql(&tokens, Dialect::PostgreSQL)
+            .ok()
+            .map(|(stmt, _)| stmt),
+    )
 }
 
 // impl param for all integer types and string types
@@ -115,17 +165,22 @@ impl Param for bool {
     }
 }
 
+/// String literal with quotes and backslashes escaped.
+pub(crate) fn quote_string(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
 // Implement Param for String
 impl Param for String {
     fn as_sql_string(&self) -> String {
-        format!("'{}'", self)
+        quote_string(self)
     }
 }
 
 // Implement Param for &str
 impl Param for &str {
     fn as_sql_string(&self) -> String {
-        format!("'{}'", self)
+        quote_string(self)
     }
 }
 
@@ -133,7 +188,7 @@ impl Param for serde_json::Value {
     fn as_sql_string(&self) -> String {
         match self {
             serde_json::Value::Number(n) => n.to_string(),
-            serde_json::Value::String(s) => format!("'{}'", s),
+            serde_json::Value::String(s) => quote_string(s),
             serde_json::Value::Bool(b) => b.to_string(),
             serde_json::Value::Null => "NULL".to_string(),
             serde_json::Value::Array(values) => {
@@ -353,28 +408,43 @@ mod tests {
         let params = params! {1, "44", 2, 3, "55", "66"};
         let sql =
             "SELECT * FROM table WHERE a = ? AND '?' = cj AND b = ? AND c = ? AND d = ? AND e = ? AND f = ?";
-        let replaced_sql = params.replace(sql);
+        let replaced_sql = params.replace(sql).unwrap();
         assert_eq!(replaced_sql, "SELECT * FROM table WHERE a = 1 AND '?' = cj AND b = '44' AND c = 2 AND d = 3 AND e = '55' AND f = '66'");
 
         let params = params! {a => 1, b => "44", c => 2, d => 3, e => "55", f => "66"};
 
         {
             let sql = "SELECT * FROM table WHERE a = :a AND '?' = cj AND b = :b AND c = :c AND d = :d AND e = :e AND f = :f";
-            let replaced_sql = params.replace(sql);
+            let replaced_sql = params.replace(sql).unwrap();
             assert_eq!(replaced_sql, "SELECT * FROM table WHERE a = 1 AND '?' = cj AND b = '44' AND c = 2 AND d = 3 AND e = '55' AND f = '66'");
         }
 
         {
             let sql = "SELECT b = :b, a = :a FROM table WHERE a = :a AND '?' = cj AND b = :b AND c = :c AND d = :d AND e = :e AND f = :f";
-            let replaced_sql = params.replace(sql);
+            let replaced_sql = params.replace(sql).unwrap();
             assert_eq!(replaced_sql, "SELECT b = '44', a = 1 FROM table WHERE a = 1 AND '?' = cj AND b = '44' AND c = 2 AND d = 3 AND e = '55' AND f = '66'");
         }
 
         {
             let params = params! {1, "44", 2, 3, "55", "66"};
             let sql = "SELECT $3, $2, $1 FROM table WHERE a = $1 AND '?' = cj AND b = $2 AND c = $3 AND d = $4 AND e = $5 AND f = $6";
-            let replaced_sql = params.replace(sql);
+            let replaced_sql = params.replace(sql).unwrap();
             assert_eq!(replaced_sql, "SELECT 2, '44', 1 FROM table WHERE a = 1 AND '?' = cj AND b = '44' AND c = 2 AND d = 3 AND e = '55' AND f = '66'");
         }
+
+        assert!(params.replace("SELECT 'a").is_err());
+    }
+
+    #[test]
+    fn test_used_by() {
+        let params = params! {a => 1, b => "44"};
+        match params.used_by("SELECT * FROM t WHERE a = :a").unwrap() {
+            Params::NamedParams(map) => {
+                assert_eq!(map.len(), 1);
+                assert_eq!(map.get("a").unwrap(), "1");
+            }
+            _ => panic!("Expected NamedParams"),
+        }
+        assert!(params.used_by("SHOW TABLES").unwrap().is_empty());
     }
 }
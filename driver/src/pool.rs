@@ -0,0 +1,171 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use databend_driver_core::error::{Error, Result};
+
+use crate::client::{Client, Connection};
+
+/// A pool of at most `max_size` connections created by the client.
+/// Connections idle for a while are health checked before being handed out
+/// again, broken ones are replaced with new connections. Connections whose
+/// session is changed, by `USE`, `SET`, transactions or temporary tables,
+/// are closed instead of being put back, so the next user gets a clean session.
+#[derive(Clone)]
+pub struct ConnectionPool {
+    inner: Arc<PoolInner>,
+}
+
+/// Idle time after which a connection is checked with `SELECT 1` before reuse.
+const HEALTH_CHECK_IDLE: Duration = Duration::from_secs(30);
+
+struct IdleConnection {
+    conn: Arc<Connection>,
+    since: Instant,
+}
+
+struct PoolInner {
+    client: Client,
+    max_size: usize,
+    idle: Mutex<Vec<IdleConnection>>,
+    semaphore: Arc<Semaphore>,
+}
+
+impl ConnectionPool {
+    pub fn new(client: Client, max_size: usize) -> Result<Self> {
+        if max_size == 0 {
+            return Err(Error::BadArgument(
+                "max_size of connection pool should be greater than 0".to_string(),
+            ));
+        }
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                client,
+                max_size,
+                idle: Mutex::new(Vec::with_capacity(max_size)),
+                semaphore: Arc::new(Semaphore::new(max_size)),
+            }),
+        })
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.inner.max_size
+    }
+
+    /// Number of idle connections in the pool.
+    pub fn idle(&self) -> usize {
+        self.inner.idle.lock().unwrap().len()
+    }
+
+    /// Number of connections handed out and not released yet.
+    pub fn in_use(&self) -> usize {
+        self.inner.max_size - self.inner.semaphore.available_permits()
+    }
+
+    /// Wait for a free slot and take an idle connection or create a new one,
+    /// the connection goes back to the pool when the returned guard is dropped.
+    pub async fn get(&self) -> Result<PooledConnection> {
+        let permit = self
+            .inner
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::IO("connection pool is closed".to_string()))?;
+        let conn = loop {
+            let idle = self.inner.idle.lock().unwrap().pop();
+            match idle {
+                Some(idle) if idle.since.elapsed() < HEALTH_CHECK_IDLE => break idle.conn,
+                Some(idle) if is_healthy(&idle.conn).await => break idle.conn,
+                Some(idle) => {
+                    warn!("drop broken connection from pool");
+                    let _ = idle.conn.close().await;
+                }
+                None => break Arc::new(self.inner.client.get_conn().await?),
+            }
+        };
+        Ok(PooledConnection {
+            conn: Some(conn),
+            pool: self.inner.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Like `get`, but fails if no connection is released within `timeout`.
+    pub async fn get_timeout(&self, timeout: Duration) -> Result<PooledConnection> {
+        tokio::time::timeout(timeout, self.get())
+            .await
+            .map_err(|_| {
+                Error::IO(format!(
+                    "timed out waiting for a connection from pool after {:?}",
+                    timeout
+                ))
+            })?
+    }
+}
+
+async fn is_healthy(conn: &Connection) -> bool {
+    conn.exec("SELECT 1", ()).await.is_ok()
+}
+
+/// A connection taken from the pool, released back when dropped.
+pub struct PooledConnection {
+    conn: Option<Arc<Connection>>,
+    pool: Arc<PoolInner>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledConnection {
+    /// Shared handle of the connection, for bindings holding connections in `Arc`.
+    /// It should not be used after the pooled connection is released.
+    pub fn shared(&self) -> Arc<Connection> {
+        self.conn.clone().expect("connection is not released")
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn.as_ref().expect("connection is not released")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else {
+            return;
+        };
+        // still shared means a query may be running on it, e.g. by bindings
+        if !conn.session_changed() && Arc::strong_count(&conn) == 1 {
+            self.pool.idle.lock().unwrap().push(IdleConnection {
+                conn,
+                since: Instant::now(),
+            });
+            return;
+        }
+        debug!("close connection with session changed instead of returning to pool");
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                let _ = conn.close().await;
+            });
+        }
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal builders of common statements, values are bound as `?` params.
+//!
+//! ```
+//! use databend_driver::query_builder::{Insert, Select};
+//!
+//! let (sql, params) = Insert::into("books")
+//!     .columns(&["title", "year"])
+//!     .values(("Rust", 2024))
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(sql, "INSERT INTO books (title, year) VALUES (?, ?)");
+//! assert_eq!(params.len(), 2);
+//!
+//! let (sql, _) = Select::from("books")
+//!     .columns(&["title"])
+//!     .filter("year >= ?", (2020,))
+//!     .order_by("year DESC")
+//!     .limit(10)
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(sql, "SELECT title FROM books WHERE (year >= ?) ORDER BY year DESC LIMIT 10");
+//! ```
+
+use databend_driver_core::error::{Error, Result};
+
+use crate::Params;
+
+// reserved keywords that could not be used as unquoted names
+const RESERVED: &[&str] = &[
+    "ALL",
+    "AND",
+    "ANY",
+    "ARRAY",
+    "AS",
+    "ASC",
+    "BETWEEN",
+    "BY",
+    "CASE",
+    "CAST",
+    "CREATE",
+    "CROSS",
+    "DESC",
+    "DISTINCT",
+    "DROP",
+    "ELSE",
+    "END",
+    "EXCEPT",
+    "EXISTS",
+    "FALSE",
+    "FOR",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "IN",
+    "INNER",
+    "INSERT",
+    "INTERSECT",
+    "INTERVAL",
+    "INTO",
+    "IS",
+    "JOIN",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "NOT",
+    "NULL",
+    "OFFSET",
+    "ON",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "RIGHT",
+    "SELECT",
+    "SET",
+    "SOME",
+    "TABLE",
+    "THEN",
+    "TO",
+    "TRUE",
+    "UNION",
+    "UPDATE",
+    "USING",
+    "VALUES",
+    "WHEN",
+    "WHERE",
+    "WITH",
+];
+
+/// Quote identifiers that need it, each part of `db.table`: names with special characters,
+/// a leading digit, or reserved keywords. Other names are left unquoted and folded to
+/// lowercase by the server, parts already in backquotes are kept as is to match case.
+pub fn quote_ident(name: &str) -> String {
+    name.split('.')
+        .map(|part| {
+            if part.len() >= 2 && part.starts_with('`') && part.ends_with('`') {
+                part.to_string()
+            } else {
+                quote_part(part, false)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Quote a name to be matched exactly, such as a field of a result schema,
+/// names with uppercase letters are quoted too for not being folded.
+pub(crate) fn quote_exact(name: &str) -> String {
+    quote_part(name, true)
+}
+
+fn quote_part(part: &str, exact: bool) -> String {
+    let plain = part
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !(exact && part.chars().any(|c| c.is_ascii_uppercase()))
+        && !RESERVED.contains(&part.to_ascii_uppercase().as_str());
+    if plain {
+        part.to_string()
+    } else {
+        format!("`{}`", part.replace('`', "``"))
+    }
+}
+
+fn question_params(params: Params, values: &mut Vec<String>) -> Result<()> {
+    match params {
+        Params::QuestionParams(v) => {
+            values.extend(v);
+            Ok(())
+        }
+        Params::NamedParams(_) => Err(Error::BadArgument(
+            "named params are not supported by query builder".to_string(),
+        )),
+    }
+}
+
+#[derive(Debug)]
+pub struct Insert {
+    table: String,
+    columns: Vec<String>,
+    rows: Vec<Params>,
+}
+
+impl Insert {
+    pub fn into(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            columns: vec![],
+            rows: vec![],
+        }
+    }
+
+    pub fn columns(mut self, columns: &[&str]) -> Self {
+        self.columns = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Add a row, call again for more rows.
+    pub fn values<P: Into<Params>>(mut self, values: P) -> Self {
+        self.rows.push(values.into());
+        self
+    }
+
+    pub fn build(self) -> Result<(String, Params)> {
+        if self.rows.is_empty() {
+            return Err(Error::BadArgument(format!(
+                "no values to insert into {}",
+                self.table
+            )));
+        }
+        let mut sql = format!("INSERT INTO {}", quote_ident(&self.table));
+        if !self.columns.is_empty() {
+            let columns = self
+                .columns
+                .iter()
+                .map(|c| quote_ident(c))
+                .collect::<Vec<_>>();
+            sql.push_str(&format!(" ({})", columns.join(", ")));
+        }
+        let width = self.rows[0].len();
+        let mut values = vec![];
+        let mut tuples = vec![];
+        for (i, row) in self.rows.into_iter().enumerate() {
+            let expected = match self.columns.len() {
+                0 => width,
+                n => n,
+            };
+            if row.len() != expected || expected == 0 {
+                return Err(Error::BadArgument(format!(
+                    "row {} has {} values, expect {}",
+                    i,
+                    row.len(),
+                    expected
+                )));
+            }
+            tuples.push(format!("({})", vec!["?"; expected].join(", ")));
+            question_params(row, &mut values)?;
+        }
+        sql.push_str(&format!(" VALUES {}", tuples.join(", ")));
+        Ok((sql, Params::QuestionParams(values)))
+    }
+}
+
+#[derive(Debug)]
+pub struct Select {
+    table: String,
+    columns: Vec<String>,
+    filters: Vec<(String, Params)>,
+    order_by: Vec<String>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+impl Select {
+    pub fn from(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            columns: vec![],
+            filters: vec![],
+            order_by: vec![],
+            limit: None,
+            offset: None,
+        }
+    }
+
+    /// Columns to select, default to `*`.
+    pub fn columns(mut self, columns: &[&str]) -> Self {
+        self.columns = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Add a condition with `?` for the params, conditions are joined with `AND`.
+    pub fn filter<P: Into<Params>>(mut self, condition: &str, params: P) -> Self {
+        self.filters.push((condition.to_string(), params.into()));
+        self
+    }
+
+    /// Add an order like `year DESC`, which is kept as is.
+    pub fn order_by(mut self, order: &str) -> Self {
+        self.order_by.push(order.to_string());
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn build(self) -> Result<(String, Params)> {
+        let columns = match self.columns.is_empty() {
+            true => "*".to_string(),
+            false => self
+                .columns
+                .iter()
+                .map(|c| quote_ident(c))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        let mut sql = format!("SELECT {} FROM {}", columns, quote_ident(&self.table));
+        let mut values = vec![];
+        let mut conditions = vec![];
+        for (condition, params) in self.filters {
+            let placeholders = condition.matches('?').count();
+            if placeholders != params.len() {
+                return Err(Error::BadArgument(format!(
+                    "filter `{}` has {} placeholders, got {} params",
+                    condition,
+                    placeholders,
+                    params.len()
+                )));
+            }
+            conditions.push(format!("({})", condition));
+            question_params(params, &mut values)?;
+        }
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        if !self.order_by.is_empty() {
+            sql.push_str(&format!(" ORDER BY {}", self.order_by.join(", ")));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        if let Some(offset) = self.offset {
+            sql.push_str(&format!(" OFFSET {}", offset));
+        }
+        Ok((sql, Params::QuestionParams(values)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_statements() -> Result<()> {
+        let (sql, params) = Insert::into("db.`Books`")
+            .columns(&["title", "year"])
+            .values(("it's", 2024))
+            .values(("b", 2025))
+            .build()?;
+        assert_eq!(
+            sql,
+            "INSERT INTO db.`Books` (title, year) VALUES (?, ?), (?, ?)"
+        );
+        assert_eq!(params.get_by_index(1).map(|s| s.as_str()), Some("'it\\'s'"));
+        assert_eq!(params.len(), 4);
+        assert!(Insert::into("t")
+            .columns(&["a"])
+            .values((1, 2))
+            .build()
+            .is_err());
+        assert!(Insert::into("t").build().is_err());
+
+        let (sql, params) = Select::from("t")
+            .filter("a > ? AND b < ?", (1, 2))
+            .filter("c = ?", ("x",))
+            .offset(5)
+            .build()?;
+        assert_eq!(
+            sql,
+            "SELECT * FROM t WHERE (a > ? AND b < ?) AND (c = ?) OFFSET 5"
+        );
+        assert_eq!(params.len(), 3);
+        assert!(Select::from("t").filter("a = ?", ()).build().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn quote_names() {
+        assert_eq!(quote_ident("Books"), "Books");
+        assert_eq!(quote_ident("db.Books"), "db.Books");
+        assert_eq!(quote_ident("db.`Books`"), "db.`Books`");
+        assert_eq!(quote_ident("my table"), "`my table`");
+        assert_eq!(quote_ident("1st"), "`1st`");
+        assert_eq!(quote_ident("order"), "`order`");
+        assert_eq!(quote_ident("a`b"), "`a``b`");
+        assert_eq!(quote_exact("Name"), "`Name`");
+        assert_eq!(quote_exact("name"), "name");
+    }
+}
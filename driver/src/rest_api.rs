@@ -21,20 +21,29 @@ use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use async_trait::async_trait;
-use log::info;
+use log::{info, warn};
 use tokio::fs::File;
 use tokio::io::BufReader;
 use tokio_stream::Stream;
 
 use databend_client::PresignedResponse;
 use databend_client::QueryResponse;
-use databend_client::{APIClient, SchemaField};
+use databend_client::QuerySlot;
+use databend_client::StartedQuery;
+use databend_client::{APIClient, APIClientBuilder, Capabilities, SchemaField};
 use databend_driver_core::error::{Error, Result};
-use databend_driver_core::raw_rows::{RawRow, RawRowIterator, RawRowWithStats};
-use databend_driver_core::rows::{Row, RowIterator, RowStatsIterator, RowWithStats, ServerStats};
+use databend_driver_core::raw_rows::{
+    RawRow, RawRowIterator, RawRowWithStats, StringRowIterator, StringRowWithStats,
+};
+use databend_driver_core::rows::{
+    RejectedFile, Row, RowIterator, RowStatsIterator, RowWithStats, ServerStats,
+};
 use databend_driver_core::schema::{Schema, SchemaRef};
 
-use crate::conn::{ConnectionInfo, IConnection, Reader};
+use crate::conn::{ConnectionInfo, IConnection, QueryId, Reader};
+use crate::params::Params;
+
+/// Error code of the server failing to parse sql.
 
 #[derive(Clone)]
 pub struct RestAPIConnection {
@@ -51,6 +60,11 @@ impl IConnection for RestAPIConnection {
             user: self.client.username(),
             database: self.client.current_database(),
             warehouse: self.client.current_warehouse(),
+            role: self.client.current_role().await,
+            tenant: self.client.tenant().map(|t| t.to_string()),
+            tls: self.client.scheme() == "https",
+            settings: self.client.session_settings(),
+            server_version: self.client.server_version().map(|v| v.to_string()),
         }
     }
 
@@ -58,25 +72,37 @@ impl IConnection for RestAPIConnection {
         self.client.last_query_id()
     }
 
+    fn capabilities(&self) -> Capabilities {
+        self.client.capabilities()
+    }
+
+    async fn current_database(&self) -> Option<String> {
+        self.client.current_database()
+    }
+
+    async fn current_warehouse(&self) -> Option<String> {
+        self.client.current_warehouse()
+    }
+
+    async fn current_role(&self) -> Option<String> {
+        self.client.current_role().await
+    }
+
     async fn close(&self) -> Result<()> {
         self.client.close().await;
         Ok(())
     }
 
     async fn exec(&self, sql: &str) -> Result<i64> {
-        info!("exec: {}", sql);
-        let mut resp = self.client.start_query(sql).await?;
-        let node_id = resp.node_id.clone();
-        if let Some(node_id) = &node_id {
-            self.client.set_last_node_id(node_id.clone());
-        }
-        while let Some(next_uri) = resp.next_uri {
-            resp = self
-                .client
-                .query_page(&resp.id, &next_uri, &node_id)
-                .await?;
-        }
-        Ok(resp.stats.progresses.write_progress.rows as i64)
+        info!("exec: {}", self.client.log_sql(sql));
+        let started = self.client.start_query(sql).await?;
+        self.wait_for_finish(started).await
+    }
+
+    async fn exec_with_params(&self, sql: &str, params: Params) -> Result<i64> {
+        info!("exec with params: {}", self.client.log_sql(sql));
+        let started = self.start_query_with_params(sql, &params).await?;
+        self.wait_for_finish(started).await
     }
 
     async fn kill_query(&self, query_id: &str) -> Result<()> {
@@ -84,30 +110,71 @@ impl IConnection for RestAPIConnection {
     }
 
     async fn query_iter(&self, sql: &str) -> Result<RowIterator> {
-        info!("query iter: {}", sql);
+        info!("query iter: {}", self.client.log_sql(sql));
         let rows_with_progress = self.query_iter_ext(sql).await?;
         let rows = rows_with_progress.filter_rows().await;
         Ok(rows)
     }
 
+    async fn query_iter_with_params(&self, sql: &str, params: Params) -> Result<RowIterator> {
+        info!("query iter with params: {}", self.client.log_sql(sql));
+        let rows_with_progress = self.query_iter_ext_with_params(sql, params).await?;
+        let rows = rows_with_progress.filter_rows().await;
+        Ok(rows)
+    }
+
     async fn query_iter_ext(&self, sql: &str) -> Result<RowStatsIterator> {
-        info!("query iter ext: {}", sql);
-        let resp = self.client.start_query(sql).await?;
-        let resp = self.wait_for_schema(resp, true).await?;
-        let (schema, rows) = RestAPIRows::<RowWithStats>::from_response(self.client.clone(), resp)?;
-        Ok(RowStatsIterator::new(Arc::new(schema), Box::pin(rows)))
+        info!("query iter ext: {}", self.client.log_sql(sql));
+        let started = self.client.start_query(sql).await?;
+        self.rows_from_response(started).await
+    }
+
+    async fn query_iter_ext_with_params(
+        &self,
+        sql: &str,
+        params: Params,
+    ) -> Result<RowStatsIterator> {
+        info!("query iter ext with params: {}", self.client.log_sql(sql));
+        let started = self.start_query_with_params(sql, &params).await?;
+        self.rows_from_response(started).await
+    }
+
+    async fn submit(&self, sql: &str) -> Result<QueryId> {
+        info!("submit: {}", self.client.log_sql(sql));
+        let resp = self.client.start_query(sql).await?.resp;
+        self.client.detach_query(&resp.id);
+        Ok(QueryId::from_response(resp))
+    }
+
+    async fn fetch(&self, query_id: QueryId) -> Result<RowIterator> {
+        info!("fetch: {}", query_id);
+        let started = StartedQuery {
+            resp: query_id.into_response(),
+            slot: None,
+        };
+        let rows = self.rows_from_response(started).await?;
+        Ok(rows.filter_rows().await)
     }
 
     // raw data response query, only for test
     async fn query_raw_iter(&self, sql: &str) -> Result<RawRowIterator> {
-        info!("query raw iter: {}", sql);
-        let resp = self.client.start_query(sql).await?;
-        let resp = self.wait_for_schema(resp, true).await?;
+        info!("query raw iter: {}", self.client.log_sql(sql));
+        let started = self.client.start_query(sql).await?;
+        let started = self.wait_for_schema(started, true).await?;
         let (schema, rows) =
-            RestAPIRows::<RawRowWithStats>::from_response(self.client.clone(), resp)?;
+            RestAPIRows::<RawRowWithStats>::from_response(self.client.clone(), started)?;
         Ok(RawRowIterator::new(Arc::new(schema), Box::pin(rows)))
     }
 
+    async fn query_iter_raw(&self, sql: &str) -> Result<StringRowIterator> {
+        info!("query iter raw: {}", self.client.log_sql(sql));
+        let started = self.client.start_query(sql).await?;
+        let started = self.wait_for_schema(started, true).await?;
+        let (schema, rows) =
+            RestAPIRows::<StringRowWithStats>::from_response(self.client.clone(), started)?;
+        Ok(StringRowIterator::new(Arc::new(schema), Box::pin(rows)))
+    }
+
     async fn get_presigned_url(&self, operation: &str, stage: &str) -> Result<PresignedResponse> {
         info!("get presigned url: {} {}", operation, stage);
         let sql = format!("PRESIGN {} {}", operation, stage);
@@ -129,6 +196,18 @@ impl IConnection for RestAPIConnection {
         Ok(())
     }
 
+    async fn download_presigned(
+        &self,
+        presigned: PresignedResponse,
+        local_file: &Path,
+        size: u64,
+    ) -> Result<u64> {
+        Ok(self
+            .client
+            .download_presigned_ranged(presigned, local_file, size)
+            .await?)
+    }
+
     async fn load_data(
         &self,
         sql: &str,
@@ -139,23 +218,69 @@ impl IConnection for RestAPIConnection {
     ) -> Result<ServerStats> {
         info!(
             "load data: {}, size: {}, format: {:?}, copy: {:?}",
-            sql, size, file_format_options, copy_options
+            self.client.log_sql(sql),
+            size,
+            file_format_options,
+            copy_options
         );
         let now = chrono::Utc::now()
             .timestamp_nanos_opt()
             .ok_or_else(|| Error::IO("Failed to get current timestamp".to_string()))?;
         let stage = format!("@~/client/load/{}", now);
 
-        let file_format_options =
+        let mut file_format_options =
             file_format_options.unwrap_or_else(Self::default_file_format_options);
-        let copy_options = copy_options.unwrap_or_else(Self::default_copy_options);
+        let mut copy_options = copy_options.unwrap_or_default();
+        for (k, v) in Self::default_copy_options() {
+            copy_options.entry(k).or_insert(v);
+        }
+        let purge = copy_options
+            .get("purge")
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"));
+        let on_error = on_error_option(&copy_options)?;
+        if let Some(on_error) = &on_error {
+            copy_options.remove("max_errors");
+            copy_options.insert("on_error", on_error);
+        }
 
-        self.upload_to_stage(&stage, data, size).await?;
-        let resp = self
-            .client
-            .insert_with_stage(sql, &stage, file_format_options, copy_options)
-            .await?;
-        Ok(ServerStats::from(resp.stats))
+        // compress on client side only text data not compressed yet,
+        // columnar formats like parquet and orc are compressed already
+        let compression = self.client.load_compression().filter(|_| {
+            let text = file_format_options.get("type").is_some_and(|t| {
+                ["csv", "tsv", "ndjson"]
+                    .iter()
+                    .any(|f| t.eq_ignore_ascii_case(f))
+            });
+            text && file_format_options
+                .get("compression")
+                .is_none_or(|c| c.eq_ignore_ascii_case("none"))
+        });
+        if let Some(compression) = compression {
+            file_format_options.insert("compression", compression.file_format());
+        }
+        let result = async {
+            match compression {
+                Some(_) => self.client.upload_to_stage_compressed(&stage, data).await?,
+                None => self.client.upload_to_stage(&stage, data, size).await?,
+            }
+            self.client
+                .insert_with_stage(sql, &stage, file_format_options, copy_options)
+                .await
+        }
+        .await;
+        // files are purged only after loaded without errors, remove the stage
+        // on failure, with rejected rows, or without purge
+        let rejected_files = result.as_ref().map(rejected_files).unwrap_or_default();
+        if !purge || result.is_err() || !rejected_files.is_empty() {
+            if let Err(err) = self.client.query(&format!("REMOVE {}", stage)).await {
+                warn!("failed to remove stage {} after load: {}", stage, err);
+            }
+        }
+        let resp = result?;
+        let mut stats = ServerStats::from(resp.stats);
+        stats.rejected_rows = rejected_files.iter().map(|f| f.errors_seen).sum();
+        stats.rejected_files = rejected_files;
+        Ok(stats)
     }
 
     async fn load_file(
@@ -167,7 +292,10 @@ impl IConnection for RestAPIConnection {
     ) -> Result<ServerStats> {
         info!(
             "load file: {}, file: {:?}, format: {:?}, copy: {:?}",
-            sql, fp, format_options, copy_options
+            self.client.log_sql(sql),
+            fp,
+            format_options,
+            copy_options
         );
         let file = File::open(fp).await?;
         let metadata = file.metadata().await?;
@@ -193,7 +321,11 @@ impl IConnection for RestAPIConnection {
     }
 
     async fn stream_load(&self, sql: &str, data: Vec<Vec<&str>>) -> Result<ServerStats> {
-        info!("stream load: {}, length: {:?}", sql, data.len());
+        info!(
+            "stream load: {}, length: {:?}",
+            self.client.log_sql(sql),
+            data.len()
+        );
         let mut wtr = csv::WriterBuilder::new().from_writer(vec![]);
         for row in data {
             wtr.write_record(row)
@@ -215,11 +347,19 @@ impl<'o> RestAPIConnection {
         })
     }
 
+    pub async fn try_create_with_options(options: APIClientBuilder) -> Result<Self> {
+        let client = options.build().await?;
+        Ok(Self {
+            client: Arc::new(client),
+        })
+    }
+
     async fn wait_for_schema(
         &self,
-        resp: QueryResponse,
+        started: StartedQuery,
         return_on_progress: bool,
-    ) -> Result<QueryResponse> {
+    ) -> Result<StartedQuery> {
+        let resp = &started.resp;
         let node_id = resp.node_id.clone();
         if let Some(node_id) = &node_id {
             self.client.set_last_node_id(node_id.clone());
@@ -228,9 +368,12 @@ impl<'o> RestAPIConnection {
             || !resp.schema.is_empty()
             || (return_on_progress && resp.stats.progresses.has_progress())
         {
-            return Ok(resp);
+            return Ok(started);
         }
-        let mut result = resp;
+        let StartedQuery {
+            resp: mut result,
+            slot,
+        } = started;
         // preserve schema since it is not included in the final response
         while let Some(next_uri) = result.next_uri {
             result = self
@@ -245,7 +388,8 @@ impl<'o> RestAPIConnection {
                 break;
             }
         }
-        Ok(result)
+        let slot = slot.filter(|_| result.next_uri.is_some());
+        Ok(StartedQuery { resp: result, slot })
     }
 
     fn default_file_format_options() -> BTreeMap<&'o str, &'o str> {
@@ -263,13 +407,131 @@ impl<'o> RestAPIConnection {
         vec![("purge", "true")].into_iter().collect()
     }
 
+    async fn wait_for_finish(&self, started: StartedQuery) -> Result<i64> {
+        let StartedQuery {
+            mut resp,
+            slot: _slot,
+        } = started;
+        let node_id = resp.node_id.clone();
+        if let Some(node_id) = &node_id {
+            self.client.set_last_node_id(node_id.clone());
+        }
+        while let Some(next_uri) = resp.next_uri {
+            resp = self
+                .client
+                .query_page(&resp.id, &next_uri, &node_id)
+                .await?;
+        }
+        Ok(resp.stats.progresses.write_progress.rows as i64)
+    }
+
+    async fn rows_from_response(&self, started: StartedQuery) -> Result<RowStatsIterator> {
+        let started = self.wait_for_schema(started, true).await?;
+        let (schema, rows) =
+            RestAPIRows::<RowWithStats>::from_response(self.client.clone(), started)?;
+        Ok(RowStatsIterator::new(Arc::new(schema), Box::pin(rows)))
+    }
+
+    /// Send params to server so that values never appear in the sql text,
+    /// or replace them in sql if the server does not support query params.
+    async fn start_query_with_params(&self, sql: &str, params: &Params) -> Result<StartedQuery> {
+        if params.is_empty() {
+            return Ok(self.client.start_query(sql).await?);
+        }
+        if self.client.query_params_enabled() {
+            return Ok(self
+                .client
+                .start_query_with_params(sql, params.to_json_value())
+                .await?);
+        }
+        Ok(self.client.start_query(&params.replace(sql)?).await?)
+    }
+
     pub async fn query_row_batch(&self, sql: &str) -> Result<RowBatch> {
-        let resp = self.client.start_query(sql).await?;
-        let resp = self.wait_for_schema(resp, false).await?;
-        RowBatch::from_response(self.client.clone(), resp)
+        let started = self.client.start_query(sql).await?;
+        let started = self.wait_for_schema(started, false).await?;
+        RowBatch::from_response(self.client.clone(), started)
+    }
+}
+
+/// Validate a value of the `on_error` copy option, one of continue, abort or abort_N.
+pub fn parse_on_error(value: &str) -> Result<String> {
+    let value = value.to_lowercase();
+    let valid = match value.strip_prefix("abort_") {
+        Some(n) => n.parse::<u64>().is_ok_and(|n| n > 0),
+        None => value == "continue" || value == "abort",
+    };
+    if !valid {
+        return Err(Error::BadArgument(format!(
+            "Invalid value for on_error: {}, expected continue, abort or abort_N",
+            value
+        )));
+    }
+    Ok(value)
+}
+
+/// Validate `on_error` and fold `max_errors` into it as `abort_N`.
+fn on_error_option(copy_options: &BTreeMap<&str, &str>) -> Result<Option<String>> {
+    let on_error = copy_options
+        .get("on_error")
+        .map(|v| parse_on_error(v))
+        .transpose()?;
+    match copy_options.get("max_errors") {
+        None => Ok(on_error),
+        Some(v) => {
+            let n = v.parse::<u64>().ok().filter(|n| *n > 0).ok_or_else(|| {
+                Error::BadArgument(format!("Invalid value for max_errors: {}", v))
+            })?;
+            match on_error.as_deref() {
+                None | Some("abort") => Ok(Some(format!("abort_{}", n))),
+                Some(other) => Err(Error::BadArgument(format!(
+                    "max_errors can not be used with on_error={}",
+                    other
+                ))),
+            }
+        }
     }
 }
 
+/// Collect files with rejected rows from the result of a load,
+/// which has the same columns as `COPY INTO`:
+/// File, Rows_loaded, Errors_seen, First_error, First_error_line
+fn rejected_files(resp: &QueryResponse) -> Vec<RejectedFile> {
+    let column = |name: &str| {
+        resp.schema
+            .iter()
+            .position(|f| f.name.eq_ignore_ascii_case(name))
+    };
+    let (Some(file), Some(errors_seen)) = (column("file"), column("errors_seen")) else {
+        return vec![];
+    };
+    let rows_loaded = column("rows_loaded");
+    let first_error = column("first_error");
+    let first_error_line = column("first_error_line");
+    let get = |row: &[Option<String>], idx: Option<usize>| -> Option<String> {
+        idx.and_then(|i| row.get(i).cloned().flatten())
+    };
+    let get_usize = |row: &[Option<String>], idx: Option<usize>| -> Option<usize> {
+        get(row, idx).and_then(|v| v.parse().ok())
+    };
+    resp.data
+        .iter()
+        .filter_map(|row| {
+            let errors_seen = get_usize(row, Some(errors_seen)).unwrap_or_default();
+            if errors_seen == 0 {
+                return None;
+            }
+            Some(RejectedFile {
+                file: get(row, Some(file)).unwrap_or_default(),
+                rows_loaded: get_usize(row, rows_loaded).unwrap_or_default(),
+                errors_seen,
+                first_error: get(row, first_error),
+                first_error_line: get_usize(row, first_error_line),
+            })
+        })
+        .collect()
+}
+
 type PageFut = Pin<Box<dyn Future<Output = Result<QueryResponse>> + Send>>;
 
 pub struct RestAPIRows<T> {
@@ -280,28 +542,55 @@ pub struct RestAPIRows<T> {
     query_id: String,
     node_id: Option<String>,
     next_uri: Option<String>,
+    // acknowledged on drop if not fetched as the last page
+    final_uri: Option<String>,
     next_page: Option<PageFut>,
+    // released once the last page is fetched or the rows are dropped
+    slot: Option<QuerySlot>,
     _phantom: std::marker::PhantomData<T>,
 }
 
 impl<T> RestAPIRows<T> {
-    fn from_response(client: Arc<APIClient>, resp: QueryResponse) -> Result<(Schema, Self)> {
+    fn from_response(client: Arc<APIClient>, started: StartedQuery) -> Result<(Schema, Self)> {
+        let StartedQuery { resp, slot } = started;
         let schema: Schema = resp.schema.try_into()?;
         let rows = Self {
             client,
             query_id: resp.id,
             node_id: resp.node_id,
             next_uri: resp.next_uri,
+            final_uri: resp.final_uri,
             schema: Arc::new(schema.clone()),
             data: resp.data.into(),
             stats: Some(ServerStats::from(resp.stats)),
             next_page: None,
+            slot,
             _phantom: PhantomData,
         };
         Ok((schema, rows))
     }
 }
 
+impl<T> Drop for RestAPIRows<T> {
+    fn drop(&mut self) {
+        let final_uri = match self.final_uri.take() {
+            Some(final_uri) => final_uri,
+            None => return,
+        };
+        // best effort, the server would release the query after timeout anyway
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let client = self.client.clone();
+            let query_id = std::mem::take(&mut self.query_id);
+            let node_id = self.node_id.take();
+            handle.spawn(async move {
+                if let Err(e) = client.finalize_query(&query_id, &final_uri, &node_id).await {
+                    warn!("failed to finalize query {}: {}", query_id, e);
+                }
+            });
+        }
+    }
+}
+
 impl<T: FromRowStats + std::marker::Unpin> Stream for RestAPIRows<T> {
     type Item = Result<T>;
 
@@ -324,6 +613,12 @@ impl<T: FromRowStats + std::marker::Unpin> Stream for RestAPIRows<T> {
                         self.schema = Arc::new(resp.schema.try_into()?);
                     }
                     self.next_uri = resp.next_uri;
+                    if self.next_uri.is_none() {
+                        self.slot = None;
+                    }
+                    if resp.final_uri.is_some() {
+                        self.final_uri = resp.final_uri;
+                    }
                     self.next_page = None;
                     let mut new_data = resp.data.into();
                     self.data.append(&mut new_data);
@@ -331,6 +626,7 @@ impl<T: FromRowStats + std::marker::Unpin> Stream for RestAPIRows<T> {
                 }
                 Poll::Ready(Err(e)) => {
                     self.next_page = None;
+                    self.slot = None;
                     Poll::Ready(Some(Err(e)))
                 }
                 Poll::Pending => Poll::Pending,
@@ -341,6 +637,10 @@ impl<T: FromRowStats + std::marker::Unpin> Stream for RestAPIRows<T> {
                     let next_uri = next_uri.clone();
                     let query_id = self.query_id.clone();
                     let node_id = self.node_id.clone();
+                    // the last page is usually the final uri itself
+                    if self.final_uri.as_ref() == Some(&next_uri) {
+                        self.final_uri = None;
+                    }
                     self.next_page = Some(Box::pin(async move {
                         client
                             .query_page(&query_id, &next_uri, &node_id)
@@ -387,6 +687,16 @@ impl FromRowStats for RawRowWithStats {
     }
 }
 
+impl FromRowStats for StringRowWithStats {
+    fn from_stats(stats: ServerStats) -> Self {
+        StringRowWithStats::Stats(stats)
+    }
+
+    fn try_from_row(row: Vec<Option<String>>, _schema: SchemaRef) -> Result<Self> {
+        Ok(StringRowWithStats::Row(row))
+    }
+}
+
 pub struct RowBatch {
     schema: Vec<SchemaField>,
     client: Arc<APIClient>,
@@ -395,6 +705,7 @@ pub struct RowBatch {
 
     next_uri: Option<String>,
     data: Vec<Vec<Option<String>>>,
+    slot: Option<QuerySlot>,
 }
 
 impl RowBatch {
@@ -402,7 +713,8 @@ impl RowBatch {
         self.schema.clone()
     }
 
-    fn from_response(client: Arc<APIClient>, mut resp: QueryResponse) -> Result<Self> {
+    fn from_response(client: Arc<APIClient>, started: StartedQuery) -> Result<Self> {
+        let StartedQuery { mut resp, slot } = started;
         Ok(Self {
             schema: std::mem::take(&mut resp.schema),
             client,
@@ -410,6 +722,7 @@ impl RowBatch {
             node_id: resp.node_id,
             next_uri: resp.next_uri,
             data: resp.data,
+            slot,
         })
     }
 
@@ -424,6 +737,9 @@ impl RowBatch {
                 .await?;
 
             self.next_uri = resp.next_uri;
+            if self.next_uri.is_none() {
+                self.slot = None;
+            }
             if !resp.data.is_empty() {
                 return Ok(resp.data);
             }
@@ -431,3 +747,37 @@ impl RowBatch {
         Ok(vec![])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_on_error_option() {
+        let opts =
+            |v: &[(&'static str, &'static str)]| v.iter().cloned().collect::<BTreeMap<_, _>>();
+
+        assert_eq!(on_error_option(&opts(&[])).unwrap(), None);
+        assert_eq!(
+            on_error_option(&opts(&[("on_error", "CONTINUE")])).unwrap(),
+            Some("continue".to_string())
+        );
+        assert_eq!(
+            on_error_option(&opts(&[("on_error", "abort_10")])).unwrap(),
+            Some("abort_10".to_string())
+        );
+        assert_eq!(
+            on_error_option(&opts(&[("max_errors", "5")])).unwrap(),
+            Some("abort_5".to_string())
+        );
+        assert_eq!(
+            on_error_option(&opts(&[("on_error", "abort"), ("max_errors", "5")])).unwrap(),
+            Some("abort_5".to_string())
+        );
+        assert!(on_error_option(&opts(&[("on_error", "abort_0")])).is_err());
+        assert!(on_error_option(&opts(&[("on_error", "skip")])).is_err());
+        assert!(parse_on_error("continue; DROP TABLE t").is_err());
+        assert!(on_error_option(&opts(&[("max_errors", "x")])).is_err());
+        assert!(on_error_option(&opts(&[("on_error", "continue"), ("max_errors", "5")])).is_err());
+    }
+}
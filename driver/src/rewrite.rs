@@ -0,0 +1,26 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+
+/// Rewrites the sql of statements before they are sent, set with
+/// [`crate::Client::with_query_rewriter`], such as to add a `LIMIT` in development,
+/// add comments for auditing, or qualify tables with another database.
+///
+/// Applied to the sql passed to the methods of [`crate::Connection`] before
+/// parameters are bound, but not to the statements made by the driver itself,
+/// like those of `put_files` or `list_tables`.
+pub trait QueryRewriter: Send + Sync {
+    fn rewrite<'a>(&self, sql: &'a str) -> Cow<'a, str>;
+}
@@ -0,0 +1,93 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use databend_driver_core::error::{Error, Result};
+use databend_driver_core::schema::Schema;
+use log::warn;
+
+use crate::conn::IConnection;
+use crate::query_builder::quote_exact;
+use crate::Connection;
+
+/// Temporary table created by [`Connection::create_temp_table`], dropped
+/// in background when the handle is dropped, use [`TempTable::close`] to
+/// drop it in place and check errors.
+pub struct TempTable {
+    name: String,
+    conn: Option<Arc<dyn IConnection>>,
+}
+
+impl TempTable {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub async fn close(mut self) -> Result<()> {
+        match self.conn.take() {
+            Some(conn) => conn.exec(&drop_sql(&self.name)).await.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TempTable {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else {
+            return;
+        };
+        // temporary tables are gone with the session anyway,
+        // so it is fine to skip without a runtime
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let name = self.name.clone();
+            handle.spawn(async move {
+                if let Err(e) = conn.exec(&drop_sql(&name)).await {
+                    warn!("failed to drop temporary table {}: {}", name, e);
+                }
+            });
+        }
+    }
+}
+
+fn drop_sql(name: &str) -> String {
+    format!("DROP TABLE IF EXISTS {}", name)
+}
+
+impl Connection {
+    /// Create a temporary table with a unique name in the session, with columns of the schema.
+    pub async fn create_temp_table(&self, schema: &Schema) -> Result<TempTable> {
+        if schema.fields().is_empty() {
+            return Err(Error::BadArgument(
+                "temporary table needs at least one column".to_string(),
+            ));
+        }
+        let name = format!("_tmp_{}", uuid::Uuid::new_v4().simple());
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|f| format!("{} {}", quote_exact(&f.name), f.data_type))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.exec(
+            &format!("CREATE TEMPORARY TABLE {} ({})", name, columns),
+            (),
+        )
+        .await?;
+        Ok(TempTable {
+            name,
+            conn: Some(self.shared_inner()),
+        })
+    }
+}
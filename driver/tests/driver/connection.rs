@@ -14,7 +14,7 @@
 
 use std::sync::Arc;
 
-use databend_driver::Client;
+use databend_driver::{Client, ConnectionPool};
 
 use crate::common::DEFAULT_DSN;
 
@@ -38,3 +38,29 @@ async fn trait_with_clone() {
     let (val,): (String,) = row.try_into().unwrap();
     assert_eq!(val, "world");
 }
+
+#[tokio::test]
+async fn connection_pool() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let pool = ConnectionPool::new(Client::new(dsn.to_string()), 2).unwrap();
+
+    let conn1 = pool.get().await.unwrap();
+    let conn2 = pool.get().await.unwrap();
+    assert_eq!(pool.in_use(), 2);
+    assert!(pool
+        .get_timeout(std::time::Duration::from_millis(100))
+        .await
+        .is_err());
+
+    drop(conn1);
+    assert_eq!(pool.idle(), 1);
+    let conn3 = pool.get().await.unwrap();
+    let row = conn3.query_row("select 'hello'", ()).await.unwrap();
+    let (val,): (String,) = row.unwrap().try_into().unwrap();
+    assert_eq!(val, "hello");
+
+    drop(conn2);
+    drop(conn3);
+    assert_eq!(pool.in_use(), 0);
+    assert_eq!(pool.idle(), 2);
+}
@@ -219,6 +219,28 @@ async fn select_multi_page() {
     assert_eq!(ret, (0..n).collect::<Vec<u64>>());
 }
 
+#[tokio::test]
+async fn submit_and_fetch() {
+    let (conn, _) = prepare("submit_and_fetch").await;
+    let n = 26000;
+    let sql = format!("select * from NUMBERS({n}) order by number");
+    let query_id = conn.submit(&sql).await.unwrap();
+    let saved = query_id.encode().unwrap();
+
+    // fetch with another client, as if in another process
+    let (other, _) = prepare("submit_and_fetch").await;
+    let query_id = databend_driver::QueryId::decode(&saved).unwrap();
+    let rows = other.fetch(query_id).await.unwrap();
+    let ret: Vec<u64> = rows
+        .map(|r| r.unwrap().try_into().unwrap())
+        .collect::<Vec<(u64,)>>()
+        .await
+        .into_iter()
+        .map(|r| r.0)
+        .collect();
+    assert_eq!(ret, (0..n).collect::<Vec<u64>>());
+}
+
 #[tokio::test]
 async fn select_sleep() {
     let (conn, _) = prepare("select_sleep").await;
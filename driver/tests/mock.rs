@@ -0,0 +1,1237 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests against the mock server, which could run without a live server.
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio_stream::StreamExt;
+
+use databend_client_mock::{MockResult, MockServer};
+use databend_driver::{
+    BatchPolicy, Client, ConnectionPool, Credential, CredentialProvider, DsnBuilder, QueryKind,
+    QueryRewriter, QueryTracker, RecordingTransport, ReplayTransport, RequestSigner, SslMode,
+};
+
+#[tokio::test]
+async fn query_iter_pages() {
+    let server = MockServer::start().await;
+    server.set_page_size(2);
+    server.on_query(
+        "SELECT",
+        MockResult::rows(
+            &[("a", "Int32"), ("b", "Nullable(String)")],
+            vec![
+                vec![Some("1"), Some("x")],
+                vec![Some("2"), None],
+                vec![Some("3"), Some("z")],
+            ],
+        ),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let mut rows = conn.query_iter("SELECT a, b FROM t", ()).await.unwrap();
+    let mut values = vec![];
+    while let Some(row) = rows.next().await {
+        let (a, b): (i32, Option<String>) = row.unwrap().try_into().unwrap();
+        values.push((a, b));
+    }
+    assert_eq!(
+        values,
+        vec![
+            (1, Some("x".to_string())),
+            (2, None),
+            (3, Some("z".to_string()))
+        ]
+    );
+    assert_eq!(server.running_queries(), 0);
+}
+
+#[tokio::test]
+async fn drop_iter_finalizes() {
+    let server = MockServer::start().await;
+    server.set_page_size(1);
+    server.on_query(
+        "SELECT",
+        MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")]; 10]),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let mut rows = conn.query_iter("SELECT a FROM t", ()).await.unwrap();
+    rows.next().await.unwrap().unwrap();
+    assert_eq!(server.running_queries(), 1);
+    drop(rows);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(server.running_queries(), 0);
+}
+
+#[tokio::test]
+async fn close_kills_unread_queries() {
+    let server = MockServer::start().await;
+    server.set_page_size(1);
+    server.on_query(
+        "SELECT",
+        MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")]; 10]),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let mut rows = conn.query_iter("SELECT a FROM t", ()).await.unwrap();
+    rows.next().await.unwrap().unwrap();
+    // not finalized by drop
+    std::mem::forget(rows);
+    // kept to be fetched later
+    conn.submit("SELECT a FROM t").await.unwrap();
+    assert_eq!(server.running_queries(), 2);
+    conn.close().await.unwrap();
+    assert_eq!(server.running_queries(), 1);
+}
+
+#[tokio::test]
+async fn exec_and_session() {
+    let server = MockServer::start().await;
+    server.on_query("INSERT", MockResult::empty().with_write_rows(3));
+    server.on_query("DROP", MockResult::error(1025, "Unknown table t"));
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    assert_eq!(
+        conn.exec("INSERT INTO t VALUES (1), (2), (3)", ())
+            .await
+            .unwrap(),
+        3
+    );
+    let err = conn.exec("DROP TABLE t", ()).await.unwrap_err();
+    assert!(err.to_string().contains("Unknown table t"), "{}", err);
+
+    conn.exec("USE db1", ()).await.unwrap();
+    assert_eq!(conn.info().await.database, Some("db1".to_string()));
+    assert_eq!(conn.current_database().await, Some("db1".to_string()));
+    conn.exec("SET ROLE analyst", ()).await.unwrap();
+    assert_eq!(conn.current_role().await, Some("analyst".to_string()));
+    assert_eq!(conn.current_warehouse().await, None);
+}
+
+#[tokio::test]
+async fn pool_discards_changed_sessions() {
+    let server = MockServer::start().await;
+    let pool = ConnectionPool::new(Client::new(server.dsn()), 2).unwrap();
+
+    let conn = pool.get().await.unwrap();
+    conn.exec("INSERT INTO t VALUES (1)", ()).await.unwrap();
+    drop(conn);
+    assert_eq!(pool.idle(), 1);
+    // reused right away without a health check
+    let conn = pool.get().await.unwrap();
+    assert!(!server.queries().iter().any(|q| q == "SELECT 1"));
+
+    conn.exec("USE db1", ()).await.unwrap();
+    drop(conn);
+    assert_eq!(pool.idle(), 0);
+    assert_eq!(pool.in_use(), 0);
+    let conn = pool.get().await.unwrap();
+    assert_eq!(conn.current_database().await, Some("default".to_string()));
+
+    // still shared by a handle, e.g. a running query of the bindings
+    let shared = conn.shared();
+    drop(conn);
+    assert_eq!(pool.idle(), 0);
+    drop(shared);
+}
+
+#[tokio::test]
+async fn query_slots_released() {
+    let server = MockServer::start().await;
+    server.set_page_size(1);
+    server.on_query(
+        "SELECT",
+        MockResult::rows(
+            &[("a", "Int32")],
+            vec![vec![Some("1")], vec![Some("2")], vec![Some("3")]],
+        ),
+    );
+    let dsn = format!(
+        "{}&max_concurrent_queries=1&query_queue_timeout_secs=1",
+        server.dsn()
+    );
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+
+    // submitted to be fetched elsewhere
+    conn.submit("SELECT a FROM t").await.unwrap();
+    // dropped outside a runtime before the last page
+    let mut rows = conn.query_iter("SELECT a FROM t", ()).await.unwrap();
+    rows.next().await.unwrap().unwrap();
+    std::thread::spawn(move || drop(rows)).join().unwrap();
+
+    let rows = conn.query_all("SELECT a FROM t", ()).await.unwrap();
+    assert_eq!(rows.len(), 3);
+}
+
+#[tokio::test]
+async fn null_and_empty_string() {
+    let server = MockServer::start().await;
+    server.on_query(
+        "SELECT",
+        MockResult::rows(
+            &[("s", "Nullable(String)")],
+            vec![vec![None], vec![Some("")], vec![Some("NULL")]],
+        ),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    assert!(conn.capabilities().distinct_null);
+    let rows = conn.query_all("SELECT s FROM t", ()).await.unwrap();
+    let values: Vec<Option<String>> = rows
+        .into_iter()
+        .map(|row| row.try_into().map(|(s,)| s).unwrap())
+        .collect();
+    assert_eq!(
+        values,
+        vec![None, Some("".to_string()), Some("NULL".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn query_iter_raw() {
+    let server = MockServer::start().await;
+    server.set_page_size(2);
+    // not parsed, so invalid values are forwarded as is
+    server.on_query(
+        "SELECT",
+        MockResult::rows(
+            &[("a", "Int32"), ("d", "Nullable(Date)")],
+            vec![
+                vec![Some("1"), Some("2024-01-01")],
+                vec![Some("2"), None],
+                vec![Some("x"), Some("not a date")],
+            ],
+        ),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let rows = conn.query_iter_raw("SELECT a, d FROM t").await.unwrap();
+    assert_eq!(rows.schema().fields().len(), 2);
+    let rows: Vec<Vec<Option<String>>> = rows.map(|r| r.unwrap()).collect().await;
+    assert_eq!(
+        rows,
+        vec![
+            vec![Some("1".to_string()), Some("2024-01-01".to_string())],
+            vec![Some("2".to_string()), None],
+            vec![Some("x".to_string()), Some("not a date".to_string())],
+        ]
+    );
+}
+
+#[tokio::test]
+async fn query_one_and_scalar() {
+    let server = MockServer::start().await;
+    server.on_query(
+        "SELECT count",
+        MockResult::rows(&[("c", "UInt64")], vec![vec![Some("42")]]),
+    );
+    server.on_query(
+        "SELECT a, b",
+        MockResult::rows(
+            &[("a", "Int32"), ("b", "Int32")],
+            vec![vec![Some("1"), Some("2")]],
+        ),
+    );
+    server.on_query(
+        "SELECT a FROM t",
+        MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")], vec![Some("2")]]),
+    );
+    server.on_query(
+        "SELECT a FROM empty",
+        MockResult::rows(&[("a", "Int32")], vec![]),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+
+    let count: u64 = conn
+        .query_scalar("SELECT count(*) FROM t", ())
+        .await
+        .unwrap();
+    assert_eq!(count, 42);
+    let row = conn.query_one("SELECT a, b FROM t", ()).await.unwrap();
+    assert_eq!(row.len(), 2);
+    let err = conn.query_scalar::<i32, _>("SELECT a, b FROM t", ()).await;
+    assert!(err.unwrap_err().to_string().contains("one column"));
+    let err = conn.query_one("SELECT a FROM t", ()).await.unwrap_err();
+    assert!(err.to_string().contains("got more"), "{err}");
+    let err = conn.query_one("SELECT a FROM empty", ()).await.unwrap_err();
+    assert!(err.to_string().contains("got none"), "{err}");
+    assert_eq!(
+        conn.query_row("SELECT a FROM empty", ())
+            .await
+            .unwrap()
+            .map(|r| r.len()),
+        None
+    );
+}
+
+#[tokio::test]
+async fn exec_batch_policies() {
+    let server = MockServer::start().await;
+    server.on_query("INSERT", MockResult::empty().with_write_rows(3));
+    server.on_query("DROP", MockResult::error(1025, "Unknown table"));
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let sqls = [
+        "CREATE TABLE t(a int)",
+        "DROP TABLE x",
+        "INSERT INTO t VALUES (1)",
+    ];
+
+    let summaries = conn.exec_batch(&sqls, BatchPolicy::FailFast).await.unwrap();
+    assert_eq!(summaries.len(), 2);
+    assert!(summaries[0].is_ok());
+    assert!(!summaries[1].is_ok());
+    assert_eq!(server.queries().len(), 2);
+
+    let summaries = conn
+        .exec_batch(&sqls, BatchPolicy::ContinueOnError)
+        .await
+        .unwrap();
+    assert_eq!(summaries.len(), 3);
+    assert_eq!(summaries[2].result.as_ref().unwrap(), &3);
+    assert_eq!(summaries[2].sql, "INSERT INTO t VALUES (1)");
+}
+
+#[tokio::test]
+async fn exec_script() {
+    let server = MockServer::start().await;
+    server.on_query("-- row", MockResult::empty().with_write_rows(1));
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let script = "CREATE TABLE t(a string);\n-- row with ;\nINSERT INTO t VALUES ('a;b');\n";
+
+    let summaries = conn
+        .exec_script(script, BatchPolicy::FailFast)
+        .await
+        .unwrap();
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(
+        summaries[1].sql,
+        "-- row with ;\nINSERT INTO t VALUES ('a;b')"
+    );
+    assert_eq!(summaries[1].result.as_ref().unwrap(), &1);
+    assert_eq!(summaries[1].kind, QueryKind::Update);
+    assert_eq!(server.queries().len(), 2);
+}
+
+#[tokio::test]
+async fn exec_iter_progress() {
+    use databend_driver::RowWithStats;
+
+    let server = MockServer::start().await;
+    server.set_page_size(1);
+    server.on_query(
+        "DELETE",
+        MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")], vec![Some("2")]])
+            .with_write_rows(7),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let mut progress = conn
+        .exec_iter("DELETE FROM t WHERE a > 0", ())
+        .await
+        .unwrap();
+    let mut stats = vec![];
+    while let Some(item) = progress.next().await {
+        match item.unwrap() {
+            RowWithStats::Stats(ss) => stats.push(ss),
+            RowWithStats::Row(_) => panic!("rows are not expected"),
+        }
+    }
+    assert!(stats.len() > 1);
+    assert_eq!(stats.last().unwrap().write_rows, 7);
+}
+
+#[tokio::test]
+async fn temp_table_dropped() {
+    use databend_driver::{DataType, Field, NumberDataType, Schema};
+
+    let server = MockServer::start().await;
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let schema = Schema::from_vec(vec![
+        Field {
+            name: "id".to_string(),
+            data_type: DataType::Number(NumberDataType::Int64),
+        },
+        Field {
+            name: "Name".to_string(),
+            data_type: DataType::Nullable(Box::new(DataType::String)),
+        },
+    ]);
+
+    let table = conn.create_temp_table(&schema).await.unwrap();
+    let name = table.name().to_string();
+    assert!(name.starts_with("_tmp_"));
+    let create = format!(
+        "CREATE TEMPORARY TABLE {} (id Int64, `Name` Nullable(String))",
+        name
+    );
+    assert!(server.queries().contains(&create));
+    table.close().await.unwrap();
+    assert!(server
+        .queries()
+        .contains(&format!("DROP TABLE IF EXISTS {}", name)));
+
+    let table = conn.create_temp_table(&schema).await.unwrap();
+    let drop_sql = format!("DROP TABLE IF EXISTS {}", table.name());
+    drop(table);
+    for _ in 0..50 {
+        if server.queries().contains(&drop_sql) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("temporary table is not dropped");
+}
+
+#[tokio::test]
+async fn migrations_apply_pending() {
+    use databend_driver::migrations::Migrator;
+
+    let dir = std::env::temp_dir().join(format!("databend_migrations_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("0001_init.sql"), "CREATE TABLE a(x int);").unwrap();
+    std::fs::write(
+        dir.join("0002_more.sql"),
+        "CREATE TABLE b(s string);\nINSERT INTO b VALUES ('x;y');\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("README.md"), "ignored").unwrap();
+    let migrator = Migrator::new(&dir);
+    let migrations = migrator.load().unwrap();
+    assert_eq!(migrations.len(), 2);
+    assert_eq!(migrations[1].statements().len(), 2);
+
+    let server = MockServer::start().await;
+    server.on_query(
+        "SELECT version, checksum, success FROM _migrations",
+        MockResult::rows(
+            &[
+                ("version", "UInt64"),
+                ("checksum", "String"),
+                ("success", "Boolean"),
+            ],
+            vec![vec![
+                Some("1"),
+                Some(migrations[0].checksum.as_str()),
+                Some("1"),
+            ]],
+        ),
+    );
+    server.on_query(
+        "SELECT count(*) FROM system.tables",
+        MockResult::rows(&[("count()", "UInt64")], vec![vec![Some("1")]]),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+
+    let pending = Migrator::new(&dir).dry_run(true).run(&conn).await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert!(!server
+        .queries()
+        .iter()
+        .any(|q| q.starts_with("CREATE TABLE")));
+
+    let applied = migrator.run(&conn).await.unwrap();
+    assert_eq!(applied[0].name, "more");
+    let queries = server.queries();
+    assert!(queries.contains(&"INSERT INTO b VALUES ('x;y')".to_string()));
+    assert!(queries
+        .last()
+        .unwrap()
+        .starts_with("INSERT INTO _migrations"));
+
+    server.on_query("INSERT INTO b", MockResult::error(1005, "syntax error"));
+    let err = migrator.run(&conn).await.unwrap_err();
+    assert!(err.to_string().contains("statement 2"), "{err}");
+    assert!(server
+        .queries()
+        .last()
+        .unwrap()
+        .starts_with("INSERT INTO _migrations"));
+
+    server.on_query(
+        "SELECT version, checksum, success FROM _migrations",
+        MockResult::rows(
+            &[
+                ("version", "UInt64"),
+                ("checksum", "String"),
+                ("success", "Boolean"),
+            ],
+            vec![
+                vec![Some("1"), Some(migrations[0].checksum.as_str()), Some("1")],
+                vec![Some("2"), Some(migrations[1].checksum.as_str()), Some("0")],
+            ],
+        ),
+    );
+    let err = migrator.run(&conn).await.unwrap_err();
+    assert!(err.to_string().contains("failed partway"), "{err}");
+
+    std::fs::write(dir.join("0001_init.sql"), "CREATE TABLE a(x bigint);").unwrap();
+    let err = migrator.run(&conn).await.unwrap_err();
+    assert!(err.to_string().contains("checksum mismatch"), "{err}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn query_builder_exec() {
+    use databend_driver::query_builder::{Insert, Select};
+
+    let server = MockServer::start().await;
+    server.on_query("INSERT", MockResult::empty().with_write_rows(2));
+    server.on_query(
+        "SELECT",
+        MockResult::rows(&[("title", "String")], vec![vec![Some("Rust")]]),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let (sql, params) = Insert::into("books")
+        .columns(&["title", "year"])
+        .values(("Rust", 2024))
+        .values(("Go", 2023))
+        .build()
+        .unwrap();
+    assert_eq!(conn.exec(&sql, params).await.unwrap(), 2);
+    let (sql, params) = Select::from("books")
+        .columns(&["title"])
+        .filter("year > ?", (2023,))
+        .build()
+        .unwrap();
+    let title: String = conn.query_scalar(&sql, params).await.unwrap();
+    assert_eq!(title, "Rust");
+}
+
+#[tokio::test]
+async fn http2_concurrent_queries() {
+    let server = MockServer::start().await;
+    server.set_page_size(1);
+    server.on_query(
+        "SELECT",
+        MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")]; 3]),
+    );
+    let dsn = format!("{}&http2=1", server.dsn());
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    let (a, b, c) = tokio::join!(
+        conn.query_all("SELECT a FROM t", ()),
+        conn.query_all("SELECT a FROM t", ()),
+        conn.query_all("SELECT a FROM t", ())
+    );
+    for rows in [a, b, c] {
+        assert_eq!(rows.unwrap().len(), 3);
+    }
+    assert_eq!(server.running_queries(), 0);
+}
+
+#[tokio::test]
+async fn rate_limited_retries() {
+    let server = MockServer::start().await;
+    server.set_page_size(1);
+    server.on_query(
+        "SELECT",
+        MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")]; 3]),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    // start_query then query_page
+    server.fail_next(429);
+    let mut rows = conn.query_iter("SELECT a FROM t", ()).await.unwrap();
+    rows.next().await.unwrap().unwrap();
+    server.fail_next(429);
+    let rest: Vec<_> = rows.collect().await;
+    assert_eq!(rest.len(), 2);
+
+    let dsn = format!("{}&rate_limit_timeout_secs=0", server.dsn());
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    server.fail_next(429);
+    let err = conn.exec("SELECT 1", ()).await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            databend_driver::Error::Api(databend_client::Error::RateLimited(_))
+        ),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn retry_after_on_503() {
+    let server = MockServer::start().await;
+    let dsn = format!(
+        "{}&retry_base_backoff_ms=60000&retry_max_backoff_ms=60000",
+        server.dsn()
+    );
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    server.fail_next(503);
+    let start = std::time::Instant::now();
+    conn.exec("SELECT 1", ()).await.unwrap();
+    // waited for `Retry-After: 0` instead of the backoff
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn retry_policy() {
+    let server = MockServer::start().await;
+    let dsn = format!(
+        "{}&retry_status_codes=502,503&retry_base_backoff_ms=0&retry_max_attempts=3",
+        server.dsn()
+    );
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    server.fail_next(502);
+    server.fail_next(503);
+    conn.exec("SELECT 1", ()).await.unwrap();
+
+    server.fail_next(502);
+    server.fail_next(502);
+    server.fail_next(502);
+    let err = conn.exec("SELECT 1", ()).await.unwrap_err();
+    assert!(err.to_string().contains("after 3 attempts"), "{err}");
+
+    let dsn = format!("{}&retry_max_attempts=1", server.dsn());
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    server.fail_next(503);
+    assert!(conn.exec("SELECT 1", ()).await.is_err());
+    conn.exec("SELECT 1", ()).await.unwrap();
+}
+
+#[tokio::test]
+async fn dsn_builder_connect() {
+    let server = MockServer::start().await;
+    let conn = DsnBuilder::new(server.addr().ip().to_string())
+        .port(server.addr().port())
+        .user("root")
+        .password("p@ss/word")
+        .database("db1")
+        .ssl_mode(SslMode::Disable)
+        .connect()
+        .await
+        .unwrap();
+    conn.exec("SELECT 1", ()).await.unwrap();
+    assert_eq!(conn.info().await.database, Some("db1".to_string()));
+
+    let caps = conn.capabilities();
+    assert!(caps.login);
+    assert!(!caps.presign);
+    assert!(caps.server_version.unwrap().at_least(1, 2, 0));
+}
+
+#[tokio::test]
+async fn load_data_uploads() {
+    let server = MockServer::start().await;
+    server.on_query("INSERT", MockResult::empty().with_write_rows(2));
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let data = b"1,a\n2,b\n".to_vec();
+    let size = data.len() as u64;
+    let stats = conn
+        .load_data(
+            "INSERT INTO t VALUES",
+            Box::new(std::io::Cursor::new(data)),
+            size,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(stats.write_rows, 2);
+    let uploads = server.uploads();
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].data, b"1,a\n2,b\n");
+    // purged by the load itself
+    assert!(!server.queries().iter().any(|q| q.starts_with("REMOVE")));
+
+    // files with rejected rows are left by purge
+    server.on_query(
+        "INSERT",
+        MockResult::rows(
+            &[("File", "String"), ("Errors_seen", "Int32")],
+            vec![vec![Some("a.csv"), Some("1")]],
+        ),
+    );
+    let stats = conn
+        .load_data(
+            "INSERT INTO t VALUES",
+            Box::new(std::io::Cursor::new(b"1,a\nx\n".to_vec())),
+            6,
+            None,
+            Some([("on_error", "continue")].into_iter().collect()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(stats.rejected_rows, 1);
+    let removed = server
+        .queries()
+        .into_iter()
+        .filter(|q| q.starts_with("REMOVE @~/client/load/"))
+        .count();
+    assert_eq!(removed, 1);
+}
+
+#[tokio::test]
+async fn load_compression_text_only() {
+    let server = MockServer::start().await;
+    server.on_query("INSERT", MockResult::empty().with_write_rows(2));
+    let dsn = format!("{}&load_compression=zstd", server.dsn());
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    let data = b"1,a\n2,b\n".to_vec();
+    let size = data.len() as u64;
+    for format in ["CSV", "PARQUET"] {
+        let options = [("type", format)].into_iter().collect();
+        conn.load_data(
+            "INSERT INTO t VALUES",
+            Box::new(std::io::Cursor::new(data.clone())),
+            size,
+            Some(options),
+            None,
+        )
+        .await
+        .unwrap();
+    }
+    let uploads = server.uploads();
+    assert_eq!(uploads.len(), 2);
+    assert_ne!(uploads[0].data, data);
+    // parquet is compressed already
+    assert_eq!(uploads[1].data, data);
+}
+
+#[tokio::test]
+async fn track_concurrent_queries() {
+    let server = MockServer::start().await;
+    server.on_query(
+        "SELECT",
+        MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")]]),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    let (a, b) = (QueryTracker::default(), QueryTracker::default());
+    let (ra, rb) = tokio::join!(
+        a.scope(conn.query_all("SELECT 1", ())),
+        b.scope(conn.query_all("SELECT 2", ()))
+    );
+    ra.unwrap();
+    rb.unwrap();
+    assert_eq!(a.query_ids().len(), 1);
+    assert_eq!(b.query_ids().len(), 1);
+    assert_ne!(a.last_query_id(), b.last_query_id());
+}
+
+async fn paged_session_queries(client: Client) -> (Vec<i32>, Option<String>) {
+    let conn = client.get_conn().await.unwrap();
+    conn.exec("USE db1", ()).await.unwrap();
+    let rows = conn.query_all("SELECT a FROM t", ()).await.unwrap();
+    let values = rows
+        .into_iter()
+        .map(|row| row.try_into().map(|(a,): (i32,)| a).unwrap())
+        .collect();
+    (values, conn.info().await.database)
+}
+
+#[tokio::test]
+async fn record_and_replay() {
+    let fixture =
+        std::env::temp_dir().join(format!("databend_vcr_driver_{}.json", std::process::id()));
+    let server = MockServer::start().await;
+    let dsn = server.dsn();
+    server.set_page_size(2);
+    server.on_query(
+        "SELECT",
+        MockResult::rows(
+            &[("a", "Int32")],
+            vec![vec![Some("1")], vec![Some("2")], vec![Some("3")]],
+        ),
+    );
+    let recorder = Arc::new(RecordingTransport::new(&fixture));
+    let recorded = paged_session_queries(Client::new(dsn.clone()).with_transport(recorder)).await;
+    assert_eq!(recorded, (vec![1, 2, 3], Some("db1".to_string())));
+    drop(server);
+
+    let replay = Arc::new(ReplayTransport::open(&fixture).unwrap());
+    let replayed = paged_session_queries(Client::new(dsn).with_transport(replay.clone())).await;
+    assert_eq!(replayed, recorded);
+    assert_eq!(replay.remaining(), 0);
+    std::fs::remove_file(&fixture).unwrap();
+}
+
+#[tokio::test]
+async fn list_catalog() {
+    use databend_driver::TableInfo;
+
+    let server = MockServer::start().await;
+    server.on_query(
+        "SELECT schema_name FROM information_schema.schemata",
+        MockResult::rows(
+            &[("schema_name", "String")],
+            vec![vec![Some("default")], vec![Some("system")]],
+        ),
+    );
+    server.on_query(
+        "SELECT table_schema, table_name, table_type FROM information_schema.tables",
+        MockResult::rows(
+            &[
+                ("table_schema", "String"),
+                ("table_name", "String"),
+                ("table_type", "String"),
+            ],
+            vec![vec![Some("default"), Some("books"), Some("BASE TABLE")]],
+        ),
+    );
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    assert_eq!(
+        conn.list_databases().await.unwrap(),
+        vec!["default", "system"]
+    );
+    let tables = conn.list_tables(Some("default")).await.unwrap();
+    assert_eq!(
+        tables,
+        vec![TableInfo {
+            database: "default".to_string(),
+            name: "books".to_string(),
+            table_type: "BASE TABLE".to_string(),
+        }]
+    );
+    assert!(server
+        .queries()
+        .iter()
+        .any(|sql| sql.contains("WHERE table_schema = 'default'")));
+}
+
+#[tokio::test]
+async fn auto_fallback_to_rest() {
+    let server = MockServer::start().await;
+    // nothing listens on port 1, so FlightSQL fails if enabled
+    let dsn = server
+        .dsn()
+        .replace("databend://", "databend+auto://")
+        .replace(
+            "sslmode=disable",
+            "sslmode=disable&flight_port=1&tcp_nodelay=true",
+        );
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    assert_eq!(conn.info().await.handler, "RestAPI");
+    conn.exec("SELECT 1", ()).await.unwrap();
+}
+
+#[tokio::test]
+async fn get_files_ranged_resume() {
+    let server = MockServer::start().await;
+    let data: Vec<u8> = (0..20 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let size = data.len().to_string();
+    let url = server.add_file("big.bin", data.clone());
+    server.on_query(
+        "LIST",
+        MockResult::rows(
+            &[
+                ("name", "String"),
+                ("size", "UInt64"),
+                ("md5", "Nullable(String)"),
+                ("last_modified", "String"),
+                ("creator", "Nullable(String)"),
+            ],
+            vec![vec![
+                Some("big.bin"),
+                Some(&size),
+                None,
+                Some("2024-01-01 00:00:00"),
+                None,
+            ]],
+        ),
+    );
+    server.on_query(
+        "PRESIGN DOWNLOAD",
+        MockResult::rows(
+            &[
+                ("method", "String"),
+                ("headers", "String"),
+                ("url", "String"),
+            ],
+            vec![vec![Some("GET"), Some("{}"), Some(&url)]],
+        ),
+    );
+    let conn = Client::new(format!("{}&download_concurrency=2", server.dsn()))
+        .get_conn()
+        .await
+        .unwrap();
+
+    // the first 2 of 3 ranges are done by an interrupted download
+    let dir = std::env::temp_dir().join(format!("databend_get_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let local = dir.join("big.bin");
+    let mut partial = data[..16 * 1024 * 1024].to_vec();
+    partial.resize(data.len(), 0);
+    std::fs::write(&local, partial).unwrap();
+    let sidecar = dir.join("big.bin.bendsql-download");
+    let etag = serde_json::to_string(&server.file_etag("big.bin").unwrap()).unwrap();
+    let write_sidecar = |etag: &str| {
+        std::fs::write(
+            &sidecar,
+            format!(
+                r#"{{"size":{},"chunk_size":8388608,"etag":{},"done":[0,1]}}"#,
+                size, etag
+            ),
+        )
+        .unwrap();
+    };
+    write_sidecar(&etag);
+
+    let mut rows = conn
+        .get_files("@s1/", &format!("fs://{}", dir.display()))
+        .await
+        .unwrap();
+    while let Some(row) = rows.next().await {
+        row.unwrap();
+    }
+    assert_eq!(std::fs::read(&local).unwrap(), data);
+    assert!(!sidecar.exists());
+    let ranges: Vec<_> = server
+        .downloads()
+        .into_iter()
+        .map(|d| d.range.unwrap_or_default())
+        .collect();
+    assert_eq!(ranges, vec!["bytes=16777216-20971519"]);
+
+    // downloaded from scratch without the sidecar
+    std::fs::remove_file(&local).unwrap();
+    let mut rows = conn
+        .get_files("@s1/", &format!("fs://{}", dir.display()))
+        .await
+        .unwrap();
+    while let Some(row) = rows.next().await {
+        row.unwrap();
+    }
+    assert_eq!(std::fs::read(&local).unwrap(), data);
+    assert_eq!(server.downloads().len(), 4);
+
+    // restarted instead of mixed with the ranges of a changed remote file
+    let mut partial = vec![0xff; 16 * 1024 * 1024];
+    partial.resize(data.len(), 0);
+    std::fs::write(&local, partial).unwrap();
+    write_sidecar(r#""stale""#);
+    let mut rows = conn
+        .get_files("@s1/", &format!("fs://{}", dir.display()))
+        .await
+        .unwrap();
+    while let Some(row) = rows.next().await {
+        row.unwrap();
+    }
+    assert_eq!(std::fs::read(&local).unwrap(), data);
+    assert!(!sidecar.exists());
+    assert_eq!(server.downloads().len(), 8);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn non_sticky_session() {
+    let server = MockServer::start().await;
+    let conn = Client::new(server.dsn()).get_conn().await.unwrap();
+    conn.exec("SET timezone = 'Asia/Shanghai'", ())
+        .await
+        .unwrap();
+    let settings = conn.info().await.settings;
+    assert_eq!(settings.get("timezone").unwrap(), "Asia/Shanghai");
+
+    let conn = Client::new(format!("{}&sticky_session=0&timezone=UTC", server.dsn()))
+        .get_conn()
+        .await
+        .unwrap();
+    conn.exec("SET timezone = 'Asia/Shanghai'", ())
+        .await
+        .unwrap();
+    conn.exec("USE other", ()).await.unwrap();
+    let settings = conn.info().await.settings;
+    assert_eq!(settings.get("timezone").unwrap(), "UTC");
+    assert_eq!(conn.current_database().await.unwrap(), "default");
+}
+
+#[derive(Default)]
+struct CountingProvider {
+    fetched: AtomicUsize,
+    invalidated: AtomicUsize,
+}
+
+#[async_trait]
+impl CredentialProvider for CountingProvider {
+    async fn credential(&self) -> std::result::Result<Credential, databend_client::Error> {
+        let n = self.fetched.fetch_add(1, Ordering::Relaxed);
+        Ok(Credential::Token(format!("token-{}", n).into()))
+    }
+
+    fn invalidate(&self) {
+        self.invalidated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn username(&self) -> String {
+        "root".to_string()
+    }
+}
+
+#[tokio::test]
+async fn credential_provider_reload_on_401() {
+    let server = MockServer::start().await;
+    let provider = Arc::new(CountingProvider::default());
+    let conn = Client::new(server.dsn())
+        .with_credential_provider(provider.clone())
+        .get_conn()
+        .await
+        .unwrap();
+    server.fail_next(401);
+    conn.exec("SELECT 1", ()).await.unwrap();
+    assert_eq!(provider.invalidated.load(Ordering::Relaxed), 1);
+    assert!(provider.fetched.load(Ordering::Relaxed) >= 2);
+}
+
+#[tokio::test]
+async fn relogin_on_expired_session() {
+    let server = MockServer::start().await;
+    server.enable_session_tokens();
+    let provider = Arc::new(CountingProvider::default());
+    let conn = Client::new(server.dsn())
+        .with_credential_provider(provider.clone())
+        .get_conn()
+        .await
+        .unwrap();
+    conn.exec("USE other", ()).await.unwrap();
+    assert_eq!(provider.invalidated.load(Ordering::Relaxed), 0);
+
+    // the refresh token is rejected too, so login again with a reloaded credential
+    server.expire_session_tokens();
+    conn.exec("SELECT 1", ()).await.unwrap();
+    assert_eq!(provider.invalidated.load(Ordering::Relaxed), 1);
+    assert_eq!(conn.current_database().await.unwrap(), "other");
+    assert_eq!(server.queries().last().unwrap(), "SELECT 1");
+}
+
+#[derive(Default)]
+struct CountingSigner {
+    signed: AtomicUsize,
+}
+
+#[async_trait]
+impl RequestSigner for CountingSigner {
+    async fn sign(
+        &self,
+        request: &mut reqwest::Request,
+    ) -> std::result::Result<(), databend_client::Error> {
+        self.signed.fetch_add(1, Ordering::Relaxed);
+        request
+            .headers_mut()
+            .insert("X-Signature", "signed".parse().unwrap());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn request_signer() {
+    let server = MockServer::start().await;
+    let signer = Arc::new(CountingSigner::default());
+    let conn = Client::new(server.dsn())
+        .with_request_signer(signer.clone())
+        .get_conn()
+        .await
+        .unwrap();
+    // login
+    assert_eq!(signer.signed.load(Ordering::Relaxed), 1);
+    conn.exec("SELECT 1", ()).await.unwrap();
+    assert!(signer.signed.load(Ordering::Relaxed) > 1);
+
+    let provider = Arc::new(CountingProvider::default());
+    let conn = Client::new(server.dsn())
+        .with_request_signer(signer.clone())
+        .with_credential_provider(provider.clone())
+        .get_conn()
+        .await
+        .unwrap();
+    let signed = signer.signed.load(Ordering::Relaxed);
+    conn.exec("SELECT 1", ()).await.unwrap();
+    assert!(signer.signed.load(Ordering::Relaxed) > signed);
+    assert!(provider.fetched.load(Ordering::Relaxed) > 0);
+}
+
+/// Adds a limit to selects without one, like for development.
+struct LimitRewriter;
+
+impl QueryRewriter for LimitRewriter {
+    fn rewrite<'a>(&self, sql: &'a str) -> Cow<'a, str> {
+        if sql.starts_with("SELECT") && !sql.contains("LIMIT") {
+            Cow::Owned(format!("{} LIMIT 100", sql))
+        } else {
+            Cow::Borrowed(sql)
+        }
+    }
+}
+
+#[tokio::test]
+async fn query_rewriter() {
+    let server = MockServer::start().await;
+    let conn = Client::new(server.dsn())
+        .with_query_rewriter(Arc::new(LimitRewriter))
+        .get_conn()
+        .await
+        .unwrap();
+    conn.query_all("SELECT * FROM t", ()).await.unwrap();
+    conn.query_iter("SELECT * FROM t LIMIT 1", ())
+        .await
+        .unwrap();
+    conn.query_row("SELECT * FROM t WHERE a = ?", (1,))
+        .await
+        .unwrap();
+    conn.exec("DELETE FROM t", ()).await.unwrap();
+    // removed before the rewriter
+    conn.query_all("SELECT * FROM t FORMAT JSONEachRow;", ())
+        .await
+        .unwrap();
+    assert_eq!(
+        server.queries(),
+        vec![
+            "SELECT * FROM t LIMIT 100",
+            "SELECT * FROM t LIMIT 1",
+            "SELECT * FROM t WHERE a = ? LIMIT 100",
+            "DELETE FROM t",
+            "SELECT * FROM t LIMIT 100",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn http_proxy() {
+    let server = MockServer::start().await;
+    // the mock server answers requests forwarded to it as a proxy,
+    // the host of the dsn is never resolved
+    let dsn = format!(
+        "databend://root:@databend.invalid:8000/default?sslmode=disable&proxy=http://{}",
+        server.addr()
+    );
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    conn.exec("SELECT 1", ()).await.unwrap();
+    assert_eq!(server.queries(), vec!["SELECT 1"]);
+}
+
+#[tokio::test]
+async fn multi_host_failover() {
+    let server = MockServer::start().await;
+    let down = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let down_addr = down.local_addr().unwrap();
+    drop(down);
+    let dsn = format!(
+        "databend://root:@{},{}/default?sslmode=disable",
+        down_addr,
+        server.addr()
+    );
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    conn.exec("SELECT 1", ()).await.unwrap();
+    assert_eq!(server.queries(), vec!["SELECT 1"]);
+}
+
+#[tokio::test]
+async fn load_balance_round_robin() {
+    let (s1, s2) = (MockServer::start().await, MockServer::start().await);
+    let rows = MockResult::rows(
+        &[("n", "UInt64")],
+        vec![vec![Some("1")], vec![Some("2")], vec![Some("3")]],
+    );
+    for s in [&s1, &s2] {
+        s.set_page_size(1);
+        s.on_query("SELECT N", rows.clone());
+    }
+    let down = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let down_addr = down.local_addr().unwrap();
+    drop(down);
+    let dsn = format!(
+        "databend://root:@{},{},{}/default?sslmode=disable&load_balance=round_robin",
+        s1.addr(),
+        down_addr,
+        s2.addr()
+    );
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    for _ in 0..4 {
+        // pages are fetched from the node the query is started on
+        let rows = conn.query_all("SELECT n", ()).await.unwrap();
+        assert_eq!(rows.len(), 3);
+    }
+    // the node down is skipped
+    assert_eq!(s1.queries().len() + s2.queries().len(), 4);
+    assert!(!s1.queries().is_empty() && !s2.queries().is_empty());
+}
+
+#[tokio::test]
+async fn load_balance_no_failover_after_sent() {
+    let server = MockServer::start().await;
+    server.on_query("INSERT", MockResult::empty().with_write_rows(1));
+    // accepts the connection, then closes it without a response
+    let broken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let broken_addr = broken.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = broken.accept().await {
+            let mut buf = [0u8; 1024];
+            let _ = tokio::io::AsyncReadExt::read(&mut stream, &mut buf).await;
+        }
+    });
+    let dsn = format!(
+        "databend://root:@{},{}/default?sslmode=disable&load_balance=round_robin",
+        server.addr(),
+        broken_addr
+    );
+    let conn = Client::new(dsn).get_conn().await.unwrap();
+    let mut failed = 0;
+    for _ in 0..2 {
+        if conn.exec("INSERT INTO t VALUES (1)", ()).await.is_err() {
+            failed += 1;
+        }
+    }
+    // the insert sent to the broken node is not run again on the other one
+    assert_eq!(failed, 1);
+    assert_eq!(server.queries().len(), 1);
+}
+
+#[tokio::test]
+async fn health_check() {
+    let server = MockServer::start().await;
+    server.on_query(
+        "SELECT VERSION()",
+        MockResult::rows(
+            &[("version()", "String")],
+            vec![vec![Some("v1.2.700-mock")]],
+        ),
+    );
+    let report = databend_driver::health::check(&Client::new(server.dsn())).await;
+    assert!(report.healthy);
+    assert_eq!(report.version.as_deref(), Some("v1.2.700-mock"));
+    let names: Vec<_> = report.checks.iter().map(|c| c.name).collect();
+    assert_eq!(names, vec!["ping", "version", "query"]);
+    assert_eq!(
+        server.queries().last().map(|s| s.as_str()),
+        Some("SELECT 1")
+    );
+
+    server.fail_next(503);
+    server.fail_next(503);
+    server.fail_next(503);
+    let report = databend_driver::health::check_with_timeout(
+        &Client::new(server.dsn()),
+        Duration::from_millis(200),
+    )
+    .await;
+    assert!(!report.healthy);
+    assert_eq!(report.checks.len(), 1);
+    assert!(report.checks[0].error.is_some());
+}
+
+#[tokio::test]
+async fn api_client_builder() {
+    let server = MockServer::start().await;
+    let addr = server.addr();
+    let client = databend_client::APIClient::builder()
+        .host(addr.ip().to_string())
+        .port(addr.port())
+        .user("root")
+        .password("p@ss/word")
+        .database("books")
+        .tls(false)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(client.current_database().as_deref(), Some("books"));
+    client.query("SELECT 1").await.unwrap();
+    assert_eq!(server.queries(), vec!["SELECT 1"]);
+}
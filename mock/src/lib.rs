@@ -0,0 +1,684 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A mock Databend server emulating the http query api, for testing clients
+//! without a live server.
+//!
+//! ```no_run
+//! # async fn run() {
+//! use databend_client_mock::{MockResult, MockServer};
+//!
+//! let server = MockServer::start().await;
+//! server.on_query(
+//!     "SELECT",
+//!     MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")], vec![Some("2")]]),
+//! );
+//! let dsn = server.dsn();
+//! // connect to dsn with the client, and check `server.queries()` afterwards
+//! # }
+//! ```
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+
+const DEFAULT_PAGE_SIZE: usize = 10000;
+
+/// Result of the queries matched by [`MockServer::on_query`].
+#[derive(Debug, Clone, Default)]
+pub struct MockResult {
+    schema: Vec<(String, String)>,
+    data: Vec<Vec<Option<String>>>,
+    error: Option<(u16, String)>,
+    write_rows: usize,
+}
+
+impl MockResult {
+    /// Succeed without rows, the result of queries not matched.
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Rows of string values, with schema in `(name, type)`.
+    pub fn rows(schema: &[(&str, &str)], data: Vec<Vec<Option<&str>>>) -> Self {
+        Self {
+            schema: schema
+                .iter()
+                .map(|(name, ty)| (name.to_string(), ty.to_string()))
+                .collect(),
+            data: data
+                .into_iter()
+                .map(|row| row.into_iter().map(|v| v.map(|v| v.to_string())).collect())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Fail the query with the error code, like `1025` for unknown table.
+    pub fn error(code: u16, message: &str) -> Self {
+        Self {
+            error: Some((code, message.to_string())),
+            ..Default::default()
+        }
+    }
+
+    /// Report rows written in stats, returned by `exec` of the driver.
+    pub fn with_write_rows(mut self, rows: usize) -> Self {
+        self.write_rows = rows;
+        self
+    }
+}
+
+/// A request of a file added with [`MockServer::add_file`], with the `Range` header if any.
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub path: String,
+    pub range: Option<String>,
+}
+
+/// A file uploaded with `/v1/upload_to_stage`.
+#[derive(Debug, Clone)]
+pub struct Upload {
+    pub stage: String,
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+struct RunningQuery {
+    result: MockResult,
+    offset: usize,
+    page_size: usize,
+    session: Value,
+}
+
+#[derive(Default)]
+struct MockState {
+    results: Mutex<Vec<(String, MockResult)>>,
+    queries: Mutex<Vec<String>>,
+    running: Mutex<HashMap<String, RunningQuery>>,
+    uploads: Mutex<Vec<Upload>>,
+    /// data and etag of the files
+    files: Mutex<HashMap<String, (Vec<u8>, String)>>,
+    downloads: Mutex<Vec<Download>>,
+    failures: Mutex<VecDeque<u16>>,
+    page_size: Mutex<Option<usize>>,
+    next_id: AtomicU64,
+    session_tokens_enabled: AtomicBool,
+    session_tokens: Mutex<HashSet<String>>,
+}
+
+/// Mock server listening on a random local port, stopped on drop.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let state = Arc::new(MockState::default());
+        let app = Router::new()
+            .route("/v1/session/login", post(login))
+            .route("/v1/session/logout", post(logout))
+            .route("/v1/session/refresh", post(refresh))
+            .route("/v1/query", post(start_query))
+            .route("/v1/query/:id/page/:page", get(query_page))
+            .route("/v1/query/:id/final", get(final_query))
+            .route("/v1/query/:id/kill", post(kill_query))
+            .route("/v1/upload_to_stage", put(upload_to_stage))
+            .route("/files/*path", get(download_file))
+            .layer(DefaultBodyLimit::disable())
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind mock server");
+        let addr = listener.local_addr().expect("failed to get local addr");
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = rx.await;
+                })
+                .await;
+        });
+        Self {
+            addr,
+            state,
+            shutdown: Some(tx),
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// DSN to connect to the server, with database `default`.
+    pub fn dsn(&self) -> String {
+        format!(
+            "databend://root:@{}:{}/default?sslmode=disable",
+            self.addr.ip(),
+            self.addr.port()
+        )
+    }
+
+    /// Answer queries starting with `prefix`, case insensitive,
+    /// the last registered one wins if more than one match.
+    pub fn on_query(&self, prefix: &str, result: MockResult) {
+        self.state
+            .results
+            .lock()
+            .push((prefix.trim().to_uppercase(), result));
+    }
+
+    /// Respond to the next request with the http status, repeated calls queue more failures.
+    /// Retried by clients on `503` and `429`, which are sent with `Retry-After: 0`.
+    pub fn fail_next(&self, status: u16) {
+        self.state.failures.lock().push_back(status);
+    }
+
+    /// Max rows in a page, overrides `max_rows_per_page` of requests.
+    pub fn set_page_size(&self, page_size: usize) {
+        *self.state.page_size.lock() = Some(page_size);
+    }
+
+    /// Sqls of all queries started, in order.
+    pub fn queries(&self) -> Vec<String> {
+        self.state.queries.lock().clone()
+    }
+
+    pub fn uploads(&self) -> Vec<Upload> {
+        self.state.uploads.lock().clone()
+    }
+
+    /// Serve the file like a presigned url, with support of range requests,
+    /// returns the url to answer `PRESIGN DOWNLOAD` with. The file is served with
+    /// an `ETag` changed each time it is added.
+    pub fn add_file(&self, path: &str, data: Vec<u8>) -> String {
+        let path = path.trim_start_matches('/').to_string();
+        let url = format!("http://{}/files/{}", self.addr, path);
+        let etag = format!("\"{}\"", self.state.next_id.fetch_add(1, Ordering::Relaxed));
+        self.state.files.lock().insert(path, (data, etag));
+        url
+    }
+
+    /// `ETag` of the file added, with the quotes.
+    pub fn file_etag(&self, path: &str) -> Option<String> {
+        let path = path.trim_start_matches('/');
+        self.state
+            .files
+            .lock()
+            .get(path)
+            .map(|(_, etag)| etag.clone())
+    }
+
+    pub fn downloads(&self) -> Vec<Download> {
+        self.state.downloads.lock().clone()
+    }
+
+    /// Issue session tokens on login, which are required to start queries.
+    pub fn enable_session_tokens(&self) {
+        self.state
+            .session_tokens_enabled
+            .store(true, Ordering::Relaxed);
+    }
+
+    /// Reject all session and refresh tokens issued so far, as if expired.
+    pub fn expire_session_tokens(&self) {
+        self.state.session_tokens.lock().clear();
+    }
+
+    /// Queries not finished, or finished but not finalized or killed.
+    pub fn running_queries(&self) -> usize {
+        self.state.running.lock().len()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+fn error_response(status: StatusCode, code: u16, message: &str) -> Response {
+    (
+        status,
+        Json(json!({"error": {"code": code, "message": message}})),
+    )
+        .into_response()
+}
+
+fn injected_failure(state: &MockState) -> Option<Response> {
+    let status = state.failures.lock().pop_front()?;
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = error_response(status, 1001, "injected failure");
+    if matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        response
+            .headers_mut()
+            .insert("retry-after", HeaderValue::from_static("0"));
+    }
+    Some(response)
+}
+
+fn stats(result: &MockResult, rows: usize) -> Value {
+    json!({
+        "scan_progress": {"rows": rows, "bytes": 0},
+        "write_progress": {"rows": result.write_rows, "bytes": 0},
+        "result_progress": {"rows": rows, "bytes": 0},
+        "running_time_ms": 1.0,
+    })
+}
+
+fn unquote(s: &str) -> String {
+    s.trim()
+        .trim_end_matches(';')
+        .trim()
+        .trim_matches(|c| c == '\'' || c == '"' || c == '`')
+        .to_string()
+}
+
+/// Apply `USE`, `SET` and `SET ROLE` to the session sent back to client.
+fn apply_session(sql: &str, session: &mut Value) {
+    if !session.is_object() {
+        *session = json!({});
+    }
+    let sql = sql.trim();
+    let upper = sql.to_uppercase();
+    if upper.starts_with("USE ") {
+        session["database"] = json!(unquote(&sql[4..]));
+    } else if upper.starts_with("SET ROLE ") {
+        session["role"] = json!(unquote(&sql[9..]));
+    } else if upper.starts_with("SET ") {
+        if let Some((k, v)) = sql[4..].split_once('=') {
+            let k = k.trim().to_lowercase();
+            if !session["settings"].is_object() {
+                session["settings"] = json!({});
+            }
+            session["settings"][k] = json!(unquote(v));
+        }
+    }
+}
+
+/// Build the page from `offset`, the last page points to the final uri like the server.
+fn page(id: &str, query: &mut RunningQuery) -> Value {
+    let end = (query.offset + query.page_size).min(query.result.data.len());
+    let data = &query.result.data[query.offset..end];
+    let page_no = query.offset / query.page_size.max(1) + 1;
+    query.offset = end;
+    let (state, next_uri) = if end < query.result.data.len() {
+        ("Running", format!("/v1/query/{}/page/{}", id, page_no))
+    } else {
+        ("Succeeded", format!("/v1/query/{}/final", id))
+    };
+    let schema = query
+        .result
+        .schema
+        .iter()
+        .map(|(name, ty)| json!({"name": name, "type": ty}))
+        .collect::<Vec<_>>();
+    json!({
+        "id": id,
+        "session": query.session,
+        "schema": schema,
+        "data": data,
+        "state": state,
+        "error": null,
+        "stats": stats(&query.result, end),
+        "next_uri": next_uri,
+        "final_uri": format!("/v1/query/{}/final", id),
+        "kill_uri": format!("/v1/query/{}/kill", id),
+    })
+}
+
+async fn login(State(state): State<Arc<MockState>>) -> Response {
+    if let Some(resp) = injected_failure(&state) {
+        return resp;
+    }
+    let tokens = if state.session_tokens_enabled.load(Ordering::Relaxed) {
+        issue_tokens(&state)
+    } else {
+        Value::Null
+    };
+    Json(json!({"version": "v1.2.700-mock", "tokens": tokens})).into_response()
+}
+
+fn issue_tokens(state: &MockState) -> Value {
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let (session, refresh) = (format!("session-{}", id), format!("refresh-{}", id));
+    let mut tokens = state.session_tokens.lock();
+    tokens.insert(session.clone());
+    tokens.insert(refresh.clone());
+    json!({"session_token": session, "session_token_ttl_in_secs": 3600, "refresh_token": refresh})
+}
+
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+async fn refresh(State(state): State<Arc<MockState>>, headers: HeaderMap) -> Response {
+    let valid = bearer(&headers).is_some_and(|t| state.session_tokens.lock().contains(t));
+    if !valid {
+        return error_response(StatusCode::UNAUTHORIZED, 5103, "refresh token not found");
+    }
+    Json(issue_tokens(&state)).into_response()
+}
+
+async fn logout() -> Response {
+    Json(json!({})).into_response()
+}
+
+async fn start_query(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    if let Some(resp) = injected_failure(&state) {
+        return resp;
+    }
+    if state.session_tokens_enabled.load(Ordering::Relaxed)
+        && !bearer(&headers).is_some_and(|t| state.session_tokens.lock().contains(t))
+    {
+        return error_response(StatusCode::UNAUTHORIZED, 5101, "session token expired");
+    }
+    let sql = body["sql"].as_str().unwrap_or_default().to_string();
+    state.queries.lock().push(sql.clone());
+    let id = match headers
+        .get("X-DATABEND-QUERY-ID")
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(id) => id.to_string(),
+        None => format!("mock-{}", state.next_id.fetch_add(1, Ordering::Relaxed) + 1),
+    };
+    let mut session = body["session"].clone();
+    apply_session(&sql, &mut session);
+
+    let upper = sql.trim().to_uppercase();
+    let result = state
+        .results
+        .lock()
+        .iter()
+        .rev()
+        .find(|(prefix, _)| upper.starts_with(prefix))
+        .map(|(_, result)| result.clone())
+        .unwrap_or_default();
+    if let Some((code, message)) = &result.error {
+        return Json(json!({
+            "id": id,
+            "session": session,
+            "schema": [],
+            "data": [],
+            "state": "Failed",
+            "error": {"code": code, "message": message},
+            "stats": stats(&result, 0),
+        }))
+        .into_response();
+    }
+
+    let page_size = state
+        .page_size
+        .lock()
+        .or_else(|| {
+            body["pagination"]["max_rows_per_page"]
+                .as_u64()
+                .map(|n| n as usize)
+        })
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .max(1);
+    let mut query = RunningQuery {
+        result,
+        offset: 0,
+        page_size,
+        session,
+    };
+    let resp = page(&id, &mut query);
+    state.running.lock().insert(id, query);
+    Json(resp).into_response()
+}
+
+async fn query_page(
+    State(state): State<Arc<MockState>>,
+    Path((id, _page)): Path<(String, usize)>,
+) -> Response {
+    if let Some(resp) = injected_failure(&state) {
+        return resp;
+    }
+    match state.running.lock().get_mut(&id) {
+        Some(query) => Json(page(&id, query)).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, 1001, "query not found"),
+    }
+}
+
+async fn final_query(State(state): State<Arc<MockState>>, Path(id): Path<String>) -> Response {
+    match state.running.lock().remove(&id) {
+        Some(query) => Json(json!({
+            "id": id,
+            "session": query.session,
+            "schema": [],
+            "data": [],
+            "state": "Succeeded",
+            "error": null,
+            "stats": stats(&query.result, query.offset),
+        }))
+        .into_response(),
+        None => error_response(StatusCode::NOT_FOUND, 1001, "query not found"),
+    }
+}
+
+async fn kill_query(State(state): State<Arc<MockState>>, Path(id): Path<String>) -> Response {
+    state.running.lock().remove(&id);
+    StatusCode::OK.into_response()
+}
+
+/// Extract the file name and content of the first part of a multipart body.
+fn parse_multipart(headers: &HeaderMap, body: &[u8]) -> Option<(String, Vec<u8>)> {
+    let content_type = headers.get("content-type")?.to_str().ok()?;
+    let boundary = content_type.split("boundary=").nth(1)?.trim_matches('"');
+    let delimiter = format!("\r\n--{}", boundary);
+    let header_end = find(body, b"\r\n\r\n")?;
+    let part_headers = String::from_utf8_lossy(&body[..header_end]);
+    let file_name = part_headers
+        .split("filename=\"")
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .unwrap_or_default()
+        .to_string();
+    let content = &body[header_end + 4..];
+    let end = find(content, delimiter.as_bytes()).unwrap_or(content.len());
+    Some((file_name, content[..end].to_vec()))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+async fn upload_to_stage(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(resp) = injected_failure(&state) {
+        return resp;
+    }
+    let stage = headers
+        .get("X-DATABEND-STAGE-NAME")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let (path, data) = match parse_multipart(&headers, &body) {
+        Some(part) => part,
+        None => return error_response(StatusCode::BAD_REQUEST, 1006, "invalid multipart body"),
+    };
+    state.uploads.lock().push(Upload {
+        stage: stage.clone(),
+        path: path.clone(),
+        data,
+    });
+    Json(json!({"id": "upload", "stage_name": stage, "state": "SUCCESS", "files": [path]}))
+        .into_response()
+}
+
+/// Parse `bytes=start-end` of the Range header, with the end inclusive.
+fn parse_range(range: &str, len: usize) -> Option<(usize, usize)> {
+    if len == 0 {
+        return None;
+    }
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let start: usize = start.parse().ok()?;
+    let end = match end {
+        "" => len - 1,
+        end => end.parse::<usize>().ok()?.min(len - 1),
+    };
+    (start <= end).then_some((start, end))
+}
+
+async fn download_file(
+    State(state): State<Arc<MockState>>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(resp) = injected_failure(&state) {
+        return resp;
+    }
+    let range = headers
+        .get("range")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    state.downloads.lock().push(Download {
+        path: path.clone(),
+        range: range.clone(),
+    });
+    let Some((data, etag)) = state.files.lock().get(&path).cloned() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match range {
+        None => (StatusCode::OK, [("etag", etag)], data).into_response(),
+        Some(range) => match parse_range(&range, data.len()) {
+            Some((start, end)) => {
+                let content_range = format!("bytes {}-{}/{}", start, end, data.len());
+                (
+                    StatusCode::PARTIAL_CONTENT,
+                    [("content-range", content_range), ("etag", etag)],
+                    data[start..=end].to_vec(),
+                )
+                    .into_response()
+            }
+            None => StatusCode::RANGE_NOT_SATISFIABLE.into_response(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use databend_client::{APIClient, Error};
+
+    #[tokio::test]
+    async fn paginate_and_finalize() {
+        let server = MockServer::start().await;
+        server.set_page_size(2);
+        server.on_query(
+            "select",
+            MockResult::rows(
+                &[("a", "Int32")],
+                vec![vec![Some("1")], vec![Some("2")], vec![None]],
+            ),
+        );
+        let client = APIClient::new(&server.dsn(), None).await.unwrap();
+        let resp = client.query("SELECT a FROM t").await.unwrap();
+        assert_eq!(resp.schema.len(), 1);
+        assert_eq!(server.queries(), vec!["SELECT a FROM t"]);
+        assert_eq!(server.running_queries(), 0);
+    }
+
+    #[tokio::test]
+    async fn drop_kills_unread_queries() {
+        let server = MockServer::start().await;
+        server.set_page_size(1);
+        server.on_query(
+            "select",
+            MockResult::rows(&[("a", "Int32")], vec![vec![Some("1")]; 3]),
+        );
+        let client = APIClient::new(&server.dsn(), None).await.unwrap();
+        client.start_query("SELECT a FROM t").await.unwrap();
+        assert_eq!(server.running_queries(), 1);
+        drop(client);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(server.running_queries(), 0);
+    }
+
+    #[tokio::test]
+    async fn session_and_errors() {
+        let server = MockServer::start().await;
+        server.on_query(
+            "SELECT * FROM missing",
+            MockResult::error(1025, "Unknown table"),
+        );
+        let client = APIClient::new(&server.dsn(), None).await.unwrap();
+        client.query("USE db1").await.unwrap();
+        client.query("SET max_threads = 4").await.unwrap();
+        assert_eq!(client.current_database(), Some("db1".to_string()));
+        match client.query("SELECT * FROM missing").await {
+            Err(Error::QueryFailed(e)) => assert_eq!(e.code, 1025),
+            r => panic!("unexpected {:?}", r.map(|r| r.id)),
+        }
+
+        server.fail_next(500);
+        assert!(client.query("SELECT 1").await.is_err());
+        assert!(client.query("SELECT 1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn upload() {
+        let server = MockServer::start().await;
+        let client = APIClient::new(&server.dsn(), None).await.unwrap();
+        let data = Box::new(std::io::Cursor::new(b"1,a\n2,b\n".to_vec()));
+        client
+            .upload_to_stage("@~/dir/data.csv", data, 8)
+            .await
+            .unwrap();
+        let uploads = server.uploads();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].stage, "~");
+        assert_eq!(uploads[0].path, "dir/data.csv");
+        assert_eq!(uploads[0].data, b"1,a\n2,b\n");
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_range("bytes=0-3", 10), Some((0, 3)));
+        assert_eq!(parse_range("bytes=8-", 10), Some((8, 9)));
+        assert_eq!(parse_range("bytes=5-20", 10), Some((5, 9)));
+        assert_eq!(parse_range("bytes=0-", 0), None);
+        assert_eq!(parse_range("bytes=6-2", 10), None);
+    }
+}
@@ -48,6 +48,13 @@ pub enum Error {
     #[cfg(feature = "flight-sql")]
     Arrow(arrow_schema::ArrowError),
     Convert(ConvertError),
+    WithContext(Box<Error>, String),
+}
+
+impl Error {
+    pub fn with_context(self, ctx: &str) -> Self {
+        Error::WithContext(Box::new(self), ctx.to_string())
+    }
 }
 
 impl std::fmt::Display for Error {
@@ -82,6 +89,7 @@ impl std::fmt::Display for Error {
                 e.data, e.target, e.message
             ),
             Error::Api(e) => write!(f, "APIError: {}", e),
+            Error::WithContext(e, ctx) => write!(f, "fail to {}: {}", ctx, e),
         }
     }
 }
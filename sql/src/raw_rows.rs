@@ -148,3 +148,48 @@ impl Stream for RawRowIterator {
         Pin::new(&mut self.it).poll_next(cx)
     }
 }
+
+/// Row as the strings sent by the server, or the stats of a page.
+#[derive(Clone, Debug)]
+pub enum StringRowWithStats {
+    Row(Vec<Option<String>>),
+    Stats(ServerStats),
+}
+
+type StringRowStream = Pin<Box<dyn Stream<Item = Result<Vec<Option<String>>>> + Send>>;
+
+/// Rows of a query as the strings sent by the server, `None` for `NULL`,
+/// without parsing them into values, for forwarding data as is.
+pub struct StringRowIterator {
+    schema: SchemaRef,
+    it: StringRowStream,
+}
+
+impl StringRowIterator {
+    pub fn new(
+        schema: SchemaRef,
+        it: Pin<Box<dyn Stream<Item = Result<StringRowWithStats>> + Send>>,
+    ) -> Self {
+        let it = it.filter_map(|r| match r {
+            Ok(StringRowWithStats::Row(r)) => Some(Ok(r)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        });
+        Self {
+            schema,
+            it: Box::pin(it),
+        }
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for StringRowIterator {
+    type Item = Result<Vec<Option<String>>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.it).poll_next(cx)
+    }
+}
@@ -17,6 +17,8 @@ use std::task::Context;
 use std::task::Poll;
 
 use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::{Stream, StreamExt};
 
 #[cfg(feature = "flight-sql")]
@@ -26,6 +28,8 @@ use crate::error::{Error, Result};
 use crate::schema::SchemaRef;
 use crate::value::Value;
 
+const VALUE_EXCERPT_LEN: usize = 64;
+
 #[derive(Clone, Debug)]
 pub enum RowWithStats {
     Row(Row),
@@ -57,6 +61,22 @@ pub struct ServerStats {
 
     #[serde(default)]
     pub spill_bytes: usize,
+
+    #[serde(default)]
+    pub rejected_rows: usize,
+
+    #[serde(default)]
+    pub rejected_files: Vec<RejectedFile>,
+}
+
+/// Rows rejected from a single file while loading data with `on_error`.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct RejectedFile {
+    pub file: String,
+    pub rows_loaded: usize,
+    pub errors_seen: usize,
+    pub first_error: Option<String>,
+    pub first_error_line: Option<usize>,
 }
 
 impl ServerStats {
@@ -79,6 +99,9 @@ impl ServerStats {
         self.running_time_ms += other.running_time_ms;
         self.spill_file_nums += other.spill_file_nums;
         self.spill_bytes += other.spill_bytes;
+        self.rejected_rows += other.rejected_rows;
+        self.rejected_files
+            .extend(other.rejected_files.iter().cloned());
     }
 }
 
@@ -94,6 +117,8 @@ impl From<databend_client::QueryStats> for ServerStats {
             spill_file_nums: stats.progresses.spill_progress.file_nums,
             spill_bytes: stats.progresses.spill_progress.bytes,
             running_time_ms: stats.running_time_ms,
+            rejected_rows: 0,
+            rejected_files: vec![],
         };
         if let Some(total) = stats.progresses.total_scan {
             p.total_rows = total.rows;
@@ -142,12 +167,32 @@ impl TryFrom<(SchemaRef, Vec<Option<String>>)> for Row {
         let mut values: Vec<Value> = Vec::new();
         for (i, field) in schema.fields().iter().enumerate() {
             let val: Option<&str> = data.get(i).and_then(|v| v.as_deref());
-            values.push(Value::try_from((&field.data_type, val))?);
+            let value = Value::try_from((&field.data_type, val)).map_err(|e| {
+                e.with_context(&format!(
+                    "convert column {} `{}` of type {} from {}",
+                    i,
+                    field.name,
+                    field.data_type,
+                    value_excerpt(val)
+                ))
+            })?;
+            values.push(value);
         }
         Ok(Self::new(schema, values))
     }
 }
 
+/// Quoted value cut to `VALUE_EXCERPT_LEN` chars, so that errors of big values stay readable.
+fn value_excerpt(val: Option<&str>) -> String {
+    match val {
+        None => "NULL".to_string(),
+        Some(v) => match v.char_indices().nth(VALUE_EXCERPT_LEN) {
+            Some((end, _)) => format!("'{}...' ({} bytes)", &v[..end], v.len()),
+            None => format!("'{}'", v),
+        },
+    }
+}
+
 impl IntoIterator for Row {
     type Item = Value;
     type IntoIter = std::vec::IntoIter<Self::Item>;
@@ -196,7 +241,15 @@ impl TryFrom<RecordBatch> for Rows {
             for j in 0..batch_schema.fields().len() {
                 let v = batch.column(j);
                 let field = batch_schema.field(j);
-                let value = Value::try_from((field, v, i))?;
+                let value = Value::try_from((field, v, i)).map_err(|e| {
+                    e.with_context(&format!(
+                        "convert column {} `{}` of type {} at row {}",
+                        j,
+                        field.name(),
+                        field.data_type(),
+                        i
+                    ))
+                })?;
                 values.push(value);
             }
             rows.push(Row::new(schema.clone(), values));
@@ -237,6 +290,7 @@ macro_rules! impl_tuple_from_row {
                 if expected_len != row.len() {
                     return Err(format!("row size mismatch: expected {} columns, got {}", expected_len, row.len()));
                 }
+                let schema = row.schema();
                 let mut vals_iter = row.into_iter().enumerate();
 
                 Ok((
@@ -248,7 +302,7 @@ macro_rules! impl_tuple_from_row {
                                            // so it is safe to unwrap
                             let t = col_value.get_type();
                             $Ti::try_from(col_value)
-                                .map_err(|_| format!("failed converting column {} from type({:?}) to type({})", col_ix, t, std::any::type_name::<$Ti>()))?
+                                .map_err(|_| format!("failed converting column {} `{}` from type({:?}) to type({})", col_ix, schema.fields().get(col_ix).map(|f| f.name.as_str()).unwrap_or_default(), t, std::any::type_name::<$Ti>()))?
                         }
                     ,)+
                 ))
@@ -275,6 +329,9 @@ impl_tuple_from_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14
 impl_tuple_from_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15);
 impl_tuple_from_row!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16);
 
+/// Rows read ahead for the slowest consumer of [`RowIterator::tee`].
+const TEE_BUFFER_ROWS: usize = 1024;
+
 pub struct RowIterator {
     schema: SchemaRef,
     it: Pin<Box<dyn Stream<Item = Result<Row>> + Send>>,
@@ -301,6 +358,98 @@ impl RowIterator {
         }
         Ok(ret)
     }
+
+    /// Like `try_collect`, but fails once more than `max_rows` rows are returned,
+    /// instead of running out of memory with an unexpectedly large result.
+    /// The rest of the rows are not read.
+    pub async fn collect_typed<T>(mut self, max_rows: usize) -> Result<Vec<T>>
+    where
+        T: TryFrom<Row>,
+        T::Error: std::fmt::Display,
+    {
+        let mut ret = Vec::new();
+        while let Some(row) = self.it.next().await {
+            if ret.len() == max_rows {
+                return Err(Error::InvalidResponse(format!(
+                    "expect at most {} rows, got more",
+                    max_rows
+                )));
+            }
+            let v = T::try_from(row?).map_err(|e| Error::Parsing(e.to_string()))?;
+            ret.push(v)
+        }
+        Ok(ret)
+    }
+
+    /// Split into `n` iterators yielding the same rows, so that they could be
+    /// consumed in parallel without running the query again, such as writing
+    /// to a file while aggregating. See [`RowIterator::tee_with_buffer`].
+    pub fn tee(self, n: usize) -> Vec<RowIterator> {
+        self.tee_with_buffer(n, TEE_BUFFER_ROWS)
+    }
+
+    /// Like `tee`, reading ahead at most `buffer` rows for the slowest consumer,
+    /// the others wait for it when the buffer is full. Dropped consumers are
+    /// skipped. Errors are yielded by all consumers, the first alive one gets
+    /// the original error and the others get a copy of it.
+    /// Rows are read in a task spawned on the current tokio runtime.
+    pub fn tee_with_buffer(self, n: usize, buffer: usize) -> Vec<RowIterator> {
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            (0..n).map(|_| mpsc::channel(buffer.max(1))).unzip();
+        let mut it = self.it;
+        tokio::spawn(async move {
+            let mut senders: Vec<Option<mpsc::Sender<Result<Row>>>> =
+                senders.into_iter().map(Some).collect();
+            while let Some(item) = it.next().await {
+                match item {
+                    Ok(row) => {
+                        for sender in senders.iter_mut() {
+                            if let Some(tx) = sender {
+                                if tx.send(Ok(row.clone())).await.is_err() {
+                                    *sender = None;
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        let copy = copy_error(&e);
+                        let mut original = Some(e);
+                        for sender in senders.iter_mut() {
+                            let Some(tx) = sender else { continue };
+                            let e = original.take().unwrap_or_else(|| copy_error(&copy));
+                            if let Err(mpsc::error::SendError(Err(e))) = tx.send(Err(e)).await {
+                                // dropped, give the error to the next one
+                                *sender = None;
+                                original = Some(e);
+                            }
+                        }
+                    }
+                }
+                if senders.iter().all(Option::is_none) {
+                    break;
+                }
+            }
+        });
+        receivers
+            .into_iter()
+            .map(|rx| RowIterator::new(self.schema.clone(), Box::pin(ReceiverStream::new(rx))))
+            .collect()
+    }
+}
+
+/// Copy of the error for the consumers of a tee, errors not of plain messages
+/// are copied as `Protocol` with the message.
+fn copy_error(e: &Error) -> Error {
+    match e {
+        Error::Parsing(msg) => Error::Parsing(msg.clone()),
+        Error::Protocol(msg) => Error::Protocol(msg.clone()),
+        Error::Transport(msg) => Error::Transport(msg.clone()),
+        Error::IO(msg) => Error::IO(msg.clone()),
+        Error::BadArgument(msg) => Error::BadArgument(msg.clone()),
+        Error::InvalidResponse(msg) => Error::InvalidResponse(msg.clone()),
+        Error::WithContext(e, ctx) => Error::WithContext(Box::new(copy_error(e)), ctx.clone()),
+        e => Error::Protocol(e.to_string()),
+    }
 }
 
 impl Stream for RowIterator {
@@ -336,6 +485,12 @@ impl RowStatsIterator {
         });
         RowIterator::new(self.schema, Box::pin(it))
     }
+
+    /// Keep only the stats, for the progress of statements like `INSERT` or `DELETE`.
+    pub async fn filter_stats(self) -> RowStatsIterator {
+        let it = self.it.filter(|r| !matches!(r, Ok(RowWithStats::Row(_))));
+        RowStatsIterator::new(self.schema, Box::pin(it))
+    }
 }
 
 impl Stream for RowStatsIterator {
@@ -345,3 +500,117 @@ impl Stream for RowStatsIterator {
         Pin::new(&mut self.it).poll_next(cx)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::{DataType, Field, NumberDataType, Schema};
+    use crate::value::NumberValue;
+
+    #[test]
+    fn convert_error_context() {
+        let schema = SchemaRef::new(Schema::from_vec(vec![
+            Field {
+                name: "id".to_string(),
+                data_type: DataType::Number(NumberDataType::Int32),
+            },
+            Field {
+                name: "price".to_string(),
+                data_type: DataType::Number(NumberDataType::Float64),
+            },
+        ]));
+        let err = Row::try_from((
+            schema.clone(),
+            vec![Some("1".to_string()), Some("x".repeat(100))],
+        ))
+        .unwrap_err();
+        assert!(
+            err.to_string().starts_with(&format!(
+                "fail to convert column 1 `price` of type Float64 from '{}...' (100 bytes): ",
+                "x".repeat(64)
+            )),
+            "{}",
+            err
+        );
+
+        let row =
+            Row::try_from((schema, vec![Some("1".to_string()), Some("2.5".to_string())])).unwrap();
+        let err = <(i32, String)>::try_from(row).unwrap_err();
+        assert!(err.contains("column 1 `price`"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn tee_rows() {
+        let schema = SchemaRef::new(Schema::from_vec(vec![Field {
+            name: "a".to_string(),
+            data_type: DataType::Number(NumberDataType::Int32),
+        }]));
+        let row = |v| Row::from_vec(schema.clone(), vec![Value::Number(NumberValue::Int32(v))]);
+        let items = vec![
+            Ok(row(1)),
+            Ok(row(2)),
+            Err(Error::InvalidResponse("broken".to_string())),
+            Ok(row(3)),
+        ];
+        let it = RowIterator::new(schema.clone(), Box::pin(tokio_stream::iter(items)));
+        let mut tees = it.tee_with_buffer(3, 1);
+        // dropped consumers do not block the others
+        tees.remove(0);
+        let mut b = tees.pop().unwrap();
+        let mut a = tees.pop().unwrap();
+        let (a, b) = tokio::join!(
+            async move {
+                let mut values = vec![];
+                while let Some(r) = a.next().await {
+                    values.push(
+                        r.map(|r| r.values()[0].to_string())
+                            .map_err(|e| e.to_string()),
+                    );
+                }
+                values
+            },
+            async move {
+                let mut values = vec![];
+                while let Some(r) = b.next().await {
+                    values.push(
+                        r.map(|r| r.values()[0].to_string())
+                            .map_err(|e| e.to_string()),
+                    );
+                }
+                values
+            }
+        );
+        assert_eq!(a.len(), 4);
+        assert_eq!(a[..2], [Ok("1".to_string()), Ok("2".to_string())]);
+        assert_eq!(a[2], Err("ResponseError: broken".to_string()));
+        assert_eq!(a[3], Ok("3".to_string()));
+        assert_eq!(b, a);
+    }
+
+    #[tokio::test]
+    async fn collect_typed_limit() -> Result<()> {
+        let schema = SchemaRef::new(Schema::from_vec(vec![Field {
+            name: "a".to_string(),
+            data_type: DataType::Number(NumberDataType::Int32),
+        }]));
+        let rows = || {
+            let items = (1..=3)
+                .map(|v| {
+                    Ok(Row::from_vec(
+                        schema.clone(),
+                        vec![Value::Number(NumberValue::Int32(v))],
+                    ))
+                })
+                .collect::<Vec<_>>();
+            RowIterator::new(schema.clone(), Box::pin(tokio_stream::iter(items)))
+        };
+        let values: Vec<(i32,)> = rows().collect_typed(3).await?;
+        assert_eq!(values, vec![(1,), (2,), (3,)]);
+        let err = rows().collect_typed::<(i32,)>(2).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ResponseError: expect at most 2 rows, got more"
+        );
+        Ok(())
+    }
+}
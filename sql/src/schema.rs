@@ -106,6 +106,10 @@ impl DataType {
             _ => false,
         }
     }
+
+    pub fn is_nullable(&self) -> bool {
+        matches!(self, DataType::Nullable(_) | DataType::Null)
+    }
 }
 
 impl std::fmt::Display for DataType {
@@ -419,13 +423,13 @@ struct TypeDesc<'t> {
     args: Vec<TypeDesc<'t>>,
 }
 
-fn parse_type_desc(s: &str) -> Result<TypeDesc> {
+fn parse_type_desc(s: &str) -> Result<TypeDesc<'_>> {
     let mut name = "";
     let mut args = vec![];
     let mut depth = 0;
     let mut start = 0;
     let mut nullable = false;
-    for (i, c) in s.chars().enumerate() {
+    for (i, c) in s.char_indices() {
         match c {
             '(' => {
                 if depth == 0 {
@@ -444,21 +448,17 @@ fn parse_type_desc(s: &str) -> Result<TypeDesc> {
                     start = i + 1;
                 }
             }
-            ',' => {
-                if depth == 1 {
-                    let s = &s[start..i];
-                    args.push(parse_type_desc(s)?);
-                    start = i + 1;
-                }
+            ',' if depth == 1 => {
+                let s = &s[start..i];
+                args.push(parse_type_desc(s)?);
+                start = i + 1;
             }
-            ' ' => {
-                if depth == 0 {
-                    let s = &s[start..i];
-                    if !s.is_empty() {
-                        name = s;
-                    }
-                    start = i + 1;
+            ' ' if depth == 0 => {
+                let s = &s[start..i];
+                if !s.is_empty() {
+                    name = s;
                 }
+                start = i + 1;
             }
             _ => {}
         }
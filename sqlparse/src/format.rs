@@ -0,0 +1,63 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_common_ast::parser::token::{TokenKind, Tokenizer};
+
+/// Split a trailing `FORMAT <name>` of a `SELECT`, a habit from ClickHouse that
+/// Databend does not support, into the query without it and the format name.
+pub fn split_format_clause(sql: &str) -> Option<(&str, &str)> {
+    let tokens = Tokenizer::new(sql)
+        .map_while(|t| t.ok())
+        .take_while(|t| t.kind != TokenKind::EOI)
+        .collect::<Vec<_>>();
+    let mut end = tokens.len();
+    while end > 0 && tokens[end - 1].kind == TokenKind::SemiColon {
+        end -= 1;
+    }
+    let tokens = &tokens[..end];
+    if tokens.len() < 4 || !matches!(tokens[0].kind, TokenKind::SELECT | TokenKind::WITH) {
+        return None;
+    }
+    let (clause, name) = (&tokens[end - 2], &tokens[end - 1]);
+    if clause.kind != TokenKind::FORMAT
+        || !(name.kind == TokenKind::Ident || name.kind.is_keyword())
+    {
+        return None;
+    }
+    Some((sql[..clause.span.start as usize].trim_end(), name.text()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_clause() {
+        assert_eq!(
+            split_format_clause("SELECT * FROM t FORMAT CSV;"),
+            Some(("SELECT * FROM t", "CSV"))
+        );
+        assert_eq!(
+            split_format_clause("with s AS (SELECT 1) select * from s format JSONEachRow"),
+            Some(("with s AS (SELECT 1) select * from s", "JSONEachRow"))
+        );
+        assert_eq!(split_format_clause("SELECT 'a FORMAT CSV'"), None);
+        assert_eq!(split_format_clause("SELECT a AS format FROM t"), None);
+        assert_eq!(split_format_clause("INSERT INTO t FORMAT CSV"), None);
+        assert_eq!(
+            split_format_clause("SELECT * FROM t WHERE a = 1 FORMAT `csv`"),
+            None
+        );
+    }
+}
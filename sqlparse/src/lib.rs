@@ -0,0 +1,31 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Statement handling with the tokenizer of the Databend sql parser, shared by
+//! bendsql, the driver and the bindings, so that scripts are split and
+//! statements are classified the same way everywhere.
+
+mod format;
+mod query_kind;
+mod split;
+
+pub use format::split_format_clause;
+pub use query_kind::changes_session;
+pub use query_kind::destructive_stmt;
+pub use query_kind::replace_newline_in_box_display;
+pub use query_kind::DestructiveStmt;
+pub use query_kind::GenType;
+pub use query_kind::QueryKind;
+pub use split::split_statements;
+pub use split::take_statements;
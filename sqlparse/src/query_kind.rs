@@ -0,0 +1,433 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_common_ast::parser::token::{TokenKind, Tokenizer};
+
+use nom::branch::alt;
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::multispace0;
+use nom::multi::separated_list0;
+use nom::number::float;
+use nom::Parser;
+use nom::{bytes::complete::take_while, character::complete::char, IResult};
+
+// alter current user's password tokens
+const ALTER_USER_PASSWORD_TOKENS: [TokenKind; 6] = [
+    TokenKind::USER,
+    TokenKind::USER,
+    TokenKind::LParen,
+    TokenKind::RParen,
+    TokenKind::IDENTIFIED,
+    TokenKind::BY,
+];
+
+#[derive(PartialEq, Debug)]
+pub enum QueryKind {
+    Query,
+    Update,
+    Explain,
+    Put(String, String),
+    Get(String, String),
+    // gendata(tpch, scale = 1, override = 1)
+    GenData(GenType, f32, bool),
+    AlterUserPassword,
+    Graphical,
+    ShowCreate,
+    // statements without rows processed, like GRANT, BEGIN, USE or KILL
+    Command,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum GenType {
+    TPCH,
+    TPCDS,
+}
+
+impl From<&str> for QueryKind {
+    fn from(query: &str) -> Self {
+        let mut tz = Tokenizer::new(query);
+        match tz.next() {
+            Some(Ok(t)) => match t.kind {
+                TokenKind::EXPLAIN => {
+                    if query.to_lowercase().contains("graphical") {
+                        QueryKind::Graphical
+                    } else {
+                        QueryKind::Explain
+                    }
+                }
+                TokenKind::SHOW => match tz.next() {
+                    Some(Ok(t)) if t.kind == TokenKind::CREATE => QueryKind::ShowCreate,
+                    _ => QueryKind::Query,
+                },
+                TokenKind::PUT => {
+                    let args: Vec<String> = query
+                        .split_ascii_whitespace()
+                        .skip(1)
+                        .map(|x| x.to_owned())
+                        .collect();
+                    if args.len() == 2 {
+                        QueryKind::Put(args[0].clone(), args[1].clone())
+                    } else {
+                        QueryKind::Query
+                    }
+                }
+                TokenKind::GET => {
+                    let args: Vec<String> = query
+                        .split_ascii_whitespace()
+                        .skip(1)
+                        .map(|x| x.to_owned())
+                        .collect();
+                    if args.len() == 2 {
+                        QueryKind::Get(args[0].clone(), args[1].clone())
+                    } else {
+                        QueryKind::Query
+                    }
+                }
+                TokenKind::ALTER => {
+                    let mut tzs = vec![];
+                    while let Some(Ok(t)) = tz.next() {
+                        tzs.push(t.kind);
+                        if tzs.len() == ALTER_USER_PASSWORD_TOKENS.len() {
+                            break;
+                        }
+                    }
+                    if tzs == ALTER_USER_PASSWORD_TOKENS {
+                        QueryKind::AlterUserPassword
+                    } else {
+                        QueryKind::Update
+                    }
+                }
+                TokenKind::DELETE
+                | TokenKind::UPDATE
+                | TokenKind::INSERT
+                | TokenKind::REPLACE
+                | TokenKind::MERGE
+                | TokenKind::COPY
+                | TokenKind::CREATE
+                | TokenKind::DROP
+                | TokenKind::UNDROP
+                | TokenKind::RENAME
+                | TokenKind::TRUNCATE
+                | TokenKind::OPTIMIZE
+                | TokenKind::VACUUM
+                | TokenKind::ANALYZE
+                | TokenKind::REFRESH => QueryKind::Update,
+                TokenKind::GRANT
+                | TokenKind::REVOKE
+                | TokenKind::BEGIN
+                | TokenKind::START
+                | TokenKind::COMMIT
+                | TokenKind::ROLLBACK
+                | TokenKind::ABORT
+                | TokenKind::USE
+                | TokenKind::SET
+                | TokenKind::UNSET
+                | TokenKind::KILL => QueryKind::Command,
+
+                _ => gendata_parser(query)
+                    .map(|(_, k)| k)
+                    .unwrap_or(QueryKind::Query),
+            },
+            _ => QueryKind::Query,
+        }
+    }
+}
+
+/// A `DROP` or `TRUNCATE` statement, with the objects it removes.
+#[derive(PartialEq, Debug)]
+pub struct DestructiveStmt {
+    /// Statement and object type, like `DROP TABLE`.
+    pub action: String,
+    pub objects: Vec<String>,
+}
+
+/// Finer classification of `QueryKind::Update`, for confirming before
+/// removing objects or data.
+pub fn destructive_stmt(query: &str) -> Option<DestructiveStmt> {
+    let tokens = Tokenizer::new(query)
+        .map_while(|t| t.ok())
+        .take_while(|t| !matches!(t.kind, TokenKind::EOI | TokenKind::SemiColon))
+        .collect::<Vec<_>>();
+    let first = tokens.first()?;
+    if !matches!(first.kind, TokenKind::DROP | TokenKind::TRUNCATE) {
+        return None;
+    }
+
+    // object type are the keywords before `IF EXISTS` or the names,
+    // a keyword followed by `.` is a name like `default.t`
+    let mut action = vec![first.text().to_uppercase()];
+    let mut i = 1;
+    while i < tokens.len()
+        && tokens[i].kind.is_keyword()
+        && tokens[i].kind != TokenKind::IF
+        && tokens.get(i + 1).map(|t| t.kind) != Some(TokenKind::Dot)
+    {
+        action.push(tokens[i].text().to_uppercase());
+        i += 1;
+    }
+    if tokens.get(i).map(|t| t.kind) == Some(TokenKind::IF)
+        && tokens.get(i + 1).map(|t| t.kind) == Some(TokenKind::EXISTS)
+    {
+        i += 2;
+    }
+
+    let mut objects = vec![];
+    let mut name = String::new();
+    let mut after_dot = false;
+    for token in &tokens[i..] {
+        match token.kind {
+            TokenKind::Comma if !name.is_empty() => {
+                objects.push(std::mem::take(&mut name));
+                continue;
+            }
+            TokenKind::Dot if !name.is_empty() => {
+                after_dot = true;
+            }
+            TokenKind::Ident | TokenKind::LiteralString => {}
+            // keywords as names, like `default`, but not trailing `ALL` or `PURGE`
+            kind if kind.is_keyword() && (name.is_empty() || after_dot) => {}
+            _ => break,
+        }
+        if token.kind != TokenKind::Dot {
+            after_dot = false;
+        }
+        name.push_str(token.text());
+    }
+    if !name.is_empty() {
+        objects.push(name);
+    }
+    // names that are keywords, like `DROP DATABASE default`
+    if objects.is_empty() && action.len() > 2 {
+        objects.push(action.pop()?.to_lowercase());
+    }
+    Some(DestructiveStmt {
+        action: action.join(" "),
+        objects,
+    })
+}
+
+/// Whether the statement changes the state kept by the session, like `USE`,
+/// `SET`, transactions and temporary tables, which should not be shared with
+/// the next user of a pooled connection.
+pub fn changes_session(query: &str) -> bool {
+    let mut tokens = Tokenizer::new(query)
+        .map_while(|t| t.ok())
+        .take_while(|t| !matches!(t.kind, TokenKind::EOI | TokenKind::SemiColon));
+    match tokens.next().map(|t| t.kind) {
+        Some(
+            TokenKind::USE
+            | TokenKind::SET
+            | TokenKind::UNSET
+            | TokenKind::BEGIN
+            | TokenKind::START,
+        ) => true,
+        Some(TokenKind::CREATE) => tokens
+            .take_while(|t| t.kind != TokenKind::TABLE)
+            .any(|t| matches!(t.kind, TokenKind::TEMP | TokenKind::TEMPORARY)),
+        _ => false,
+    }
+}
+
+pub fn replace_newline_in_box_display(query: &str) -> bool {
+    let mut tz = Tokenizer::new(query);
+    match tz.next() {
+        Some(Ok(t)) => match t.kind {
+            TokenKind::EXPLAIN => false,
+            TokenKind::SHOW => !matches!(tz.next(), Some(Ok(t)) if t.kind == TokenKind::CREATE),
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+// Define the parser for the GenType
+fn gen_type(input: &str) -> IResult<&str, GenType> {
+    let (input, gen_type_str) = alt((tag_no_case("tpch"), tag_no_case("tpcds"))).parse(input)?;
+    let gen_type = match gen_type_str.to_ascii_lowercase().as_str() {
+        "tpch" => GenType::TPCH,
+        "tpcds" => GenType::TPCDS,
+        _ => {
+            return Err(nom::Err::Error(nom::error::Error::new(
+                input,
+                nom::error::ErrorKind::Tag,
+            )))
+        }
+    };
+    Ok((input, gen_type))
+}
+
+// Define the parser for the key-value pair (e.g., "scale = 100")
+fn key_value(input: &str) -> IResult<&str, (&str, f32)> {
+    let (input, _) = multispace0(input)?;
+    let (input, key) = take_while(|c: char| c.is_alphabetic())(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, _) = char('=')(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, value) = float().parse(input)?;
+    Ok((input, (key, value)))
+}
+
+// Define the parser for the entire gendata function
+fn gendata_parser(input: &str) -> IResult<&str, QueryKind> {
+    let (input, _) = tag_no_case("gendata")(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, _) = char('(')(input)?;
+    let (input, _) = multispace0(input)?;
+
+    // Parse the GenType
+    let (input, gen_type) = gen_type(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, _) = char(',')(input)?;
+    let (input, _) = multispace0(input)?;
+
+    // Parse the key-value pairs
+    let (input, key_values) = separated_list0(char(','), key_value).parse(input)?;
+
+    let (input, _) = multispace0(input)?;
+    let (input, _) = char(')')(input)?;
+
+    // Extract scale and override from key_values
+    let mut scale = 0f32;
+    let mut override_val = false;
+    for (key, value) in key_values {
+        match key.to_ascii_lowercase().as_str() {
+            "sf" | "scale" => scale = value,
+            "override" => override_val = value > 0.0,
+            _ => {}
+        }
+    }
+
+    Ok((
+        input,
+        QueryKind::GenData(gen_type, scale as _, override_val),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{changes_session, destructive_stmt, DestructiveStmt, QueryKind};
+
+    #[test]
+    fn test_query_kind() {
+        let cases = vec![
+            (
+                QueryKind::from("gendata(tpch, scale = 1, override = 0)"),
+                QueryKind::GenData(super::GenType::TPCH, 1.0f32, false),
+            ),
+            (
+                QueryKind::from("gendata(tpcds, scale = 10, override = 1)"),
+                QueryKind::GenData(super::GenType::TPCDS, 10.0f32, true),
+            ),
+            (
+                QueryKind::from("gendata(tpcds, scale = 0.1, override = 1)"),
+                QueryKind::GenData(super::GenType::TPCDS, 0.1f32, true),
+            ),
+        ];
+
+        for (l, r) in cases {
+            assert_eq!(l, r)
+        }
+
+        let cases = vec![
+            ("SELECT 1", QueryKind::Query),
+            ("WITH t AS (SELECT 1) SELECT * FROM t", QueryKind::Query),
+            ("CALL admin$license_info()", QueryKind::Query),
+            ("SHOW TABLES", QueryKind::Query),
+            ("SHOW CREATE TABLE t", QueryKind::ShowCreate),
+            (
+                "MERGE INTO t USING s ON t.a = s.a WHEN MATCHED THEN DELETE",
+                QueryKind::Update,
+            ),
+            ("REPLACE INTO t ON (a) VALUES (1)", QueryKind::Update),
+            ("COPY INTO t FROM @s", QueryKind::Update),
+            ("VACUUM TABLE t", QueryKind::Update),
+            ("ALTER TABLE t ADD COLUMN b INT", QueryKind::Update),
+            (
+                "ALTER USER USER() IDENTIFIED BY 'x'",
+                QueryKind::AlterUserPassword,
+            ),
+            ("GRANT SELECT ON db.* TO ROLE r", QueryKind::Command),
+            ("REVOKE ROLE r FROM u", QueryKind::Command),
+            ("BEGIN", QueryKind::Command),
+            ("COMMIT", QueryKind::Command),
+            ("USE db", QueryKind::Command),
+            ("SET max_threads = 1", QueryKind::Command),
+            ("KILL QUERY 'id'", QueryKind::Command),
+        ];
+        for (query, kind) in cases {
+            assert_eq!(QueryKind::from(query), kind, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_changes_session() {
+        for query in [
+            "use db",
+            "SET max_threads = 1",
+            "UNSET max_threads",
+            "SET ROLE r",
+            "BEGIN",
+            "START TRANSACTION",
+            "CREATE TEMP TABLE t (a INT)",
+            "create or replace temporary table t (a int)",
+        ] {
+            assert!(changes_session(query), "{}", query);
+        }
+        for query in [
+            "SELECT 1",
+            "CREATE TABLE temp (a INT)",
+            "CREATE TRANSIENT TABLE t (a INT)",
+            "COMMIT",
+            "INSERT INTO t VALUES (1)",
+        ] {
+            assert!(!changes_session(query), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_destructive_stmt() {
+        let stmt = |action: &str, objects: &[&str]| {
+            Some(DestructiveStmt {
+                action: action.to_string(),
+                objects: objects.iter().map(|o| o.to_string()).collect(),
+            })
+        };
+        assert_eq!(
+            destructive_stmt("drop table if exists db.t1 all"),
+            stmt("DROP TABLE", &["db.t1"])
+        );
+        assert_eq!(
+            destructive_stmt("DROP TABLE default.`my table`;"),
+            stmt("DROP TABLE", &["default.`my table`"])
+        );
+        assert_eq!(
+            destructive_stmt("TRUNCATE TABLE t"),
+            stmt("TRUNCATE TABLE", &["t"])
+        );
+        assert_eq!(
+            destructive_stmt("drop database default"),
+            stmt("DROP DATABASE", &["default"])
+        );
+        assert_eq!(
+            destructive_stmt("DROP MATERIALIZED VIEW v1"),
+            stmt("DROP MATERIALIZED VIEW", &["v1"])
+        );
+        assert_eq!(
+            destructive_stmt("DROP STAGE s1, s2"),
+            stmt("DROP STAGE", &["s1", "s2"])
+        );
+        assert_eq!(destructive_stmt("DELETE FROM t"), None);
+        assert_eq!(destructive_stmt("SELECT 'drop table t'"), None);
+    }
+}
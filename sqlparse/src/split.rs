@@ -0,0 +1,137 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_common_ast::parser::token::{TokenKind, Tokenizer};
+
+/// Span of the first statement terminator in `text`, `;` or also `\G` with `slash_g`,
+/// as the tokenizer sees it, `None` if there is no complete statement yet or a token
+/// fails to tokenize before it.
+fn next_terminator(text: &str, slash_g: bool) -> Option<(usize, usize)> {
+    let mut backslash = None;
+    for token in Tokenizer::new(text) {
+        let token = token.ok()?;
+        let (start, end) = (token.span.start as usize, token.span.end as usize);
+        match token.kind {
+            TokenKind::EOI => return None,
+            TokenKind::SemiColon => return Some((start, end)),
+            TokenKind::Ident if slash_g && token.text() == "G" => {
+                if let Some(start) = backslash {
+                    return Some((start, end));
+                }
+            }
+            _ => {}
+        }
+        backslash = (token.kind == TokenKind::Backslash).then_some(start);
+    }
+    None
+}
+
+/// Split sql text by `;` with the sql tokenizer, so that `;` in strings, quoted
+/// identifiers and comments is kept, comments are kept with the statement after them.
+/// After a token fails to tokenize, the rest is kept as one statement for the server
+/// or parser to report.
+pub fn split_statements(script: &str) -> Vec<&str> {
+    let mut statements = vec![];
+    let mut rest = script;
+    while let Some((start, end)) = next_terminator(rest, false) {
+        statements.push(&rest[..start]);
+        rest = &rest[end..];
+    }
+    statements.push(rest);
+    statements
+        .into_iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Take the complete statements from the text read so far, like lines of the REPL
+/// or of a script from stdin, split the same way as [`split_statements`] and with
+/// the terminators kept. `\G` also ends a statement with `slash_g`.
+/// The incomplete rest is left in `buffer` for the following lines.
+pub fn take_statements(buffer: &mut String, slash_g: bool) -> Vec<String> {
+    let mut statements = vec![];
+    while let Some((start, end)) = next_terminator(buffer, slash_g) {
+        if !buffer[..start].trim().is_empty() {
+            statements.push(buffer[..end].trim().to_string());
+        }
+        buffer.drain(..end);
+    }
+    if buffer.trim().is_empty() {
+        buffer.clear();
+    }
+    statements
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_script() {
+        let script = "-- books\nCREATE TABLE t(a string);\n\nINSERT INTO t VALUES ('a;b'); SELECT \"x;y\" FROM t;;\n/* done; */";
+        assert_eq!(
+            split_statements(script),
+            vec![
+                "-- books\nCREATE TABLE t(a string)",
+                "INSERT INTO t VALUES ('a;b')",
+                "SELECT \"x;y\" FROM t",
+                "/* done; */",
+            ]
+        );
+        assert_eq!(
+            split_statements("SELECT 'x; SELECT 1"),
+            vec!["SELECT 'x; SELECT 1"]
+        );
+        assert!(split_statements(" ;\n").is_empty());
+    }
+
+    #[test]
+    fn take_lines() {
+        let mut buffer = String::new();
+        let mut statements = vec![];
+        for line in [
+            "-- books",
+            "SELECT 'a;",
+            "b' FROM t; SELECT 1",
+            "\\G SELECT 2;",
+            "SELECT 3",
+        ] {
+            if !buffer.is_empty() {
+                buffer.push('\n');
+            }
+            buffer.push_str(line);
+            statements.extend(take_statements(&mut buffer, true));
+        }
+        assert_eq!(
+            statements,
+            vec![
+                "-- books\nSELECT 'a;\nb' FROM t;",
+                "SELECT 1\n\\G",
+                "SELECT 2;"
+            ]
+        );
+        assert_eq!(buffer, "SELECT 3");
+
+        // the same as split_statements without `\G`
+        let script = "CREATE TABLE t(a string);\n\nINSERT INTO t VALUES ('a;b'); SELECT 1 \\G;";
+        let mut buffer = script.to_string();
+        let taken = take_statements(&mut buffer, false);
+        let taken = taken
+            .iter()
+            .map(|s| s.trim_end_matches(';'))
+            .collect::<Vec<_>>();
+        assert_eq!(taken, split_statements(script));
+    }
+}